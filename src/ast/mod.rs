@@ -1,7 +1,10 @@
 /// Abstract Syntax Tree for the G-code DSL
 /// Designed to be intuitive for machinists while capturing all necessary CNC info
+#[cfg(feature = "ast-serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Program {
     pub header: Header,
     pub operations: Vec<Operation>,
@@ -9,6 +12,7 @@ pub struct Program {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Header {
     pub units: Units,
     pub work_offset: WorkOffset,
@@ -16,18 +20,21 @@ pub struct Header {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Footer {
     pub return_to: Position,
     pub end_code: String, // M30, M02, etc.
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum Units {
     Metric,   // G21
     Imperial, // G20
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum WorkOffset {
     G54,
     G55,
@@ -38,6 +45,7 @@ pub enum WorkOffset {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct SafetyConfig {
     pub max_spindle_rpm: Option<f64>,
     pub max_feed_rate: Option<f64>,
@@ -45,6 +53,7 @@ pub struct SafetyConfig {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum CoolantMode {
     Off,     // M09
     Flood,   // M08
@@ -54,6 +63,7 @@ pub enum CoolantMode {
 
 /// Top-level machining operations
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum Operation {
     ToolChange(ToolChange),
     Spindle(SpindleCommand),
@@ -63,6 +73,8 @@ pub enum Operation {
     Face(FaceOp),
     Tap(TapOp),
     Comment(String),
+    /// Coolant for the cuts that follow, switched on as the next one starts
+    Coolant(CoolantMode),
     // New DSL v2 operations
     PartDef(PartDef),
     Setup(SetupBlock),
@@ -81,6 +93,7 @@ pub enum Operation {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct ToolChange {
     pub tool_number: u8,
     /// Optional string identifier from tool library (e.g., "EM_250_4FL")
@@ -89,28 +102,36 @@ pub struct ToolChange {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct ToolData {
     pub diameter: f64,
     pub length: f64,
     pub flutes: u8,
     pub material: ToolMaterial,
+    pub center_cutting: bool, // safe to plunge straight down; otherwise pockets ramp in
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum ToolMaterial {
     HSS,
     Carbide,
     Cobalt,
     Ceramic,
+    CoatedCarbide, // TiAlN/TiN/AlTiN coated carbide
+    CBN,           // cubic boron nitride, for hardened steel and cast iron
+    Diamond,       // PCD, for non-ferrous and abrasives
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct SpindleCommand {
     pub direction: SpindleDir,
     pub rpm: f64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum SpindleDir {
     CW,  // M03
     CCW, // M04
@@ -119,6 +140,7 @@ pub enum SpindleDir {
 
 /// Drill operation - supports patterns
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct DrillOp {
     pub positions: Vec<Position>,
     pub depth: f64,
@@ -130,6 +152,7 @@ pub struct DrillOp {
 
 /// Pocket operation
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct PocketOp {
     pub geometry: Geometry,
     pub depth: f64,
@@ -142,6 +165,7 @@ pub struct PocketOp {
 
 /// Profile operation - cut along geometry
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct ProfileOp {
     pub geometry: Geometry,
     pub depth: f64,
@@ -149,9 +173,12 @@ pub struct ProfileOp {
     pub stock_to_leave: f64,
     pub feed_rate: f64,
     pub plunge_feed: f64,
+    pub cutter_comp: bool, // emit G41/G42 and let the control apply the tool radius
+    pub lead_radius: Option<f64>, // arc lead-in/out radius; None = tool radius, 0 = straight plunge
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum CutSide {
     Inside,
     Outside,
@@ -160,6 +187,7 @@ pub enum CutSide {
 
 /// Facing operation
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct FaceOp {
     pub bounds: Rectangle,
     pub depth: f64,
@@ -169,15 +197,18 @@ pub struct FaceOp {
 
 /// Tapping operation
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct TapOp {
     pub positions: Vec<Position>,
     pub depth: f64,
     pub pitch: f64, // thread pitch
     pub retract_height: f64,
+    pub thread: Option<String>, // size the pitch came from, e.g. 1/4-20
 }
 
 /// Geometric primitives
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum Geometry {
     Rect(Rectangle),
     Circle(Circle),
@@ -186,30 +217,69 @@ pub enum Geometry {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Rectangle {
     pub bottom_left: Position,
     pub width: f64,
     pub height: f64,
     pub corner_radius: Option<f64>,
-    pub rotation: f64, // degrees
+    pub rotation: f64, // degrees counter-clockwise about the center
+}
+
+impl Rectangle {
+    pub fn center(&self) -> Position {
+        Position::new(
+            self.bottom_left.x + self.width / 2.0,
+            self.bottom_left.y + self.height / 2.0,
+        )
+    }
+
+    /// Corner points after rotation, counter-clockwise from the bottom left
+    pub fn corners(&self) -> [Position; 4] {
+        let center = self.center();
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (hw, hh) = (self.width / 2.0, self.height / 2.0);
+        [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)].map(|(x, y)| {
+            Position::new(center.x + x * cos - y * sin, center.y + x * sin + y * cos)
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Circle {
     pub center: Position,
     pub diameter: f64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Polygon {
     pub center: Position,
     pub circumradius: f64,
     pub sides: u8,
-    pub rotation: f64,
+    pub rotation: f64, // degrees from +X to the first vertex
+}
+
+impl Polygon {
+    /// Corner points, counter-clockwise from the first vertex
+    pub fn vertices(&self) -> Vec<Position> {
+        let step = 360.0 / self.sides as f64;
+        (0..self.sides)
+            .map(|i| {
+                let angle = (self.rotation + i as f64 * step).to_radians();
+                Position::new(
+                    self.center.x + self.circumradius * angle.cos(),
+                    self.center.y + self.circumradius * angle.sin(),
+                )
+            })
+            .collect()
+    }
 }
 
 /// 2D position (X, Y)
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Position {
     pub x: f64,
     pub y: f64,
@@ -223,6 +293,7 @@ impl Position {
 
 /// 3D point (X, Y, Z)
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct Point3D {
     pub x: f64,
     pub y: f64,
@@ -235,6 +306,7 @@ pub struct Point3D {
 
 /// Part definition - describes what we're making
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct PartDef {
     pub name: String,
     pub stock: Option<StockDef>,
@@ -243,6 +315,7 @@ pub struct PartDef {
 
 /// Stock definition
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct StockDef {
     pub material: String, // e.g., "6061-T6", "1018"
     pub size_x: f64,
@@ -251,40 +324,61 @@ pub struct StockDef {
 }
 
 /// Setup configuration
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct SetupBlock {
     pub zero: ZeroConfig,
     pub material: Option<String>, // Material grade for Black Book lookup
     pub z_min: Option<f64>,       // Hard Z floor - do not go below
     pub y_limit: Option<f64>,     // Y travel limit (negative = behind tool)
+    pub rigid_tapping: bool,      // M29 rigid tapping with feed-per-rev G84
+    pub no_length_comp: bool,     // leave G43 H off tool changes (lengths set another way)
+    pub group_tools: bool,        // fold each tool's later operations into its first visit
+    pub keep_pattern_order: bool, // visit pattern holes as written, not in travel order
+    pub milling_direction: Option<MillingDirection>, // None = zig-zag rasters, CCW contours
 }
 
-/// Zero/origin configuration
-#[derive(Debug, Clone, PartialEq)]
+/// Which side of the cutter meets the material, for an M03 (clockwise) spindle
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
+pub enum MillingDirection {
+    Climb,        // chip starts thick: better finish and tool life on rigid machines
+    Conventional, // chip starts thin: kinder to machines with backlash
+}
+
+/// Zero/origin configuration, left front top unless set
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct ZeroConfig {
     pub x_ref: XRef,
     pub y_ref: YRef,
     pub z_ref: ZRef,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum XRef {
+    #[default]
     Left,
     Right,
     Center,
     Value(f64),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum YRef {
+    #[default]
     Front,
     Back,
     Center,
     Value(f64),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum ZRef {
+    #[default]
     Top,
     Bottom,
     Center,
@@ -293,6 +387,7 @@ pub enum ZRef {
 
 /// Direction for cuts
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum Direction {
     XPositive,
     XNegative,
@@ -304,6 +399,7 @@ pub enum Direction {
 
 /// Z constraint for operations
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum ZConstraint {
     Positive, // Z+ - only climb, no plunge below Z0
     Negative, // Z- - only plunge
@@ -313,33 +409,50 @@ pub enum ZConstraint {
 
 /// Cut operation - new simplified syntax
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct CutOp {
     pub direction: Direction,
     pub sweep: f64,  // Width of cut pattern
     pub depth: f64,  // Distance into material
     pub height: f64, // Z height of feature (for stepdown calc)
     pub z_constraint: ZConstraint,
+    pub origin: Position, // Where the sweep starts: the work zero
 }
 
 /// Clear operation - remove material
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct ClearOp {
     pub direction: Direction,
     pub sweep: f64,
     pub depth: f64,
     pub height: f64,
     pub z_constraint: ZConstraint,
+    pub origin: Position,
 }
 
 /// Drill operation - v2 simplified syntax
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct DrillV2Op {
     pub diameter: f64,
     pub position: Position,
     pub depth: DrillDepth, // Thru or specific depth
+    pub spot: bool,        // spot the hole with a shallow G81 first so the drill doesn't walk
+    pub peck: Option<PeckMode>, // None = Black Book default, pecking only when deeper than a peck
+    pub dwell: Option<f64>,     // seconds at the bottom with a G82, for flat bottoms and counterbores
+}
+
+/// How a peck cycle clears chips between pecks
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
+pub enum PeckMode {
+    Break, // G73: short retract that snaps the chip, fast in non-ferrous metals
+    Clear, // G83: full retract to R that pulls chips out of deep holes
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum DrillDepth {
     Thru,
     Depth(f64),
@@ -347,13 +460,39 @@ pub enum DrillDepth {
 
 /// Pocket operation - v2 simplified syntax
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct PocketV2Op {
     pub shape: PocketShape,
     pub position: Position,
     pub depth: f64,
+    /// Wall stock left by roughing and removed by a final perimeter pass
+    pub finish: Option<f64>,
+    /// Overcut at the inside corners of a rectangular pocket
+    pub corners: CornerRelief,
+    /// Clear with trochoidal loops at adaptive (thin, deep) engagement instead of a raster
+    pub adaptive: bool,
+    /// Plunge feed, in place of the tool library's or the Black Book's
+    pub plunge: Option<f64>,
+    /// Width of cut as a fraction of the tool diameter, in place of the Black Book's
+    pub stepover: Option<f64>,
+    /// Depth of each pass, in place of the Black Book's
+    pub stepdown: Option<f64>,
+}
+
+/// Inside-corner relief so a square-cornered part fits a milled pocket
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
+pub enum CornerRelief {
+    #[default]
+    None,
+    /// Cut diagonally into each corner
+    Dogbone,
+    /// Cut along one wall into each corner
+    TBone,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum PocketShape {
     Rect { width: f64, height: f64 },
     Circle { diameter: f64 },
@@ -361,12 +500,14 @@ pub enum PocketShape {
 
 /// Face operation - v2 simplified syntax
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct FaceV2Op {
     pub position: FacePosition, // stock, at X Y, or just use work area
     pub depth: f64,             // how much to face off
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum FacePosition {
     Stock,        // Face entire stock area
     At(f64, f64), // Face centered at X, Y
@@ -378,6 +519,7 @@ pub enum FacePosition {
 
 /// Pattern definition for repeating operations
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum Pattern {
     /// Grid pattern (rows x cols)
     Grid {
@@ -411,8 +553,118 @@ pub enum Pattern {
     },
 }
 
+impl Pattern {
+    /// Expand the pattern into its positions, in the order written: grids row by
+    /// row from the start, circles and arcs by angle, lines from the start
+    pub fn positions(&self) -> Vec<Position> {
+        match self {
+            Pattern::Grid {
+                rows,
+                cols,
+                spacing_x,
+                spacing_y,
+                start_position,
+            } => {
+                let mut positions = Vec::new();
+                for row in 0..*rows {
+                    for col in 0..*cols {
+                        positions.push(Position::new(
+                            start_position.x + col as f64 * spacing_x,
+                            start_position.y + row as f64 * spacing_y,
+                        ));
+                    }
+                }
+                positions
+            }
+            Pattern::BoltCircle {
+                count,
+                diameter,
+                center,
+                start_angle,
+            } => {
+                let mut positions = Vec::new();
+                let radius = diameter / 2.0;
+                let start_rad = start_angle.to_radians();
+                let angle_step = 2.0 * std::f64::consts::PI / *count as f64;
+
+                for i in 0..*count {
+                    let angle = start_rad + i as f64 * angle_step;
+                    positions.push(Position::new(
+                        center.x + radius * angle.cos(),
+                        center.y + radius * angle.sin(),
+                    ));
+                }
+                positions
+            }
+            Pattern::Line {
+                count,
+                spacing,
+                direction,
+                start_position,
+            } => {
+                let mut positions = Vec::new();
+                let (dx, dy) = match direction {
+                    Direction::XPositive => (1.0, 0.0),
+                    Direction::XNegative => (-1.0, 0.0),
+                    Direction::YPositive => (0.0, 1.0),
+                    Direction::YNegative => (0.0, -1.0),
+                    _ => (1.0, 0.0),
+                };
+
+                for i in 0..*count {
+                    positions.push(Position::new(
+                        start_position.x + i as f64 * spacing * dx,
+                        start_position.y + i as f64 * spacing * dy,
+                    ));
+                }
+                positions
+            }
+            Pattern::Arc {
+                count,
+                radius,
+                center,
+                start_angle,
+                end_angle,
+            } => {
+                let mut positions = Vec::new();
+                let start_rad = start_angle.to_radians();
+                let end_rad = end_angle.to_radians();
+                let angle_range = end_rad - start_rad;
+                let angle_step = if *count > 1 {
+                    angle_range / (*count - 1) as f64
+                } else {
+                    0.0
+                };
+
+                for i in 0..*count {
+                    let angle = start_rad + i as f64 * angle_step;
+                    positions.push(Position::new(
+                        center.x + radius * angle.cos(),
+                        center.y + radius * angle.sin(),
+                    ));
+                }
+                positions
+            }
+        }
+    }
+
+    /// The positions in the order that keeps rapids short. Grids snake, every other
+    /// row run backwards so no rapid crosses the part to the start of the next row.
+    /// Circles, arcs and lines already step from each hole to its neighbour.
+    pub fn travel_order(&self) -> Vec<Position> {
+        let mut positions = self.positions();
+        if let Pattern::Grid { cols, .. } = self {
+            for row in positions.chunks_mut((*cols).max(1) as usize).skip(1).step_by(2) {
+                row.reverse();
+            }
+        }
+        positions
+    }
+}
+
 /// Drill operation with pattern support
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct DrillPatternOp {
     pub diameter: f64,
     pub depth: DrillDepth,
@@ -421,6 +673,7 @@ pub struct DrillPatternOp {
 
 /// Pocket operation with pattern support  
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct PocketPatternOp {
     pub shape: PocketShape,
     pub depth: f64,
@@ -433,13 +686,45 @@ pub struct PocketPatternOp {
 
 /// Chamfer operation - bevel edges
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct ChamferOp {
-    pub width: f64,           // Width of chamfer (leg of 45° triangle)
+    pub width: f64,           // Width of chamfer across the top face
     pub geometry: ChamferGeometry,
     pub position: Position,
+    pub side: CutSide,        // Which side of the edge is free space; holes are always inside
+    pub depth: Option<f64>,   // Tool tip below the top face; None puts the tip on the chamfer's foot
+    pub tool_angle: f64,      // Included angle of the chamfer mill in degrees
+}
+
+impl ChamferOp {
+    /// How far below the top face the tool tip runs
+    pub fn tip_depth(&self) -> f64 {
+        self.depth.unwrap_or_else(|| self.min_depth())
+    }
+
+    /// Shallowest tip depth whose cone reaches the full width without leaving a lip
+    pub fn min_depth(&self) -> f64 {
+        self.width / self.half_angle_tan()
+    }
+
+    /// Distance from the edge to the tool axis, out into free space. A deeper tip
+    /// widens the cone at the top face, so the axis backs off to keep the same width.
+    pub fn edge_offset(&self) -> f64 {
+        self.cone_radius() - self.width
+    }
+
+    /// Radius of the tool's cone where it crosses the top face
+    pub fn cone_radius(&self) -> f64 {
+        self.tip_depth() * self.half_angle_tan()
+    }
+
+    fn half_angle_tan(&self) -> f64 {
+        (self.tool_angle / 2.0).to_radians().tan()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum ChamferGeometry {
     Rect { width: f64, height: f64 },
     Circle { diameter: f64 },
@@ -448,6 +733,7 @@ pub enum ChamferGeometry {
 
 /// Deburr operation - clean up edges
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub struct DeburrOp {
     pub pass_depth: f64,      // How deep to cut (typically 0.005-0.010")
     pub geometry: DeburrGeometry,
@@ -455,6 +741,7 @@ pub struct DeburrOp {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "ast-serde", derive(Serialize, Deserialize))]
 pub enum DeburrGeometry {
     Rect { width: f64, height: f64 },
     Circle { diameter: f64 },
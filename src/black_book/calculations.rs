@@ -22,21 +22,22 @@ pub fn compute_parameters(
     }
 
     // Look up base parameters
-    let (sfm_min, sfm_max, sfm_rec) = lookup_sfm(material, tool.tool_material);
+    let (sfm_min, sfm_max, sfm_rec) = tool_sfm(material, tool);
     let base_chip_load = lookup_chip_load(material, tool.diameter, tool.tool_material);
 
-    // Apply engagement factor for chip thinning
+    // Apply engagement factor for chip thinning, and the stronger edge of a corner radius
     let engagement_factor = get_engagement_factor(engagement.radial_engagement_pct);
-    let adjusted_chip_load = base_chip_load * engagement_factor;
+    let adjusted_chip_load = base_chip_load * engagement_factor * corner_radius_factor(tool);
 
-    // Calculate RPM: RPM = (3.82 × SFM) / Diameter
-    let rpm = ((3.82 * sfm_rec) / tool.diameter) as u32;
+    // Calculate RPM: RPM = (3.82 × SFM) / Diameter, on the diameter actually cutting
+    let cutting_diameter = effective_diameter(tool, engagement.axial_doc);
+    let rpm = ((3.82 * sfm_rec) / cutting_diameter) as u32;
 
     // Calculate feed rate: IPM = RPM × IPT × Number of Flutes
     let feed_rate_ipm = rpm as f64 * adjusted_chip_load * tool.flute_count as f64;
 
     // Calculate actual SFM at this RPM
-    let actual_sfm = (rpm as f64 * tool.diameter) / 3.82;
+    let actual_sfm = (rpm as f64 * cutting_diameter) / 3.82;
 
     // Calculate MRR (Material Removal Rate)
     // MRR = WOC × DOC × IPM
@@ -85,6 +86,14 @@ pub fn compute_parameters(
         ));
     }
 
+    // Check for combustible chips
+    if material.fire_hazard {
+        warnings.push(format!(
+            "{} chips are flammable - keep a Class D extinguisher nearby, no water-based coolant, clear fine chips often",
+            material.name
+        ));
+    }
+
     // Check if SFM is in range
     if actual_sfm < sfm_min {
         warnings.push(format!(
@@ -114,6 +123,66 @@ pub fn compute_parameters(
     })
 }
 
+/// Diameter in contact with the work: a ball end mill shallower than its
+/// radius cuts on the chord Deff = 2√(DOC × (D − DOC)), not the full diameter
+pub fn effective_diameter(tool: &ToolGeometry, axial_doc: f64) -> f64 {
+    let radius = tool.diameter / 2.0;
+    if tool.ball_nose && axial_doc > 0.0 && axial_doc < radius {
+        2.0 * (axial_doc * (tool.diameter - axial_doc)).sqrt()
+    } else {
+        tool.diameter
+    }
+}
+
+/// Coating names that mean a bare tool
+const UNCOATED: [&str; 3] = ["none", "uncoated", "bright"];
+
+/// HSS and cobalt have no coated table; a TiN-family coating buys them roughly this much speed
+const COATED_HSS_SFM_FACTOR: f64 = 1.25;
+
+/// Most a corner radius can raise chip load, reached at a radius of 10% of diameter
+const MAX_CORNER_RADIUS_FEED_BONUS: f64 = 0.10;
+
+/// Whether the tool carries a real coating (TiN, TiCN, TiAlN, AlTiN, AlCrN, diamond, ...)
+pub fn is_coated(tool: &ToolGeometry) -> bool {
+    tool.coating.as_deref().is_some_and(|c| {
+        let c = c.trim().to_lowercase();
+        !c.is_empty() && !UNCOATED.contains(&c.as_str())
+    })
+}
+
+/// SFM range for this tool, coating included: any coating moves carbide onto the
+/// material's `sfm_coated` table and speeds HSS/cobalt up by `COATED_HSS_SFM_FACTOR`.
+/// "none", "uncoated" and "bright" count as bare.
+pub fn tool_sfm(material: &MaterialData, tool: &ToolGeometry) -> (f64, f64, f64) {
+    if !is_coated(tool) {
+        return lookup_sfm(material, tool.tool_material);
+    }
+    match tool.tool_material {
+        ToolMaterial::Carbide => material.sfm_coated,
+        ToolMaterial::HSS | ToolMaterial::Cobalt => {
+            let (min, max, rec) = lookup_sfm(material, tool.tool_material);
+            (
+                min * COATED_HSS_SFM_FACTOR,
+                max * COATED_HSS_SFM_FACTOR,
+                rec * COATED_HSS_SFM_FACTOR,
+            )
+        }
+        other => lookup_sfm(material, other),
+    }
+}
+
+/// Chip load multiplier for a corner radius: the radius spreads the load off the
+/// sharp corner, so feed rises with radius/diameter up to `MAX_CORNER_RADIUS_FEED_BONUS`
+pub fn corner_radius_factor(tool: &ToolGeometry) -> f64 {
+    match tool.corner_radius {
+        Some(radius) if radius > 0.0 && tool.diameter > 0.0 => {
+            1.0 + (radius / tool.diameter).min(MAX_CORNER_RADIUS_FEED_BONUS)
+        }
+        _ => 1.0,
+    }
+}
+
 /// Look up SFM range for material and tool
 pub fn lookup_sfm(material: &MaterialData, tool_material: ToolMaterial) -> (f64, f64, f64) {
     match tool_material {
@@ -127,7 +196,8 @@ pub fn lookup_sfm(material: &MaterialData, tool_material: ToolMaterial) -> (f64,
     }
 }
 
-/// Look up base chip load for tool diameter
+/// Look up base chip load for tool diameter. The tables are in inches, so the
+/// diameter must be too; `BlackBook` converts metric programs at its boundary.
 pub fn lookup_chip_load(
     material: &MaterialData,
     tool_diameter: f64,
@@ -139,6 +209,47 @@ pub fn lookup_chip_load(
         _ => &material.chip_loads_carbide,
     };
 
+    lookup_by_diameter(chip_loads, tool_diameter)
+}
+
+/// Drilling SFM as a fraction of milling SFM when a material has no drill data
+const DRILL_SFM_FALLBACK_FACTOR: f64 = 0.7;
+
+/// Compute twist drill speed and feed
+pub fn compute_drilling(
+    material: &MaterialData,
+    tool: &ToolGeometry,
+) -> Result<DrillingParameters, BlackBookError> {
+    if tool.diameter <= 0.0 {
+        return Err(BlackBookError::InvalidToolDiameter(tool.diameter));
+    }
+
+    let drill_sfm = match tool.tool_material {
+        ToolMaterial::HSS | ToolMaterial::Cobalt => material.sfm_drill_hss,
+        _ => material.sfm_drill_carbide,
+    };
+    let sfm = drill_sfm
+        .unwrap_or_else(|| tool_sfm(material, tool).2 * DRILL_SFM_FALLBACK_FACTOR);
+
+    // Without a feed table, treat the drill as a two-lip cutter at milling chip load
+    let feed_per_rev = match &material.feed_per_rev {
+        Some(table) => lookup_by_diameter(table, tool.diameter),
+        None => lookup_chip_load(material, tool.diameter, tool.tool_material) * 2.0,
+    };
+
+    // RPM = (3.82 × SFM) / Diameter, IPM = RPM × IPR
+    let rpm = ((3.82 * sfm) / tool.diameter) as u32;
+
+    Ok(DrillingParameters {
+        rpm,
+        sfm: (rpm as f64 * tool.diameter) / 3.82,
+        feed_per_rev,
+        feed_rate_ipm: rpm as f64 * feed_per_rev,
+    })
+}
+
+/// Look up a per-diameter table (indexed by TOOL_DIAMETERS), interpolating between sizes
+fn lookup_by_diameter(table: &[f64], tool_diameter: f64) -> f64 {
     // Find closest diameter
     let mut closest_idx = 0;
     let mut closest_diff = f64::INFINITY;
@@ -157,12 +268,12 @@ pub fn lookup_chip_load(
         let dia_high = TOOL_DIAMETERS[closest_idx + 1];
         let pct = (tool_diameter - dia_low) / (dia_high - dia_low);
 
-        let cl_low = chip_loads[closest_idx];
-        let cl_high = chip_loads[closest_idx + 1];
+        let cl_low = table[closest_idx];
+        let cl_high = table[closest_idx + 1];
 
         cl_low + (cl_high - cl_low) * pct
     } else {
-        chip_loads[closest_idx]
+        table[closest_idx]
     }
 }
 
@@ -177,6 +288,8 @@ fn get_unit_horsepower(material: &MaterialData) -> f64 {
                 0.5
             } else if material.name.contains("Copper") {
                 0.8
+            } else if material.name.contains("Magnesium") {
+                0.15
             } else {
                 0.4
             }
@@ -236,7 +349,7 @@ pub fn calculate_operation_params(
     match operation {
         OperationType::Roughing => {
             // Aggressive for material removal
-            let (sfm_min, sfm_max, _) = lookup_sfm(material, tool.tool_material);
+            let (sfm_min, sfm_max, _) = tool_sfm(material, tool);
             let sfm = sfm_min + (sfm_max - sfm_min) * 0.6; // 60% of range
 
             let rpm = ((3.82 * sfm) / tool.diameter) as u32;
@@ -253,7 +366,7 @@ pub fn calculate_operation_params(
         }
         OperationType::Finishing => {
             // Conservative for surface finish
-            let (_, sfm_max, _) = lookup_sfm(material, tool.tool_material);
+            let (_, sfm_max, _) = tool_sfm(material, tool);
             let sfm = sfm_max * 0.9; // Near max for good finish
 
             let rpm = ((3.82 * sfm) / tool.diameter) as u32;
@@ -270,7 +383,7 @@ pub fn calculate_operation_params(
         }
         OperationType::Adaptive => {
             // High speed machining style
-            let (_sfm_min, sfm_max, _) = lookup_sfm(material, tool.tool_material);
+            let (_sfm_min, sfm_max, _) = tool_sfm(material, tool);
             let sfm = sfm_max * 0.85;
 
             let rpm = ((3.82 * sfm) / tool.diameter) as u32;
@@ -291,6 +404,42 @@ pub fn calculate_operation_params(
     }
 }
 
+/// Microinches per inch
+const MICROINCHES_PER_INCH: f64 = 1.0e6;
+
+/// Micrometers per inch
+const MICROMETERS_PER_INCH: f64 = 25_400.0;
+
+/// Predicted arithmetic-mean roughness (Ra) of a machined surface
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceFinish {
+    pub microinches: f64,
+    pub micrometers: f64,
+}
+
+/// Estimate Ra left by a tool with `tool_nose_radius`, all inputs in inches. Feed marks
+/// follow the turning approximation Ra ≈ f²/(32·r); stepover leaves scallops of height
+/// h = r − √(r² − (s/2)²), whose Ra is about h/4. The rougher of the two sets the finish.
+/// For the wall of a side-milling pass the nose radius is the tool radius, with no stepover.
+pub fn estimate_surface_finish(
+    feed_per_tooth: f64,
+    tool_nose_radius: f64,
+    stepover: f64,
+) -> SurfaceFinish {
+    let r = tool_nose_radius;
+    let feed_ra = feed_per_tooth.powi(2) / (32.0 * r);
+
+    let half = (stepover / 2.0).min(r);
+    let scallop_height = r - (r * r - half * half).sqrt();
+    let scallop_ra = scallop_height / 4.0;
+
+    let ra = feed_ra.max(scallop_ra);
+    SurfaceFinish {
+        microinches: ra * MICROINCHES_PER_INCH,
+        micrometers: ra * MICROMETERS_PER_INCH,
+    }
+}
+
 /// Operation types for parameter optimization
 #[derive(Debug, Clone, Copy)]
 pub enum OperationType {
@@ -351,6 +500,25 @@ mod tests {
         assert!(f10 >= 3.0);
     }
 
+    #[test]
+    fn test_surface_finish_estimate() {
+        // Textbook turning case: 0.002 IPT on a 0.015" nose radius is about 8.3 µin
+        let finish = estimate_surface_finish(0.002, 0.015, 0.0);
+        assert!((finish.microinches - 8.33).abs() < 0.01);
+        assert!((finish.micrometers - 0.212).abs() < 0.001);
+
+        // A 1/4" ball at 0.010" stepover leaves a 0.0001" scallop, Ra ≈ 25 µin,
+        // which outweighs the feed marks of a light chip load
+        let finish = estimate_surface_finish(0.001, 0.125, 0.010);
+        assert!((finish.microinches - 25.0).abs() < 0.1);
+        assert!(finish.microinches > estimate_surface_finish(0.001, 0.125, 0.0).microinches);
+
+        // Halving the feed quarters the feed-mark roughness
+        let coarse = estimate_surface_finish(0.004, 0.0625, 0.0);
+        let fine = estimate_surface_finish(0.002, 0.0625, 0.0);
+        assert!((coarse.microinches / fine.microinches - 4.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_unit_horsepower() {
         let db = load_material_database();
@@ -377,6 +545,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         let rough = calculate_operation_params(material, &tool, OperationType::Roughing);
@@ -398,6 +567,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         let adaptive = calculate_operation_params(material, &tool, OperationType::Adaptive);
@@ -2,8 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
-/// Material category for grouping
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Material category for grouping; declaration order is listing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum MaterialCategory {
     NonFerrous,
     SteelLowAlloy,
@@ -18,6 +18,25 @@ pub enum MaterialCategory {
     Composite,
 }
 
+impl std::fmt::Display for MaterialCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // `pad` so listings can align the column
+        f.pad(match self {
+            MaterialCategory::NonFerrous => "non-ferrous",
+            MaterialCategory::SteelLowAlloy => "steel-low-alloy",
+            MaterialCategory::SteelHighAlloy => "steel-high-alloy",
+            MaterialCategory::StainlessAustenitic => "stainless-austenitic",
+            MaterialCategory::StainlessMartensitic => "stainless-martensitic",
+            MaterialCategory::StainlessPrecipitation => "stainless-precipitation",
+            MaterialCategory::CastIron => "cast-iron",
+            MaterialCategory::Titanium => "titanium",
+            MaterialCategory::HighTempAlloy => "high-temp-alloy",
+            MaterialCategory::Plastic => "plastic",
+            MaterialCategory::Composite => "composite",
+        })
+    }
+}
+
 /// Complete material cutting data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MaterialData {
@@ -46,6 +65,17 @@ pub struct MaterialData {
     pub recommended_engagement: f64, // % radial engagement
     pub coolant_required: bool,
     pub high_feed_recommended: bool, // For chip thinning
+    #[serde(default)]
+    pub fire_hazard: bool, // Chips can ignite (magnesium)
+
+    // Twist drill data; None falls back to derated milling values
+    #[serde(default)]
+    pub sfm_drill_hss: Option<f64>,
+    #[serde(default)]
+    pub sfm_drill_carbide: Option<f64>,
+    // Feed per revolution (inches) by drill diameter, same sizes as chip loads
+    #[serde(default)]
+    pub feed_per_rev: Option<Vec<f64>>,
 }
 
 /// Load the complete material database
@@ -76,6 +106,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 30.0,
             coolant_required: false,
             high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: Some(250.0),
+            sfm_drill_carbide: Some(600.0),
+            feed_per_rev: Some(vec![0.002, 0.003, 0.004, 0.006, 0.008, 0.009, 0.010, 0.012]),
         },
     );
 
@@ -100,6 +134,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 25.0,
             coolant_required: false,
             high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: Some(220.0),
+            sfm_drill_carbide: Some(550.0),
+            feed_per_rev: Some(vec![0.002, 0.003, 0.004, 0.006, 0.008, 0.009, 0.010, 0.012]),
         },
     );
 
@@ -128,6 +166,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 25.0,
             coolant_required: false,
             high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: Some(220.0),
+            sfm_drill_carbide: Some(550.0),
+            feed_per_rev: Some(vec![0.002, 0.003, 0.004, 0.006, 0.008, 0.009, 0.010, 0.012]),
         },
     );
 
@@ -152,6 +194,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 40.0,
             coolant_required: false,
             high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: Some(200.0),
+            sfm_drill_carbide: Some(500.0),
+            feed_per_rev: Some(vec![0.002, 0.003, 0.004, 0.005, 0.007, 0.008, 0.009, 0.011]),
         },
     );
 
@@ -176,6 +222,39 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 20.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
+        },
+    );
+
+    db.insert(
+        "Magnesium AZ31B".to_string(),
+        MaterialData {
+            name: "Magnesium AZ31B".to_string(),
+            category: MaterialCategory::NonFerrous,
+            grades: vec!["AZ31B".to_string(), "AZ31B-H24".to_string()],
+            description: "Lightweight wrought magnesium, machines freely but chips are flammable"
+                .to_string(),
+            hardness_hrc: None,
+            hardness_hb: Some(73),
+            machinability_rating: 500.0,
+            sfm_hss: (400.0, 900.0, 650.0),
+            sfm_cobalt: (500.0, 1100.0, 800.0),
+            sfm_carbide: (1000.0, 2500.0, 1800.0),
+            sfm_coated: (1000.0, 2500.0, 1800.0),
+            sfm_ceramic: None,
+            chip_loads_carbide: vec![0.0015, 0.002, 0.003, 0.004, 0.005, 0.006, 0.007, 0.008],
+            chip_loads_hss: vec![0.001, 0.0015, 0.002, 0.003, 0.003, 0.004, 0.004, 0.005],
+            max_doc_diameter_ratio: 1.5,
+            recommended_engagement: 40.0,
+            coolant_required: false, // Never water-based coolant - run dry or mineral oil
+            high_feed_recommended: true, // Thin chips ignite; keep them thick
+            fire_hazard: true,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -203,6 +282,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 30.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: Some(80.0),
+            sfm_drill_carbide: Some(250.0),
+            feed_per_rev: Some(vec![0.002, 0.003, 0.004, 0.005, 0.006, 0.007, 0.008, 0.010]),
         },
     );
 
@@ -227,6 +310,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 20.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: Some(60.0),
+            sfm_drill_carbide: Some(200.0),
+            feed_per_rev: Some(vec![0.0015, 0.002, 0.003, 0.004, 0.005, 0.006, 0.007, 0.008]),
         },
     );
 
@@ -256,6 +343,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 20.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -285,6 +376,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 15.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -317,6 +412,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 10.0,
             coolant_required: true,
             high_feed_recommended: true, // To avoid work hardening
+            fire_hazard: false,
+            sfm_drill_hss: Some(40.0),
+            sfm_drill_carbide: Some(150.0),
+            feed_per_rev: Some(vec![0.001, 0.0015, 0.002, 0.003, 0.004, 0.005, 0.006, 0.007]),
         },
     );
 
@@ -341,6 +440,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 10.0,
             coolant_required: true,
             high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -365,6 +468,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 15.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -391,6 +498,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 12.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -418,6 +529,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 40.0,
             coolant_required: false, // Often run dry
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -442,6 +557,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 35.0,
             coolant_required: true,
             high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -473,6 +592,10 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 10.0,
             coolant_required: true,      // Flood coolant essential
             high_feed_recommended: true, // To keep heat in chip
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -502,6 +625,138 @@ pub fn load_material_database() -> std::collections::HashMap<String, MaterialDat
             recommended_engagement: 8.0,
             coolant_required: true,
             high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
+        },
+    );
+
+    // PLASTICS
+    // ========
+
+    db.insert(
+        "Delrin (Acetal)".to_string(),
+        MaterialData {
+            name: "Delrin (Acetal)".to_string(),
+            category: MaterialCategory::Plastic,
+            grades: vec![
+                "Delrin".to_string(),
+                "Acetal".to_string(),
+                "POM".to_string(),
+            ],
+            description: "Stiff, dimensionally stable engineering plastic, machines cleanly"
+                .to_string(),
+            hardness_hrc: None,
+            hardness_hb: None,
+            machinability_rating: 300.0,
+            sfm_hss: (300.0, 600.0, 450.0),
+            sfm_cobalt: (350.0, 700.0, 500.0),
+            sfm_carbide: (500.0, 1000.0, 800.0),
+            sfm_coated: (500.0, 1000.0, 800.0),
+            sfm_ceramic: None,
+            chip_loads_carbide: vec![0.002, 0.003, 0.004, 0.005, 0.006, 0.008, 0.009, 0.010],
+            chip_loads_hss: vec![0.0015, 0.002, 0.003, 0.004, 0.005, 0.006, 0.007, 0.008],
+            max_doc_diameter_ratio: 1.5,
+            recommended_engagement: 50.0,
+            coolant_required: false,
+            high_feed_recommended: true, // Rubbing melts the chip
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
+        },
+    );
+
+    db.insert(
+        "Acrylic (PMMA)".to_string(),
+        MaterialData {
+            name: "Acrylic (PMMA)".to_string(),
+            category: MaterialCategory::Plastic,
+            grades: vec![
+                "Acrylic".to_string(),
+                "PMMA".to_string(),
+                "Plexiglas".to_string(),
+            ],
+            description: "Brittle, clear plastic; chips and melts if fed too lightly".to_string(),
+            hardness_hrc: None,
+            hardness_hb: None,
+            machinability_rating: 200.0,
+            sfm_hss: (200.0, 500.0, 350.0),
+            sfm_cobalt: (250.0, 550.0, 400.0),
+            sfm_carbide: (400.0, 800.0, 600.0),
+            sfm_coated: (400.0, 800.0, 600.0),
+            sfm_ceramic: None,
+            chip_loads_carbide: vec![0.002, 0.003, 0.003, 0.004, 0.005, 0.006, 0.007, 0.008],
+            chip_loads_hss: vec![0.0015, 0.002, 0.002, 0.003, 0.004, 0.005, 0.005, 0.006],
+            max_doc_diameter_ratio: 1.0,
+            recommended_engagement: 40.0,
+            coolant_required: false, // Air blast to clear chips
+            high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
+        },
+    );
+
+    db.insert(
+        "Polycarbonate".to_string(),
+        MaterialData {
+            name: "Polycarbonate".to_string(),
+            category: MaterialCategory::Plastic,
+            grades: vec!["PC".to_string(), "Lexan".to_string()],
+            description: "Tough, impact resistant plastic; gummy when overheated".to_string(),
+            hardness_hrc: None,
+            hardness_hb: None,
+            machinability_rating: 150.0,
+            sfm_hss: (200.0, 450.0, 300.0),
+            sfm_cobalt: (250.0, 500.0, 350.0),
+            sfm_carbide: (400.0, 800.0, 600.0),
+            sfm_coated: (400.0, 800.0, 600.0),
+            sfm_ceramic: None,
+            chip_loads_carbide: vec![0.002, 0.003, 0.003, 0.004, 0.005, 0.006, 0.007, 0.008],
+            chip_loads_hss: vec![0.0015, 0.002, 0.002, 0.003, 0.004, 0.005, 0.005, 0.006],
+            max_doc_diameter_ratio: 1.0,
+            recommended_engagement: 40.0,
+            coolant_required: false,
+            high_feed_recommended: true,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
+        },
+    );
+
+    // COMPOSITES
+    // ==========
+
+    db.insert(
+        "G10/FR4".to_string(),
+        MaterialData {
+            name: "G10/FR4".to_string(),
+            category: MaterialCategory::Composite,
+            grades: vec!["G10".to_string(), "FR4".to_string(), "FR-4".to_string()],
+            description: "Glass-epoxy laminate; abrasive to tools, dust is a respiratory hazard"
+                .to_string(),
+            hardness_hrc: None,
+            hardness_hb: None,
+            machinability_rating: 30.0,
+            sfm_hss: (100.0, 200.0, 150.0), // Glass fibers destroy HSS quickly
+            sfm_cobalt: (120.0, 250.0, 180.0),
+            sfm_carbide: (300.0, 700.0, 500.0),
+            sfm_coated: (400.0, 800.0, 600.0),
+            sfm_ceramic: None,
+            chip_loads_carbide: vec![0.001, 0.0015, 0.002, 0.0025, 0.003, 0.004, 0.004, 0.005],
+            chip_loads_hss: vec![0.0005, 0.001, 0.001, 0.0015, 0.002, 0.0025, 0.003, 0.003],
+            max_doc_diameter_ratio: 1.0,
+            recommended_engagement: 30.0,
+            coolant_required: false, // Vacuum the dust instead
+            high_feed_recommended: false,
+            fire_hazard: false,
+            sfm_drill_hss: None,
+            sfm_drill_carbide: None,
+            feed_per_rev: None,
         },
     );
 
@@ -13,6 +13,7 @@
 //! - Depth of cut adjustments
 //! - Tool material (HSS, Carbide, etc.)
 
+use crate::ast::{PeckMode, Units};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -22,6 +23,10 @@ pub mod validators;
 
 pub use calculations::*;
 pub use materials::*;
+pub use validators::MachineProfile;
+
+const MM_PER_INCH: f64 = 25.4;
+const CM3_PER_IN3: f64 = 16.387;
 
 /// Cutting tool material type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -57,9 +62,14 @@ pub struct ToolGeometry {
     pub tool_material: ToolMaterial,
     pub corner_radius: Option<f64>, // for corner radius end mills
     pub coating: Option<String>,
+    #[serde(default)]
+    pub ball_nose: bool, // ball end mill: cuts on a smaller diameter at shallow DOC
 }
 
 /// Cutting parameters result
+///
+/// Lengths and feeds are in the Black Book's unit system: inches (IPM, IPT) by
+/// default, millimeters (mm/min, mm/tooth) when metric.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CuttingParameters {
     pub rpm: u32,
@@ -69,10 +79,33 @@ pub struct CuttingParameters {
     pub doc: f64,                   // recommended depth of cut (axial)
     pub woc: f64,                   // recommended width of cut (radial)
     pub hp_required: f64,           // approximate horsepower
-    pub material_removal_rate: f64, // cubic inches per minute
+    pub material_removal_rate: f64, // cubic inches (or cm³) per minute
     pub warnings: Vec<String>,
 }
 
+impl CuttingParameters {
+    /// Convert inch-based results to millimeters (mm/min feed, mm/tooth chip load)
+    fn into_metric(self) -> Self {
+        Self {
+            feed_rate_ipm: self.feed_rate_ipm * MM_PER_INCH,
+            chip_load_ipt: self.chip_load_ipt * MM_PER_INCH,
+            doc: self.doc * MM_PER_INCH,
+            woc: self.woc * MM_PER_INCH,
+            material_removal_rate: self.material_removal_rate * CM3_PER_IN3,
+            ..self
+        }
+    }
+}
+
+/// Twist drill parameters result, in the Black Book's unit system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrillingParameters {
+    pub rpm: u32,
+    pub sfm: f64,           // surface feet per minute
+    pub feed_per_rev: f64,  // inches per revolution
+    pub feed_rate_ipm: f64, // inches per minute
+}
+
 /// Workpiece engagement parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Engagement {
@@ -84,12 +117,73 @@ pub struct Engagement {
 /// The Black Book - main interface
 pub struct BlackBook {
     materials: HashMap<String, MaterialData>,
+    /// Units of tool diameters, engagements, and returned feeds
+    unit_system: Units,
 }
 
 impl BlackBook {
     pub fn new() -> Self {
         Self {
             materials: materials::load_material_database(),
+            unit_system: Units::Imperial,
+        }
+    }
+
+    /// Load material overrides from a JSON map of name -> MaterialData,
+    /// merged over the built-in database
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let materials: HashMap<String, MaterialData> = serde_json::from_str(&content)?;
+
+        let mut book = Self::new();
+        book.merge_materials(materials);
+        Ok(book)
+    }
+
+    /// Add or replace materials; entries with an existing key win over the defaults
+    pub fn merge_materials(&mut self, materials: HashMap<String, MaterialData>) {
+        self.materials.extend(materials);
+    }
+
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.unit_system = units;
+        self
+    }
+
+    pub fn set_units(&mut self, units: Units) {
+        self.unit_system = units;
+    }
+
+    /// Find a material by key, then by grade, then by case-insensitive substring
+    ///
+    /// `"6061"`, `"6061-T651"`, and `"Aluminum 6061-T6"` all resolve to the same entry.
+    pub fn resolve_material(&self, name: &str) -> Result<&MaterialData, BlackBookError> {
+        if let Some(material) = self.materials.get(name) {
+            return Ok(material);
+        }
+
+        // Sorted so ambiguous names resolve the same way on every run
+        let mut keys: Vec<&String> = self.materials.keys().collect();
+        keys.sort();
+
+        let query = name.trim().to_lowercase();
+        let by_grade = keys.iter().find(|k| {
+            self.materials[**k]
+                .grades
+                .iter()
+                .any(|g| g.to_lowercase() == query)
+        });
+        let by_substring = || {
+            keys.iter().find(|k| {
+                let material = &self.materials[**k];
+                k.to_lowercase().contains(&query)
+                    || material.grades.iter().any(|g| g.to_lowercase().contains(&query))
+            })
+        };
+
+        match by_grade.or_else(by_substring) {
+            Some(key) if !query.is_empty() => Ok(&self.materials[*key]),
+            _ => Err(BlackBookError::UnknownMaterial(name.to_string())),
         }
     }
 
@@ -100,31 +194,142 @@ impl BlackBook {
         tool: &ToolGeometry,
         engagement: &Engagement,
     ) -> Result<CuttingParameters, BlackBookError> {
-        let material = self
-            .materials
-            .get(material_name)
-            .ok_or(BlackBookError::UnknownMaterial(material_name.to_string()))?;
+        let material = self.resolve_material(material_name)?;
+
+        match self.unit_system {
+            Units::Imperial => calculations::compute_parameters(material, tool, engagement),
+            Units::Metric => {
+                // The SFM and chip-load tables are in inches; convert in and back out
+                let tool_in = ToolGeometry {
+                    diameter: tool.diameter / MM_PER_INCH,
+                    corner_radius: tool.corner_radius.map(|r| r / MM_PER_INCH),
+                    ..tool.clone()
+                };
+                let engagement_in = Engagement {
+                    axial_doc: engagement.axial_doc / MM_PER_INCH,
+                    radial_woc: engagement.radial_woc / MM_PER_INCH,
+                    ..engagement.clone()
+                };
+                calculations::compute_parameters(material, &tool_in, &engagement_in)
+                    .map(CuttingParameters::into_metric)
+            }
+        }
+    }
+
+    /// Recommended RPM, feed, and engagement for a roughing, finishing, or adaptive toolpath
+    pub fn calculate_operation(
+        &self,
+        material_name: &str,
+        tool: &ToolGeometry,
+        operation: OperationType,
+    ) -> Result<RecommendedParameters, BlackBookError> {
+        let material = self.resolve_material(material_name)?;
+
+        Ok(match self.unit_system {
+            Units::Imperial => calculations::calculate_operation_params(material, tool, operation),
+            Units::Metric => {
+                let tool_in = ToolGeometry {
+                    diameter: tool.diameter / MM_PER_INCH,
+                    corner_radius: tool.corner_radius.map(|r| r / MM_PER_INCH),
+                    ..tool.clone()
+                };
+                let params = calculations::calculate_operation_params(material, &tool_in, operation);
+                RecommendedParameters {
+                    feed_rate_ipm: params.feed_rate_ipm * MM_PER_INCH,
+                    doc: params.doc * MM_PER_INCH,
+                    woc: params.woc * MM_PER_INCH,
+                    ..params
+                }
+            }
+        })
+    }
+
+    /// Calculate twist drill RPM and feed for a given drill
+    pub fn calculate_drilling(
+        &self,
+        material_name: &str,
+        tool: &ToolGeometry,
+    ) -> Result<DrillingParameters, BlackBookError> {
+        let material = self.resolve_material(material_name)?;
+
+        match self.unit_system {
+            Units::Imperial => calculations::compute_drilling(material, tool),
+            Units::Metric => {
+                let tool_in = ToolGeometry {
+                    diameter: tool.diameter / MM_PER_INCH,
+                    ..tool.clone()
+                };
+                calculations::compute_drilling(material, &tool_in).map(|params| {
+                    DrillingParameters {
+                        feed_per_rev: params.feed_per_rev * MM_PER_INCH,
+                        feed_rate_ipm: params.feed_rate_ipm * MM_PER_INCH,
+                        ..params
+                    }
+                })
+            }
+        }
+    }
 
-        calculations::compute_parameters(material, tool, engagement)
+    /// Peck style for deep holes: chip-breaking G73 in non-ferrous metals, whose
+    /// chips come out easily, and full-retract G83 everywhere else
+    pub fn suggest_peck_mode(&self, material_name: &str) -> Result<PeckMode, BlackBookError> {
+        let material = self.resolve_material(material_name)?;
+        Ok(match material.category {
+            MaterialCategory::NonFerrous => PeckMode::Break,
+            _ => PeckMode::Clear,
+        })
     }
 
-    /// Get recommended chip load for tool diameter
+    /// Peck depth for deep holes, as a multiple of the drill diameter: long pecks in
+    /// free-cutting non-ferrous metals and plastics, shorter ones where chips are
+    /// stringy or heat builds at the point
+    pub fn peck_factor(&self, material_name: &str) -> Result<f64, BlackBookError> {
+        let material = self.resolve_material(material_name)?;
+        Ok(match material.category {
+            MaterialCategory::NonFerrous | MaterialCategory::Plastic => 1.5,
+            MaterialCategory::SteelLowAlloy | MaterialCategory::CastIron | MaterialCategory::Composite => 1.0,
+            MaterialCategory::SteelHighAlloy => 0.75,
+            MaterialCategory::StainlessAustenitic
+            | MaterialCategory::StainlessMartensitic
+            | MaterialCategory::StainlessPrecipitation
+            | MaterialCategory::Titanium => 0.5,
+            MaterialCategory::HighTempAlloy => 0.4,
+        })
+    }
+
+    /// Share of the cutting feed to plunge at: more in non-ferrous metals and plastics,
+    /// which cut freely, less in alloys that work-harden or hold heat at the tip
+    pub fn plunge_factor(&self, material_name: &str) -> Result<f64, BlackBookError> {
+        let material = self.resolve_material(material_name)?;
+        Ok(match material.category {
+            MaterialCategory::NonFerrous | MaterialCategory::Plastic => 0.5,
+            MaterialCategory::SteelLowAlloy | MaterialCategory::CastIron | MaterialCategory::Composite => 0.3,
+            MaterialCategory::SteelHighAlloy => 0.25,
+            MaterialCategory::StainlessAustenitic
+            | MaterialCategory::StainlessMartensitic
+            | MaterialCategory::StainlessPrecipitation
+            | MaterialCategory::Titanium => 0.2,
+            MaterialCategory::HighTempAlloy => 0.15,
+        })
+    }
+
+    /// Get recommended chip load for tool diameter. Metric diameters are converted to
+    /// inches to pick the table row, and the chip load comes back in mm/tooth.
     pub fn get_chip_load(
         &self,
         material_name: &str,
         tool_diameter: f64,
         tool_material: ToolMaterial,
     ) -> Result<f64, BlackBookError> {
-        let material = self
-            .materials
-            .get(material_name)
-            .ok_or(BlackBookError::UnknownMaterial(material_name.to_string()))?;
-
-        Ok(calculations::lookup_chip_load(
-            material,
-            tool_diameter,
-            tool_material,
-        ))
+        let material = self.resolve_material(material_name)?;
+
+        Ok(match self.unit_system {
+            Units::Imperial => calculations::lookup_chip_load(material, tool_diameter, tool_material),
+            Units::Metric => {
+                calculations::lookup_chip_load(material, tool_diameter / MM_PER_INCH, tool_material)
+                    * MM_PER_INCH
+            }
+        })
     }
 
     /// Get SFM range for material and tool
@@ -133,10 +338,7 @@ impl BlackBook {
         material_name: &str,
         tool_material: ToolMaterial,
     ) -> Result<(f64, f64), BlackBookError> {
-        let material = self
-            .materials
-            .get(material_name)
-            .ok_or(BlackBookError::UnknownMaterial(material_name.to_string()))?;
+        let material = self.resolve_material(material_name)?;
 
         let (min, max, _) = calculations::lookup_sfm(material, tool_material);
         Ok((min, max))
@@ -147,6 +349,14 @@ impl BlackBook {
         self.materials.keys().collect()
     }
 
+    /// Every material keyed by the name the `material` keyword accepts,
+    /// sorted by category then name
+    pub fn catalog(&self) -> Vec<(&String, &MaterialData)> {
+        let mut entries: Vec<_> = self.materials.iter().collect();
+        entries.sort_by(|a, b| a.1.category.cmp(&b.1.category).then_with(|| a.0.cmp(b.0)));
+        entries
+    }
+
     /// Search materials by category
     pub fn materials_by_category(&self, category: MaterialCategory) -> Vec<&MaterialData> {
         self.materials
@@ -213,6 +423,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: Some("TiAlN".to_string()),
+            ball_nose: false,
         };
 
         let engagement = Engagement {
@@ -247,6 +458,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         let engagement = Engagement {
@@ -278,6 +490,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         // Compare high vs low radial engagement
@@ -317,6 +530,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         let engagement = Engagement {
@@ -343,6 +557,7 @@ mod tests {
             tool_material: ToolMaterial::HSS,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         let carbide_tool = ToolGeometry {
@@ -351,6 +566,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         let engagement = Engagement {
@@ -394,4 +610,283 @@ mod tests {
 
         assert!(large > small, "Larger tools should have higher chip loads");
     }
+
+    #[test]
+    fn test_metric_chip_load_uses_inch_rows() {
+        let metric = BlackBook::new().with_units(Units::Metric);
+
+        // A 6mm tool (0.236") reads the 1/4" row, 0.002"/tooth, not the 1/8" row
+        let chip_load = metric
+            .get_chip_load("Aluminum 6061-T6", 6.0, ToolMaterial::Carbide)
+            .unwrap();
+        assert!((chip_load - 0.002 * MM_PER_INCH).abs() < 1e-9, "{}", chip_load);
+    }
+
+    #[test]
+    fn test_plastics_and_composites() {
+        let bb = BlackBook::new();
+        assert!(!bb.materials_by_category(MaterialCategory::Plastic).is_empty());
+        assert!(!bb.materials_by_category(MaterialCategory::Composite).is_empty());
+    }
+
+    #[test]
+    fn test_magnesium_fire_warning() {
+        let bb = BlackBook::new();
+
+        let tool = ToolGeometry {
+            diameter: 0.5,
+            flute_count: 3,
+            tool_material: ToolMaterial::Carbide,
+            corner_radius: None,
+            coating: None,
+            ball_nose: false,
+        };
+
+        let engagement = Engagement {
+            axial_doc: 0.25,
+            radial_woc: 0.2,
+            radial_engagement_pct: 40.0,
+        };
+
+        let params = bb
+            .calculate("Magnesium AZ31B", &tool, &engagement)
+            .expect("Should calculate parameters for magnesium");
+        assert!(params.warnings.iter().any(|w| w.contains("flammable")));
+
+        let params = bb
+            .calculate("Delrin (Acetal)", &tool, &engagement)
+            .expect("Should calculate parameters for Delrin");
+        assert!(!params.warnings.iter().any(|w| w.contains("flammable")));
+    }
+
+    #[test]
+    fn test_drilling_uses_drill_data() {
+        let bb = BlackBook::new();
+
+        let tool = ToolGeometry {
+            diameter: 0.25,
+            flute_count: 2,
+            tool_material: ToolMaterial::Carbide,
+            corner_radius: None,
+            coating: None,
+            ball_nose: false,
+        };
+        let engagement = Engagement {
+            axial_doc: 0.25,
+            radial_woc: 0.125,
+            radial_engagement_pct: 50.0,
+        };
+
+        let milling = bb.calculate("Aluminum 6061-T6", &tool, &engagement).unwrap();
+        let drilling = bb.calculate_drilling("Aluminum 6061-T6", &tool).unwrap();
+
+        assert_ne!(drilling.rpm, milling.rpm);
+        assert_eq!(drilling.rpm, (3.82 * 600.0 / 0.25) as u32);
+        assert!((drilling.feed_rate_ipm - drilling.rpm as f64 * 0.004).abs() < 1e-9);
+
+        // Materials without drill data fall back to derated milling SFM
+        let inconel = bb.calculate_drilling("Inconel 718", &tool).unwrap();
+        assert_eq!(inconel.rpm, (3.82 * 55.0 * 0.7 / 0.25) as u32);
+        assert!(inconel.feed_per_rev > 0.0);
+    }
+
+    #[test]
+    fn test_materials_file_round_trip() {
+        let defaults = BlackBook::new();
+
+        // A shop alloy plus an override of a built-in entry
+        let mut shop = defaults.materials["Aluminum 6061-T6"].clone();
+        shop.name = "Shop Alloy X".to_string();
+        shop.sfm_carbide = (500.0, 900.0, 700.0);
+        let mut tweaked = defaults.materials["Steel 1018"].clone();
+        tweaked.sfm_carbide = (300.0, 500.0, 400.0);
+
+        let mut overrides = HashMap::new();
+        overrides.insert("Shop Alloy X".to_string(), shop);
+        overrides.insert("Steel 1018".to_string(), tweaked);
+
+        let path = std::env::temp_dir().join("swarf_materials_round_trip.json");
+        std::fs::write(&path, serde_json::to_string_pretty(&overrides).unwrap()).unwrap();
+        let bb = BlackBook::from_file(path.to_str().unwrap()).expect("Should load materials file");
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(bb.materials.len(), defaults.materials.len() + 1);
+        assert_eq!(bb.get_sfm_range("Shop Alloy X", ToolMaterial::Carbide).unwrap(), (500.0, 900.0));
+        assert_eq!(bb.get_sfm_range("Steel 1018", ToolMaterial::Carbide).unwrap(), (300.0, 500.0));
+        assert!(bb.materials.contains_key("Aluminum 6061-T6"));
+    }
+
+    #[test]
+    fn test_ball_mill_effective_diameter() {
+        let bb = BlackBook::new();
+        let flat = ToolGeometry {
+            diameter: 0.25,
+            flute_count: 2,
+            tool_material: ToolMaterial::Carbide,
+            corner_radius: None,
+            coating: None,
+            ball_nose: false,
+        };
+        let ball = ToolGeometry {
+            ball_nose: true,
+            ..flat.clone()
+        };
+        let shallow = Engagement {
+            axial_doc: 0.02,
+            radial_woc: 0.01,
+            radial_engagement_pct: 4.0,
+        };
+
+        let flat_params = bb.calculate("Aluminum 6061-T6", &flat, &shallow).unwrap();
+        let ball_params = bb.calculate("Aluminum 6061-T6", &ball, &shallow).unwrap();
+
+        // Deff = 2 * sqrt(0.02 * (0.25 - 0.02)) ≈ 0.1356", so the ball spins ~1.84x faster
+        let d_eff = 2.0 * (0.02_f64 * 0.23).sqrt();
+        assert!((calculations::effective_diameter(&ball, 0.02) - d_eff).abs() < 1e-9);
+        let ratio = ball_params.rpm as f64 / flat_params.rpm as f64;
+        assert!((ratio - 0.25 / d_eff).abs() < 0.01, "RPM ratio {}", ratio);
+        assert!((ball_params.sfm - flat_params.sfm).abs() < 1.0);
+
+        // At or below the ball's center it cuts on the full diameter
+        let deep = Engagement {
+            axial_doc: 0.125,
+            ..shallow
+        };
+        assert_eq!(
+            bb.calculate("Aluminum 6061-T6", &ball, &deep).unwrap().rpm,
+            bb.calculate("Aluminum 6061-T6", &flat, &deep).unwrap().rpm
+        );
+    }
+
+    #[test]
+    fn test_coating_and_corner_radius_adjustments() {
+        let bb = BlackBook::new();
+        let bare = ToolGeometry {
+            diameter: 0.25,
+            flute_count: 3,
+            tool_material: ToolMaterial::Carbide,
+            corner_radius: None,
+            coating: None,
+            ball_nose: false,
+        };
+        let engagement = Engagement {
+            axial_doc: 0.125,
+            radial_woc: 0.0625,
+            radial_engagement_pct: 25.0,
+        };
+        let calc = |tool: &ToolGeometry| bb.calculate("Aluminum 6061-T6", tool, &engagement).unwrap();
+
+        // TiAlN moves carbide onto the coated table; "uncoated" stays bare
+        let coated = ToolGeometry {
+            coating: Some("TiAlN".to_string()),
+            ..bare.clone()
+        };
+        let uncoated = ToolGeometry {
+            coating: Some("uncoated".to_string()),
+            ..bare.clone()
+        };
+        assert!(calc(&coated).rpm > calc(&bare).rpm);
+        assert_eq!(calc(&uncoated).rpm, calc(&bare).rpm);
+
+        let hss = ToolGeometry {
+            tool_material: ToolMaterial::HSS,
+            ..bare.clone()
+        };
+        let hss_tin = ToolGeometry {
+            coating: Some("TiN".to_string()),
+            ..hss.clone()
+        };
+        assert!(calc(&hss_tin).rpm > calc(&hss).rpm);
+
+        // A corner radius raises chip load a little, capped at +10%
+        let radiused = ToolGeometry {
+            corner_radius: Some(0.015),
+            ..bare.clone()
+        };
+        let big_radius = ToolGeometry {
+            corner_radius: Some(0.1),
+            ..bare.clone()
+        };
+        let ratio = calc(&radiused).chip_load_ipt / calc(&bare).chip_load_ipt;
+        assert!((ratio - 1.06).abs() < 1e-9, "chip load ratio {}", ratio);
+        let capped = calc(&big_radius).chip_load_ipt / calc(&bare).chip_load_ipt;
+        assert!((capped - 1.10).abs() < 1e-9, "chip load ratio {}", capped);
+    }
+
+    #[test]
+    fn test_catalog_sorted_by_category_then_name() {
+        let bb = BlackBook::new();
+        let catalog = bb.catalog();
+
+        assert_eq!(catalog.len(), bb.list_materials().len());
+        assert!(catalog.windows(2).all(|w| (w[0].1.category, w[0].0) <= (w[1].1.category, w[1].0)));
+        assert_eq!(catalog[0].1.category, MaterialCategory::NonFerrous);
+        assert_eq!(MaterialCategory::StainlessAustenitic.to_string(), "stainless-austenitic");
+    }
+
+    #[test]
+    fn test_material_aliases() {
+        let bb = BlackBook::new();
+
+        assert_eq!(bb.resolve_material("6061").unwrap().name, "Aluminum 6061-T6");
+        assert_eq!(bb.resolve_material("6061-t651").unwrap().name, "Aluminum 6061-T6");
+        assert_eq!(bb.resolve_material("A36").unwrap().name, "Steel 1018");
+        assert_eq!(bb.resolve_material("delrin").unwrap().name, "Delrin (Acetal)");
+
+        assert_eq!(
+            bb.resolve_material("Unobtainium").unwrap_err(),
+            BlackBookError::UnknownMaterial("Unobtainium".to_string())
+        );
+    }
+
+    #[test]
+    fn test_metric_matches_imperial() {
+        let imperial = BlackBook::new();
+        let metric = BlackBook::new().with_units(Units::Metric);
+
+        let tool_in = ToolGeometry {
+            diameter: 0.25,
+            flute_count: 3,
+            tool_material: ToolMaterial::Carbide,
+            corner_radius: None,
+            coating: None,
+            ball_nose: false,
+        };
+        let tool_mm = ToolGeometry {
+            diameter: 6.35,
+            ..tool_in.clone()
+        };
+
+        let engagement_in = Engagement {
+            axial_doc: 0.125,
+            radial_woc: 0.0625,
+            radial_engagement_pct: 25.0,
+        };
+        let engagement_mm = Engagement {
+            axial_doc: 3.175,
+            radial_woc: 1.5875,
+            radial_engagement_pct: 25.0,
+        };
+
+        let inch = imperial
+            .calculate("Aluminum 6061-T6", &tool_in, &engagement_in)
+            .unwrap();
+        let mm = metric
+            .calculate("Aluminum 6061-T6", &tool_mm, &engagement_mm)
+            .unwrap();
+
+        assert_eq!(inch.rpm, mm.rpm, "1/4\" and 6.35mm tools should run the same RPM");
+        assert!((mm.feed_rate_ipm - inch.feed_rate_ipm * 25.4).abs() < 1e-6); // mm/min
+        assert!((mm.chip_load_ipt - inch.chip_load_ipt * 25.4).abs() < 1e-9);
+
+        let inch = imperial
+            .calculate_operation("Aluminum 6061-T6", &tool_in, OperationType::Adaptive)
+            .unwrap();
+        let mm = metric
+            .calculate_operation("Aluminum 6061-T6", &tool_mm, OperationType::Adaptive)
+            .unwrap();
+        assert_eq!(inch.rpm, mm.rpm);
+        assert!((mm.woc - inch.woc * 25.4).abs() < 1e-9);
+        assert!((mm.doc - inch.doc * 25.4).abs() < 1e-9);
+    }
 }
@@ -72,41 +72,62 @@ pub fn validate_parameters(
         });
     }
 
-    // Material-specific checks
+    // Material-specific checks. Plastics melt below about half the book chip load,
+    // where the edge rubs instead of cutting
+    let melt_chip_load =
+        calculations::lookup_chip_load(material, tool.diameter, tool.tool_material) * 0.5;
+    let light_chip = params.chip_load_ipt < melt_chip_load;
     match material.category {
-        MaterialCategory::StainlessAustenitic => {
-            if params.feed_rate_ipm < tool.diameter * 20.0 {
-                issues.push(ValidationIssue {
-                    severity: Severity::Error,
-                    code: "WORK_HARDENING_RISK".to_string(),
-                    message: "Low feed rate may cause work hardening in austenitic stainless"
-                        .to_string(),
-                    suggestion: Some(format!(
-                        "Increase feed to at least {:.1} IPM to stay ahead of hardening front",
-                        tool.diameter * 30.0
-                    )),
-                });
-            }
+        MaterialCategory::StainlessAustenitic if params.feed_rate_ipm < tool.diameter * 20.0 => {
+            issues.push(ValidationIssue {
+                severity: Severity::Error,
+                code: "WORK_HARDENING_RISK".to_string(),
+                message: "Low feed rate may cause work hardening in austenitic stainless"
+                    .to_string(),
+                suggestion: Some(format!(
+                    "Increase feed to at least {:.1} IPM to stay ahead of hardening front",
+                    tool.diameter * 30.0
+                )),
+            });
         }
-        MaterialCategory::Titanium => {
-            if params.sfm > 150.0 {
-                issues.push(ValidationIssue {
-                    severity: Severity::Warning,
-                    code: "TITANIUM_HEAT".to_string(),
-                    message: "High SFM generates excessive heat in titanium".to_string(),
-                    suggestion: Some("Reduce SFM below 150, ensure flood coolant".to_string()),
-                });
-            }
+        // Only a single flute clears the chip fast enough to carry the heat away
+        MaterialCategory::Plastic if light_chip || tool.flute_count > 1 => {
+            let message = if light_chip {
+                format!(
+                    "Chip load {:.4}\" is too light for {} - the cutter rubs and melts the chip",
+                    params.chip_load_ipt, material.name
+                )
+            } else {
+                format!(
+                    "{} flutes pack melted chips in {}",
+                    tool.flute_count, material.name
+                )
+            };
+            issues.push(ValidationIssue {
+                severity: Severity::Warning,
+                code: "MELT_RISK".to_string(),
+                message,
+                suggestion: Some(format!(
+                    "Use a single-flute O-flute cutter and feed at least {:.1} IPM",
+                    melt_chip_load * 2.0 * params.rpm as f64
+                )),
+            });
         }
-        MaterialCategory::HighTempAlloy => {
-            if params.doc > tool.diameter * 0.2 {
-                issues.push(ValidationIssue {
-                    severity: Severity::Warning,
-                    code: "NICKEL_ALLOY_DOC".to_string(),
-                    message: "Deep cuts cause rapid tool wear in nickel alloys".to_string(),
-                    suggestion: Some("Use multiple shallow passes".to_string()),
-                });
-            }
+        MaterialCategory::Titanium if params.sfm > 150.0 => {
+            issues.push(ValidationIssue {
+                severity: Severity::Warning,
+                code: "TITANIUM_HEAT".to_string(),
+                message: "High SFM generates excessive heat in titanium".to_string(),
+                suggestion: Some("Reduce SFM below 150, ensure flood coolant".to_string()),
+            });
+        }
+        MaterialCategory::HighTempAlloy if params.doc > tool.diameter * 0.2 => {
+            issues.push(ValidationIssue {
+                severity: Severity::Warning,
+                code: "NICKEL_ALLOY_DOC".to_string(),
+                message: "Deep cuts cause rapid tool wear in nickel alloys".to_string(),
+                suggestion: Some("Use multiple shallow passes".to_string()),
+            });
         }
         _ => {}
     }
@@ -214,6 +235,50 @@ pub fn check_safety_limits(
     issues
 }
 
+/// Spindle and axis limits of the machine a program runs on, loaded from JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MachineProfile {
+    pub max_rpm: u32,
+    pub max_feed: f64, // inches per minute
+    pub max_hp: f64,
+    /// Base speed of a constant-power spindle; below it torque is the limit
+    #[serde(default)]
+    pub low_torque_rpm: Option<u32>,
+}
+
+impl MachineProfile {
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Horsepower the spindle delivers at `rpm`. Under the base speed a constant-power
+    /// spindle runs at constant torque, so power falls off in proportion to speed.
+    pub fn available_hp(&self, rpm: u32) -> f64 {
+        match self.low_torque_rpm {
+            Some(base) if rpm < base => self.max_hp * rpm as f64 / base as f64,
+            _ => self.max_hp,
+        }
+    }
+
+    /// Safety limits for `params` (inch units), with power derated at low RPM
+    pub fn check(&self, params: &CuttingParameters) -> Vec<ValidationIssue> {
+        let available_hp = self.available_hp(params.rpm);
+        let mut issues = check_safety_limits(params, self.max_rpm, self.max_feed, available_hp);
+        if available_hp < self.max_hp {
+            for issue in issues.iter_mut().filter(|i| i.code == "MACHINE_HP_LIMIT") {
+                issue.message.push_str(&format!(
+                    " at {} RPM (below {} RPM base speed)",
+                    params.rpm,
+                    self.low_torque_rpm.unwrap_or_default()
+                ));
+                issue.suggestion = Some("Raise RPM toward base speed or take lighter passes".to_string());
+            }
+        }
+        issues
+    }
+}
+
 /// Check tool life estimates
 pub fn estimate_tool_life(
     material: &MaterialData,
@@ -222,24 +287,19 @@ pub fn estimate_tool_life(
     tool_material: ToolMaterial,
 ) -> ToolLifeEstimate {
     // Very rough estimation based on Taylor's Tool Life Equation
-    // VT^n = C
-
-    let n = 0.25; // Exponent for carbide
-    let c = match tool_material {
-        ToolMaterial::HSS => 80.0,
-        ToolMaterial::Cobalt => 120.0,
-        ToolMaterial::Carbide => 400.0,
-        ToolMaterial::CoatedCarbide => 600.0,
-        ToolMaterial::Ceramic => 2000.0,
-        ToolMaterial::CBN => 3000.0,
-        ToolMaterial::Diamond => 5000.0,
+    // VT^n = C, anchored so the recommended SFM for the material and tool
+    // lasts REFERENCE_TOOL_LIFE_MINUTES
+
+    let n = match tool_material {
+        ToolMaterial::HSS => 0.125,
+        ToolMaterial::Cobalt => 0.15,
+        ToolMaterial::Carbide | ToolMaterial::CoatedCarbide | ToolMaterial::Diamond => 0.25,
+        ToolMaterial::Ceramic | ToolMaterial::CBN => 0.5,
     };
-
-    // Adjust C for material machinability
-    let adjusted_c = c * (material.machinability_rating / 100.0);
+    let (_, _, sfm_rec) = lookup_sfm(material, tool_material);
 
     // Minutes of tool life
-    let life_minutes = (adjusted_c / sfm).powf(1.0 / n);
+    let life_minutes = REFERENCE_TOOL_LIFE_MINUTES * (sfm_rec / sfm).powf(1.0 / n);
 
     // Adjust for chip load (aggressive chip load reduces life)
     let chip_load_factor = 1.0 / (1.0 + (chip_load / 0.01) * 0.1);
@@ -260,6 +320,9 @@ pub fn estimate_tool_life(
     }
 }
 
+/// Minutes a tool lasts at the recommended SFM, a typical planning figure for end mills
+const REFERENCE_TOOL_LIFE_MINUTES: f64 = 45.0;
+
 #[derive(Debug, Clone)]
 pub struct ToolLifeEstimate {
     pub estimated_minutes: f64,
@@ -284,6 +347,7 @@ mod tests {
             tool_material: ToolMaterial::Carbide,
             corner_radius: None,
             coating: None,
+            ball_nose: false,
         };
 
         // Create parameters with low feed (will cause work hardening warning)
@@ -305,6 +369,67 @@ mod tests {
         assert!(issues.iter().any(|i| i.code == "WORK_HARDENING_RISK"));
     }
 
+    #[test]
+    fn test_plastic_melt_risk() {
+        let db = load_material_database();
+        let acrylic = db.get("Acrylic (PMMA)").unwrap();
+        let tool = |flute_count| ToolGeometry {
+            diameter: 0.25,
+            flute_count,
+            tool_material: ToolMaterial::Carbide,
+            corner_radius: None,
+            coating: None,
+            ball_nose: false,
+        };
+        let params = |feed_rate_ipm: f64, flutes: u8| CuttingParameters {
+            rpm: 10000,
+            feed_rate_ipm,
+            chip_load_ipt: feed_rate_ipm / (10000.0 * flutes as f64),
+            sfm: 654.0,
+            doc: 0.1,
+            woc: 0.1,
+            hp_required: 0.1,
+            material_removal_rate: 0.1,
+            warnings: vec![],
+        };
+        let melt = |feed, flutes| {
+            validate_parameters(&params(feed, flutes), acrylic, &tool(flutes))
+                .into_iter()
+                .find(|i| i.code == "MELT_RISK")
+        };
+
+        // 10 IPM on two flutes is 0.0005" a tooth against a 0.003" book chip
+        let issue = melt(10.0, 2).expect("light feed should melt acrylic");
+        assert_eq!(issue.severity, Severity::Warning);
+        assert!(issue.message.contains("0.0005"), "{}", issue.message);
+        assert!(issue.suggestion.unwrap().contains("O-flute"));
+
+        // A full chip on a single flute is fine; a second flute packs chips even at full feed
+        assert!(melt(40.0, 1).is_none());
+        let issue = melt(80.0, 2).expect("two flutes should melt acrylic");
+        assert!(issue.message.contains("2 flutes pack melted chips"), "{}", issue.message);
+        assert!(melt(120.0, 3).is_some());
+    }
+
+    #[test]
+    fn test_tool_life_anchored_on_recommended_sfm() {
+        let db = load_material_database();
+        let aluminum = db.get("Aluminum 6061-T6").unwrap();
+        let minutes = |sfm, chip_load, tool_material| {
+            estimate_tool_life(aluminum, sfm, chip_load, tool_material).estimated_minutes
+        };
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        // The book's 1200 SFM for carbide lasts the reference 45 minutes
+        assert!(close(minutes(1200.0, 0.0, ToolMaterial::Carbide), 45.0));
+        // A 0.01" chip takes a tenth off
+        assert!(close(minutes(1200.0, 0.01, ToolMaterial::Carbide), 45.0 / 1.1));
+        // Life goes with (book / actual)^(1/n): n = 0.25 for carbide, 0.125 for HSS
+        assert!(close(minutes(2400.0, 0.0, ToolMaterial::Carbide), 45.0 / 16.0));
+        assert!(close(minutes(600.0, 0.0, ToolMaterial::Carbide), 45.0 * 16.0));
+        assert!(close(minutes(900.0, 0.0, ToolMaterial::HSS), 45.0 / 256.0));
+    }
+
     #[test]
     fn test_rpm_limits() {
         assert_eq!(get_max_rpm_for_diameter(0.125), 30000);
@@ -334,6 +459,34 @@ mod tests {
         assert!(issues.iter().any(|i| i.code == "MACHINE_HP_LIMIT"));
     }
 
+    #[test]
+    fn test_machine_profile_derates_low_rpm() {
+        let machine: MachineProfile = serde_json::from_str(
+            r#"{ "max_rpm": 10000, "max_feed": 200.0, "max_hp": 3.0, "low_torque_rpm": 4000 }"#,
+        )
+        .unwrap();
+        assert_eq!(machine.available_hp(8000), 3.0);
+        assert!((machine.available_hp(1000) - 0.75).abs() < 1e-9);
+
+        // 1 HP is fine at speed but too much for the spindle at 1000 RPM
+        let params = |rpm| CuttingParameters {
+            rpm,
+            feed_rate_ipm: 20.0,
+            chip_load_ipt: 0.002,
+            sfm: 300.0,
+            doc: 0.25,
+            woc: 0.2,
+            hp_required: 1.0,
+            material_removal_rate: 1.0,
+            warnings: vec![],
+        };
+        assert!(machine.check(&params(8000)).is_empty());
+        let issues = machine.check(&params(1000));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].code, "MACHINE_HP_LIMIT");
+        assert!(issues[0].message.contains("below 4000 RPM base speed"));
+    }
+
     #[test]
     fn test_tool_life_estimate() {
         let db = load_material_database();
@@ -0,0 +1,90 @@
+//! Closed polyline contours - the outlines of polygon and path geometry,
+//! offset for the tool and sliced into rows for pocketing
+
+use crate::ast::Position;
+
+/// Contour points counter-clockwise, dropping repeated points and a closing point
+/// that returns to the start
+pub fn closed_ccw(points: &[Position]) -> Vec<(f64, f64)> {
+    let mut pts: Vec<(f64, f64)> = Vec::with_capacity(points.len());
+    for p in points {
+        if pts.last() != Some(&(p.x, p.y)) {
+            pts.push((p.x, p.y));
+        }
+    }
+    if pts.len() > 1 && pts.first() == pts.last() {
+        pts.pop();
+    }
+    if signed_area(&pts) < 0.0 {
+        pts.reverse();
+    }
+    pts
+}
+
+/// Shoelace area, positive when the points run counter-clockwise
+pub fn signed_area(pts: &[(f64, f64)]) -> f64 {
+    let n = pts.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (pts[i], pts[(i + 1) % n]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Unit normal pointing out of a counter-clockwise contour along the edge from `a` to `b`
+fn outward_normal(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let len = (b.0 - a.0).hypot(b.1 - a.1);
+    ((b.1 - a.1) / len, -(b.0 - a.0) / len)
+}
+
+/// Counter-clockwise contour grown outward by `distance`, or shrunk when negative.
+/// Each corner moves to where its two shifted edges meet, so corners stay sharp.
+pub fn offset(pts: &[(f64, f64)], distance: f64) -> Vec<(f64, f64)> {
+    let n = pts.len();
+    (0..n)
+        .map(|i| {
+            let p = pts[i];
+            let n1 = outward_normal(pts[(i + n - 1) % n], p);
+            let n2 = outward_normal(p, pts[(i + 1) % n]);
+            let miter = 1.0 + n1.0 * n2.0 + n1.1 * n2.1;
+            if miter < 1e-9 {
+                // The edge doubles back on itself; push straight off it
+                return (p.0 + n1.0 * distance, p.1 + n1.1 * distance);
+            }
+            let scale = distance / miter;
+            (p.0 + (n1.0 + n2.0) * scale, p.1 + (n1.1 + n2.1) * scale)
+        })
+        .collect()
+}
+
+/// Whether shrinking `original` into `inset` turned any edge around,
+/// meaning the tool no longer fits somewhere in the contour
+pub fn collapsed(original: &[(f64, f64)], inset: &[(f64, f64)]) -> bool {
+    let n = original.len();
+    n < 3
+        || (0..n).any(|i| {
+            let (a, b) = (original[i], original[(i + 1) % n]);
+            let (c, d) = (inset[i], inset[(i + 1) % n]);
+            (b.0 - a.0) * (d.0 - c.0) + (b.1 - a.1) * (d.1 - c.1) <= 0.0
+        })
+}
+
+/// X spans, left to right, where the horizontal line at `y` lies inside the contour
+pub fn spans_at(pts: &[(f64, f64)], y: f64) -> Vec<(f64, f64)> {
+    let n = pts.len();
+    let mut xs: Vec<f64> = (0..n)
+        .filter_map(|i| {
+            let (a, b) = (pts[i], pts[(i + 1) % n]);
+            // Half-open so a vertex on the line is counted once
+            if (a.1 <= y) != (b.1 <= y) {
+                Some(a.0 + (y - a.1) * (b.0 - a.0) / (b.1 - a.1))
+            } else {
+                None
+            }
+        })
+        .collect();
+    xs.sort_by(f64::total_cmp);
+    xs.chunks_exact(2).map(|span| (span[0], span[1])).collect()
+}
@@ -0,0 +1,271 @@
+//! Work datum - maps DSL coordinates, measured from the setup's zero, onto the
+//! stock frame the G-code runs in: X0 Y0 at the stock's left front corner, Z0 on top
+
+use crate::ast::*;
+
+/// Where the setup's zero sits on the stock. DSL positions are distances from it
+/// into the stock, so from a right or back zero positive X or Y runs left or forward.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Datum {
+    origin_x: f64,
+    origin_y: f64,
+    origin_z: f64,
+    flip_x: bool,
+    flip_y: bool,
+}
+
+impl Datum {
+    pub fn new(zero: &ZeroConfig, stock: &StockDef) -> Self {
+        let (origin_x, flip_x) = match zero.x_ref {
+            XRef::Left => (0.0, false),
+            XRef::Right => (stock.size_x, true),
+            XRef::Center => (stock.size_x / 2.0, false),
+            XRef::Value(x) => (x, false),
+        };
+        let (origin_y, flip_y) = match zero.y_ref {
+            YRef::Front => (0.0, false),
+            YRef::Back => (stock.size_y, true),
+            YRef::Center => (stock.size_y / 2.0, false),
+            YRef::Value(y) => (y, false),
+        };
+        let origin_z = match zero.z_ref {
+            ZRef::Top => 0.0,
+            ZRef::Bottom => -stock.size_z,
+            ZRef::Center => -stock.size_z / 2.0,
+            ZRef::Value(z) => z,
+        };
+
+        Self {
+            origin_x,
+            origin_y,
+            origin_z,
+            flip_x,
+            flip_y,
+        }
+    }
+
+    /// Left front top zero: DSL and stock coordinates agree
+    pub fn is_identity(&self) -> bool {
+        !self.flip_x
+            && !self.flip_y
+            && self.origin_x == 0.0
+            && self.origin_y == 0.0
+            && self.origin_z == 0.0
+    }
+
+    pub fn position(&self, p: Position) -> Position {
+        Position::new(self.x(p.x), self.y(p.y))
+    }
+
+    /// Absolute Z, such as the setup's z-min
+    pub fn z(&self, z: f64) -> f64 {
+        self.origin_z + z
+    }
+
+    fn x(&self, x: f64) -> f64 {
+        if self.flip_x {
+            self.origin_x - x
+        } else {
+            self.origin_x + x
+        }
+    }
+
+    fn y(&self, y: f64) -> f64 {
+        if self.flip_y {
+            self.origin_y - y
+        } else {
+            self.origin_y + y
+        }
+    }
+
+    /// Angle in degrees from +X, mirrored with the flipped axes
+    fn angle(&self, degrees: f64) -> f64 {
+        match (self.flip_x, self.flip_y) {
+            (false, false) => degrees,
+            (true, false) => 180.0 - degrees,
+            (false, true) => -degrees,
+            (true, true) => degrees + 180.0,
+        }
+    }
+
+    fn direction(&self, direction: Direction) -> Direction {
+        match direction {
+            Direction::XPositive if self.flip_x => Direction::XNegative,
+            Direction::XNegative if self.flip_x => Direction::XPositive,
+            Direction::YPositive if self.flip_y => Direction::YNegative,
+            Direction::YNegative if self.flip_y => Direction::YPositive,
+            other => other,
+        }
+    }
+
+    /// A cut or clear sweep width, negative where the axis it runs across is flipped
+    fn sweep(&self, direction: Direction, sweep: f64) -> f64 {
+        let flipped = match direction {
+            Direction::XPositive | Direction::XNegative => self.flip_y,
+            Direction::YPositive | Direction::YNegative => self.flip_x,
+            Direction::ZPositive | Direction::ZNegative => false,
+        };
+        if flipped {
+            -sweep
+        } else {
+            sweep
+        }
+    }
+
+    fn rectangle(&self, r: &Rectangle) -> Rectangle {
+        // A flipped axis turns the far corner into the bottom left one
+        let corner = self.position(r.bottom_left);
+        Rectangle {
+            bottom_left: Position::new(
+                if self.flip_x { corner.x - r.width } else { corner.x },
+                if self.flip_y { corner.y - r.height } else { corner.y },
+            ),
+            rotation: if self.flip_x != self.flip_y { -r.rotation } else { r.rotation },
+            ..r.clone()
+        }
+    }
+
+    fn geometry(&self, geometry: &Geometry) -> Geometry {
+        match geometry {
+            Geometry::Rect(r) => Geometry::Rect(self.rectangle(r)),
+            Geometry::Circle(c) => Geometry::Circle(Circle {
+                center: self.position(c.center),
+                ..c.clone()
+            }),
+            Geometry::Polygon(p) => Geometry::Polygon(Polygon {
+                center: self.position(p.center),
+                rotation: self.angle(p.rotation),
+                ..p.clone()
+            }),
+            Geometry::Path(points) => {
+                Geometry::Path(points.iter().map(|p| self.position(*p)).collect())
+            }
+        }
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> Pattern {
+        match pattern {
+            Pattern::Grid {
+                rows,
+                cols,
+                spacing_x,
+                spacing_y,
+                start_position,
+            } => Pattern::Grid {
+                rows: *rows,
+                cols: *cols,
+                spacing_x: if self.flip_x { -spacing_x } else { *spacing_x },
+                spacing_y: if self.flip_y { -spacing_y } else { *spacing_y },
+                start_position: self.position(*start_position),
+            },
+            Pattern::BoltCircle {
+                count,
+                diameter,
+                center,
+                start_angle,
+            } => Pattern::BoltCircle {
+                count: *count,
+                diameter: *diameter,
+                center: self.position(*center),
+                start_angle: self.angle(*start_angle),
+            },
+            Pattern::Line {
+                count,
+                spacing,
+                direction,
+                start_position,
+            } => Pattern::Line {
+                count: *count,
+                spacing: *spacing,
+                direction: self.direction(*direction),
+                start_position: self.position(*start_position),
+            },
+            Pattern::Arc {
+                count,
+                radius,
+                center,
+                start_angle,
+                end_angle,
+            } => Pattern::Arc {
+                count: *count,
+                radius: *radius,
+                center: self.position(*center),
+                start_angle: self.angle(*start_angle),
+                end_angle: self.angle(*end_angle),
+            },
+        }
+    }
+
+    /// The operation with every XY position on the stock frame. Cut and clear start
+    /// at the work zero and run the way its axes do; everything positionless passes through.
+    pub fn operation(&self, op: &Operation) -> Operation {
+        let positions = |ps: &[Position]| ps.iter().map(|p| self.position(*p)).collect();
+
+        match op {
+            Operation::Drill(d) => Operation::Drill(DrillOp {
+                positions: positions(&d.positions),
+                ..d.clone()
+            }),
+            Operation::Tap(t) => Operation::Tap(TapOp {
+                positions: positions(&t.positions),
+                ..t.clone()
+            }),
+            Operation::Pocket(p) => Operation::Pocket(PocketOp {
+                geometry: self.geometry(&p.geometry),
+                ..p.clone()
+            }),
+            Operation::Profile(p) => Operation::Profile(ProfileOp {
+                geometry: self.geometry(&p.geometry),
+                ..p.clone()
+            }),
+            Operation::Face(f) => Operation::Face(FaceOp {
+                bounds: self.rectangle(&f.bounds),
+                ..f.clone()
+            }),
+            Operation::FaceV2(f) => Operation::FaceV2(FaceV2Op {
+                position: match f.position {
+                    FacePosition::At(x, y) => FacePosition::At(self.x(x), self.y(y)),
+                    FacePosition::Stock => FacePosition::Stock,
+                },
+                ..f.clone()
+            }),
+            Operation::DrillV2(d) => Operation::DrillV2(DrillV2Op {
+                position: self.position(d.position),
+                ..d.clone()
+            }),
+            Operation::PocketV2(p) => Operation::PocketV2(PocketV2Op {
+                position: self.position(p.position),
+                ..p.clone()
+            }),
+            Operation::DrillPattern(d) => Operation::DrillPattern(DrillPatternOp {
+                pattern: self.pattern(&d.pattern),
+                ..d.clone()
+            }),
+            Operation::PocketPattern(p) => Operation::PocketPattern(PocketPatternOp {
+                pattern: self.pattern(&p.pattern),
+                ..p.clone()
+            }),
+            Operation::Chamfer(c) => Operation::Chamfer(ChamferOp {
+                position: self.position(c.position),
+                ..c.clone()
+            }),
+            Operation::Deburr(d) => Operation::Deburr(DeburrOp {
+                position: self.position(d.position),
+                ..d.clone()
+            }),
+            Operation::Cut(c) => Operation::Cut(CutOp {
+                direction: self.direction(c.direction),
+                sweep: self.sweep(c.direction, c.sweep),
+                origin: self.position(c.origin),
+                ..c.clone()
+            }),
+            Operation::Clear(c) => Operation::Clear(ClearOp {
+                direction: self.direction(c.direction),
+                sweep: self.sweep(c.direction, c.sweep),
+                origin: self.position(c.origin),
+                ..c.clone()
+            }),
+            other => other.clone(),
+        }
+    }
+}
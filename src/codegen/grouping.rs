@@ -0,0 +1,55 @@
+//! Tool grouping - folds later visits of a tool into its first one, so a program
+//! that goes back and forth between tools loads each of them fewer times
+
+use crate::ast::*;
+
+/// Statements whose effect carries into every cut after them
+fn sets_state(op: &Operation) -> bool {
+    matches!(
+        op,
+        Operation::Setup(_)
+            | Operation::StockDef(_)
+            | Operation::PartDef(_)
+            | Operation::Spindle(_)
+            | Operation::Coolant(_)
+    )
+}
+
+/// Operations with each tool's later runs appended to its earlier run, and the number
+/// of tool changes dropped. A run (a tool change and the operations up to the next one)
+/// only moves up to a run loading an identical tool, and never past a run that sets
+/// state, nor when it sets state itself, so every cut keeps the speeds, coolant, and
+/// setup it was written under.
+pub fn group_by_tool(operations: &[Operation]) -> (Vec<Operation>, usize) {
+    // Operations ahead of the first tool change stay where they are
+    let mut runs: Vec<(Option<ToolChange>, Vec<Operation>)> = vec![(None, Vec::new())];
+    for op in operations {
+        match op {
+            Operation::ToolChange(tc) => runs.push((Some(tc.clone()), Vec::new())),
+            other => runs.last_mut().unwrap().1.push(other.clone()),
+        }
+    }
+
+    let mut grouped: Vec<(Option<ToolChange>, Vec<Operation>)> = Vec::new();
+    let mut saved = 0;
+    for (tool, ops) in runs {
+        if tool.is_some() && !ops.iter().any(sets_state) {
+            let earlier = grouped
+                .iter()
+                .rposition(|(t, o)| *t == tool || t.is_none() || o.iter().any(sets_state))
+                .filter(|&i| grouped[i].0 == tool);
+            if let Some(i) = earlier {
+                grouped[i].1.extend(ops);
+                saved += 1;
+                continue;
+            }
+        }
+        grouped.push((tool, ops));
+    }
+
+    let operations = grouped
+        .into_iter()
+        .flat_map(|(tool, ops)| tool.map(Operation::ToolChange).into_iter().chain(ops))
+        .collect();
+    (operations, saved)
+}
@@ -1,52 +1,764 @@
 //! G-code generator
 //! Converts AST into validated G-code output
 
-use crate::ast::*;
-use crate::black_book::{BlackBook, Engagement, ToolGeometry};
+mod contour;
+pub mod datum;
+mod grouping;
+pub mod simulate;
+
+use std::borrow::Cow;
+use std::fmt::Write;
 
-#[derive(Debug)]
+use crate::ast::*;
+use crate::black_book::validators::{estimate_tool_life, validate_parameters, Severity, ValidationIssue};
+use crate::black_book::{
+    BlackBook, CuttingParameters, Engagement, MachineProfile, OperationType, ToolGeometry,
+};
+use datum::Datum;
+
+/// Generated program text. Lines are written straight into one buffer, each
+/// terminated by a newline, so emitting never allocates a string per line.
+#[derive(Debug, Clone)]
 pub struct GCodeOutput {
-    pub lines: Vec<String>,
+    buf: String,
     pub line_number: u32,
     pub step: u32,
 }
 
 impl GCodeOutput {
     pub fn new() -> Self {
+        Self::numbered_from(10, 10)
+    }
+
+    /// Empty output whose next `emit` is numbered `line_number`, counting by `step`
+    pub fn numbered_from(line_number: u32, step: u32) -> Self {
         Self {
-            lines: Vec::new(),
-            line_number: 10,
-            step: 10,
+            buf: String::new(),
+            line_number,
+            step,
         }
     }
 
     pub fn emit(&mut self, code: &str) {
-        self.lines
-            .push(format!("N{:04} {}", self.line_number, code));
+        // Writing into a String cannot fail
+        let _ = writeln!(self.buf, "N{:04} {}", self.line_number, code);
         self.line_number += self.step;
     }
 
     pub fn emit_comment(&mut self, comment: &str) {
-        self.lines.push(format!("; {}", comment));
+        let _ = writeln!(self.buf, "; {}", comment);
+    }
+
+    /// Append a line exactly as given, without a line number
+    pub fn push_line(&mut self, line: &str) {
+        self.buf.push_str(line);
+        self.buf.push('\n');
+    }
+
+    /// Insert a comment so it becomes line `index` (0-based)
+    pub fn insert_comment(&mut self, index: usize, comment: &str) {
+        let at = match index {
+            0 => 0,
+            _ => self
+                .buf
+                .match_indices('\n')
+                .nth(index - 1)
+                .map_or(self.buf.len(), |(i, _)| i + 1),
+        };
+        self.buf.insert_str(at, &format!("; {}\n", comment));
+    }
+
+    pub fn lines(&self) -> std::str::Lines<'_> {
+        self.buf.lines()
+    }
+
+    /// The program text, without a trailing newline
+    pub fn as_str(&self) -> &str {
+        self.buf.strip_suffix('\n').unwrap_or(&self.buf)
+    }
+
+    pub fn into_string(mut self) -> String {
+        if self.buf.ends_with('\n') {
+            self.buf.pop();
+        }
+        self.buf
+    }
+
+    /// Re-emit the program with every G02/G03 replaced by G01 chords, for
+    /// controls that can't interpolate arcs. Assumes absolute XY and I/J centers.
+    pub fn linearize_arcs(&self) -> GCodeOutput {
+        use crate::post::extract_word;
+
+        let mut out = GCodeOutput::numbered_from(10, self.step);
+        let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+
+        for line in self.lines() {
+            if let Some(comment) = line.strip_prefix("; ") {
+                out.emit_comment(comment);
+                continue;
+            }
+            let code = match line.split_once(' ') {
+                Some((n, rest)) if n.starts_with('N') => rest,
+                _ => line,
+            };
+
+            let words: Vec<&str> = code.split_whitespace().collect();
+            let clockwise = words.contains(&"G02");
+            if clockwise || words.contains(&"G03") {
+                let end_x = extract_word(code, 'X').unwrap_or(x);
+                let end_y = extract_word(code, 'Y').unwrap_or(y);
+                let end_z = extract_word(code, 'Z').unwrap_or(z);
+                let center_x = x + extract_word(code, 'I').unwrap_or(0.0);
+                let center_y = y + extract_word(code, 'J').unwrap_or(0.0);
+                let feed = extract_word(code, 'F');
+
+                let radius = (x - center_x).hypot(y - center_y);
+                let start_angle = (y - center_y).atan2(x - center_x);
+                let sweep = arc_sweep((x, y), (end_x, end_y), (center_x, center_y), clockwise);
+                let tau = 2.0 * std::f64::consts::PI;
+                let segments = ((sweep / tau) * ARC_SEGMENTS_PER_REV as f64).ceil().max(1.0) as usize;
+
+                for i in 1..=segments {
+                    let t = i as f64 / segments as f64;
+                    let angle = if clockwise {
+                        start_angle - sweep * t
+                    } else {
+                        start_angle + sweep * t
+                    };
+                    let (px, py) = if i == segments {
+                        (end_x, end_y)
+                    } else {
+                        (center_x + radius * angle.cos(), center_y + radius * angle.sin())
+                    };
+                    let mut chord = format!("G01 X{:.4} Y{:.4}", px, py);
+                    if end_z != z {
+                        chord.push_str(&format!(" Z{:.4}", z + (end_z - z) * t));
+                    }
+                    if let Some(f) = feed {
+                        chord.push_str(&format!(" F{:.1}", f));
+                    }
+                    out.emit(&chord);
+                }
+            } else {
+                out.emit(code);
+            }
+
+            x = extract_word(code, 'X').unwrap_or(x);
+            y = extract_word(code, 'Y').unwrap_or(y);
+            z = extract_word(code, 'Z').unwrap_or(z);
+        }
+
+        out
+    }
+
+    /// Re-emit the program in G91 incremental moves. An axis is written absolute
+    /// until a move fixes its position, at the start and after each tool change;
+    /// canned cycles run in G90 with their hole's XY written out; and G90 is
+    /// restored before the end code. Each delta keeps its word's decimals.
+    pub fn to_incremental(&self) -> GCodeOutput {
+        use crate::post::extract_word;
+
+        let mut out = GCodeOutput::numbered_from(10, self.step);
+        // Absolute X, Y, Z where known, with the words' decimals
+        let mut position: [Option<(f64, usize)>; 3] = [None; 3];
+        let mut incremental = false;
+        let mut in_cycle = false;
+
+        for line in self.lines() {
+            if let Some(comment) = line.strip_prefix("; ") {
+                out.emit_comment(comment);
+                continue;
+            }
+            let block = match line.split_once(' ') {
+                Some((n, rest)) if n.starts_with('N') => rest,
+                _ => {
+                    out.push_line(line);
+                    continue;
+                }
+            };
+            let (code, comment) = match block.find(';') {
+                Some(i) => (block[..i].trim_end(), &block[i..]),
+                None => (block, ""),
+            };
+            let words: Vec<&str> = code.split_whitespace().collect();
+            let has = |w: &str| words.contains(&w);
+            let mut set_mode = |out: &mut GCodeOutput, to_incremental: bool| {
+                if incremental != to_incremental {
+                    out.emit(if to_incremental { "G91" } else { "G90" });
+                    incremental = to_incremental;
+                }
+            };
+
+            if has("M30") || has("M02") || has("M2") {
+                set_mode(&mut out, false);
+            }
+            if has("G80") || ["G00", "G01", "G02", "G03"].iter().any(|m| has(m)) {
+                in_cycle = false;
+            }
+            let starts_cycle = words.iter().any(|w| CANNED_CYCLES.contains(w));
+            in_cycle |= starts_cycle;
+
+            let axes = ['X', 'Y', 'Z'];
+            let targets = axes.map(|axis| {
+                let word = words.iter().find(|w| w.starts_with(axis))?;
+                let decimals = word.split_once('.').map_or(0, |(_, d)| d.len());
+                extract_word(code, axis).map(|v| (v, decimals))
+            });
+            let moves = targets.iter().any(Option::is_some);
+            let known = axes
+                .iter()
+                .enumerate()
+                .all(|(i, _)| targets[i].is_none() || position[i].is_some());
+
+            let emitted = if in_cycle {
+                // Cycles stay absolute and name their hole, so posts that expand
+                // them don't need to add up the moves before
+                set_mode(&mut out, false);
+                let mut words = words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+                if starts_cycle {
+                    let hole = ['X', 'Y']
+                        .into_iter()
+                        .enumerate()
+                        .filter(|&(i, _)| targets[i].is_none())
+                        .filter_map(|(i, axis)| {
+                            position[i].map(|(at, decimals)| format!("{}{:.*}", axis, decimals, at))
+                        });
+                    words.splice(1..1, hole.collect::<Vec<_>>());
+                }
+                words.join(" ")
+            } else if moves && known {
+                set_mode(&mut out, true);
+                // Each word with whether it's an axis that doesn't move
+                let words: Vec<(String, bool)> = words
+                    .iter()
+                    .map(|w| match axes.iter().position(|a| w.starts_with(*a)) {
+                        Some(i) => {
+                            let ((to, to_decimals), (from, from_decimals)) =
+                                (targets[i].unwrap(), position[i].unwrap());
+                            let delta = format!("{:.*}", to_decimals.max(from_decimals), to - from);
+                            let zero = delta.trim_start_matches('-').bytes().all(|b| b == b'0' || b == b'.');
+                            let delta = if zero { delta.trim_start_matches('-') } else { &delta };
+                            (format!("{}{}", axes[i], delta), zero)
+                        }
+                        None => (w.to_string(), false),
+                    })
+                    .collect();
+                // Drop axes that stay put, unless the block has no other motion
+                let all_still = words
+                    .iter()
+                    .all(|(w, zero)| *zero || !axes.iter().any(|a| w.starts_with(*a)));
+                words
+                    .into_iter()
+                    .filter(|(_, zero)| all_still || !zero)
+                    .map(|(w, _)| w)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            } else {
+                if moves {
+                    set_mode(&mut out, false);
+                }
+                code.to_string()
+            };
+
+            // Cycles return to their starting Z
+            for (i, target) in targets.iter().enumerate() {
+                if target.is_some() && !(in_cycle && i == 2) {
+                    position[i] = *target;
+                }
+            }
+            if has("M06") || has("M6") {
+                position = [None; 3];
+            }
+
+            if comment.is_empty() {
+                out.emit(&emitted);
+            } else {
+                out.emit(&format!("{} {}", emitted, comment));
+            }
+        }
+
+        if incremental {
+            out.emit("G90");
+        }
+        out
+    }
+
+    /// Re-emit the program with axis words (X Y Z I J K R Q) rounded to `precision`
+    /// decimals, if given, and its numbered blocks renumbered in order, keeping each
+    /// number's width, or with their N words stripped when `line_numbers` is false.
+    /// Comments and unnumbered lines keep their numbering as they are.
+    pub fn reformat(&self, precision: Option<usize>, line_numbers: bool) -> GCodeOutput {
+        let mut out = GCodeOutput::numbered_from(self.step, self.step);
+
+        for line in self.lines() {
+            if line.starts_with(';') {
+                out.push_line(line);
+                continue;
+            }
+            let (number, code) = match line.split_once(' ') {
+                Some((n, rest))
+                    if n.len() > 1 && n.starts_with('N') && n[1..].bytes().all(|b| b.is_ascii_digit()) =>
+                {
+                    (Some(n.len() - 1), rest)
+                }
+                _ => (None, line),
+            };
+
+            let code = match precision {
+                Some(digits) => round_axis_words(code, digits),
+                None => code.to_string(),
+            };
+            match number {
+                Some(width) if line_numbers => {
+                    out.push_line(&format!("N{:0width$} {}", out.line_number, code, width = width));
+                    out.line_number += out.step;
+                }
+                _ => out.push_line(&code),
+            }
+        }
+
+        out
+    }
+
+    /// Estimated run time in seconds. Feed moves run at their F word (times S under
+    /// G95), rapids at `rapid_ipm` (converted under G21), canned cycles feed in and
+    /// rapid back to the initial plane, and dwells and tool changes add their time.
+    pub fn estimate_cycle_time(&self, rapid_ipm: f64) -> f64 {
+        self.run_time(rapid_ipm, TOOL_CHANGE_SECONDS)
+    }
+
+    /// Seconds the tool spends feeding: as `estimate_cycle_time`, but rapids and
+    /// tool changes take no time
+    pub fn estimate_cutting_time(&self) -> f64 {
+        self.run_time(f64::INFINITY, 0.0)
+    }
+
+    /// Subprograms follow the main program: the number of each, the cycle it runs,
+    /// and whether it returns in G91
+    fn subprogram_cycles(&self) -> Vec<(f64, Option<&str>, bool)> {
+        let mut subprograms: Vec<(f64, Option<&str>, bool)> = Vec::new();
+        for line in self.lines() {
+            if let Some(number) = line.strip_prefix('O') {
+                subprograms.push((number.parse().unwrap_or(0.0), None, false));
+            } else if let Some((_, cycle, incremental)) = subprograms.last_mut() {
+                let code = block_code(line);
+                for word in code.split_whitespace() {
+                    if CANNED_CYCLES.contains(&word) {
+                        *cycle = Some(code);
+                    }
+                    *incremental = match word {
+                        "G90" => false,
+                        "G91" => true,
+                        _ => *incremental,
+                    };
+                }
+            }
+        }
+        subprograms
+    }
+
+    fn run_time(&self, rapid_ipm: f64, tool_change_seconds: f64) -> f64 {
+        use crate::post::extract_word;
+
+        let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+        let (mut feed, mut rpm) = (0.0, 0.0);
+        let (mut metric, mut per_rev) = (false, false);
+        let mut motion = "G00";
+        let mut cycle: Option<&str> = None;
+        let mut relative = false;
+        let mut seconds = 0.0;
+
+        let subprograms = self.subprogram_cycles();
+        for line in self.lines() {
+            if line.starts_with(';') || line.starts_with('(') {
+                continue;
+            }
+            if line.starts_with('O') {
+                break;
+            }
+            let code = block_code(line);
+            let words: Vec<&str> = code.split_whitespace().collect();
+            let has = |w: &str| words.contains(&w);
+
+            metric = (metric || has("G21")) && !has("G20");
+            per_rev = (per_rev || has("G95")) && !has("G94");
+            feed = extract_word(code, 'F').unwrap_or(feed);
+            rpm = extract_word(code, 'S').unwrap_or(rpm);
+            let feed_rate = if per_rev { feed * rpm } else { feed };
+            let rapid_rate = if metric { rapid_ipm * 25.4 } else { rapid_ipm };
+
+            if has("M06") || has("M6") {
+                seconds += tool_change_seconds;
+            }
+            if has("G04") {
+                seconds += extract_word(code, 'P').unwrap_or(0.0);
+                continue;
+            }
+
+            // A call drills one hole where the tool is
+            if has("M98") {
+                let number = extract_word(code, 'P');
+                if let Some(&(_, sub_cycle, incremental)) = subprograms.iter().find(|s| Some(s.0) == number) {
+                    if let Some(sub_cycle) = sub_cycle {
+                        let sub_feed = extract_word(sub_cycle, 'F').unwrap_or(feed);
+                        seconds += canned_cycle_seconds(sub_cycle, z, rapid_rate, sub_feed);
+                    }
+                    relative = incremental;
+                }
+                continue;
+            }
+            relative = (relative || has("G91")) && !has("G90");
+
+            // Any motion code cancels a canned cycle, as does G80
+            if let Some(m) = ["G00", "G01", "G02", "G03"].into_iter().find(|m| has(m)) {
+                motion = m;
+                cycle = None;
+            }
+            if has("G80") {
+                cycle = None;
+            }
+
+            let word = |letter: char, from: f64| match extract_word(code, letter) {
+                Some(v) if relative => from + v,
+                Some(v) => v,
+                None => from,
+            };
+            let end_x = word('X', x);
+            let end_y = word('Y', y);
+            let starts_cycle = words.iter().any(|w| CANNED_CYCLES.contains(w));
+            if starts_cycle {
+                cycle = Some(code);
+            }
+            if let Some(cycle) = cycle {
+                // Each new XY in a modal cycle is another hole
+                if starts_cycle || end_x != x || end_y != y {
+                    seconds += (end_x - x).hypot(end_y - y) / rapid_rate * 60.0;
+                    seconds += canned_cycle_seconds(cycle, z, rapid_rate, feed_rate);
+                    x = end_x;
+                    y = end_y;
+                }
+                continue;
+            }
+
+            let end_z = word('Z', z);
+            let length = match motion {
+                "G02" | "G03" if extract_word(code, 'I').is_some() || extract_word(code, 'J').is_some() => {
+                    let center_x = x + extract_word(code, 'I').unwrap_or(0.0);
+                    let center_y = y + extract_word(code, 'J').unwrap_or(0.0);
+                    let radius = (x - center_x).hypot(y - center_y);
+                    let sweep = arc_sweep((x, y), (end_x, end_y), (center_x, center_y), motion == "G02");
+                    (radius * sweep).hypot(end_z - z)
+                }
+                _ => ((end_x - x).powi(2) + (end_y - y).powi(2) + (end_z - z).powi(2)).sqrt(),
+            };
+            let rate = if motion == "G00" { rapid_rate } else { feed_rate };
+            if length > 0.0 && rate > 0.0 {
+                seconds += length / rate * 60.0;
+            }
+            x = end_x;
+            y = end_y;
+            z = end_z;
+        }
+
+        seconds
+    }
+}
+
+/// Drilling cycles that stay modal until G80 or another motion code
+const CANNED_CYCLES: [&str; 6] = ["G73", "G81", "G82", "G83", "G84", "G85"];
+
+/// Seconds per tool change in cycle-time estimates
+const TOOL_CHANGE_SECONDS: f64 = 10.0;
+
+/// Default rapid traverse rate (IPM) for cycle-time estimates
+const DEFAULT_RAPID_RATE: f64 = 400.0;
+
+/// Angle swept from `start` to `end` around `center`; equal ends are a full circle
+fn arc_sweep(start: (f64, f64), end: (f64, f64), center: (f64, f64), clockwise: bool) -> f64 {
+    let start_angle = (start.1 - center.1).atan2(start.0 - center.0);
+    let end_angle = (end.1 - center.1).atan2(end.0 - center.0);
+    let tau = 2.0 * std::f64::consts::PI;
+    let sweep = if clockwise {
+        start_angle - end_angle
+    } else {
+        end_angle - start_angle
+    }
+    .rem_euclid(tau);
+    if sweep < 1e-9 {
+        tau
+    } else {
+        sweep
+    }
+}
+
+/// The words of one block, without its line number or trailing comment
+fn block_code(line: &str) -> &str {
+    let code = match line.split_once(' ') {
+        Some((n, rest)) if n.starts_with('N') => rest,
+        _ => line,
+    };
+    code.split(';').next().unwrap_or("")
+}
+
+/// Time for one hole of a canned cycle started from `initial_z`: rapid to R, feed
+/// to depth (pecking back to R for G83), then rapid out - or feed out for taps and reams
+fn canned_cycle_seconds(code: &str, initial_z: f64, rapid_rate: f64, feed_rate: f64) -> f64 {
+    use crate::post::extract_word;
+
+    let (rapid, fed) = canned_cycle_travel(code, initial_z);
+    let mut seconds = rapid / rapid_rate * 60.0;
+    if code.split_whitespace().any(|w| w == "G82") {
+        seconds += extract_word(code, 'P').unwrap_or(0.0);
+    }
+    if feed_rate > 0.0 {
+        seconds += fed / feed_rate * 60.0;
+    }
+    seconds
+}
+
+/// Rapid and feed distance for one hole of a canned cycle started from `initial_z`
+fn canned_cycle_travel(code: &str, initial_z: f64) -> (f64, f64) {
+    use crate::post::extract_word;
+
+    let words: Vec<&str> = code.split_whitespace().collect();
+    let r = extract_word(code, 'R').unwrap_or(initial_z);
+    let bottom = extract_word(code, 'Z').unwrap_or(r);
+    let depth = (r - bottom).abs();
+
+    let mut rapid = (initial_z - r).abs();
+    let mut fed = depth;
+    if words.contains(&"G84") || words.contains(&"G85") {
+        fed += depth;
+        rapid += (initial_z - r).abs();
+    } else {
+        rapid += (initial_z - bottom).abs();
+    }
+    if words.contains(&"G83") {
+        if let Some(q) = extract_word(code, 'Q').filter(|q| *q > 0.0) {
+            // Out to R and back down after every peck but the last
+            let pecks = (depth / q).ceil() as usize;
+            rapid += (1..pecks).map(|i| 2.0 * i as f64 * q).sum::<f64>();
+        }
     }
+    (rapid, fed)
+}
+
+/// Round the axis words of one block, leaving its comments as written
+fn round_axis_words(code: &str, digits: usize) -> String {
+    let (code, comment) = match code.find(';') {
+        Some(i) => code.split_at(i),
+        None => (code, ""),
+    };
+    let mut in_paren = false;
+    let mut words: Vec<String> = Vec::new();
+    for word in code.split_whitespace() {
+        let starts_paren = word.starts_with('(');
+        let rounded = match word.split_at(1) {
+            (letter, value) if !in_paren && !starts_paren && "XYZIJKRQ".contains(letter) => {
+                value.parse::<f64>().ok().map(|v| {
+                    let v = format!("{:.*}", digits, v);
+                    // Small negatives round to -0.000; print them as zero
+                    match v.strip_prefix('-') {
+                        Some(abs) if abs.bytes().all(|b| b == b'0' || b == b'.') => format!("{}{}", letter, abs),
+                        _ => format!("{}{}", letter, v),
+                    }
+                })
+            }
+            _ => None,
+        };
+        in_paren = (in_paren || starts_paren) && !word.ends_with(')');
+        words.push(rounded.unwrap_or_else(|| word.to_string()));
+    }
+
+    let mut block = words.join(" ");
+    if !comment.is_empty() {
+        block.push(' ');
+        block.push_str(comment);
+    }
+    block
 }
 
 impl std::fmt::Display for GCodeOutput {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.lines.join("\n"))
+        f.write_str(self.as_str())
+    }
+}
+
+impl<S: AsRef<str>> Extend<S> for GCodeOutput {
+    fn extend<I: IntoIterator<Item = S>>(&mut self, lines: I) {
+        for line in lines {
+            self.push_line(line.as_ref());
+        }
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for GCodeOutput {
+    fn from_iter<I: IntoIterator<Item = S>>(lines: I) -> Self {
+        let mut output = Self::new();
+        output.extend(lines);
+        output
+    }
+}
+
+/// Chords per full circle when arcs are linearized (10-degree increments)
+const ARC_SEGMENTS_PER_REV: usize = 36;
+
+/// Default pocket entry ramp angle (degrees) - gentle enough for any end mill
+const DEFAULT_RAMP_ANGLE: f64 = 3.0;
+
+/// Shallower ramps than this would take an unbounded number of passes
+const MIN_RAMP_ANGLE: f64 = 0.5;
+
+/// O-number of the first subprogram
+const SUBPROGRAM_START: u32 = 1000;
+
+/// Face passes overlap by this percent of the cutter diameter unless told otherwise
+const DEFAULT_FACE_OVERLAP_PCT: f64 = 10.0;
+
+/// A rectangle's own axes on the part: toolpaths are laid out unrotated,
+/// then turned by `rotate` about the rectangle's center
+#[derive(Debug, Clone, Copy)]
+struct RectFrame {
+    center: (f64, f64),
+    sin: f64,
+    cos: f64,
+}
+
+impl RectFrame {
+    fn new(rect: &Rectangle) -> Self {
+        let center = rect.center();
+        let (sin, cos) = rect.rotation.to_radians().sin_cos();
+        Self {
+            center: (center.x, center.y),
+            sin,
+            cos,
+        }
+    }
+
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        let (vx, vy) = self.vector((dx, dy));
+        (self.center.0 + vx, self.center.1 + vy)
+    }
+
+    fn vector(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (x * self.cos - y * self.sin, x * self.sin + y * self.cos)
+    }
+}
+
+/// `X.. Y..` for a feed move, leaving out an axis that doesn't change
+fn axis_words(from: (f64, f64), to: (f64, f64)) -> String {
+    let x = format!("X{:.3}", to.0);
+    let y = format!("Y{:.3}", to.1);
+    let x_moves = x != format!("X{:.3}", from.0);
+    let y_moves = y != format!("Y{:.3}", from.1);
+    match (x_moves, y_moves) {
+        (false, true) => y,
+        (true, false) => x,
+        _ => format!("{} {}", x, y),
+    }
+}
+
+/// M-code that turns a coolant mode on; `Off` has none, M09 stops whatever is running.
+/// Through-spindle is M51 here and renamed by posts whose controls use another code.
+fn coolant_code(mode: CoolantMode) -> Option<&'static str> {
+    match mode {
+        CoolantMode::Flood => Some("M08"),
+        CoolantMode::Mist => Some("M07"),
+        CoolantMode::Through => Some("M51"),
+        CoolantMode::Off => None,
     }
 }
 
+/// `YYYY-MM-DD HH:MM UTC` for seconds since the Unix epoch
+fn utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = (secs % 86_400 / 3600, secs % 3600 / 60);
+
+    // Civil date from days since 1970-01-01, in 400-year eras starting March 1st
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, hour, minute)
+}
+
+/// G41/G42 setup for a profile cut with control-side cutter compensation
+#[derive(Debug, Clone, Copy)]
+struct CutterComp {
+    code: &'static str,
+    register: u8,
+    tool_radius: f64,
+}
+
 pub struct CodeGenerator {
     output: GCodeOutput,
     current_tool: Option<u8>,
     current_tool_data: Option<ToolData>,
     current_material: Option<String>,
+    /// Last commanded spindle speed; None while the spindle is stopped
+    current_rpm: Option<f64>,
     black_book: BlackBook,
     setup: Option<SetupBlock>,
     stock: Option<StockDef>,
     max_rpm: Option<f64>,
+    /// Machine the program runs on; each cut's spindle load is checked against it
+    machine: Option<MachineProfile>,
     tool_library: Option<crate::tool_library::ToolLibrary>,
+    /// Coating of the active tool, when it came from the tool library
+    tool_coating: Option<String>,
+    /// Spindle limit of the active tool, when it came from the tool library
+    tool_max_rpm: Option<f64>,
+    /// Plunge feed of the active tool, when it came from the tool library
+    tool_plunge_feed: Option<f64>,
+    /// `plunge` feed of the operation being emitted
+    plunge_override: Option<f64>,
+    /// Pocket entry ramp angle in degrees for tools that can't plunge
+    ramp_angle: f64,
+    /// Emit arcs as G01 chords for controls without G02/G03
+    linear_arcs: bool,
+    /// Write moves as G91 incremental deltas
+    incremental: bool,
+    /// Drill patterns from one M98 subprogram instead of a cycle per hole
+    use_subprograms: bool,
+    /// Subprogram bodies by O-number, written after the main program
+    subprograms: Vec<(u32, Vec<String>)>,
+    /// O-number of the main program, which subprograms skip
+    program_number: Option<u32>,
+    /// Engage rigid tapping (M29) and feed taps per revolution
+    rigid_tapping: bool,
+    /// Fold each tool's later operations into its first visit
+    group_tools: bool,
+    /// Apply the tool's length offset (G43 H) after each tool change
+    length_comp: bool,
+    /// Coolant the program asks for, from the header and `coolant` statements
+    coolant: CoolantMode,
+    /// Coolant the machine is running, switched to `coolant` as each cut starts
+    coolant_on: CoolantMode,
+    /// Already warned that the current dry stretch needs flood
+    dry_warned: bool,
+    /// Rapid traverse rate (IPM) assumed by the cycle-time estimate
+    rapid_rate: f64,
+    /// How much each face pass overlaps the last, as a percent of the cutter diameter
+    face_overlap_pct: f64,
+    /// Unit system of the program, for the units named in comments
+    units: Units,
+    /// Stamp the prologue with the generation time (off for reproducible output)
+    timestamp: bool,
+    /// Black Book and machine findings on the cuts, each kept once
+    issues: Vec<ValidationIssue>,
+    /// Minutes each tool number has spent cutting so far
+    tool_minutes: Vec<(u8, f64)>,
+    /// Reworking a finished part (`part <name> existing`) rather than cutting stock
+    existing_part: bool,
+    /// Top of the tallest `Z+` feature standing above Z0, or Z0
+    material_top: f64,
 }
 
 impl CodeGenerator {
@@ -56,11 +768,37 @@ impl CodeGenerator {
             current_tool: None,
             current_tool_data: None,
             current_material: None,
+            current_rpm: None,
             black_book: BlackBook::new(),
             setup: None,
             stock: None,
             max_rpm: None,
+            machine: None,
             tool_library: None,
+            tool_coating: None,
+            tool_max_rpm: None,
+            tool_plunge_feed: None,
+            plunge_override: None,
+            ramp_angle: DEFAULT_RAMP_ANGLE,
+            linear_arcs: false,
+            incremental: false,
+            use_subprograms: false,
+            subprograms: Vec::new(),
+            program_number: None,
+            rigid_tapping: false,
+            group_tools: false,
+            length_comp: true,
+            coolant: CoolantMode::Off,
+            coolant_on: CoolantMode::Off,
+            dry_warned: false,
+            rapid_rate: DEFAULT_RAPID_RATE,
+            face_overlap_pct: DEFAULT_FACE_OVERLAP_PCT,
+            units: Units::Imperial,
+            timestamp: false,
+            issues: Vec::new(),
+            tool_minutes: Vec::new(),
+            existing_part: false,
+            material_top: 0.0,
         }
     }
 
@@ -69,36 +807,161 @@ impl CodeGenerator {
         self
     }
 
+    /// Check cuts against a machine's limits; its max RPM also caps the spindle
+    pub fn with_machine(mut self, machine: MachineProfile) -> Self {
+        let limit = machine.max_rpm as f64;
+        self.max_rpm = Some(self.max_rpm.map_or(limit, |rpm| rpm.min(limit)));
+        self.machine = Some(machine);
+        self
+    }
+
+    pub fn with_ramp_angle(mut self, degrees: f64) -> Self {
+        self.ramp_angle = degrees.max(MIN_RAMP_ANGLE);
+        self
+    }
+
+    pub fn with_linear_arcs(mut self) -> Self {
+        self.linear_arcs = true;
+        self
+    }
+
+    pub fn with_incremental(mut self) -> Self {
+        self.incremental = true;
+        self
+    }
+
+    /// Drill each hole of a pattern with an `M98` call, for controls with subprograms
+    pub fn with_subprograms(mut self) -> Self {
+        self.use_subprograms = true;
+        self
+    }
+
+    /// The post starts the main program with `O{number}`, so no subprogram takes it
+    pub fn with_program_number(mut self, number: u32) -> Self {
+        self.program_number = Some(number);
+        self
+    }
+
+    pub fn with_rigid_tapping(mut self) -> Self {
+        self.rigid_tapping = true;
+        self
+    }
+
+    /// Group operations by tool to save tool changes, as `setup { group-tools }` does
+    pub fn with_tool_grouping(mut self) -> Self {
+        self.group_tools = true;
+        self
+    }
+
+    /// Leave tool length comp to the post or control instead of emitting G43 H
+    pub fn without_length_comp(mut self) -> Self {
+        self.length_comp = false;
+        self
+    }
+
+    pub fn with_black_book(mut self, black_book: BlackBook) -> Self {
+        self.black_book = black_book;
+        self
+    }
+
     pub fn with_tool_library(mut self, library: crate::tool_library::ToolLibrary) -> Self {
         self.tool_library = Some(library);
         self
     }
 
-    pub fn generate(&mut self, program: &Program) -> String {
-        self.emit_header(&program.header);
+    /// Machine rapid rate in IPM for the cycle-time estimate (default 400)
+    pub fn with_rapid_rate(mut self, ipm: f64) -> Self {
+        self.rapid_rate = ipm;
+        self
+    }
 
-        for op in &program.operations {
-            self.emit_operation(op);
-        }
+    /// Overlap between face passes, as a percent of the cutter diameter (default 10,
+    /// held to 0-90 so passes always advance)
+    pub fn with_face_overlap(mut self, pct: f64) -> Self {
+        self.face_overlap_pct = pct.clamp(0.0, 90.0);
+        self
+    }
 
-        self.emit_footer(&program.footer);
+    /// Note the UTC time the program was generated in its prologue
+    pub fn with_timestamp(mut self) -> Self {
+        self.timestamp = true;
+        self
+    }
+
+    /// Findings on the feeds and speeds of the last generated program, from the
+    /// Black Book's parameter checks and the machine profile
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
 
+    pub fn generate(&mut self, program: &Program) -> String {
+        self.emit_program(program);
         self.output.to_string()
     }
 
     pub fn generate_output(&mut self, program: &Program) -> GCodeOutput {
-        self.emit_header(&program.header);
+        self.emit_program(program);
+        std::mem::take(&mut self.output)
+    }
 
-        for op in &program.operations {
+    fn emit_program(&mut self, program: &Program) {
+        self.issues.clear();
+        self.tool_minutes.clear();
+        self.material_top = program
+            .operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::Cut(CutOp { height, z_constraint: ZConstraint::Positive, .. })
+                | Operation::Clear(ClearOp { height, z_constraint: ZConstraint::Positive, .. }) => {
+                    Some(*height)
+                }
+                _ => None,
+            })
+            .fold(0.0, f64::max);
+        self.emit_header(program);
+
+        let group_tools = self.group_tools
+            || program
+                .operations
+                .iter()
+                .any(|op| matches!(op, Operation::Setup(s) if s.group_tools));
+        let operations = if group_tools {
+            let (operations, saved) = grouping::group_by_tool(&program.operations);
+            if saved > 0 {
+                self.output.emit_comment(&format!(
+                    "OPERATIONS REORDERED BY TOOL: {} tool change(s) saved",
+                    saved
+                ));
+            }
+            Cow::Owned(operations)
+        } else {
+            Cow::Borrowed(&program.operations)
+        };
+
+        for op in operations.iter() {
             self.emit_operation(op);
         }
 
         self.emit_footer(&program.footer);
+        for (number, body) in std::mem::take(&mut self.subprograms) {
+            self.output.push_line(&format!("O{}", number));
+            for block in body {
+                self.output.emit(&block);
+            }
+            self.output.emit("M99");
+        }
+        if self.linear_arcs {
+            self.output = self.output.linearize_arcs();
+        }
 
-        GCodeOutput {
-            lines: self.output.lines.clone(),
-            line_number: self.output.line_number,
-            step: self.output.step,
+        // Under the PROGRAM START comment, once every move is known
+        let seconds = self.output.estimate_cycle_time(self.rapid_rate).round() as u64;
+        self.output.insert_comment(
+            1,
+            &format!("ESTIMATED CYCLE TIME: {:02}:{:02}", seconds / 60, seconds % 60),
+        );
+        if self.incremental {
+            self.output = self.output.to_incremental();
         }
     }
 
@@ -123,18 +986,7 @@ impl CodeGenerator {
         ));
 
         // Get cutting parameters from Black Book
-        let bb_tool = crate::black_book::ToolGeometry {
-            diameter: tool.diameter,
-            flute_count: tool.flutes,
-            tool_material: match tool.material {
-                crate::ast::ToolMaterial::HSS => crate::black_book::ToolMaterial::HSS,
-                crate::ast::ToolMaterial::Carbide => crate::black_book::ToolMaterial::Carbide,
-                crate::ast::ToolMaterial::Cobalt => crate::black_book::ToolMaterial::Cobalt,
-                crate::ast::ToolMaterial::Ceramic => crate::black_book::ToolMaterial::Ceramic,
-            },
-            corner_radius: None,
-            coating: None,
-        };
+        let bb_tool = self.black_book_tool(tool);
 
         let engagement = crate::black_book::Engagement {
             axial_doc: tool.diameter,
@@ -143,15 +995,26 @@ impl CodeGenerator {
         };
 
         if let Ok(params) = self.black_book.calculate(material, &bb_tool, &engagement) {
+            let (surface_speed, chip_load) = match self.units {
+                Units::Imperial => (format!("{:.0} SFM", params.sfm), "IPT"),
+                Units::Metric => (format!("{:.0} m/min", params.sfm * 0.3048), "mm/tooth"),
+            };
             self.output.emit_comment(&format!("RPM: {:.0}", params.rpm));
             self.output
-                .emit_comment(&format!("Feed Rate: {:.1} IPM", params.feed_rate_ipm));
+                .emit_comment(&format!("Surface Speed: {}", surface_speed));
+            self.output.emit_comment(&format!(
+                "Feed Rate: {:.1} {}",
+                params.feed_rate_ipm,
+                self.feed_unit()
+            ));
             self.output
                 .emit_comment(&format!("Max DOC (stepdown): {:.3}", tool.diameter * 0.8));
             self.output
                 .emit_comment(&format!("Max WOC (stepover): {:.3}", tool.diameter * 0.4));
-            self.output
-                .emit_comment(&format!("Chip Load: {:.4} IPT", params.chip_load_ipt));
+            self.output.emit_comment(&format!(
+                "Chip Load: {:.4} {}",
+                params.chip_load_ipt, chip_load
+            ));
 
             // Add any warnings
             if !params.warnings.is_empty() {
@@ -166,8 +1029,19 @@ impl CodeGenerator {
             .emit_comment("================================================");
     }
 
-    fn emit_header(&mut self, header: &Header) {
+    fn emit_header(&mut self, program: &Program) {
+        let header = &program.header;
+        // Black Book math must see diameters and feeds in the program's units
+        self.units = header.units;
+        self.black_book.set_units(header.units);
+
+        // The header's spindle limit clamps like `with_max_rpm`; the lower one wins
+        if let Some(limit) = header.safety.max_spindle_rpm {
+            self.max_rpm = Some(self.max_rpm.map_or(limit, |rpm| rpm.min(limit)));
+        }
+
         self.output.emit_comment("PROGRAM START");
+        self.emit_prologue(program);
 
         // Emit cutting parameters summary if we have material and tool info
         self.emit_cutting_parameters_summary();
@@ -193,37 +1067,181 @@ impl CodeGenerator {
         self.output.emit(offset_code);
 
         // Coolant
-        match header.safety.coolant {
-            CoolantMode::Flood => self.output.emit("M08"),
-            CoolantMode::Mist => self.output.emit("M07"),
-            _ => {}
+        if let Some(code) = coolant_code(header.safety.coolant) {
+            self.output.emit(code);
         }
+        self.coolant = header.safety.coolant;
+        self.coolant_on = header.safety.coolant;
     }
 
-    fn emit_operation(&mut self, op: &Operation) {
-        match op {
-            Operation::ToolChange(tc) => self.emit_tool_change(tc),
-            Operation::Spindle(sp) => self.emit_spindle(sp),
-            Operation::Drill(d) => self.emit_drill(d),
-            Operation::Pocket(p) => self.emit_pocket(p),
-            Operation::Profile(p) => self.emit_profile(p),
-            Operation::Face(f) => self.emit_face(f),
-            Operation::FaceV2(f) => self.emit_face_v2(f),
-            Operation::StockDef(s) => {
-                self.stock = Some(s.clone());
-                self.output.emit_comment(&format!(
-                    "STOCK: {} x {} x {} {}",
+    /// Traceability block under PROGRAM START: part, material, stock, the swarf
+    /// version (and time) that generated it, and every tool the program loads
+    fn emit_prologue(&mut self, program: &Program) {
+        let mut part = None;
+        let mut stock = None;
+        let mut material = None;
+        let mut tools: Vec<&ToolChange> = Vec::new();
+        for op in &program.operations {
+            match op {
+                Operation::PartDef(p) => {
+                    part = Some(p.name.as_str());
+                    stock = stock.or(p.stock.as_ref());
+                }
+                Operation::StockDef(s) => stock = stock.or(Some(s)),
+                Operation::Setup(s) => material = material.or(s.material.as_deref()),
+                Operation::ToolChange(tc) if !tools.iter().any(|t| t.tool_number == tc.tool_number) => {
+                    tools.push(tc)
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(name) = part {
+            self.output.emit_comment(&format!("PART: {}", name));
+        }
+        if let Some(material) = material.or(stock.map(|s| s.material.as_str())) {
+            self.output.emit_comment(&format!("MATERIAL: {}", material));
+        }
+        if let Some(s) = stock {
+            self.output.emit_comment(&format!(
+                "STOCK: {} x {} x {}{}",
+                s.size_x,
+                s.size_y,
+                s.size_z,
+                self.length_mark()
+            ));
+        }
+        let mut generated = format!("GENERATED BY: swarf {}", env!("CARGO_PKG_VERSION"));
+        if self.timestamp {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            generated.push_str(&format!(" on {}", utc_timestamp(now)));
+        }
+        self.output.emit_comment(&generated);
+
+        if !tools.is_empty() {
+            self.output.emit_comment("TOOLS:");
+        }
+        for tc in tools {
+            let mut line = format!("  T{}", tc.tool_number);
+            if let Some(data) = &tc.tool_data {
+                line.push_str(&format!(
+                    " - {} DIA {}FL {}",
+                    data.diameter,
+                    data.flutes,
+                    format!("{:?}", data.material).to_uppercase()
+                ));
+            }
+            if let Some(id) = &tc.tool_id {
+                line.push_str(&format!(" ({})", id));
+            }
+            self.output.emit_comment(&line);
+        }
+    }
+
+    /// Bring the machine's coolant to the program's mode as a cut starts, so
+    /// M07/M08/M09 appear only on a change - including back on after a tool change
+    fn apply_coolant(&mut self) {
+        if self.coolant_on != self.coolant {
+            // Each mode is a separate output; stop one before starting the other
+            if self.coolant_on != CoolantMode::Off {
+                self.output.emit("M09");
+            }
+            if let Some(code) = coolant_code(self.coolant) {
+                self.output.emit(code);
+            }
+            self.coolant_on = self.coolant;
+            self.dry_warned = false;
+        }
+
+        if self.coolant == CoolantMode::Off && !self.dry_warned {
+            let needs_flood = self
+                .current_material
+                .as_ref()
+                .and_then(|m| self.black_book.resolve_material(m).ok())
+                .filter(|m| m.coolant_required)
+                .map(|m| m.name.clone());
+            if let Some(name) = needs_flood {
+                self.output.emit_comment(&format!(
+                    "WARNING: {} requires flood coolant - cutting dry",
+                    name
+                ));
+                self.dry_warned = true;
+            }
+        }
+    }
+
+    fn emit_operation(&mut self, op: &Operation) {
+        // Positions are written from the setup's zero; toolpaths run on the stock frame
+        let op = match self.datum() {
+            Some(datum) => Cow::Owned(datum.operation(op)),
+            None => Cow::Borrowed(op),
+        };
+
+        let cutting = matches!(
+            op.as_ref(),
+            Operation::Drill(_)
+                | Operation::Pocket(_)
+                | Operation::Profile(_)
+                | Operation::Face(_)
+                | Operation::Tap(_)
+                | Operation::Cut(_)
+                | Operation::Clear(_)
+                | Operation::DrillV2(_)
+                | Operation::PocketV2(_)
+                | Operation::FaceV2(_)
+                | Operation::DrillPattern(_)
+                | Operation::PocketPattern(_)
+                | Operation::Chamfer(_)
+                | Operation::Deburr(_)
+        );
+        if cutting {
+            self.apply_coolant();
+        }
+        let start = self.output.lines().count();
+
+        match op.as_ref() {
+            Operation::ToolChange(tc) => self.emit_tool_change(tc),
+            Operation::Spindle(sp) => self.emit_spindle(sp),
+            Operation::Drill(d) => self.emit_drill(d),
+            Operation::Pocket(p) => self.emit_pocket(p),
+            Operation::Profile(p) => self.emit_profile(p),
+            Operation::Face(f) => self.emit_face(f),
+            Operation::FaceV2(f) => self.emit_face_v2(f),
+            Operation::StockDef(s) => {
+                let datum = self.datum();
+                self.stock = Some(s.clone());
+                self.output.emit_comment(&format!(
+                    "STOCK: {} x {} x {} {}",
                     s.size_x, s.size_y, s.size_z, s.material
                 ));
+                self.note_work_zero(datum);
             }
             Operation::Tap(t) => self.emit_tap(t),
             Operation::Comment(c) => self.output.emit_comment(c),
-            Operation::PartDef(_) => {
+            Operation::Coolant(mode) => self.coolant = *mode,
+            Operation::PartDef(p) => {
                 // Part definition is metadata, no G-code emitted
+                self.existing_part = p.existing;
+                if let Some(stock) = &p.stock {
+                    let datum = self.datum();
+                    self.stock = Some(stock.clone());
+                    self.note_work_zero(datum);
+                }
+                if p.existing {
+                    self.output.emit_comment(&format!(
+                        "EXISTING PART: pockets without a finish allowance leave {:.4}{}",
+                        self.existing_part_allowance(),
+                        self.length_mark()
+                    ));
+                }
             }
             Operation::Setup(setup) => {
+                let datum = self.datum();
                 self.setup = Some(setup.clone());
                 self.emit_setup(setup);
+                self.note_work_zero(datum);
             }
             Operation::Cut(cut) => self.emit_cut(cut),
             Operation::Clear(clear) => self.emit_clear(clear),
@@ -234,6 +1252,97 @@ impl CodeGenerator {
             Operation::Chamfer(chamfer) => self.emit_chamfer(chamfer),
             Operation::Deburr(deburr) => self.emit_deburr(deburr),
         }
+
+        if cutting {
+            self.note_tool_life(start);
+        }
+    }
+
+    /// Add the cut written from line `start` to the active tool's time in the
+    /// material, and note its estimated life (Taylor's equation at the running
+    /// SFM and the Black Book chip load) under the operation's comments. Warns
+    /// once the tool has cut for longer than that.
+    fn note_tool_life(&mut self, start: usize) {
+        let cut: GCodeOutput = self.output.lines().skip(start).collect();
+        // Operations that pick their own speed start the spindle themselves
+        let rpm = cut
+            .lines()
+            .rev()
+            .find_map(|l| crate::post::extract_word(block_code(l), 'S'))
+            .or(self.current_rpm);
+        let (Some(number), Some(tool), Some(rpm)) =
+            (self.current_tool, self.current_tool_data.as_ref(), rpm)
+        else {
+            return;
+        };
+        let Some(material) = self.current_material.as_deref() else {
+            return;
+        };
+        let Ok(data) = self.black_book.resolve_material(material) else {
+            return;
+        };
+
+        // Taylor's equation works in feet per minute and inches per tooth
+        let to_inches = match self.units {
+            Units::Metric => 1.0 / 25.4,
+            Units::Imperial => 1.0,
+        };
+        let bb_tool = self.black_book_tool(tool);
+        let engagement = Engagement {
+            axial_doc: tool.diameter,
+            radial_woc: tool.diameter * 0.4,
+            radial_engagement_pct: 40.0,
+        };
+        let Ok(params) = self.black_book.calculate(material, &bb_tool, &engagement) else {
+            return;
+        };
+        let sfm = rpm * std::f64::consts::PI * tool.diameter * to_inches / 12.0;
+        let life = estimate_tool_life(
+            data,
+            sfm,
+            params.chip_load_ipt * to_inches,
+            bb_tool.tool_material,
+        )
+        .estimated_minutes;
+
+        let minutes = cut.estimate_cutting_time() / 60.0;
+        let total = match self.tool_minutes.iter_mut().find(|(t, _)| *t == number) {
+            Some((_, total)) => {
+                *total += minutes;
+                *total
+            }
+            None => {
+                self.tool_minutes.push((number, minutes));
+                minutes
+            }
+        };
+
+        // Last of the operation's leading comments
+        let block = self
+            .output
+            .lines()
+            .skip(start)
+            .position(|l| !l.starts_with(';'))
+            .map_or(start, |i| start + i);
+        self.output.insert_comment(
+            block,
+            &format!(
+                "Tool life: ~{:.0} min estimated, T{} has cut {:.1} min",
+                life, number, total
+            ),
+        );
+
+        if total > life && total - minutes <= life {
+            self.report_issue(ValidationIssue {
+                severity: Severity::Warning,
+                code: "TOOL_LIFE_EXCEEDED".to_string(),
+                message: format!(
+                    "T{} has cut for {:.1} min, past its estimated {:.0} min life",
+                    number, total, life
+                ),
+                suggestion: Some("Plan a tool change or a fresh edge before this operation".to_string()),
+            });
+        }
     }
 
     fn emit_setup(&mut self, setup: &SetupBlock) {
@@ -246,14 +1355,196 @@ impl CodeGenerator {
         }
         if let Some(ref material) = setup.material {
             self.output.emit_comment(&format!("Material: {}", material));
+            if let Err(e) = self.black_book.resolve_material(material) {
+                self.output.emit_comment(&format!(
+                    "WARNING: {} - using default feeds and speeds",
+                    e
+                ));
+            }
             self.current_material = Some(material.clone());
         }
     }
 
-    /// Apply max RPM limit, scaling feed proportionally to maintain chip load
-    fn apply_rpm_limit(&self, rpm: f64, feed: f64) -> (f64, f64) {
-        if let Some(max_rpm) = self.max_rpm {
+    /// Datum for the setup's zero on the defined stock, unless it is the left front top corner
+    fn datum(&self) -> Option<Datum> {
+        let zero = &self.setup.as_ref()?.zero;
+        Some(Datum::new(zero, self.stock.as_ref()?)).filter(|d| !d.is_identity())
+    }
+
+    /// Say where G54 sits once a setup's zero moves the toolpaths off it
+    fn note_work_zero(&mut self, before: Option<Datum>) {
+        let datum = self.datum();
+        if datum.is_some() && datum != before {
+            self.output
+                .emit_comment("WORK ZERO: G54 at stock left front top");
+        }
+    }
+
+    fn length_comp_enabled(&self) -> bool {
+        self.length_comp && !self.setup.as_ref().is_some_and(|s| s.no_length_comp)
+    }
+
+    /// The setup's `z-min` on the stock frame
+    fn z_min(&self) -> Option<f64> {
+        let z_min = self.setup.as_ref()?.z_min?;
+        Some(self.datum().map_or(z_min, |d| d.z(z_min)))
+    }
+
+    /// Clamp a cut depth (positive = below Z0) so the tool never goes below the setup's `z-min`
+    fn clamp_depth(&mut self, depth: f64) -> f64 {
+        let Some(z_min) = self.z_min() else {
+            return depth;
+        };
+        let max_depth = (-z_min).max(0.0);
+        if depth > max_depth {
+            self.output.emit_comment(&format!(
+                "WARNING: depth {:.4} violates z-min {:.4} - clamped to Z{:.4}",
+                depth, z_min, -max_depth
+            ));
+            max_depth
+        } else {
+            depth
+        }
+    }
+
+    /// Clamp signed Z levels to the setup's `z-min`
+    fn clamp_z_levels(&mut self, levels: Vec<f64>) -> Vec<f64> {
+        let Some(z_min) = self.z_min() else {
+            return levels;
+        };
+        if levels.iter().any(|z| *z < z_min) {
+            self.output.emit_comment(&format!(
+                "WARNING: passes below z-min {:.4} - clamped",
+                z_min
+            ));
+        }
+        levels.into_iter().map(|z| z.max(z_min)).collect()
+    }
+
+    /// Radius of the loaded tool, or `default` when no tool data is known
+    fn current_tool_radius(&self, default: f64) -> f64 {
+        self.current_tool_data
+            .as_ref()
+            .map(|t| t.diameter / 2.0)
+            .unwrap_or(default)
+    }
+
+    /// Highest Y the tool center may reach so the cutter edge stays behind the setup's `y-limit`
+    fn y_center_limit(&self, tool_dia: f64) -> Option<f64> {
+        self.setup
+            .as_ref()
+            .and_then(|s| s.y_limit)
+            .map(|limit| limit - tool_dia / 2.0)
+    }
+
+    /// Black Book view of a tool, with the active library tool's coating
+    fn black_book_tool(&self, tool: &ToolData) -> ToolGeometry {
+        ToolGeometry {
+            diameter: tool.diameter,
+            flute_count: tool.flutes,
+            tool_material: match tool.material {
+                crate::ast::ToolMaterial::HSS => crate::black_book::ToolMaterial::HSS,
+                crate::ast::ToolMaterial::Carbide => crate::black_book::ToolMaterial::Carbide,
+                crate::ast::ToolMaterial::Cobalt => crate::black_book::ToolMaterial::Cobalt,
+                crate::ast::ToolMaterial::Ceramic => crate::black_book::ToolMaterial::Ceramic,
+                crate::ast::ToolMaterial::CoatedCarbide => {
+                    crate::black_book::ToolMaterial::CoatedCarbide
+                }
+                crate::ast::ToolMaterial::CBN => crate::black_book::ToolMaterial::CBN,
+                crate::ast::ToolMaterial::Diamond => crate::black_book::ToolMaterial::Diamond,
+            },
+            corner_radius: None,
+            coating: self.tool_coating.clone(),
+            ball_nose: false,
+        }
+    }
+
+    /// Feed rate unit for comments
+    fn feed_unit(&self) -> &'static str {
+        match self.units {
+            Units::Imperial => "IPM",
+            Units::Metric => "mm/min",
+        }
+    }
+
+    /// Length mark for comments: inches as `0.250"`, millimeters as `6.350 mm`
+    fn length_mark(&self) -> &'static str {
+        match self.units {
+            Units::Imperial => "\"",
+            Units::Metric => " mm",
+        }
+    }
+
+    /// Height clear of clamps and fixtures above the stock top: 1" or 25 mm over
+    /// Z0, or over the tallest `Z+` feature left standing above it
+    fn safe_z(&self) -> f64 {
+        let clearance = match self.units {
+            Units::Imperial => 1.0,
+            Units::Metric => 25.0,
+        };
+        self.material_top + clearance
+    }
+
+    /// Rapid plane just above the surface being cut: 0.1" or 2.5 mm over Z0
+    fn clearance(&self) -> f64 {
+        match self.units {
+            Units::Imperial => 0.1,
+            Units::Metric => 2.5,
+        }
+    }
+
+    /// Clearance over the floor just cut when repositioning between one-way raster
+    /// passes or trochoidal lanes: 0.02" or 0.5 mm, so the tool never rapids on it
+    fn pass_lift(&self) -> f64 {
+        match self.units {
+            Units::Imperial => 0.02,
+            Units::Metric => 0.5,
+        }
+    }
+
+    /// Depth of the G81 spot cycle ahead of a `drill ... spot` hole: 0.05" or
+    /// 1.25 mm, deep enough to seat the drill point so it can't walk
+    fn spot_depth(&self) -> f64 {
+        match self.units {
+            Units::Imperial => 0.05,
+            Units::Metric => 1.25,
+        }
+    }
+
+    /// Finish allowance a reworked part's pockets leave unless they set one: the
+    /// walls are already close to size, so 0.005" or 0.125 mm
+    fn existing_part_allowance(&self) -> f64 {
+        match self.units {
+            Units::Imperial => 0.005,
+            Units::Metric => 0.125,
+        }
+    }
+
+    /// Rapid up to the safe Z at the end of an operation
+    fn retract_to_safe_z(&mut self) {
+        self.output.emit(&format!("G00 Z{:.3}", self.safe_z()));
+    }
+
+    /// Rapid to the clearance plane, to approach or leave the cut
+    fn retract_to_clearance(&mut self) {
+        self.output.emit(&format!("G00 Z{:.1}", self.clearance()));
+    }
+
+    /// Apply max RPM limit (machine or tool, whichever is lower), scaling feed
+    /// proportionally to maintain chip load. Clamping is noted in the output.
+    fn apply_rpm_limit(&mut self, rpm: f64, feed: f64) -> (f64, f64) {
+        let machine = self.max_rpm.map(|limit| (limit, "machine max RPM"));
+        let tool = self.tool_max_rpm.map(|limit| (limit, "tool max RPM"));
+        let limit = match (machine, tool) {
+            (Some(machine), Some(tool)) => Some(if tool.0 < machine.0 { tool } else { machine }),
+            (machine, tool) => machine.or(tool),
+        };
+        if let Some((max_rpm, source)) = limit {
             if rpm > max_rpm {
+                self.output.emit_comment(&format!(
+                    "RPM {:.0} clamped to {:.0} by {}",
+                    rpm, max_rpm, source
+                ));
                 let scale = max_rpm / rpm;
                 return (max_rpm, feed * scale);
             }
@@ -261,44 +1552,88 @@ impl CodeGenerator {
         (rpm, feed)
     }
 
-    fn calculate_drill_params(&self, diameter: f64, depth: f64) -> (f64, f64, f64) {
+    /// Check the cut at `rpm` and `feed` against the Black Book's safe limits for the
+    /// tool and material, and the machine's. Power is scaled by any RPM clamp since
+    /// a slower feed removes metal more slowly. Each finding is noted once, where it
+    /// first shows up, and kept for `issues`.
+    fn check_cutting_params(
+        &mut self,
+        params: &CuttingParameters,
+        tool: &ToolGeometry,
+        rpm: f64,
+        feed: f64,
+    ) {
+        let scale = if params.feed_rate_ipm > 0.0 { feed / params.feed_rate_ipm } else { 1.0 };
+        // Both checks work in inches
+        let to_inches = match self.units {
+            Units::Metric => 1.0 / 25.4,
+            Units::Imperial => 1.0,
+        };
+        let actual = CuttingParameters {
+            rpm: rpm.round() as u32,
+            feed_rate_ipm: feed * to_inches,
+            chip_load_ipt: params.chip_load_ipt * to_inches,
+            doc: params.doc * to_inches,
+            woc: params.woc * to_inches,
+            hp_required: params.hp_required * scale,
+            ..params.clone()
+        };
+        let tool = ToolGeometry {
+            diameter: tool.diameter * to_inches,
+            ..tool.clone()
+        };
+
+        let mut issues = Vec::new();
+        let material = self.current_material.as_deref();
+        if let Some(material) = material.and_then(|m| self.black_book.resolve_material(m).ok()) {
+            issues.extend(validate_parameters(&actual, material, &tool));
+        }
+        if let Some(machine) = &self.machine {
+            issues.extend(machine.check(&actual));
+        }
+
+        for issue in issues {
+            self.report_issue(issue);
+        }
+    }
+
+    /// Note a finding in the output and keep it for `issues`, unless already reported
+    fn report_issue(&mut self, issue: ValidationIssue) {
+        let seen = self
+            .issues
+            .iter()
+            .any(|i| i.code == issue.code && i.message == issue.message);
+        if seen {
+            return;
+        }
+        self.output
+            .emit_comment(&format!("{} {}: {}", issue.severity, issue.code, issue.message));
+        if let Some(suggestion) = &issue.suggestion {
+            self.output.emit_comment(&format!("  {}", suggestion));
+        }
+        self.issues.push(issue);
+    }
+
+    fn calculate_drill_params(&mut self, diameter: f64, depth: f64) -> (f64, f64, f64) {
         // Returns (rpm, feed_rate, peck_depth)
         if let Some(ref material) = self.current_material {
             if let Some(ref tool_data) = self.current_tool_data {
                 // Convert tool data to Black Book format
                 let tool = ToolGeometry {
                     diameter,
-                    flute_count: tool_data.flutes,
-                    tool_material: match tool_data.material {
-                        crate::ast::ToolMaterial::HSS => crate::black_book::ToolMaterial::HSS,
-                        crate::ast::ToolMaterial::Carbide => {
-                            crate::black_book::ToolMaterial::Carbide
-                        }
-                        crate::ast::ToolMaterial::Cobalt => crate::black_book::ToolMaterial::Cobalt,
-                        crate::ast::ToolMaterial::Ceramic => {
-                            crate::black_book::ToolMaterial::Ceramic
-                        }
-                    },
-                    corner_radius: None,
-                    coating: None,
-                };
-
-                let engagement = Engagement {
-                    axial_doc: depth,
-                    radial_woc: diameter * 0.5, // Half diameter for drilling
-                    radial_engagement_pct: 50.0,
+                    ..self.black_book_tool(tool_data)
                 };
 
-                if let Ok(params) = self.black_book.calculate(material, &tool, &engagement) {
-                    // For drilling, use lower feed than milling
+                if let Ok(params) = self.black_book.calculate_drilling(material, &tool) {
                     let peck_depth = if depth > 3.0 * diameter {
-                        diameter * 1.5 // Deep hole peck
+                        // Deep hole peck, shorter in materials that pack chips or heat
+                        let factor = self.black_book.peck_factor(material).unwrap_or(1.5);
+                        diameter * factor
                     } else {
                         depth // No peck for shallow holes
                     };
 
-                    let (rpm, feed) =
-                        self.apply_rpm_limit(params.rpm as f64, params.feed_rate_ipm * 0.7);
+                    let (rpm, feed) = self.apply_rpm_limit(params.rpm as f64, params.feed_rate_ipm);
                     return (rpm, feed, peck_depth);
                 }
             }
@@ -309,31 +1644,25 @@ impl CodeGenerator {
         (rpm, feed, depth)
     }
 
-    fn calculate_pocket_params(&self, tool_dia: f64, _depth: f64) -> (f64, f64, f64, f64) {
+    fn calculate_pocket_params(&mut self, tool_dia: f64, depth: f64) -> (f64, f64, f64, f64) {
         // Returns (rpm, feed_rate, stepdown, stepover)
         if let Some(ref material) = self.current_material {
             if let Some(ref tool_data) = self.current_tool_data {
                 let tool = ToolGeometry {
                     diameter: tool_dia,
-                    flute_count: tool_data.flutes,
-                    tool_material: match tool_data.material {
-                        crate::ast::ToolMaterial::HSS => crate::black_book::ToolMaterial::HSS,
-                        crate::ast::ToolMaterial::Carbide => {
-                            crate::black_book::ToolMaterial::Carbide
-                        }
-                        crate::ast::ToolMaterial::Cobalt => crate::black_book::ToolMaterial::Cobalt,
-                        crate::ast::ToolMaterial::Ceramic => {
-                            crate::black_book::ToolMaterial::Ceramic
-                        }
-                    },
-                    corner_radius: None,
-                    coating: None,
+                    ..self.black_book_tool(tool_data)
                 };
 
-                // Use default DOC ratio - could query Black Book if we add a method
-                let max_doc_ratio: f64 = 1.0; // Default to 1x diameter
-
-                let stepdown = tool_dia * max_doc_ratio.min(1.0);
+                // The material's DOC limit, at most 1x diameter at 40% stepover, and
+                // no deeper than the pocket itself
+                let max_doc_ratio = self
+                    .black_book
+                    .resolve_material(material)
+                    .map_or(1.0, |data| data.max_doc_diameter_ratio);
+                let mut stepdown = tool_dia * max_doc_ratio.min(1.0);
+                if depth > 0.0 {
+                    stepdown = stepdown.min(depth);
+                }
                 let stepover = tool_dia * 0.4; // 40% stepover default
 
                 let engagement = Engagement {
@@ -344,6 +1673,7 @@ impl CodeGenerator {
 
                 if let Ok(params) = self.black_book.calculate(material, &tool, &engagement) {
                     let (rpm, feed) = self.apply_rpm_limit(params.rpm as f64, params.feed_rate_ipm);
+                    self.check_cutting_params(&params, &tool, rpm, feed);
                     return (rpm, feed, stepdown, stepover);
                 }
             }
@@ -354,6 +1684,31 @@ impl CodeGenerator {
         (rpm, feed, tool_dia * 0.5, tool_dia * 0.4)
     }
 
+    /// RPM, feed, stepdown, and stepover for trochoidal clearing at the Black Book's
+    /// adaptive engagement: a thin bite taken deep
+    fn calculate_adaptive_params(&mut self, tool_dia: f64) -> (f64, f64, f64, f64) {
+        if let Some(ref material) = self.current_material {
+            if let Some(ref tool_data) = self.current_tool_data {
+                let tool = ToolGeometry {
+                    diameter: tool_dia,
+                    ..self.black_book_tool(tool_data)
+                };
+
+                if let Ok(params) =
+                    self.black_book
+                        .calculate_operation(material, &tool, OperationType::Adaptive)
+                {
+                    let (rpm, feed) = self.apply_rpm_limit(params.rpm as f64, params.feed_rate_ipm);
+                    return (rpm, feed, params.doc, params.woc);
+                }
+            }
+        }
+
+        // Default values: the raster's speeds, at adaptive engagement
+        let (rpm, feed, _, _) = self.calculate_pocket_params(tool_dia, 0.0);
+        (rpm, feed, tool_dia * 1.5, tool_dia * 0.1)
+    }
+
     fn emit_cut(&mut self, cut: &CutOp) {
         self.output.emit_comment(&format!(
             "CUT {:?} sweep:{} depth:{} height:{}",
@@ -371,23 +1726,55 @@ impl CodeGenerator {
         let (rpm, feed_rate, stepdown, _stepover) =
             self.calculate_pocket_params(tool_dia, cut.height);
 
-        // Calculate number of Z passes for the height
-        let num_passes = (cut.height / stepdown).ceil() as i32;
+        let levels = Self::sweep_z_levels(cut.height, stepdown, cut.z_constraint);
+        let levels = self.clamp_z_levels(levels);
 
         self.output.emit_comment(&format!(
-            "Black Book: RPM={:.0}, Feed={:.1} IPM, Stepdown={:.3}\"",
-            rpm, feed_rate, stepdown
+            "Black Book: RPM={:.0}, Feed={:.1} {}, Stepdown={:.3}{}",
+            rpm,
+            feed_rate,
+            self.feed_unit(),
+            stepdown,
+            self.length_mark()
         ));
         self.output.emit_comment(&format!(
             "Z Passes required: {} for height {}",
-            num_passes, cut.height
+            levels.len(),
+            cut.height
         ));
+        self.emit_plunge_comment(feed_rate);
+
+        let Some((along, sign, across)) = Self::sweep_axes(cut.direction) else {
+            self.output
+                .emit_comment("Z-axis cut direction has no sweep toolpath - skipped");
+            return;
+        };
 
         // Spindle speed
         self.output.emit(&format!("S{:.0} M03", rpm));
 
-        // TODO: Generate actual toolpath based on direction and constraints
-        self.output.emit("; Cut operation - TODO");
+        // Each Z pass takes the full depth into the material in a single sweep
+        let safe_z = self.sweep_safe_z(cut.height, cut.z_constraint);
+        let (start_along, start_across) = Self::sweep_start(along, cut.origin);
+        let wall = start_along + sign * cut.depth;
+        self.output.emit(&format!("G00 Z{:.4}", safe_z));
+        for (i, z) in levels.iter().enumerate() {
+            self.output
+                .emit_comment(&format!("Pass {}/{}: Z={:.3}", i + 1, levels.len(), z));
+            self.output.emit(&format!(
+                "G00 {}{:.4} {}{:.4}",
+                across, start_across, along, wall
+            ));
+            self.output
+                .emit(&format!("G01 Z{:.4} F{:.1}", z, self.plunge_feed(feed_rate)));
+            self.output.emit(&format!(
+                "G01 {}{:.4} F{:.1}",
+                across,
+                start_across + cut.sweep,
+                feed_rate
+            ));
+            self.output.emit(&format!("G00 Z{:.4}", safe_z));
+        }
     }
 
     fn emit_clear(&mut self, clear: &ClearOp) {
@@ -395,99 +1782,314 @@ impl CodeGenerator {
             "CLEAR {:?} sweep:{} depth:{} height:{}",
             clear.direction, clear.sweep, clear.depth, clear.height
         ));
-        // TODO: Generate actual toolpath
-        self.output.emit("; Clear operation - TODO");
-    }
 
-    fn emit_drill_v2(&mut self, drill: &DrillV2Op) {
-        self.output.emit_comment(&format!(
-            "DRILL dia:{} at X{:.4} Y{:.4}",
-            drill.diameter, drill.position.x, drill.position.y
-        ));
+        let tool_dia = self
+            .current_tool_data
+            .as_ref()
+            .map(|t| t.diameter)
+            .unwrap_or(0.25);
 
-        // Calculate depth
-        let depth = match &drill.depth {
-            DrillDepth::Thru => 0.5, // Default through depth
-            DrillDepth::Depth(z) => *z,
-        };
+        let (rpm, feed_rate, stepdown, stepover) =
+            self.calculate_pocket_params(tool_dia, clear.height);
 
-        // Get cutting parameters from Black Book
-        let (rpm, feed_rate, peck_depth) = self.calculate_drill_params(drill.diameter, depth);
+        let levels = Self::sweep_z_levels(clear.height, stepdown, clear.z_constraint);
+        let levels = self.clamp_z_levels(levels);
 
-        // Output calculated parameters
         self.output.emit_comment(&format!(
-            "Black Book: RPM={:.0}, Feed={:.1} IPM, Peck={:.3}\"",
-            rpm, feed_rate, peck_depth
+            "Black Book: RPM={:.0}, Feed={:.1} {}, Stepdown={:.3}{}, Stepover={:.3}{}",
+            rpm,
+            feed_rate,
+            self.feed_unit(),
+            stepdown,
+            self.length_mark(),
+            stepover,
+            self.length_mark()
+        ));
+        self.output.emit_comment(&format!(
+            "Z Passes required: {} for height {}",
+            levels.len(),
+            clear.height
         ));
+        self.emit_plunge_comment(feed_rate);
+
+        let Some((along, sign, across)) = Self::sweep_axes(clear.direction) else {
+            self.output
+                .emit_comment("Z-axis clear direction has no sweep toolpath - skipped");
+            return;
+        };
 
-        // Spindle speed
         self.output.emit(&format!("S{:.0} M03", rpm));
 
-        // Move to position
-        self.output.emit(&format!(
-            "G00 X{:.4} Y{:.4}",
-            drill.position.x, drill.position.y
-        ));
+        // Raster the sweep x depth area: zigzag across the sweep, stepping into the material
+        let num_steps = (clear.depth / stepover).ceil().max(1.0) as i32;
+        let actual_stepover = clear.depth / num_steps as f64;
+        let safe_z = self.sweep_safe_z(clear.height, clear.z_constraint);
+        let (start_along, start_across) = Self::sweep_start(along, clear.origin);
+        self.output.emit(&format!("G00 Z{:.4}", safe_z));
 
-        // Drill cycle
-        if peck_depth < depth {
-            // Peck drilling for deep holes
+        for (i, z) in levels.iter().enumerate() {
+            self.output
+                .emit_comment(&format!("Pass {}/{}: Z={:.3}", i + 1, levels.len(), z));
             self.output.emit(&format!(
-                "G83 R0.1 Z-{:.4} Q{:.4} F{:.1}",
-                depth, peck_depth, feed_rate
+                "G00 {}{:.4} {}{:.4}",
+                across, start_across, along, start_along
             ));
-        } else {
-            // Standard drill cycle
             self.output
-                .emit(&format!("G81 R0.1 Z-{:.4} F{:.1}", depth, feed_rate));
+                .emit(&format!("G01 Z{:.4} F{:.1}", z, self.plunge_feed(feed_rate)));
+
+            for step in 0..=num_steps {
+                let offset = start_along + sign * step as f64 * actual_stepover;
+                if step > 0 {
+                    self.output
+                        .emit(&format!("G01 {}{:.4} F{:.1}", along, offset, feed_rate));
+                }
+                let end = start_across + if step % 2 == 0 { clear.sweep } else { 0.0 };
+                self.output
+                    .emit(&format!("G01 {}{:.4} F{:.1}", across, end, feed_rate));
+            }
+
+            self.output.emit(&format!("G00 Z{:.4}", safe_z));
         }
     }
 
-    fn emit_pocket_v2(&mut self, pocket: &PocketV2Op) {
-        // Get tool diameter (from current tool or default)
-        let tool_dia = self
-            .current_tool_data
-            .as_ref()
-            .map(|t| t.diameter)
-            .unwrap_or(0.25); // Default 1/4" end mill
+    /// Z levels for stepped cut/clear passes.
+    /// `Z+` features stand above Z0, so passes step down from the top and stop at Z0;
+    /// otherwise passes step down into the material from Z0.
+    fn sweep_z_levels(height: f64, stepdown: f64, constraint: ZConstraint) -> Vec<f64> {
+        let num_passes = (height / stepdown).ceil().max(1.0) as usize;
+        (1..=num_passes)
+            .map(|pass| {
+                let z = (pass as f64 * stepdown).min(height);
+                match constraint {
+                    ZConstraint::Positive => height - z,
+                    ZConstraint::Min(floor) => (-z).max(floor),
+                    ZConstraint::Negative | ZConstraint::Free => -z,
+                }
+            })
+            .collect()
+    }
 
-        // Calculate cutting parameters from Black Book
-        let (rpm, feed_rate, stepdown, stepover) =
-            self.calculate_pocket_params(tool_dia, pocket.depth);
+    /// Clearance above the feature: a `Z+` feature stands `height` above Z0
+    fn sweep_safe_z(&self, height: f64, constraint: ZConstraint) -> f64 {
+        match constraint {
+            ZConstraint::Positive => height + self.clearance(),
+            _ => self.clearance(),
+        }
+    }
 
-        // Calculate number of passes
-        let num_passes = (pocket.depth / stepdown).ceil() as i32;
+    /// (along, across) coordinates of a sweep's start
+    fn sweep_start(along: char, origin: Position) -> (f64, f64) {
+        if along == 'X' {
+            (origin.x, origin.y)
+        } else {
+            (origin.y, origin.x)
+        }
+    }
 
-        match &pocket.shape {
+    /// (axis moved along, sign of travel, axis swept across) for a cut direction
+    fn sweep_axes(direction: Direction) -> Option<(char, f64, char)> {
+        match direction {
+            Direction::XPositive => Some(('X', 1.0, 'Y')),
+            Direction::XNegative => Some(('X', -1.0, 'Y')),
+            Direction::YPositive => Some(('Y', 1.0, 'X')),
+            Direction::YNegative => Some(('Y', -1.0, 'X')),
+            Direction::ZPositive | Direction::ZNegative => None,
+        }
+    }
+
+    fn emit_drill_v2(&mut self, drill: &DrillV2Op) {
+        self.output.emit_comment(&format!(
+            "DRILL dia:{} at X{:.4} Y{:.4}",
+            drill.diameter, drill.position.x, drill.position.y
+        ));
+        let (depth, feed_rate, peck_depth) = self.start_drill(drill);
+
+        // Move to position above the stock
+        self.retract_to_clearance();
+        self.output.emit(&format!(
+            "G00 X{:.4} Y{:.4}",
+            drill.position.x, drill.position.y
+        ));
+
+        // Spot first with the same drill so the point starts on center
+        if drill.spot {
+            let spot_depth = self.spot_depth().min(depth);
+            self.output.emit_comment(&format!("Spot drill Z-{:.4}", spot_depth));
+            self.output.emit(&format!(
+                "G81 R{:.1} Z-{:.4} F{:.1}",
+                self.clearance(),
+                spot_depth,
+                feed_rate
+            ));
+        }
+
+        let cycle = self.drill_cycle(drill, depth, feed_rate, peck_depth);
+        self.output.emit(&cycle);
+    }
+
+    /// Work out a hole's depth and Black Book feeds and start the spindle for it,
+    /// returning the depth, feed, and peck
+    fn start_drill(&mut self, drill: &DrillV2Op) -> (f64, f64, f64) {
+        // Calculate depth
+        let depth = match &drill.depth {
+            DrillDepth::Thru => {
+                // Stock thickness plus the 118° point so the full diameter breaks out
+                let thickness = self.stock.as_ref().map(|s| s.size_z).unwrap_or(0.5);
+                let breakthrough = drill.diameter * 0.3;
+                let depth = thickness + breakthrough;
+                self.output.emit_comment(&format!(
+                    "Thru depth: {:.4} = stock {:.4} + tip {:.4}",
+                    depth, thickness, breakthrough
+                ));
+                depth
+            }
+            DrillDepth::Depth(z) => *z,
+        };
+        let depth = self.clamp_depth(depth);
+
+        // Get cutting parameters from Black Book
+        let (rpm, feed_rate, peck_depth) = self.calculate_drill_params(drill.diameter, depth);
+
+        // Output calculated parameters
+        self.output.emit_comment(&format!(
+            "Black Book: RPM={:.0}, Feed={:.1} {}, Peck={:.3}{}",
+            rpm,
+            feed_rate,
+            self.feed_unit(),
+            peck_depth,
+            self.length_mark()
+        ));
+
+        // Spindle speed
+        self.output.emit(&format!("S{:.0} M03", rpm));
+
+        (depth, feed_rate, peck_depth)
+    }
+
+    /// The canned cycle block that drills one hole at the current XY
+    fn drill_cycle(&self, drill: &DrillV2Op, depth: f64, feed_rate: f64, peck_depth: f64) -> String {
+        // Drill cycle: dwell at the bottom in one plunge, peck when asked to,
+        // or by default when deeper than one peck
+        if let Some(dwell) = drill.dwell {
+            format!(
+                "G82 R{:.1} Z-{:.4} P{:.2} F{:.1}",
+                self.clearance(),
+                depth,
+                dwell,
+                feed_rate
+            )
+        } else if drill.peck.is_some() || peck_depth < depth {
+            let mode = drill.peck.unwrap_or_else(|| {
+                self.current_material
+                    .as_ref()
+                    .and_then(|m| self.black_book.suggest_peck_mode(m).ok())
+                    .unwrap_or(PeckMode::Clear)
+            });
+            let cycle = match mode {
+                PeckMode::Break => "G73",
+                PeckMode::Clear => "G83",
+            };
+            format!(
+                "{} R{:.1} Z-{:.4} Q{:.4} F{:.1}",
+                cycle,
+                self.clearance(),
+                depth,
+                peck_depth,
+                feed_rate
+            )
+        } else {
+            // Standard drill cycle
+            format!("G81 R{:.1} Z-{:.4} F{:.1}", self.clearance(), depth, feed_rate)
+        }
+    }
+
+    fn emit_pocket_v2(&mut self, pocket: &PocketV2Op) {
+        // Get tool diameter (from current tool or default)
+        let tool_dia = self
+            .current_tool_data
+            .as_ref()
+            .map(|t| t.diameter)
+            .unwrap_or(0.25); // Default 1/4" end mill
+
+        let depth = self.clamp_depth(pocket.depth);
+        self.plunge_override = pocket.plunge;
+
+        // Calculate cutting parameters from Black Book
+        let (rpm, feed_rate, bb_stepdown, bb_stepover) = if pocket.adaptive {
+            self.calculate_adaptive_params(tool_dia)
+        } else {
+            self.calculate_pocket_params(tool_dia, depth)
+        };
+        // The pocket's own engagement wins; feed and speed stay the Black Book's
+        let stepdown = pocket.stepdown.unwrap_or(bb_stepdown);
+        let stepover = pocket.stepover.map_or(bb_stepover, |s| s * tool_dia);
+
+        // Calculate number of passes
+        let num_passes = (depth / stepdown).ceil() as i32;
+
+        match &pocket.shape {
             PocketShape::Rect { width, height } => {
                 self.output.emit_comment(&format!(
                     "POCKET RECT {}x{} at X{:.4} Y{:.4} depth:{:.4}",
-                    width, height, pocket.position.x, pocket.position.y, pocket.depth
+                    width, height, pocket.position.x, pocket.position.y, depth
                 ));
                 self.output.emit_comment(&format!(
-                    "Black Book: RPM={:.0}, Feed={:.1} IPM, Stepdown={:.3}\", Stepover={:.3}\"",
-                    rpm, feed_rate, stepdown, stepover
+                    "Black Book: RPM={:.0}, Feed={:.1} {}, Stepdown={:.3}{}, Stepover={:.3}{}",
+                    rpm,
+                    feed_rate,
+                    self.feed_unit(),
+                    bb_stepdown,
+                    self.length_mark(),
+                    bb_stepover,
+                    self.length_mark()
                 ));
                 self.output.emit_comment(&format!(
-                    "Passes required: {} (DOC={:.3}\")",
-                    num_passes, stepdown
+                    "Passes required: {} (DOC={:.3}{})",
+                    num_passes,
+                    stepdown,
+                    self.length_mark()
                 ));
+                if pocket.adaptive {
+                    self.output
+                        .emit_comment("Adaptive clearing: trochoidal loops at constant engagement");
+                }
             }
             PocketShape::Circle { diameter } => {
                 self.output.emit_comment(&format!(
                     "POCKET CIRCLE dia:{} at X{:.4} Y{:.4} depth:{:.4}",
-                    diameter, pocket.position.x, pocket.position.y, pocket.depth
+                    diameter, pocket.position.x, pocket.position.y, depth
                 ));
                 self.output.emit_comment(&format!(
-                    "Black Book: RPM={:.0}, Feed={:.1} IPM, Stepdown={:.3}\", Stepover={:.3}\"",
-                    rpm, feed_rate, stepdown, stepover
+                    "Black Book: RPM={:.0}, Feed={:.1} {}, Stepdown={:.3}{}, Stepover={:.3}{}",
+                    rpm,
+                    feed_rate,
+                    self.feed_unit(),
+                    bb_stepdown,
+                    self.length_mark(),
+                    bb_stepover,
+                    self.length_mark()
                 ));
                 self.output.emit_comment(&format!(
-                    "Passes required: {} (DOC={:.3}\")",
-                    num_passes, stepdown
+                    "Passes required: {} (DOC={:.3}{})",
+                    num_passes,
+                    stepdown,
+                    self.length_mark()
                 ));
             }
         }
+        if pocket.stepdown.is_some() {
+            self.output
+                .emit_comment(&format!("Stepdown override: {:.3}{}", stepdown, self.length_mark()));
+        }
+        if let Some(fraction) = pocket.stepover {
+            self.output.emit_comment(&format!(
+                "Stepover override: {:.3}{} ({:.0}% of tool)",
+                stepover,
+                self.length_mark(),
+                fraction * 100.0
+            ));
+        }
+        self.emit_plunge_comment(feed_rate);
 
         // Spindle speed
         self.output.emit(&format!("S{:.0} M03", rpm));
@@ -495,21 +2097,66 @@ impl CodeGenerator {
         // Spindle speed
         self.output.emit(&format!("S{:.0} M03", rpm));
 
-        // Generate passes
+        // Roughing stays clear of the walls by the finish allowance; an existing
+        // part's walls are near size, so by default it leaves only a skim
+        let allowance = match pocket.finish {
+            Some(finish) => finish,
+            None if self.existing_part => self.existing_part_allowance(),
+            None => 0.0,
+        };
+        if allowance > 0.0 {
+            self.output
+                .emit_comment(&format!("Finish allowance: {:.4}{}", allowance, self.length_mark()));
+            if let Some(flutes) = self.current_tool_data.as_ref().map(|t| t.flutes) {
+                // The spring pass cuts the wall with the tool's side, one pass deep
+                if rpm > 0.0 && flutes > 0 {
+                    let feed_per_tooth = feed_rate / (rpm * flutes as f64);
+                    let finish = crate::black_book::estimate_surface_finish(
+                        feed_per_tooth,
+                        tool_dia / 2.0,
+                        0.0,
+                    );
+                    self.output.emit_comment(&format!(
+                        "Estimated wall finish: Ra {:.1} uin ({:.2} um)",
+                        finish.microinches, finish.micrometers
+                    ));
+                }
+            }
+        }
+
+        // Clear the stock before rapiding over to the pocket
+        self.retract_to_clearance();
+
+        // Generate passes, each entering from the floor of the one before
         for pass_num in 1..=num_passes {
-            let z_depth = (pass_num as f64 * stepdown).min(pocket.depth);
+            let from_z = ((pass_num - 1) as f64 * stepdown).min(depth);
+            let z_depth = (pass_num as f64 * stepdown).min(depth);
             self.output.emit_comment(&format!(
                 "Pass {}/{}: Z={:.3}",
                 pass_num, num_passes, -z_depth
             ));
 
             match &pocket.shape {
+                PocketShape::Rect { width, height } if pocket.adaptive => {
+                    self.generate_trochoidal_pocket(
+                        pocket.position.x,
+                        pocket.position.y,
+                        width - 2.0 * allowance,
+                        height - 2.0 * allowance,
+                        from_z,
+                        z_depth,
+                        tool_dia,
+                        stepover,
+                        feed_rate,
+                    );
+                }
                 PocketShape::Rect { width, height } => {
                     self.generate_rectangular_pocket(
                         pocket.position.x,
                         pocket.position.y,
-                        *width,
-                        *height,
+                        width - 2.0 * allowance,
+                        height - 2.0 * allowance,
+                        from_z,
                         z_depth,
                         tool_dia,
                         stepover,
@@ -520,7 +2167,8 @@ impl CodeGenerator {
                     self.generate_circular_pocket(
                         pocket.position.x,
                         pocket.position.y,
-                        *diameter,
+                        diameter - 2.0 * allowance,
+                        from_z,
                         z_depth,
                         tool_dia,
                         stepover,
@@ -530,16 +2178,159 @@ impl CodeGenerator {
             }
         }
 
+        if allowance > 0.0 {
+            self.emit_pocket_finish(pocket, depth, tool_dia, feed_rate);
+        }
+        if let PocketShape::Rect { width, height } = pocket.shape {
+            if pocket.corners != CornerRelief::None {
+                self.emit_corner_relief(pocket, width, height, depth, tool_dia, feed_rate);
+            }
+        }
+
         // Retract
-        self.output.emit("G00 Z0.1");
+        self.retract_to_clearance();
+        self.plunge_override = None;
+    }
+
+    /// Spring pass around the pocket wall at full depth, taking off the finish allowance
+    fn emit_pocket_finish(&mut self, pocket: &PocketV2Op, depth: f64, tool_dia: f64, feed_rate: f64) {
+        let (cx, cy) = (pocket.position.x, pocket.position.y);
+        let tool_radius = tool_dia / 2.0;
+        let ccw = self.contour_ccw(CutSide::Inside);
+        self.output
+            .emit_comment(&format!("FINISH PASS Z={:.3}", -depth));
+
+        match pocket.shape {
+            PocketShape::Rect { width, height } => {
+                let (half_w, half_h) = (width / 2.0 - tool_radius, height / 2.0 - tool_radius);
+                if half_w <= 0.0 || half_h <= 0.0 {
+                    return;
+                }
+                let (min_x, max_x) = (cx - half_w, cx + half_w);
+                let min_y = cy - half_h;
+                let mut max_y = cy + half_h;
+                if let Some(limit) = self.y_center_limit(tool_dia) {
+                    if min_y > limit {
+                        return;
+                    }
+                    max_y = max_y.min(limit);
+                }
+
+                self.output.emit(&format!(
+                    "G01 X{:.4} Y{:.4} Z-{:.4} F{:.1}",
+                    min_x, min_y, depth, feed_rate
+                ));
+                if ccw {
+                    self.output.emit(&format!("G01 X{:.4}", max_x));
+                    self.output.emit(&format!("G01 Y{:.4}", max_y));
+                    self.output.emit(&format!("G01 X{:.4}", min_x));
+                    self.output.emit(&format!("G01 Y{:.4}", min_y));
+                } else {
+                    self.output.emit(&format!("G01 Y{:.4}", max_y));
+                    self.output.emit(&format!("G01 X{:.4}", max_x));
+                    self.output.emit(&format!("G01 Y{:.4}", min_y));
+                    self.output.emit(&format!("G01 X{:.4}", min_x));
+                }
+            }
+            PocketShape::Circle { diameter } => {
+                let radius = diameter / 2.0 - tool_radius;
+                if radius <= 0.0 {
+                    return;
+                }
+                let arc = if ccw { "G03" } else { "G02" };
+                self.output.emit(&format!(
+                    "G01 X{:.4} Y{:.4} Z-{:.4} F{:.1}",
+                    cx + radius,
+                    cy,
+                    depth,
+                    feed_rate
+                ));
+                self.output.emit(&format!(
+                    "{} X{:.4} Y{:.4} I{:.4} J0.0000 F{:.1}",
+                    arc,
+                    cx + radius,
+                    cy,
+                    -radius,
+                    feed_rate
+                ));
+            }
+        }
+    }
+
+    /// Overcut each inside corner of a rectangular pocket past the tool-radius fillet.
+    /// Dogbones move the tool diagonally until its edge reaches the corner; T-bones
+    /// move it one tool radius along the pocket's long axis.
+    fn emit_corner_relief(
+        &mut self,
+        pocket: &PocketV2Op,
+        width: f64,
+        height: f64,
+        depth: f64,
+        tool_dia: f64,
+        feed_rate: f64,
+    ) {
+        let tool_radius = tool_dia / 2.0;
+        let (half_w, half_h) = (width / 2.0 - tool_radius, height / 2.0 - tool_radius);
+        if half_w <= 0.0 || half_h <= 0.0 {
+            return;
+        }
+        let (cx, cy) = (pocket.position.x, pocket.position.y);
+
+        // Corners clipped away by the y-limit aren't corners of the finished pocket
+        let top_reachable = match self.y_center_limit(tool_dia) {
+            Some(limit) if cy - half_h > limit => return,
+            Some(limit) => cy + half_h <= limit,
+            None => true,
+        };
+
+        self.output.emit_comment(&format!(
+            "CORNER RELIEF {} Z={:.3}",
+            if pocket.corners == CornerRelief::Dogbone { "dogbone" } else { "tbone" },
+            -depth
+        ));
+        for (sx, sy) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            if sy > 0.0 && !top_reachable {
+                continue;
+            }
+            let corner = (cx + sx * half_w, cy + sy * half_h);
+            let (dx, dy) = match pocket.corners {
+                CornerRelief::Dogbone => {
+                    let d = tool_radius * (1.0 - std::f64::consts::FRAC_1_SQRT_2);
+                    (sx * d, sy * d)
+                }
+                _ if width >= height => (sx * tool_radius, 0.0),
+                _ => (0.0, sy * tool_radius),
+            };
+            self.output.emit(&format!(
+                "G01 X{:.4} Y{:.4} F{:.1}",
+                corner.0, corner.1, feed_rate
+            ));
+            self.output
+                .emit(&format!("G01 X{:.4} Y{:.4}", corner.0 + dx, corner.1 + dy));
+            self.output
+                .emit(&format!("G01 X{:.4} Y{:.4}", corner.0, corner.1));
+        }
+    }
+
+    /// A pattern's positions in travel order, or as written under `keep-pattern-order`
+    fn pattern_positions(&self, pattern: &Pattern) -> Vec<Position> {
+        if self.setup.as_ref().is_some_and(|s| s.keep_pattern_order) {
+            pattern.positions()
+        } else {
+            pattern.travel_order()
+        }
     }
 
     fn emit_drill_pattern(&mut self, drill: &DrillPatternOp) {
         self.output
             .emit_comment(&format!("DRILL PATTERN - DIA={:.3}", drill.diameter));
 
-        // Calculate positions from pattern
-        let positions = self.calculate_pattern_positions(&drill.pattern);
+        let positions = self.pattern_positions(&drill.pattern);
+        // Incremental output would re-add the moves between calls
+        if self.use_subprograms && !self.incremental && positions.len() > 1 {
+            self.emit_drill_subprogram(drill, &positions);
+            return;
+        }
 
         // Generate drill operations for each position
         for (i, pos) in positions.iter().enumerate() {
@@ -549,16 +2340,64 @@ impl CodeGenerator {
                 diameter: drill.diameter,
                 position: *pos,
                 depth: drill.depth.clone(),
+                spot: false,
+                peck: None,
+                dwell: None,
             };
             self.emit_drill_v2(&drill_op);
         }
     }
 
+    /// One subprogram drills the hole under the tool and leaves G91 on, so the
+    /// pattern is a rapid to each hole, relative to the last, and an `M98` call
+    fn emit_drill_subprogram(&mut self, drill: &DrillPatternOp, positions: &[Position]) {
+        let mut number = SUBPROGRAM_START + self.subprograms.len() as u32;
+        if self
+            .program_number
+            .is_some_and(|main| (SUBPROGRAM_START..=number).contains(&main))
+        {
+            number += 1;
+        }
+        let hole = DrillV2Op {
+            diameter: drill.diameter,
+            position: positions[0],
+            depth: drill.depth.clone(),
+            spot: false,
+            peck: None,
+            dwell: None,
+        };
+        let (depth, feed_rate, peck_depth) = self.start_drill(&hole);
+        let cycle = self.drill_cycle(&hole, depth, feed_rate, peck_depth);
+        self.subprograms
+            .push((number, vec![format!("G90 {}", cycle), "G80".to_string(), "G91".to_string()]));
+        self.output.emit_comment(&format!(
+            "{} holes from subprogram O{}",
+            positions.len(),
+            number
+        ));
+
+        self.retract_to_clearance();
+        let mut last = positions[0];
+        self.output
+            .emit(&format!("G00 X{:.4} Y{:.4}", last.x, last.y));
+        for (i, pos) in positions.iter().enumerate() {
+            if i > 0 {
+                self.output.emit(&format!(
+                    "G00 X{:.4} Y{:.4}",
+                    pos.x - last.x,
+                    pos.y - last.y
+                ));
+                last = *pos;
+            }
+            self.output.emit(&format!("M98 P{}", number));
+        }
+        self.output.emit("G90");
+    }
+
     fn emit_pocket_pattern(&mut self, pocket: &PocketPatternOp) {
         self.output.emit_comment("POCKET PATTERN");
 
-        // Calculate positions from pattern
-        let positions = self.calculate_pattern_positions(&pocket.pattern);
+        let positions = self.pattern_positions(&pocket.pattern);
 
         // Generate pocket operations for each position
         for (i, pos) in positions.iter().enumerate() {
@@ -569,103 +2408,17 @@ impl CodeGenerator {
                 shape: pocket.shape.clone(),
                 position: *pos,
                 depth: pocket.depth,
+                finish: None,
+                corners: CornerRelief::None,
+                adaptive: false,
+                plunge: None,
+                stepover: None,
+                stepdown: None,
             };
             self.emit_pocket_v2(&pocket_op);
         }
     }
 
-    fn calculate_pattern_positions(&self, pattern: &Pattern) -> Vec<Position> {
-        match pattern {
-            Pattern::Grid {
-                rows,
-                cols,
-                spacing_x,
-                spacing_y,
-                start_position,
-            } => {
-                let mut positions = Vec::new();
-                for row in 0..*rows {
-                    for col in 0..*cols {
-                        positions.push(Position::new(
-                            start_position.x + col as f64 * spacing_x,
-                            start_position.y + row as f64 * spacing_y,
-                        ));
-                    }
-                }
-                positions
-            }
-            Pattern::BoltCircle {
-                count,
-                diameter,
-                center,
-                start_angle,
-            } => {
-                let mut positions = Vec::new();
-                let radius = diameter / 2.0;
-                let start_rad = start_angle.to_radians();
-                let angle_step = 2.0 * std::f64::consts::PI / *count as f64;
-
-                for i in 0..*count {
-                    let angle = start_rad + i as f64 * angle_step;
-                    positions.push(Position::new(
-                        center.x + radius * angle.cos(),
-                        center.y + radius * angle.sin(),
-                    ));
-                }
-                positions
-            }
-            Pattern::Line {
-                count,
-                spacing,
-                direction,
-                start_position,
-            } => {
-                let mut positions = Vec::new();
-                let (dx, dy) = match direction {
-                    Direction::XPositive => (1.0, 0.0),
-                    Direction::XNegative => (-1.0, 0.0),
-                    Direction::YPositive => (0.0, 1.0),
-                    Direction::YNegative => (0.0, -1.0),
-                    _ => (1.0, 0.0),
-                };
-
-                for i in 0..*count {
-                    positions.push(Position::new(
-                        start_position.x + i as f64 * spacing * dx,
-                        start_position.y + i as f64 * spacing * dy,
-                    ));
-                }
-                positions
-            }
-            Pattern::Arc {
-                count,
-                radius,
-                center,
-                start_angle,
-                end_angle,
-            } => {
-                let mut positions = Vec::new();
-                let start_rad = start_angle.to_radians();
-                let end_rad = end_angle.to_radians();
-                let angle_range = end_rad - start_rad;
-                let angle_step = if *count > 1 {
-                    angle_range / (*count - 1) as f64
-                } else {
-                    0.0
-                };
-
-                for i in 0..*count {
-                    let angle = start_rad + i as f64 * angle_step;
-                    positions.push(Position::new(
-                        center.x + radius * angle.cos(),
-                        center.y + radius * angle.sin(),
-                    ));
-                }
-                positions
-            }
-        }
-    }
-
     /// Generate zigzag raster pocket for rectangular pockets
     #[allow(clippy::too_many_arguments)]
     fn generate_rectangular_pocket(
@@ -674,6 +2427,7 @@ impl CodeGenerator {
         center_y: f64,
         width: f64,
         height: f64,
+        from_z: f64,
         depth: f64,
         tool_dia: f64,
         stepover: f64,
@@ -685,20 +2439,78 @@ impl CodeGenerator {
         let min_x = center_x - half_width;
         let max_x = center_x + half_width;
         let min_y = center_y - half_height;
-        let max_y = center_y + half_height;
-
-        // Rapid to start position (center of pocket)
-        self.output
-            .emit(&format!("G00 X{:.4} Y{:.4}", center_x, center_y));
+        let mut max_y = center_y + half_height;
 
-        // Plunge to depth
-        self.output
-            .emit(&format!("G01 Z-{:.4} F{:.1}", depth, feed_rate * 0.3));
+        // Keep the cutter behind the y-limit (clamp side of the stock)
+        if let Some(limit) = self.y_center_limit(tool_dia) {
+            if min_y > limit {
+                self.output
+                    .emit_comment("WARNING: pocket lies beyond y-limit - skipped");
+                return;
+            }
+            if max_y > limit {
+                self.output.emit_comment(&format!(
+                    "Y clipped to {:.4} by y-limit",
+                    limit
+                ));
+                max_y = limit;
+            }
+        }
+        let start_y = center_y.min(max_y);
+
+        // One-way passes run from `pass_from` to `pass_to`. The engaged side of each
+        // pass is +Y, which an M03 cutter climbs when travelling -X.
+        let one_way = self.milling_direction().map(|dir| match dir {
+            MillingDirection::Climb => (max_x, min_x),
+            MillingDirection::Conventional => (min_x, max_x),
+        });
+        let (ramp_from, ramp_to) = one_way.unwrap_or((min_x, max_x));
+
+        let plunge = self.can_plunge() || max_x - min_x <= 0.0;
+        if plunge {
+            // Rapid to start position (center of pocket) and plunge to depth
+            self.output
+                .emit(&format!("G00 X{:.4} Y{:.4}", center_x, start_y));
+            self.emit_pass_top(from_z, feed_rate);
+            self.output
+                .emit(&format!("G01 Z-{:.4} F{:.1}", depth, self.plunge_feed(feed_rate)));
+        } else {
+            // Zig-zag ramp across the center of the pocket, ending where passes start
+            self.output
+                .emit(&format!("G00 X{:.4} Y{:.4}", ramp_from, start_y));
+            self.emit_pass_top(from_z, feed_rate);
+            let (plunge_feed, source) = self.plunge_source(feed_rate);
+            self.emit_zigzag_ramp(ramp_from, ramp_to, from_z, depth, (plunge_feed, &source));
+        }
 
         // Calculate number of Y steps
         let y_range = max_y - min_y;
         let num_passes = (y_range / stepover).ceil() as i32;
-        let actual_stepover = y_range / num_passes as f64;
+        // A pocket as wide as the tool is a single pass
+        let actual_stepover = if num_passes > 0 { y_range / num_passes as f64 } else { 0.0 };
+
+        if let Some((pass_from, pass_to)) = one_way {
+            if plunge {
+                self.output
+                    .emit(&format!("G01 X{:.4} F{:.1}", pass_from, feed_rate));
+            }
+            for i in 0..=num_passes {
+                let y = min_y + i as f64 * actual_stepover;
+                if i > 0 {
+                    // Lift off the floor and return along the pass just cut
+                    self.output
+                        .emit(&format!("G00 Z{:.4}", self.pass_lift() - depth));
+                    self.output.emit(&format!("G00 X{:.4}", pass_from));
+                    self.output
+                        .emit(&format!("G01 Z-{:.4} F{:.1}", depth, self.plunge_feed(feed_rate)));
+                }
+                self.output
+                    .emit(&format!("G01 Y{:.4} F{:.1}", y, feed_rate));
+                self.output
+                    .emit(&format!("G01 X{:.4} F{:.1}", pass_to, feed_rate));
+            }
+            return;
+        }
 
         // Zigzag pattern
         for i in 0..=num_passes {
@@ -726,135 +2538,447 @@ impl CodeGenerator {
         }
     }
 
-    /// Generate spiral pocket for circular pockets
+    /// Clear a rectangular pocket with trochoidal loops: lanes along its long axis, each a
+    /// chain of tool-center circles advancing `stepover` per loop so every front arc takes
+    /// the same thin bite, then a lap around the wall for the cusps the loops leave
     #[allow(clippy::too_many_arguments)]
-    fn generate_circular_pocket(
+    fn generate_trochoidal_pocket(
         &mut self,
         center_x: f64,
         center_y: f64,
-        diameter: f64,
+        width: f64,
+        height: f64,
+        from_z: f64,
         depth: f64,
         tool_dia: f64,
         stepover: f64,
         feed_rate: f64,
     ) {
-        // Calculate pocket radius (leave tool radius on edge)
-        let pocket_radius = diameter / 2.0 - tool_dia / 2.0;
+        let tool_radius = tool_dia / 2.0;
 
-        if pocket_radius <= 0.0 {
-            // Tool is too big for pocket, just do a center drill
-            self.output
-                .emit(&format!("G00 X{:.4} Y{:.4}", center_x, center_y));
-            self.output
-                .emit(&format!("G01 Z-{:.4} F{:.1}", depth, feed_rate * 0.3));
+        // Lanes run along u, the long axis; v crosses them
+        let along_x = width >= height;
+        let (half_u, half_v) = if along_x {
+            (width / 2.0 - tool_radius, height / 2.0 - tool_radius)
+        } else {
+            (height / 2.0 - tool_radius, width / 2.0 - tool_radius)
+        };
+        let loop_radius = half_v.min(tool_radius);
+
+        let clipped = self
+            .y_center_limit(tool_dia)
+            .is_some_and(|limit| center_y + height / 2.0 - tool_radius > limit);
+        if loop_radius < stepover || clipped {
+            self.output.emit_comment(if clipped {
+                "Trochoidal loops would cross the y-limit - clearing with a raster"
+            } else {
+                "Slot too narrow for trochoidal loops - clearing with a raster"
+            });
+            self.generate_rectangular_pocket(
+                center_x, center_y, width, height, from_z, depth, tool_dia, stepover, feed_rate,
+            );
             return;
         }
 
-        // Calculate number of spiral passes
-        let num_passes = (pocket_radius / stepover).ceil() as i32;
-
-        // Rapid to center
-        self.output
-            .emit(&format!("G00 X{:.4} Y{:.4}", center_x, center_y));
+        // Counter-clockwise loops climb with M03; conventional milling mirrors them
+        let conventional = self.milling_direction() == Some(MillingDirection::Conventional);
+        let (arc, side) = if conventional { ("G02", -1.0) } else { ("G03", 1.0) };
+        let to_xy = |u: f64, v: f64| {
+            let v = v * side;
+            if along_x {
+                (center_x + u, center_y + v)
+            } else {
+                (center_x - v, center_y + u)
+            }
+        };
+        let arc_move = |from: (f64, f64), to: (f64, f64), center: (f64, f64)| {
+            format!(
+                "{} X{:.4} Y{:.4} I{:.4} J{:.4} F{:.1}",
+                arc,
+                to.0,
+                to.1,
+                center.0 - from.0,
+                center.1 - from.1,
+                feed_rate
+            )
+        };
 
-        // Plunge to depth
-        self.output
-            .emit(&format!("G01 Z-{:.4} F{:.1}", depth, feed_rate * 0.3));
+        // Each loop clears a disk one loop radius plus one tool radius around its
+        // center; neighbouring lanes overlap by a stepover so no rib stands between them
+        let reach = loop_radius + tool_radius;
+        let span = 2.0 * (half_v - loop_radius);
+        let lanes = if span < 1e-9 {
+            1
+        } else {
+            (span / (2.0 * reach - stepover)).ceil() as usize + 1
+        };
+        let lane_pitch = if lanes > 1 { span / (lanes - 1) as f64 } else { 0.0 };
+        let u_start = -half_u + loop_radius;
+        let run = 2.0 * (half_u - loop_radius);
+        let loops = (run / stepover).ceil() as usize;
+        let advance = if loops > 0 { run / loops as f64 } else { 0.0 };
 
-        // Spiral outward
-        let points_per_rev = 36; // 10-degree increments
-        for i in 0..=(num_passes * points_per_rev) {
-            let angle = 2.0 * std::f64::consts::PI * (i as f64 / points_per_rev as f64);
-            let radius = stepover * (i as f64 / points_per_rev as f64);
-            let r = radius.min(pocket_radius);
+        self.output.emit_comment(&format!(
+            "Trochoidal: {} lane(s) of {} loops, radius {:.4}, advance {:.4}",
+            lanes, loops, loop_radius, advance
+        ));
 
-            let x = center_x + r * angle.cos();
-            let y = center_y + r * angle.sin();
+        for lane in 0..lanes {
+            let v = -half_v + loop_radius + lane as f64 * lane_pitch;
+            let first = to_xy(u_start, v);
+            let entry = (first.0 + loop_radius, first.1);
 
+            // Fresh stock lies between lanes: come up off the floor to cross it
+            if lane > 0 || from_z > 0.0 {
+                self.output.emit(&format!("G00 Z{:.4}", self.pass_lift() - from_z));
+            }
             self.output
-                .emit(&format!("G01 X{:.4} Y{:.4} F{:.1}", x, y, feed_rate));
+                .emit(&format!("G00 X{:.4} Y{:.4}", entry.0, entry.1));
+            self.emit_pass_top(from_z, feed_rate);
+            if self.can_plunge() {
+                self.output
+                    .emit(&format!("G01 Z-{:.4} F{:.1}", depth, self.plunge_feed(feed_rate)));
+                self.output.emit(&arc_move(entry, entry, first));
+            } else {
+                let (plunge_feed, source) = self.plunge_source(feed_rate);
+                self.emit_helix(
+                    first.0,
+                    first.1,
+                    loop_radius,
+                    from_z,
+                    depth,
+                    feed_rate,
+                    (plunge_feed, &source),
+                );
+            }
 
-            // Stop once we reach full radius
-            if r >= pocket_radius {
-                break;
+            let mut at = to_xy(u_start, v + loop_radius);
+            if (at.0 - entry.0).hypot(at.1 - entry.1) > 1e-9 {
+                self.output.emit(&arc_move(entry, at, first));
+            }
+            for i in 1..=loops {
+                let u_prev = u_start + (i - 1) as f64 * advance;
+                let u = u_start + i as f64 * advance;
+
+                // Back round through the cleared slot to the bottom of the next loop,
+                // then forward round its front, biting `advance` into fresh stock
+                let bottom = to_xy(u, v - loop_radius);
+                self.output
+                    .emit(&arc_move(at, bottom, to_xy((u_prev + u) / 2.0, v)));
+                let top = to_xy(u, v + loop_radius);
+                self.output.emit(&arc_move(bottom, top, to_xy(u, v)));
+                at = top;
             }
         }
 
-        // Finish with full circle at outer radius to clean up
-        self.output.emit_comment("Finish pass");
-        for i in 0..=points_per_rev {
-            let angle = 2.0 * std::f64::consts::PI * (i as f64 / points_per_rev as f64);
-            let x = center_x + pocket_radius * angle.cos();
-            let y = center_y + pocket_radius * angle.sin();
+        // The loops only touch the walls; one lap takes off the cusps between them
+        self.output.emit_comment("Wall lap");
+        for (u, v) in [
+            (half_u, half_v),
+            (-half_u, half_v),
+            (-half_u, -half_v),
+            (half_u, -half_v),
+            (half_u, half_v),
+        ] {
+            let (x, y) = to_xy(u, v);
             self.output
                 .emit(&format!("G01 X{:.4} Y{:.4} F{:.1}", x, y, feed_rate));
         }
     }
 
-    fn calculate_chamfer_feed(&self) -> f64 {
-        if let Some(ref tool) = self.current_tool_data {
-            if let Some(ref material) = self.current_material {
-                let bb_tool = crate::black_book::ToolGeometry {
-                    diameter: tool.diameter,
-                    flute_count: tool.flutes,
-                    tool_material: match tool.material {
-                        crate::ast::ToolMaterial::HSS => crate::black_book::ToolMaterial::HSS,
-                        crate::ast::ToolMaterial::Carbide => crate::black_book::ToolMaterial::Carbide,
-                        crate::ast::ToolMaterial::Cobalt => crate::black_book::ToolMaterial::Cobalt,
-                        crate::ast::ToolMaterial::Ceramic => crate::black_book::ToolMaterial::Ceramic,
-                    },
-                    corner_radius: None,
-                    coating: None,
-                };
-                let engagement = crate::black_book::Engagement {
-                    axial_doc: tool.diameter * 0.1, // Light engagement for chamfering
-                    radial_woc: tool.diameter * 0.2,
-                    radial_engagement_pct: 20.0,
-                };
-                if let Ok(params) = self.black_book.calculate(material, &bb_tool, &engagement) {
-                    let (_, feed) = self.apply_rpm_limit(params.rpm as f64, params.feed_rate_ipm * 0.5);
-                    return feed;
-                }
-            }
-        }
-        20.0 // Default conservative feed
+    /// Generate spiral pocket for circular pockets
+    #[allow(clippy::too_many_arguments)]
+    fn generate_circular_pocket(
+        &mut self,
+        center_x: f64,
+        center_y: f64,
+        diameter: f64,
+        from_z: f64,
+        depth: f64,
+        tool_dia: f64,
+        stepover: f64,
+        feed_rate: f64,
+    ) {
+        // Calculate pocket radius (leave tool radius on edge)
+        let pocket_radius = diameter / 2.0 - tool_dia / 2.0;
+
+        if pocket_radius <= 0.0 {
+            // Tool is too big for pocket, just do a center drill
+            if !self.can_plunge() {
+                self.output.emit_comment(
+                    "WARNING: no room to ramp - plunging a tool not marked center-cutting",
+                );
+            }
+            self.output
+                .emit(&format!("G00 X{:.4} Y{:.4}", center_x, center_y));
+            self.emit_pass_top(from_z, feed_rate);
+            self.output
+                .emit(&format!("G01 Z-{:.4} F{:.1}", depth, self.plunge_feed(feed_rate)));
+            return;
+        }
+
+        if self.can_plunge() {
+            // Rapid to center and plunge to depth
+            self.output
+                .emit(&format!("G00 X{:.4} Y{:.4}", center_x, center_y));
+            self.emit_pass_top(from_z, feed_rate);
+            self.output
+                .emit(&format!("G01 Z-{:.4} F{:.1}", depth, self.plunge_feed(feed_rate)));
+        } else {
+            // Helix down around the center, then step in to start the spiral
+            let helix_radius = (tool_dia / 2.0).min(pocket_radius);
+            self.output.emit(&format!(
+                "G00 X{:.4} Y{:.4}",
+                center_x + helix_radius,
+                center_y
+            ));
+            self.emit_pass_top(from_z, feed_rate);
+            let (plunge_feed, source) = self.plunge_source(feed_rate);
+            self.emit_helix(
+                center_x,
+                center_y,
+                helix_radius,
+                from_z,
+                depth,
+                feed_rate,
+                (plunge_feed, &source),
+            );
+            self.output.emit(&format!(
+                "G01 X{:.4} Y{:.4} F{:.1}",
+                center_x, center_y, feed_rate
+            ));
+        }
+
+        // Counter-clockwise keeps the wall on the cutter's right: climb with M03
+        let conventional = self.milling_direction() == Some(MillingDirection::Conventional);
+        let (arc, direction) = if conventional { ("G02", (0.0, -1.0)) } else { ("G03", (0.0, 1.0)) };
+
+        // Spiral outward as half-turn arcs, each growing by half a stepover so
+        // consecutive laps sit one stepover apart (tangent at every joint)
+        let mut radius: f64 = 0.0;
+        let mut side = 1.0; // +1 starts the half-turn right of center, -1 left
+        while radius < pocket_radius {
+            let next = (radius + stepover / 2.0).min(pocket_radius);
+            self.output.emit(&format!(
+                "{} X{:.4} Y{:.4} I{:.4} J0.0000 F{:.1}",
+                arc,
+                center_x - side * next,
+                center_y,
+                -side * (radius + next) / 2.0,
+                feed_rate
+            ));
+            radius = next;
+            side = -side;
+        }
+
+        // Finish with full circle at outer radius to clean up, arcing on and off the wall
+        self.output.emit_comment("Finish pass");
+        let start = (center_x + pocket_radius, center_y);
+        let lead = (tool_dia / 2.0).min(pocket_radius / 2.0);
+        let entry = Self::lead_in_start(start, direction, (-1.0, 0.0), lead);
+        self.output.emit(&format!(
+            "G01 X{:.4} Y{:.4} F{:.1}",
+            entry.0, entry.1, feed_rate
+        ));
+        self.emit_lead_in(start, direction, (-1.0, 0.0), lead, feed_rate, 4);
+        self.output.emit(&format!(
+            "{} X{:.4} Y{:.4} I{:.4} J0.0000 F{:.1}",
+            arc, start.0, start.1, -pocket_radius, feed_rate
+        ));
+        self.emit_lead_out(start, direction, (-1.0, 0.0), lead, feed_rate, 4);
+    }
+
+    /// Milling direction requested by the setup block, if any
+    fn milling_direction(&self) -> Option<MillingDirection> {
+        self.setup.as_ref().and_then(|s| s.milling_direction)
+    }
+
+    /// Whether a contour on `side` runs counter-clockwise. Without a setup preference
+    /// contours always do; otherwise an M03 cutter climbs with the tool on the left of
+    /// its path: clockwise around outsides, counter-clockwise inside.
+    fn contour_ccw(&self, side: CutSide) -> bool {
+        match (self.milling_direction(), side) {
+            (None, _) | (_, CutSide::On) => true,
+            (Some(MillingDirection::Climb), side) => side == CutSide::Inside,
+            (Some(MillingDirection::Conventional), side) => side == CutSide::Outside,
+        }
+    }
+
+    /// Plunge feed for a cut fed at `feed_rate`, and where it came from: the
+    /// operation's `plunge`, the library tool's plunge feed, or the Black Book's
+    /// share of the feed for the material (30% without one)
+    fn plunge_source(&self, feed_rate: f64) -> (f64, String) {
+        if let Some(feed) = self.plunge_override {
+            return (feed, "plunge override".to_string());
+        }
+        if let Some(feed) = self.tool_plunge_feed {
+            return (feed, "tool library".to_string());
+        }
+        match self
+            .current_material
+            .as_ref()
+            .and_then(|m| self.black_book.plunge_factor(m).ok())
+        {
+            Some(factor) => (
+                feed_rate * factor,
+                format!("Black Book {:.0}% of feed", factor * 100.0),
+            ),
+            None => (feed_rate * 0.3, "30% of feed".to_string()),
+        }
+    }
+
+    fn plunge_feed(&self, feed_rate: f64) -> f64 {
+        self.plunge_source(feed_rate).0
+    }
+
+    fn emit_plunge_comment(&mut self, feed_rate: f64) {
+        let (feed, source) = self.plunge_source(feed_rate);
+        self.output
+            .emit_comment(&format!("Plunge: {:.1} {} ({})", feed, self.feed_unit(), source));
+    }
+
+    /// Whether the current tool may plunge straight down instead of ramping
+    fn can_plunge(&self) -> bool {
+        self.current_tool_data
+            .as_ref()
+            .map(|t| t.center_cutting)
+            .unwrap_or(false)
+    }
+
+    /// Feed down to the floor left by the previous pass (the stock top on the first)
+    fn emit_pass_top(&mut self, from_z: f64, feed_rate: f64) {
+        if from_z > 0.0 {
+            self.output
+                .emit(&format!("G01 Z-{:.4} F{:.1}", from_z, self.plunge_feed(feed_rate)));
+        } else {
+            self.output
+                .emit(&format!("G01 Z0.0000 F{:.1}", self.plunge_feed(feed_rate)));
+        }
+    }
+
+    /// Ramp from `from_z` to `depth` with legs between `x_a` and `x_b`,
+    /// dropping at most `ramp_angle` per leg; ends back on `x_a` at full depth.
+    /// The legs run at the plunge feed, noted with where it came from.
+    fn emit_zigzag_ramp(&mut self, x_a: f64, x_b: f64, from_z: f64, depth: f64, plunge: (f64, &str)) {
+        let leg = (x_b - x_a).abs();
+        let drop_per_leg = leg * self.ramp_angle.to_radians().tan();
+        let mut z = from_z;
+        let mut at_a = true;
+
+        self.output.emit_comment(&format!(
+            "Ramp entry at {:.1} deg, F{:.1} ({})",
+            self.ramp_angle, plunge.0, plunge.1
+        ));
+        while z < depth || !at_a {
+            z = (z + drop_per_leg).min(depth);
+            let x = if at_a { x_b } else { x_a };
+            self.output
+                .emit(&format!("G01 X{:.4} Z-{:.4} F{:.1}", x, z, plunge.0));
+            at_a = !at_a;
+        }
+    }
+
+    /// Helical descent from `from_z` to `depth` on a circle of `radius` around the
+    /// center, starting and ending at 3 o'clock, with a flat lap to clean the floor.
+    /// The laps down run at the plunge feed and the flat lap at `feed_rate`.
+    #[allow(clippy::too_many_arguments)]
+    fn emit_helix(
+        &mut self,
+        center_x: f64,
+        center_y: f64,
+        radius: f64,
+        from_z: f64,
+        depth: f64,
+        feed_rate: f64,
+        plunge: (f64, &str),
+    ) {
+        let pitch = 2.0 * std::f64::consts::PI * radius * self.ramp_angle.to_radians().tan();
+        let laps = ((depth - from_z) / pitch).ceil().max(1.0) as i32;
+        let start_x = center_x + radius;
+
+        self.output.emit_comment(&format!(
+            "Helical entry at {:.1} deg, {} lap(s), F{:.1} ({})",
+            self.ramp_angle, laps, plunge.0, plunge.1
+        ));
+        for lap in 1..=laps {
+            let z = from_z + (depth - from_z) * lap as f64 / laps as f64;
+            self.output.emit(&format!(
+                "G03 X{:.4} Y{:.4} Z-{:.4} I{:.4} J0.0000 F{:.1}",
+                start_x, center_y, z, -radius, plunge.0
+            ));
+        }
+        self.output.emit(&format!(
+            "G03 X{:.4} Y{:.4} I{:.4} J0.0000 F{:.1}",
+            start_x, center_y, -radius, feed_rate
+        ));
+    }
+
+    fn calculate_chamfer_feed(&mut self) -> f64 {
+        if let Some(ref tool) = self.current_tool_data {
+            if let Some(ref material) = self.current_material {
+                let bb_tool = self.black_book_tool(tool);
+                let engagement = crate::black_book::Engagement {
+                    axial_doc: tool.diameter * 0.1, // Light engagement for chamfering
+                    radial_woc: tool.diameter * 0.2,
+                    radial_engagement_pct: 20.0,
+                };
+                if let Ok(params) = self.black_book.calculate(material, &bb_tool, &engagement) {
+                    let (_, feed) = self.apply_rpm_limit(params.rpm as f64, params.feed_rate_ipm * 0.5);
+                    return feed;
+                }
+            }
+        }
+        20.0 // Default conservative feed
     }
 
     fn emit_chamfer(&mut self, chamfer: &ChamferOp) {
         use crate::ast::ChamferGeometry;
 
         self.output.emit_comment(&format!(
-            "CHAMFER - width: {:.3}",
-            chamfer.width
+            "CHAMFER - width: {:.3}, {:.0} deg tool",
+            chamfer.width, chamfer.tool_angle
         ));
 
-        // Get tool diameter for calculations
-        let tool_dia = self.current_tool_data.as_ref().map(|t| t.diameter).unwrap_or(0.25);
         let feed_rate = self.calculate_chamfer_feed();
+        self.emit_plunge_comment(feed_rate);
+
+        // The cone crosses the top face `width` in from the edge with the tip at this Z
+        let chamfer_depth = chamfer.tip_depth();
+        if let Some(tool) = &self.current_tool_data {
+            if chamfer.cone_radius() > tool.diameter / 2.0 {
+                self.output.emit_comment(&format!(
+                    "WARNING: chamfer needs a {:.4} cone radius, more than the {:.4} tool has",
+                    chamfer.cone_radius(),
+                    tool.diameter / 2.0
+                ));
+            }
+        }
 
-        // Calculate chamfer depth (Z) based on width (45° chamfer)
-        let chamfer_depth = chamfer.width;
+        // Tool axis path: the edge moved toward free space by the cone's offset
+        let outward = match (&chamfer.geometry, chamfer.side) {
+            (ChamferGeometry::Hole { .. }, _) | (_, CutSide::Inside) => -chamfer.edge_offset(),
+            _ => chamfer.edge_offset(),
+        };
+        let center_x = chamfer.position.x;
+        let center_y = chamfer.position.y;
 
         match &chamfer.geometry {
             ChamferGeometry::Rect { width, height } => {
                 // Chamfer around rectangle perimeter
-                let half_width = width / 2.0;
-                let half_height = height / 2.0;
-                let center_x = chamfer.position.x;
-                let center_y = chamfer.position.y;
+                let half_width = width / 2.0 + outward;
+                let half_height = height / 2.0 + outward;
 
-                // Start at bottom-left corner (offset by tool radius)
-                let start_x = center_x - half_width + tool_dia/2.0;
-                let start_y = center_y - half_height + tool_dia/2.0;
-                let end_x = center_x + half_width - tool_dia/2.0;
-                let end_y = center_y + half_height - tool_dia/2.0;
+                let start_x = center_x - half_width;
+                let start_y = center_y - half_height;
+                let end_x = center_x + half_width;
+                let end_y = center_y + half_height;
 
                 // Rapid to start position at safe height
                 self.output.emit(&format!("G00 X{:.4} Y{:.4}", start_x, start_y));
-                self.output.emit("G00 Z0.1");
+                self.retract_to_clearance();
 
                 // Plunge to chamfer depth
-                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", chamfer_depth, feed_rate * 0.3));
+                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", chamfer_depth, self.plunge_feed(feed_rate)));
 
                 // Cut around rectangle
                 self.output.emit(&format!("G01 X{:.4} F{:.1}", end_x, feed_rate));
@@ -863,20 +2987,26 @@ impl CodeGenerator {
                 self.output.emit(&format!("G01 Y{:.4}", start_y));
 
                 // Retract
-                self.output.emit("G00 Z0.1");
+                self.retract_to_clearance();
             }
-            ChamferGeometry::Circle { diameter } => {
-                // Chamfer around circle perimeter
-                let radius = diameter / 2.0 - tool_dia/2.0;
-                let center_x = chamfer.position.x;
-                let center_y = chamfer.position.y;
+            ChamferGeometry::Circle { diameter } | ChamferGeometry::Hole { diameter } => {
+                let radius = diameter / 2.0 + outward;
+
+                if radius <= 0.0 {
+                    // The cone spans the whole hole: countersink straight down
+                    self.output.emit(&format!("G00 X{:.4} Y{:.4}", center_x, center_y));
+                    self.retract_to_clearance();
+                    self.output.emit(&format!("G01 Z-{:.4} F{:.1}", chamfer_depth, self.plunge_feed(feed_rate)));
+                    self.retract_to_clearance();
+                    return;
+                }
 
                 // Rapid to start position
                 self.output.emit(&format!("G00 X{:.4} Y{:.4}", center_x + radius, center_y));
-                self.output.emit("G00 Z0.1");
+                self.retract_to_clearance();
 
                 // Plunge to chamfer depth
-                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", chamfer_depth, feed_rate * 0.3));
+                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", chamfer_depth, self.plunge_feed(feed_rate)));
 
                 // Cut circle
                 self.output.emit(&format!(
@@ -889,43 +3019,12 @@ impl CodeGenerator {
                 ));
 
                 // Retract
-                self.output.emit("G00 Z0.1");
-            }
-            ChamferGeometry::Hole { diameter } => {
-                // Chamfer top edge of hole (countersink style)
-                let radius = diameter / 2.0;
-                let center_x = chamfer.position.x;
-                let center_y = chamfer.position.y;
-
-                // Rapid to center
-                self.output.emit(&format!("G00 X{:.4} Y{:.4}", center_x, center_y));
-                self.output.emit("G00 Z0.1");
-
-                // Plunge to chamfer depth
-                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", chamfer_depth, feed_rate * 0.3));
-
-                // Cut outward spiral for chamfer
-                let points_per_rev = 36;
-                let num_passes = (radius / (tool_dia * 0.3)).ceil() as i32;
-                for i in 0..=(num_passes * points_per_rev) {
-                    let angle = 2.0 * std::f64::consts::PI * (i as f64 / points_per_rev as f64);
-                    let r = (tool_dia * 0.3) * (i as f64 / points_per_rev as f64);
-                    let r = r.min(radius);
-                    let x = center_x + r * angle.cos();
-                    let y = center_y + r * angle.sin();
-                    self.output.emit(&format!("G01 X{:.4} Y{:.4} F{:.1}", x, y, feed_rate));
-                    if r >= radius {
-                        break;
-                    }
-                }
-
-                // Retract
-                self.output.emit("G00 Z0.1");
+                self.retract_to_clearance();
             }
         }
     }
 
-    fn calculate_deburr_feed(&self) -> f64 {
+    fn calculate_deburr_feed(&mut self) -> f64 {
         // Even more conservative than chamfering
         self.calculate_chamfer_feed() * 0.5
     }
@@ -941,6 +3040,7 @@ impl CodeGenerator {
         // Get tool diameter for calculations
         let tool_dia = self.current_tool_data.as_ref().map(|t| t.diameter).unwrap_or(0.125);
         let feed_rate = self.calculate_deburr_feed();
+        self.emit_plunge_comment(feed_rate);
 
         let pass_depth = deburr.pass_depth;
 
@@ -959,10 +3059,10 @@ impl CodeGenerator {
 
                 // Rapid to start
                 self.output.emit(&format!("G00 X{:.4} Y{:.4}", start_x, start_y));
-                self.output.emit("G00 Z0.05"); // Start just above surface
+                self.retract_to_clearance();
 
                 // Plunge to deburr depth
-                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", pass_depth, feed_rate * 0.3));
+                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", pass_depth, self.plunge_feed(feed_rate)));
 
                 // Light cut around perimeter
                 self.output.emit(&format!("G01 X{:.4} F{:.1}", end_x, feed_rate));
@@ -971,7 +3071,7 @@ impl CodeGenerator {
                 self.output.emit(&format!("G01 Y{:.4}", start_y));
 
                 // Retract
-                self.output.emit("G00 Z0.1");
+                self.retract_to_clearance();
             }
             DeburrGeometry::Circle { diameter } => {
                 // Deburr around circle
@@ -980,8 +3080,8 @@ impl CodeGenerator {
                 let center_y = deburr.position.y;
 
                 self.output.emit(&format!("G00 X{:.4} Y{:.4}", center_x + radius, center_y));
-                self.output.emit("G00 Z0.05");
-                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", pass_depth, feed_rate * 0.3));
+                self.retract_to_clearance();
+                self.output.emit(&format!("G01 Z-{:.4} F{:.1}", pass_depth, self.plunge_feed(feed_rate)));
 
                 // Cut circle
                 self.output.emit(&format!(
@@ -993,7 +3093,7 @@ impl CodeGenerator {
                     feed_rate
                 ));
 
-                self.output.emit("G00 Z0.1");
+                self.retract_to_clearance();
             }
             DeburrGeometry::Profile => {
                 // Deburr the part profile - requires stock knowledge
@@ -1013,13 +3113,13 @@ impl CodeGenerator {
                     let end_y = center_y + half_height;
 
                     self.output.emit(&format!("G00 X{:.4} Y{:.4}", start_x, start_y));
-                    self.output.emit("G00 Z0.05");
-                    self.output.emit(&format!("G01 Z-{:.4} F{:.1}", pass_depth, feed_rate * 0.3));
+                    self.retract_to_clearance();
+                    self.output.emit(&format!("G01 Z-{:.4} F{:.1}", pass_depth, self.plunge_feed(feed_rate)));
                     self.output.emit(&format!("G01 X{:.4} F{:.1}", end_x, feed_rate));
                     self.output.emit(&format!("G01 Y{:.4}", end_y));
                     self.output.emit(&format!("G01 X{:.4}", start_x));
                     self.output.emit(&format!("G01 Y{:.4}", start_y));
-                    self.output.emit("G00 Z0.1");
+                    self.retract_to_clearance();
                 }
             }
         }
@@ -1032,14 +3132,28 @@ impl CodeGenerator {
         // Spindle off, coolant off for tool change
         self.output.emit("M05");
         self.output.emit("M09");
+        self.current_rpm = None;
+        self.coolant_on = CoolantMode::Off;
 
-        // Tool change
+        // Tool change, then its length offset from the H register of the same number
         self.output.emit(&format!("T{} M06", tc.tool_number));
+        if self.length_comp_enabled() {
+            self.output.emit(&format!("G43 H{}", tc.tool_number));
+        }
 
         self.current_tool = Some(tc.tool_number);
         if let Some(ref data) = tc.tool_data {
             self.current_tool_data = Some(data.clone());
         }
+        let library_tool = self
+            .tool_library
+            .as_ref()
+            .and_then(|lib| lib.lookup(tc.tool_id.as_deref(), tc.tool_number));
+        self.tool_coating = library_tool
+            .and_then(|t| t.coating)
+            .map(|c| c.as_str().to_string());
+        self.tool_max_rpm = library_tool.and_then(|t| t.max_rpm);
+        self.tool_plunge_feed = library_tool.and_then(|t| t.default_plunge_feed);
         // Tool data comment
         if let Some(data) = &tc.tool_data {
             self.output.emit_comment(&format!(
@@ -1055,18 +3169,22 @@ impl CodeGenerator {
         match sp.direction {
             SpindleDir::CW => {
                 self.output.emit(&format!("S{} M03", sp.rpm as u32));
+                self.current_rpm = Some(sp.rpm);
             }
             SpindleDir::CCW => {
                 self.output.emit(&format!("S{} M04", sp.rpm as u32));
+                self.current_rpm = Some(sp.rpm);
             }
             SpindleDir::Off => {
                 self.output.emit("M05");
+                self.current_rpm = None;
             }
         }
     }
 
     fn emit_drill(&mut self, d: &DrillOp) {
         self.output.emit_comment("DRILL CYCLE");
+        let depth = self.clamp_depth(d.depth);
 
         // Rapid to retract height
         self.output.emit(&format!("G00 Z{:.3}", d.retract_height));
@@ -1082,13 +3200,13 @@ impl CodeGenerator {
                     // G83 peck drilling
                     self.output.emit(&format!(
                         "G83 Z{:.3} R{:.3} Q{:.3} F{:.1}",
-                        -d.depth, d.retract_height, peck, d.feed_rate
+                        -depth, d.retract_height, peck, d.feed_rate
                     ));
                 } else {
                     // G81 standard drilling
                     self.output.emit(&format!(
                         "G81 Z{:.3} R{:.3} F{:.1}",
-                        -d.depth, d.retract_height, d.feed_rate
+                        -depth, d.retract_height, d.feed_rate
                     ));
                 }
 
@@ -1107,6 +3225,10 @@ impl CodeGenerator {
 
     fn emit_pocket(&mut self, p: &PocketOp) {
         self.output.emit_comment("POCKET OPERATION");
+        let p = &PocketOp {
+            depth: self.clamp_depth(p.depth),
+            ..p.clone()
+        };
 
         // Calculate toolpaths based on geometry
         match &p.geometry {
@@ -1116,17 +3238,20 @@ impl CodeGenerator {
             Geometry::Circle(circ) => {
                 self.emit_circle_pocket(circ, p);
             }
-            _ => {
-                self.output.emit_comment("UNSUPPORTED GEOMETRY");
+            Geometry::Polygon(poly) => {
+                self.emit_contour_pocket(&poly.vertices(), p);
+            }
+            Geometry::Path(points) => {
+                self.emit_contour_pocket(points, p);
             }
         }
 
-        // Retract
-        self.output.emit("G00 Z50.0");
+        self.retract_to_safe_z();
     }
 
     fn emit_rect_pocket(&mut self, rect: &Rectangle, p: &PocketOp) {
-        let tool_radius = 3.0; // Assume 6mm tool for now
+        let frame = RectFrame::new(rect);
+        let tool_radius = self.current_tool_radius(3.0); // 6mm tool if none loaded
         let stepover_dist = tool_radius * 2.0 * p.stepover;
 
         // Calculate pocket bounds (inside tool center)
@@ -1146,7 +3271,10 @@ impl CodeGenerator {
             self.output
                 .emit_comment(&format!("DEPTH PASS {} Z={:.3}", depth_pass, current_z));
 
-            // Plunge to depth
+            // Position over the first pass and plunge to depth
+            let first = frame.point(min_x, min_y);
+            self.output
+                .emit(&format!("G00 X{:.3} Y{:.3}", first.0, first.1));
             self.output
                 .emit(&format!("G01 Z{:.3} F{:.1}", current_z, p.plunge_feed));
 
@@ -1160,12 +3288,19 @@ impl CodeGenerator {
                 let x_start = if i % 2 == 0 { min_x } else { max_x };
                 let x_end = if i % 2 == 0 { max_x } else { min_x };
 
-                // Move to start of pass
-                self.output.emit(&format!("G00 X{:.3} Y{:.3}", x_start, y));
+                // Step over to the start of the pass
+                let start = frame.point(x_start, y);
+                if i > 0 {
+                    self.output.emit(&format!(
+                        "G01 X{:.3} Y{:.3} F{:.1}",
+                        start.0, start.1, p.feed_rate
+                    ));
+                }
 
-                // Cut across
+                // Cut across, along the rectangle's width
+                let end = frame.point(x_end, y);
                 self.output
-                    .emit(&format!("G01 X{:.3} F{:.1}", x_end, p.feed_rate));
+                    .emit(&format!("G01 {} F{:.1}", axis_words(start, end), p.feed_rate));
             }
         }
 
@@ -1182,17 +3317,21 @@ impl CodeGenerator {
             let fw = rect.width - allowance * 2.0;
             let fh = rect.height - allowance * 2.0;
 
-            self.output
-                .emit(&format!("G01 X{:.3} Y{:.3} F{:.1}", fx, fy, p.feed_rate));
-            self.output.emit(&format!("G01 X{:.3}", fx + fw));
-            self.output.emit(&format!("G01 Y{:.3}", fy + fh));
-            self.output.emit(&format!("G01 X{:.3}", fx));
-            self.output.emit(&format!("G01 Y{:.3}", fy));
+            let corner = frame.point(fx, fy);
+            self.output.emit(&format!(
+                "G01 X{:.3} Y{:.3} F{:.1}",
+                corner.0, corner.1, p.feed_rate
+            ));
+            self.emit_rect_corners(
+                &frame,
+                (fx, fy),
+                &[(fx + fw, fy), (fx + fw, fy + fh), (fx, fy + fh), (fx, fy)],
+            );
         }
     }
 
     fn emit_circle_pocket(&mut self, circ: &Circle, p: &PocketOp) {
-        let tool_radius = 3.0;
+        let tool_radius = self.current_tool_radius(3.0);
         let radius = circ.diameter / 2.0 - tool_radius;
 
         if radius <= 0.0 {
@@ -1233,98 +3372,566 @@ impl CodeGenerator {
         }
     }
 
+    /// Zigzag rows clipped to a polygon or path outline, then a lap around its walls
+    fn emit_contour_pocket(&mut self, points: &[Position], p: &PocketOp) {
+        let tool_radius = self.current_tool_radius(3.0);
+        let stepover_dist = tool_radius * 2.0 * p.stepover;
+
+        // Tool center paths: the finished wall, and the roughing wall short of it by the allowance
+        let outline = contour::closed_ccw(points);
+        let wall = contour::offset(&outline, -tool_radius);
+        let rough = contour::offset(&outline, -tool_radius - p.finish_pass.unwrap_or(0.0));
+        if contour::collapsed(&outline, &rough) {
+            self.output.emit_comment("ERROR: Tool too large for pocket");
+            return;
+        }
+
+        let (min_y, max_y) = rough
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| (lo.min(p.1), hi.max(p.1)));
+        let num_rows = ((max_y - min_y) / stepover_dist).ceil().max(1.0) as usize;
+        let row_step = (max_y - min_y) / num_rows as f64;
+
+        let num_depth_passes = (p.depth / p.stepdown).ceil() as i32;
+
+        for depth_pass in 1..=num_depth_passes {
+            let current_z = -(depth_pass as f64 * p.stepdown).min(p.depth);
+
+            self.output
+                .emit_comment(&format!("DEPTH PASS {} Z={:.3}", depth_pass, current_z));
+
+            // Rows run through the middle of each band; the lap after them cleans the walls
+            let mut plunged = false;
+            let mut prev_spans = 0;
+            for row in 0..num_rows {
+                let y = min_y + (row as f64 + 0.5) * row_step;
+                let mut spans = contour::spans_at(&rough, y);
+                if row % 2 == 1 {
+                    spans = spans.into_iter().rev().map(|(a, b)| (b, a)).collect();
+                }
+
+                for (i, &(x_start, x_end)) in spans.iter().enumerate() {
+                    if !plunged {
+                        self.output.emit(&format!("G00 X{:.3} Y{:.3}", x_start, y));
+                        self.output
+                            .emit(&format!("G01 Z{:.3} F{:.1}", current_z, p.plunge_feed));
+                        plunged = true;
+                    } else if i == 0 && spans.len() == 1 && prev_spans == 1 {
+                        // Step over along the wall, inside the cleared area
+                        self.output.emit(&format!(
+                            "G01 X{:.3} Y{:.3} F{:.1}",
+                            x_start, y, p.feed_rate
+                        ));
+                    } else {
+                        // The way to another island of the row may cross stock
+                        self.retract_to_clearance();
+                        self.output.emit(&format!("G00 X{:.3} Y{:.3}", x_start, y));
+                        self.output
+                            .emit(&format!("G01 Z{:.3} F{:.1}", current_z, p.plunge_feed));
+                    }
+                    self.output
+                        .emit(&format!("G01 X{:.3} F{:.1}", x_end, p.feed_rate));
+                }
+                if !spans.is_empty() {
+                    prev_spans = spans.len();
+                }
+            }
+
+            self.retract_to_clearance();
+            self.emit_contour_lap(&rough, current_z, p.plunge_feed, p.feed_rate);
+            self.retract_to_clearance();
+        }
+
+        if p.finish_pass.is_some() {
+            self.output.emit_comment("FINISH PASS");
+            self.emit_contour_lap(&wall, -p.depth, p.plunge_feed, p.feed_rate);
+        }
+    }
+
+    /// Plunge at the first point and cut once around the closed contour back to it
+    fn emit_contour_lap(&mut self, pts: &[(f64, f64)], z: f64, plunge_feed: f64, feed_rate: f64) {
+        self.output
+            .emit(&format!("G00 X{:.3} Y{:.3}", pts[0].0, pts[0].1));
+        self.output
+            .emit(&format!("G01 Z{:.3} F{:.1}", z, plunge_feed));
+        for (i, (x, y)) in pts.iter().skip(1).chain(&pts[..1]).enumerate() {
+            if i == 0 {
+                self.output
+                    .emit(&format!("G01 X{:.3} Y{:.3} F{:.1}", x, y, feed_rate));
+            } else {
+                self.output.emit(&format!("G01 X{:.3} Y{:.3}", x, y));
+            }
+        }
+    }
+
     fn emit_profile(&mut self, p: &ProfileOp) {
         self.output.emit_comment("PROFILE OPERATION");
+        let p = &ProfileOp {
+            depth: self.clamp_depth(p.depth),
+            ..p.clone()
+        };
+
+        let tool_radius = self.current_tool_radius(3.0);
+        let ccw = self.contour_ccw(p.side);
+        let comp = if p.cutter_comp {
+            self.cutter_comp(p.side, ccw)
+        } else {
+            None
+        };
 
-        let tool_radius = 3.0;
+        // With G41/G42 the control adds the tool radius; only stock to leave is programmed
+        let radius_offset = if comp.is_some() { 0.0 } else { tool_radius };
         let offset = match p.side {
-            CutSide::Inside => -tool_radius - p.stock_to_leave,
-            CutSide::Outside => tool_radius + p.stock_to_leave,
+            CutSide::Inside => -radius_offset - p.stock_to_leave,
+            CutSide::Outside => radius_offset + p.stock_to_leave,
             CutSide::On => 0.0,
         };
 
-        // Apply G41/G42 compensation or calculate manually
-        // For now, simplified manual offset
-
         match &p.geometry {
             Geometry::Rect(rect) => {
-                self.emit_rect_profile(rect, p, offset);
+                self.emit_rect_profile(rect, p, offset, comp, ccw);
             }
             Geometry::Circle(circ) => {
-                self.emit_circle_profile(circ, p, offset);
+                self.emit_circle_profile(circ, p, offset, comp, ccw);
+            }
+            Geometry::Polygon(poly) => {
+                self.emit_contour_profile(&poly.vertices(), p, offset, comp, ccw);
+            }
+            Geometry::Path(points) => {
+                self.emit_contour_profile(points, p, offset, comp, ccw);
             }
-            _ => {}
         }
 
-        self.output.emit("G00 Z50.0");
+        self.retract_to_safe_z();
+    }
+
+    /// Compensation side and D register for a profile, or None to offset manually
+    fn cutter_comp(&mut self, side: CutSide, ccw: bool) -> Option<CutterComp> {
+        // Counter-clockwise, inside keeps the tool on the left; clockwise swaps sides
+        let code = match (side, ccw) {
+            (CutSide::Inside, true) | (CutSide::Outside, false) => "G41",
+            (CutSide::Outside, true) | (CutSide::Inside, false) => "G42",
+            (CutSide::On, _) => return None,
+        };
+        let Some(register) = self.current_tool else {
+            self.output.emit_comment(
+                "WARNING: cutter comp needs an active tool for the D register - offsetting manually",
+            );
+            return None;
+        };
+        Some(CutterComp {
+            code,
+            register,
+            tool_radius: self.current_tool_radius(3.0),
+        })
     }
 
-    fn emit_rect_profile(&mut self, rect: &Rectangle, p: &ProfileOp, offset: f64) {
-        let x = rect.bottom_left.x + offset;
-        let y = rect.bottom_left.y + offset;
-        let w = rect.width - offset * 2.0;
-        let h = rect.height - offset * 2.0;
+    fn emit_rect_profile(
+        &mut self,
+        rect: &Rectangle,
+        p: &ProfileOp,
+        offset: f64,
+        comp: Option<CutterComp>,
+        ccw: bool,
+    ) {
+        let frame = RectFrame::new(rect);
+
+        // Positive offset grows the path outward, matching emit_circle_profile
+        let x = rect.bottom_left.x - offset;
+        let y = rect.bottom_left.y - offset;
+        let w = rect.width + offset * 2.0;
+        let h = rect.height + offset * 2.0;
 
         let num_depth_passes = (p.depth / 5.0).ceil() as i32; // Simplified stepdown
 
+        // Bottom edge is cut in +X counter-clockwise, -X clockwise;
+        // inside profiles have free space above it
+        let free_side = frame.vector(if p.side == CutSide::Inside { (0.0, 1.0) } else { (0.0, -1.0) });
+        let direction = frame.vector(if ccw { (1.0, 0.0) } else { (-1.0, 0.0) });
+        let (near_x, far_x) = if ccw { (x + w, x) } else { (x, x + w) };
+        let mut lead = self.lead_radius(p);
+        if p.side == CutSide::Inside {
+            lead = lead.min(w / 2.0).min(h / 2.0);
+        }
+
         for pass in 1..=num_depth_passes {
             let z = -(pass as f64 * 5.0).min(p.depth);
 
-            self.output.emit(&format!("G00 X{:.3} Y{:.3}", x, y));
-            self.output
-                .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
-
-            self.output
-                .emit(&format!("G01 X{:.3} F{:.1}", x + w, p.feed_rate));
-            self.output.emit(&format!("G01 Y{:.3}", y + h));
-            self.output.emit(&format!("G01 X{:.3}", x));
-            self.output.emit(&format!("G01 Y{:.3}", y));
-        }
-    }
+            // Once around from the middle of the bottom edge
+            let mid = (x + w / 2.0, y);
+            let around = [(near_x, y), (near_x, y + h), (far_x, y + h), (far_x, y), mid];
 
-    fn emit_circle_profile(&mut self, circ: &Circle, p: &ProfileOp, offset: f64) {
-        let radius = circ.diameter / 2.0 + offset;
-        let cx = circ.center.x;
-        let cy = circ.center.y;
+            if let Some(comp) = comp {
+                // Perpendicular lead-in to the middle of the bottom edge
+                let r = comp.tool_radius;
+                let lead_y = if p.side == CutSide::Inside {
+                    y + (r * 2.0).min(h - r)
+                } else {
+                    y - r * 2.0
+                };
+                let start = frame.point(mid.0, mid.1);
+                let lead_point = frame.point(mid.0, lead_y);
 
-        let num_depth_passes = (p.depth / 5.0).ceil() as i32;
+                self.output
+                    .emit(&format!("G00 X{:.3} Y{:.3}", lead_point.0, lead_point.1));
+                self.output
+                    .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
+                self.output.emit(&format!(
+                    "G01 {} D{} X{:.3} Y{:.3} F{:.1}",
+                    comp.code, comp.register, start.0, start.1, p.feed_rate
+                ));
+                self.emit_rect_corners(&frame, mid, &around);
+                self.output.emit(&format!(
+                    "G01 G40 X{:.3} Y{:.3}",
+                    lead_point.0, lead_point.1
+                ));
+                continue;
+            }
 
-        for pass in 1..=num_depth_passes {
-            let z = -(pass as f64 * 5.0).min(p.depth);
+            if lead > 0.0 {
+                // Arc on and off at the middle of the bottom edge
+                let start = frame.point(mid.0, mid.1);
+                let entry = Self::lead_in_start(start, direction, free_side, lead);
+                self.output
+                    .emit(&format!("G00 X{:.3} Y{:.3}", entry.0, entry.1));
+                self.output
+                    .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
+                self.emit_lead_in(start, direction, free_side, lead, p.feed_rate, 3);
+                self.emit_rect_corners(&frame, mid, &around);
+                self.emit_lead_out(start, direction, free_side, lead, p.feed_rate, 3);
+                continue;
+            }
 
+            let corner = frame.point(x, y);
             self.output
-                .emit(&format!("G00 X{:.3} Y{:.3}", cx + radius, cy));
+                .emit(&format!("G00 X{:.3} Y{:.3}", corner.0, corner.1));
             self.output
                 .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
 
-            // Full circle using G02/G03
-            self.output.emit(&format!(
-                "G03 X{:.3} Y{:.3} I{:.3} J{:.3} F{:.1}",
-                cx + radius,
-                cy,
-                -radius,
-                0.0,
-                p.feed_rate
-            ));
+            let first = if ccw { (x + w, y) } else { (x, y + h) };
+            let rest = if ccw {
+                [(x + w, y + h), (x, y + h), (x, y)]
+            } else {
+                [(x + w, y + h), (x + w, y), (x, y)]
+            };
+            let to = frame.point(first.0, first.1);
+            self.output
+                .emit(&format!("G01 {} F{:.1}", axis_words(corner, to), p.feed_rate));
+            self.emit_rect_corners(&frame, first, &rest);
         }
     }
 
-    fn emit_face(&mut self, f: &FaceOp) {
-        self.output.emit_comment("FACE MILLING");
+    /// Feed moves through points in the rectangle's own frame, starting from `from`
+    fn emit_rect_corners(&mut self, frame: &RectFrame, from: (f64, f64), points: &[(f64, f64)]) {
+        let mut at = frame.point(from.0, from.1);
+        for &(x, y) in points {
+            let to = frame.point(x, y);
+            self.output.emit(&format!("G01 {}", axis_words(at, to)));
+            at = to;
+        }
+    }
 
-        let tool_radius = 6.0; // 12mm face mill
-        let stepover_dist = tool_radius * 2.0 * f.stepover;
+    /// Profile along a polygon or path outline, always closing the loop.
+    /// Leads and cutter comp enter at the middle of the first edge, as on a rectangle.
+    fn emit_contour_profile(
+        &mut self,
+        points: &[Position],
+        p: &ProfileOp,
+        offset: f64,
+        comp: Option<CutterComp>,
+        ccw: bool,
+    ) {
+        let mut pts = contour::offset(&contour::closed_ccw(points), offset);
+        if pts.len() < 2 {
+            self.output.emit_comment("ERROR: Profile needs at least two distinct points");
+            return;
+        }
+        if !ccw {
+            pts.reverse();
+        }
 
-        let min_x = f.bounds.bottom_left.x;
-        let max_x = f.bounds.bottom_left.x + f.bounds.width;
-        let min_y = f.bounds.bottom_left.y;
-        let max_y = f.bounds.bottom_left.y + f.bounds.height;
+        let num_depth_passes = (p.depth / 5.0).ceil() as i32; // Simplified stepdown
 
-        let num_passes = ((max_y - min_y) / stepover_dist).ceil() as i32;
+        let (a, b) = (pts[0], pts[1]);
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        let direction = ((b.0 - a.0) / length, (b.1 - a.1) / length);
+        // Outside lies right of a counter-clockwise edge and left of a clockwise one
+        let outward = if ccw {
+            (direction.1, -direction.0)
+        } else {
+            (-direction.1, direction.0)
+        };
+        let free_side = if p.side == CutSide::Inside {
+            (-outward.0, -outward.1)
+        } else {
+            outward
+        };
+        let start = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        let shortest = (0..pts.len())
+            .map(|i| {
+                let (c, d) = (pts[i], pts[(i + 1) % pts.len()]);
+                (d.0 - c.0).hypot(d.1 - c.1)
+            })
+            .fold(f64::INFINITY, f64::min);
+        let mut lead = self.lead_radius(p);
+        if p.side == CutSide::Inside {
+            lead = lead.min(shortest / 2.0);
+        }
 
-        self.output
-            .emit(&format!("G00 X{:.3} Y{:.3}", min_x - tool_radius, min_y));
-        self.output.emit(&format!("G01 Z{:.3} F200.0", -f.depth));
+        // From the start point around every corner and back
+        let lap: Vec<(f64, f64)> = pts[1..]
+            .iter()
+            .chain(&pts[..1])
+            .copied()
+            .chain(std::iter::once(start))
+            .collect();
+
+        for pass in 1..=num_depth_passes {
+            let z = -(pass as f64 * 5.0).min(p.depth);
+
+            if let Some(comp) = comp {
+                // Perpendicular lead-in to the middle of the first edge
+                let r = comp.tool_radius;
+                let reach = if p.side == CutSide::Inside {
+                    (r * 2.0).min(shortest / 2.0)
+                } else {
+                    r * 2.0
+                };
+                let lead_point = (start.0 + free_side.0 * reach, start.1 + free_side.1 * reach);
+
+                self.output
+                    .emit(&format!("G00 X{:.3} Y{:.3}", lead_point.0, lead_point.1));
+                self.output
+                    .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
+                self.output.emit(&format!(
+                    "G01 {} D{} X{:.3} Y{:.3} F{:.1}",
+                    comp.code, comp.register, start.0, start.1, p.feed_rate
+                ));
+                for (x, y) in &lap {
+                    self.output.emit(&format!("G01 X{:.3} Y{:.3}", x, y));
+                }
+                self.output.emit(&format!(
+                    "G01 G40 X{:.3} Y{:.3}",
+                    lead_point.0, lead_point.1
+                ));
+                continue;
+            }
+
+            if lead > 0.0 {
+                let entry = Self::lead_in_start(start, direction, free_side, lead);
+                self.output
+                    .emit(&format!("G00 X{:.3} Y{:.3}", entry.0, entry.1));
+                self.output
+                    .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
+                self.emit_lead_in(start, direction, free_side, lead, p.feed_rate, 3);
+                for (x, y) in &lap {
+                    self.output.emit(&format!("G01 X{:.3} Y{:.3}", x, y));
+                }
+                self.emit_lead_out(start, direction, free_side, lead, p.feed_rate, 3);
+                continue;
+            }
+
+            self.emit_contour_lap(&pts, z, p.plunge_feed, p.feed_rate);
+        }
+    }
+
+    fn emit_circle_profile(
+        &mut self,
+        circ: &Circle,
+        p: &ProfileOp,
+        offset: f64,
+        comp: Option<CutterComp>,
+        ccw: bool,
+    ) {
+        let radius = circ.diameter / 2.0 + offset;
+        let cx = circ.center.x;
+        let cy = circ.center.y;
+
+        let num_depth_passes = (p.depth / 5.0).ceil() as i32;
+
+        let free_side = if p.side == CutSide::Inside { (-1.0, 0.0) } else { (1.0, 0.0) };
+        let (arc, direction) = if ccw { ("G03", (0.0, 1.0)) } else { ("G02", (0.0, -1.0)) };
+        let mut lead = self.lead_radius(p);
+        if p.side == CutSide::Inside {
+            lead = lead.min(radius);
+        }
+
+        for pass in 1..=num_depth_passes {
+            let z = -(pass as f64 * 5.0).min(p.depth);
+
+            if comp.is_none() && lead > 0.0 {
+                // Arc on and off at 3 o'clock, where the CCW cut heads +Y (CW -Y)
+                let start = (cx + radius, cy);
+                let entry = Self::lead_in_start(start, direction, free_side, lead);
+                self.output
+                    .emit(&format!("G00 X{:.3} Y{:.3}", entry.0, entry.1));
+                self.output
+                    .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
+                self.emit_lead_in(start, direction, free_side, lead, p.feed_rate, 3);
+                self.output.emit(&format!(
+                    "{} X{:.3} Y{:.3} I{:.3} J{:.3} F{:.1}",
+                    arc,
+                    cx + radius,
+                    cy,
+                    -radius,
+                    0.0,
+                    p.feed_rate
+                ));
+                self.emit_lead_out(start, direction, free_side, lead, p.feed_rate, 3);
+                continue;
+            }
+
+            // Radial lead-in toward the start point at 3 o'clock
+            let lead_x = comp.map(|comp| {
+                let r = comp.tool_radius;
+                if p.side == CutSide::Inside {
+                    cx + radius - (r * 2.0).min(radius)
+                } else {
+                    cx + radius + r * 2.0
+                }
+            });
+
+            self.output.emit(&format!(
+                "G00 X{:.3} Y{:.3}",
+                lead_x.unwrap_or(cx + radius),
+                cy
+            ));
+            self.output
+                .emit(&format!("G01 Z{:.3} F{:.1}", z, p.plunge_feed));
+            if let Some(comp) = comp {
+                self.output.emit(&format!(
+                    "G01 {} D{} X{:.3} Y{:.3} F{:.1}",
+                    comp.code,
+                    comp.register,
+                    cx + radius,
+                    cy,
+                    p.feed_rate
+                ));
+            }
+
+            // Full circle using G02/G03
+            self.output.emit(&format!(
+                "{} X{:.3} Y{:.3} I{:.3} J{:.3} F{:.1}",
+                arc,
+                cx + radius,
+                cy,
+                -radius,
+                0.0,
+                p.feed_rate
+            ));
+
+            if let Some(lead_x) = lead_x {
+                self.output
+                    .emit(&format!("G01 G40 X{:.3} Y{:.3}", lead_x, cy));
+            }
+        }
+    }
+
+    /// Lead arc radius for a profile: the DSL `lead` value, else the tool radius
+    fn lead_radius(&self, p: &ProfileOp) -> f64 {
+        p.lead_radius
+            .unwrap_or_else(|| self.current_tool_radius(3.0))
+            .max(0.0)
+    }
+
+    /// Quarter-circle arc code for a lead curving toward `free_side` while moving along `direction`
+    fn lead_arc_code(direction: (f64, f64), free_side: (f64, f64)) -> &'static str {
+        if direction.0 * free_side.1 - direction.1 * free_side.0 < 0.0 {
+            "G02"
+        } else {
+            "G03"
+        }
+    }
+
+    /// Where a lead-in arc onto `point` begins
+    ///
+    /// `direction` is the cut direction at `point` and `free_side` the unit normal pointing
+    /// away from the finished wall; the arc stays on that side so the entry leaves no mark.
+    fn lead_in_start(
+        point: (f64, f64),
+        direction: (f64, f64),
+        free_side: (f64, f64),
+        radius: f64,
+    ) -> (f64, f64) {
+        (
+            point.0 + (free_side.0 - direction.0) * radius,
+            point.1 + (free_side.1 - direction.1) * radius,
+        )
+    }
+
+    /// Quarter-circle arc from `lead_in_start` tangentially onto the contour at `point`,
+    /// written to the contour's `decimals`
+    fn emit_lead_in(
+        &mut self,
+        point: (f64, f64),
+        direction: (f64, f64),
+        free_side: (f64, f64),
+        radius: f64,
+        feed_rate: f64,
+        decimals: usize,
+    ) {
+        self.output.emit(&format!(
+            "{} X{:.*} Y{:.*} I{:.*} J{:.*} F{:.1}",
+            Self::lead_arc_code(direction, free_side),
+            decimals,
+            point.0,
+            decimals,
+            point.1,
+            decimals,
+            direction.0 * radius,
+            decimals,
+            direction.1 * radius,
+            feed_rate
+        ));
+    }
+
+    /// Arc tangentially off the contour at `point`, mirroring `emit_lead_in`
+    fn emit_lead_out(
+        &mut self,
+        point: (f64, f64),
+        direction: (f64, f64),
+        free_side: (f64, f64),
+        radius: f64,
+        feed_rate: f64,
+        decimals: usize,
+    ) {
+        let end = (
+            point.0 + (free_side.0 + direction.0) * radius,
+            point.1 + (free_side.1 + direction.1) * radius,
+        );
+
+        self.output.emit(&format!(
+            "{} X{:.*} Y{:.*} I{:.*} J{:.*} F{:.1}",
+            Self::lead_arc_code(direction, free_side),
+            decimals,
+            end.0,
+            decimals,
+            end.1,
+            decimals,
+            free_side.0 * radius,
+            decimals,
+            free_side.1 * radius,
+            feed_rate
+        ));
+    }
+
+    fn emit_face(&mut self, f: &FaceOp) {
+        self.output.emit_comment("FACE MILLING");
+        let depth = self.clamp_depth(f.depth);
+
+        let tool_radius = self.current_tool_radius(6.0); // 12mm face mill if none loaded
+        let stepover_dist = tool_radius * 2.0 * f.stepover;
+
+        let min_x = f.bounds.bottom_left.x;
+        let max_x = f.bounds.bottom_left.x + f.bounds.width;
+        let min_y = f.bounds.bottom_left.y;
+        let max_y = f.bounds.bottom_left.y + f.bounds.height;
+
+        let num_passes = ((max_y - min_y) / stepover_dist).ceil() as i32;
+
+        self.output
+            .emit(&format!("G00 X{:.3} Y{:.3}", min_x - tool_radius, min_y));
+        self.output.emit(&format!("G01 Z{:.3} F200.0", -depth));
 
         for i in 0..num_passes {
             let y = min_y + i as f64 * stepover_dist;
@@ -1339,17 +3946,21 @@ impl CodeGenerator {
                 min_x - tool_radius
             };
 
-            self.output.emit(&format!("G00 X{:.3} Y{:.3}", x_start, y));
+            self.output.emit(&format!(
+                "G01 X{:.3} Y{:.3} F{:.1}",
+                x_start, y, f.feed_rate
+            ));
             self.output
                 .emit(&format!("G01 X{:.3} F{:.1}", x_end, f.feed_rate));
         }
 
-        self.output.emit("G00 Z50.0");
+        self.retract_to_safe_z();
     }
 
     fn emit_face_v2(&mut self, f: &FaceV2Op) {
         self.output
             .emit_comment(&format!("FACE MILLING - depth: {:.3}", f.depth));
+        let depth = self.clamp_depth(f.depth);
 
         // Get tool and calculate parameters
         let tool_dia = self
@@ -1358,7 +3969,7 @@ impl CodeGenerator {
             .map(|t| t.diameter)
             .unwrap_or(1.0); // Default 1" face mill
 
-        let (rpm, feed_rate, _stepdown, stepover) = self.calculate_pocket_params(tool_dia, f.depth);
+        let (rpm, feed_rate, _stepdown, _stepover) = self.calculate_pocket_params(tool_dia, depth);
 
         // Get stock dimensions from stock definition or use defaults
         let (stock_width, stock_height) = self
@@ -1370,41 +3981,97 @@ impl CodeGenerator {
         self.output
             .emit_comment(&format!("Stock size: {} x {}", stock_width, stock_height));
 
-        // Calculate facing passes
-        let num_passes = (stock_height / stepover).ceil() as i32;
+        // Each pass overlaps the last, and the first and last hang over the stock edges
+        // by the same amount so no skin is left along them
+        let radius = tool_dia / 2.0;
+        let overlap = tool_dia * self.face_overlap_pct / 100.0;
+        let stepover = tool_dia - overlap;
+        let first_y = radius - overlap;
+        let num_passes =
+            ((stock_height + 2.0 * overlap - tool_dia) / stepover).ceil().max(0.0) as i32 + 1;
 
         self.output.emit_comment(&format!(
-            "Facing: {} passes, stepover: {:.3}",
-            num_passes, stepover
+            "Facing: {} passes, stepover: {:.3} ({:.0}% overlap)",
+            num_passes, stepover, self.face_overlap_pct
         ));
 
         self.output.emit(&format!("S{:.0} M03", rpm));
 
-        // Face milling path (zigzag)
-        let min_x = -0.1; // Start slightly outside stock
-        let max_x = stock_width + 0.1;
-        let min_y = -stepover; // Start with overlap
+        // Passes start and end with the cutter clear of the stock by a tenth of its
+        // diameter, so it enters the cut from the side and steps over in the air
+        let lead = radius + tool_dia * 0.1;
+        let min_x = -lead;
+        let max_x = stock_width + lead;
+
+        // Stop the raster at the y-limit (clamp side of the stock)
+        let y_limit = self.y_center_limit(tool_dia);
+        if let Some(limit) = y_limit {
+            let last_y = first_y + (num_passes - 1) as f64 * stepover;
+            if last_y > limit {
+                self.output.emit_comment(&format!(
+                    "Y clipped to {:.4} by y-limit",
+                    limit
+                ));
+            }
+        }
+
+        // One-way passes cut in the same direction every time; the engaged side is +Y,
+        // which an M03 cutter climbs travelling -X
+        let one_way = self.milling_direction().map(|dir| match dir {
+            MillingDirection::Climb => (max_x, min_x),
+            MillingDirection::Conventional => (min_x, max_x),
+        });
+        let (pass_from, pass_to) = one_way.unwrap_or((min_x, max_x));
+
+        let pass_y = |i: i32| first_y + i as f64 * stepover;
+        let clip = |y: f64| y_limit.map_or(y, |limit| y.min(limit));
 
         self.output
-            .emit(&format!("G00 X{:.3} Y{:.3}", min_x, min_y));
+            .emit(&format!("G00 X{:.3} Y{:.3}", pass_from, clip(pass_y(0))));
         self.output
-            .emit(&format!("G01 Z-{:.3} F{:.1}", f.depth, feed_rate * 0.5));
+            .emit(&format!("G01 Z-{:.3} F{:.1}", depth, self.plunge_feed(feed_rate)));
 
         for i in 0..num_passes {
-            let y = min_y + i as f64 * stepover;
-            let _x_start = if i % 2 == 0 { min_x } else { max_x };
-            let x_end = if i % 2 == 0 { max_x } else { min_x };
+            let clipped = y_limit.is_some_and(|limit| pass_y(i) >= limit);
+            let y = clip(pass_y(i));
+            let x_end = match one_way {
+                Some(_) => pass_to,
+                None if i % 2 == 0 => max_x,
+                None => min_x,
+            };
 
-            self.output.emit(&format!("G00 Y{:.3}", y));
+            if i > 0 {
+                if one_way.is_some() {
+                    // Lift clear of the unfaced stock and come back for the next pass
+                    self.retract_to_clearance();
+                    self.output
+                        .emit(&format!("G00 X{:.3} Y{:.3}", pass_from, y));
+                    self.output
+                        .emit(&format!("G01 Z-{:.3} F{:.1}", depth, self.plunge_feed(feed_rate)));
+                } else {
+                    // Step over off the end of the stock
+                    self.output
+                        .emit(&format!("G01 Y{:.3} F{:.1}", y, feed_rate));
+                }
+            }
             self.output
                 .emit(&format!("G01 X{:.3} F{:.1}", x_end, feed_rate));
+
+            if clipped {
+                break;
+            }
         }
 
-        self.output.emit("G00 Z0.1");
+        self.retract_to_clearance();
     }
 
     fn emit_tap(&mut self, t: &TapOp) {
-        self.output.emit_comment("TAPPING CYCLE");
+        match &t.thread {
+            Some(thread) => self.output.emit_comment(&format!("TAPPING CYCLE - {}", thread)),
+            None => self.output.emit_comment("TAPPING CYCLE"),
+        }
+        let depth = self.clamp_depth(t.depth);
+        let rigid = self.rigid_tapping || self.setup.as_ref().is_some_and(|s| s.rigid_tapping);
 
         // Rapid to retract height
         self.output.emit(&format!("G00 Z{:.3}", t.retract_height));
@@ -1415,27 +4082,47 @@ impl CodeGenerator {
 
             if i == 0 {
                 // G84 tapping cycle
-                // Calculate feed rate: RPM * pitch
-                let rpm = 500.0; // Default, should come from spindle command
-                let feed = rpm * t.pitch;
+                // Feed must be synchronized to the spindle: RPM * pitch
+                let rpm = match self.current_rpm {
+                    Some(rpm) => rpm,
+                    None => {
+                        self.output.emit_comment(
+                            "WARNING: no active spindle speed for tapping - assuming 500 RPM",
+                        );
+                        500.0
+                    }
+                };
 
-                self.output.emit(&format!(
-                    "G84 Z{:.3} R{:.3} F{:.2}",
-                    -t.depth, t.retract_height, feed
-                ));
+                if rigid {
+                    // Rigid: spindle and Z locked together, F is the pitch per revolution
+                    self.output.emit("G95");
+                    self.output.emit(&format!("M29 S{:.0}", rpm));
+                    self.output.emit(&format!(
+                        "G84 Z{:.3} R{:.3} F{:.4}",
+                        -depth, t.retract_height, t.pitch
+                    ));
+                } else {
+                    let feed = rpm * t.pitch;
+                    self.output.emit(&format!(
+                        "G84 Z{:.3} R{:.3} F{:.2}",
+                        -depth, t.retract_height, feed
+                    ));
+                }
             }
         }
 
         self.output.emit("G80");
+        if rigid {
+            self.output.emit("G94");
+        }
         self.output.emit(&format!("G00 Z{:.3}", t.retract_height));
     }
 
     fn emit_footer(&mut self, footer: &Footer) {
         self.output.emit_comment("PROGRAM END");
 
-        // Return to safe position
-        self.output
-            .emit(&format!("G00 Z{:.3}", footer.return_to.x.max(50.0)));
+        // Retract to a safe Z before parking
+        self.retract_to_safe_z();
         self.output.emit(&format!(
             "G00 X{:.3} Y{:.3}",
             footer.return_to.x, footer.return_to.y
@@ -1444,6 +4131,9 @@ impl CodeGenerator {
         // Spindle and coolant off
         self.output.emit("M05");
         self.output.emit("M09");
+        if self.length_comp_enabled() {
+            self.output.emit("G49");
+        }
 
         // Program end
         self.output.emit(&footer.end_code);
@@ -1456,24 +4146,213 @@ impl Default for GCodeOutput {
     }
 }
 
+impl Default for CodeGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse(input: &str) -> Program {
+        crate::parser::Parser::new(crate::lexer::lex(input))
+            .parse()
+            .expect("should parse")
+    }
+
+    #[test]
+    fn test_output_buffers_50k_lines() {
+        let mut output = GCodeOutput::new();
+        for i in 0..50_000 {
+            if i % 1000 == 0 {
+                output.emit_comment("Pass");
+            }
+            output.emit(&format!("G01 X{:.4} Y1.0000", i as f64 * 0.001));
+        }
+
+        // One buffer, no per-line strings: the text is just lines plus separators
+        let text = output.as_str();
+        assert_eq!(output.lines().count(), 50_050);
+        assert_eq!(
+            text.len(),
+            output.lines().map(str::len).sum::<usize>() + 50_049
+        );
+        assert_eq!(output.lines().nth(1), Some("N0010 G01 X0.0000 Y1.0000"));
+        assert!(text.ends_with("N500000 G01 X49.9990 Y1.0000"));
+        assert_eq!(output.line_number, 500_010);
+        assert_eq!(output.to_string(), output.clone().into_string());
+    }
+
+    #[test]
+    fn test_estimated_cycle_time() {
+        let output: GCodeOutput = [
+            "G20",
+            "N0010 G00 X10.0",            // 10" at 400 IPM: 1.5s
+            "N0020 G01 X20.0 F100.0",     // 10" at 100 IPM: 6s
+            "N0030 G04 P2.0",             // 2s
+            "N0040 T2 M06",               // Tool change: 10s
+            "N0050 G02 X20.0 Y0.0 I-5.0", // Full circle, R5 at 100 IPM
+            "N0060 G81 X0.0 Z-1.0 R0.0 F10.0", // 3s over, 6s down, 0.15s out
+            "N0070 G80",
+        ]
+        .into_iter()
+        .collect();
+        let circle = 2.0 * std::f64::consts::PI * 5.0 / 100.0 * 60.0;
+        let expected = 1.5 + 6.0 + 2.0 + 10.0 + circle + 9.15;
+        assert!((output.estimate_cycle_time(400.0) - expected).abs() < 1e-6);
+
+        // End to end: a plausible time lands under the PROGRAM START banner
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\nspindle cw rpm 8000\ndrill at x 1 y 1 depth 0.5 feed 10\npocket rect 2.0 1.5 0.25 at 2.0 1.0";
+        let program = parse(source);
+        let seconds = |mut gen: CodeGenerator| -> u64 {
+            let gcode = gen.generate_output(&program).into_string();
+            let (minutes, secs) = gcode.lines().nth(1)
+                .and_then(|l| l.strip_prefix("; ESTIMATED CYCLE TIME: "))
+                .and_then(|t| t.split_once(':'))
+                .expect("estimate under header");
+            minutes.parse::<u64>().unwrap() * 60 + secs.parse::<u64>().unwrap()
+        };
+        let default = seconds(CodeGenerator::new());
+        assert!(default > 10 && default < 30 * 60, "implausible: {}s", default);
+        assert!(seconds(CodeGenerator::new().with_rapid_rate(50.0)) > default);
+    }
+
+    #[test]
+    fn test_prologue_names_part_stock_and_tools() {
+        let source = "units imperial\npart bracket\nstock 4 x 3 x 0.5 \"6061-T6\"\nsetup {\n    material \"Aluminum 6061-T6\"\n}\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\ndrill 0.25 at 1.0 1.0 depth 0.25\ntool 2 dia 0.5 length 3.0 flutes 4 carbide\ndrill 0.5 at 2.0 1.0 depth 0.25\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\ndrill 0.25 at 3.0 1.0 depth 0.25";
+        let program = parse(source);
+        let gcode = CodeGenerator::new().generate(&program);
+        let header: Vec<&str> = gcode.lines().take_while(|l| l.starts_with(';')).collect();
+
+        assert_eq!(header[0], "; PROGRAM START");
+        assert!(header.contains(&"; PART: bracket"));
+        assert!(header.contains(&"; MATERIAL: Aluminum 6061-T6"));
+        assert!(header.contains(&"; STOCK: 4 x 3 x 0.5\""));
+        let generated = format!("; GENERATED BY: swarf {}", env!("CARGO_PKG_VERSION"));
+        assert!(header.contains(&generated.as_str()), "{:?}", header);
+        let tools: Vec<&&str> = header.iter().filter(|l| l.starts_with(";   T")).collect();
+        assert_eq!(tools, [&";   T1 - 0.25 DIA 2FL CARBIDE", &";   T2 - 0.5 DIA 4FL CARBIDE"]);
+
+        // The generation time is opt-in so output stays reproducible
+        let stamped = CodeGenerator::new().with_timestamp().generate(&program);
+        assert!(stamped.lines().any(|l| l.starts_with(&format!("{} on ", generated)) && l.ends_with(" UTC")));
+        assert_eq!(utc_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(utc_timestamp(1_709_210_096), "2024-02-29 12:34 UTC");
+    }
+
+    #[test]
+    fn test_zero_right_back_flips_positions() {
+        let compile = |zero: &str| {
+            let source = format!(
+                "units imperial\nstock 3 x 2 x 0.75 \"6061-T6\"\nsetup {{\n    zero {}\n    z-min -0.5\n}}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\ndrill 0.25 at 0.5 0.25 depth 1.0\ndrill 0.25 pattern grid rows 1 cols 2 spacing 1.0 0.5 at 0.5 0.5 depth 0.25",
+                zero
+            );
+            let program = parse(&source);
+            CodeGenerator::new().generate(&program)
+        };
+        let rapids = |gcode: &str| -> Vec<(f64, f64)> {
+            gcode
+                .lines()
+                .filter(|l| l.contains("G00 X") && !l.contains("X0.000 Y0.000"))
+                .map(|l| {
+                    let x = crate::post::extract_word(l, 'X').unwrap();
+                    let y = crate::post::extract_word(l, 'Y').unwrap();
+                    (x, y)
+                })
+                .collect()
+        };
+
+        let left_front = compile("left front top");
+        let right_back = compile("right back bottom");
+        assert_eq!(rapids(&left_front), vec![(0.5, 0.25), (0.5, 0.5), (1.5, 0.5)]);
+        assert_eq!(rapids(&right_back), vec![(2.5, 1.75), (2.5, 1.5), (1.5, 1.5)]);
+
+        // z-min -0.5 is half way into the stock from a top zero, under it from a bottom one
+        assert!(left_front.contains("G81 R0.1 Z-0.5000"));
+        assert!(right_back.contains("G81 R0.1 Z-1.0000"));
+    }
+
+    #[test]
+    fn test_cut_sweeps_from_flipped_zero() {
+        let compile = |zero: &str| {
+            let source = format!(
+                "units imperial\nstock 3 x 2 x 0.5 \"6061-T6\"\nsetup {{\n    zero {}\n}}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\ndrill 0.25 at 0.5 0.5 depth 0.25\ncut Y+ 1.0 0.125 0.1 at zero",
+                zero
+            );
+            let program = parse(&source);
+            CodeGenerator::new().generate(&program)
+        };
+        let after = |gcode: &str, marker: &str| -> Vec<String> {
+            let start = gcode.find(marker).unwrap();
+            gcode[start..]
+                .lines()
+                .filter(|l| l.starts_with('N') && (l.contains(" X") || l.contains(" Y")))
+                .take(2)
+                .map(|l| l.split_once(' ').unwrap().1.to_string())
+                .collect()
+        };
+
+        let left_front = compile("left front top");
+        assert!(!left_front.contains("WORK ZERO"));
+        assert!(left_front.contains("G00 X0.5000 Y0.5000"));
+        assert_eq!(after(&left_front, "; CUT"), ["G00 X0.0000 Y0.1250", "G01 X1.0000 F40.0"]);
+
+        // From the right back corner the hole and the cut both come in from there
+        let right_back = compile("right back top");
+        assert_eq!(right_back.matches("; WORK ZERO: G54 at stock left front top").count(), 1);
+        assert!(right_back.contains("G00 X2.5000 Y1.5000"));
+        assert_eq!(after(&right_back, "; CUT"), ["G00 X3.0000 Y1.8750", "G01 X2.0000 F40.0"]);
+    }
+
+    #[test]
+    fn test_coolant_toggles_per_operation() {
+        let source = "units imperial\nsetup {\n    material \"304\"\n}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 1.0 1.0 0.25 at 1.0 1.0 coolant flood\npocket rect 1.0 1.0 0.1 at 3.0 1.0 coolant mist\ndrill 0.25 at 1 2 depth 0.25\ndrill 0.25 at 2 2 depth 0.25";
+        let program = parse(source);
+        let output = CodeGenerator::new().generate(&program);
+
+        // Codes only on a change: flood, mist (after stopping flood), then back to the dry default
+        let cut = output.split("T1 M06").nth(1).unwrap();
+        let coolant: Vec<&str> = cut
+            .lines()
+            .filter_map(|l| l.split_whitespace().nth(1))
+            .filter(|code| ["M07", "M08", "M09"].contains(code))
+            .collect();
+        assert_eq!(coolant, ["M08", "M09", "M07", "M09", "M09"]);
+
+        // Stainless wants flood, so the dry drills are flagged once
+        let warnings: Vec<&str> = cut.lines().filter(|l| l.contains("cutting dry")).collect();
+        assert_eq!(warnings, ["; WARNING: Stainless 304 requires flood coolant - cutting dry"]);
+        assert!(cut.find("cutting dry") < cut.find("DRILL dia"));
+    }
+
+    #[test]
+    fn test_through_spindle_coolant() {
+        let source = "units imperial\ncoolant through\ntool 1 dia 0.25 length 2.5 flutes 2 carbide\ndrill 0.25 at 1 1 depth 1.5 peck clear\ndrill 0.25 at 2 1 depth 0.25 coolant flood";
+        let program = parse(source);
+        assert_eq!(program.header.safety.coolant, CoolantMode::Through);
+        let output = CodeGenerator::new().generate(&program);
+
+        // High-pressure on for the deep hole (again after the tool change),
+        // stopped before flood takes over
+        let coolant: Vec<&str> = output
+            .lines()
+            .filter_map(|l| l.split_whitespace().nth(1))
+            .filter(|code| ["M07", "M08", "M09", "M51"].contains(code))
+            .collect();
+        assert_eq!(coolant, ["M51", "M09", "M51", "M09", "M08", "M09"]);
+    }
+
     #[test]
     fn test_black_book_integration() {
         let mut gen = CodeGenerator::new();
 
         // Setup with material
         let setup = SetupBlock {
-            zero: ZeroConfig {
-                x_ref: crate::ast::XRef::Left,
-                y_ref: crate::ast::YRef::Front,
-                z_ref: crate::ast::ZRef::Top,
-            },
             material: Some("6061-T6".to_string()),
             z_min: Some(0.0),
-            y_limit: None,
+            ..Default::default()
         };
         gen.emit_setup(&setup);
 
@@ -1485,6 +4364,7 @@ mod tests {
                 length: 1.0,
                 flutes: 3,
                 material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
             }),
         };
         gen.emit_tool_change(&tool_change);
@@ -1494,6 +4374,9 @@ mod tests {
             diameter: 0.25,
             position: Position::new(1.0, 0.5),
             depth: DrillDepth::Thru,
+            spot: false,
+            peck: None,
+            dwell: None,
         };
         gen.emit_drill_v2(&drill);
 
@@ -1511,14 +4394,9 @@ mod tests {
 
         // Setup with material
         let setup = SetupBlock {
-            zero: ZeroConfig {
-                x_ref: crate::ast::XRef::Left,
-                y_ref: crate::ast::YRef::Front,
-                z_ref: crate::ast::ZRef::Top,
-            },
             material: Some("6061-T6".to_string()),
             z_min: Some(0.0),
-            y_limit: None,
+            ..Default::default()
         };
         gen.emit_setup(&setup);
 
@@ -1530,6 +4408,7 @@ mod tests {
                 length: 2.0,
                 flutes: 4,
                 material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
             }),
         };
         gen.emit_tool_change(&tool_change);
@@ -1548,20 +4427,81 @@ mod tests {
         assert!(output.contains("passes"));
     }
 
+    #[test]
+    fn test_cbn_tool_runs_on_the_ceramic_table() {
+        let rpm = |tool_material: &str| {
+            let source = format!(
+                "units imperial\nsetup {{\n    material \"Cast Iron Gray\"\n}}\n\
+                 tool 1 dia 0.5 length 2 flutes 4 {}\npocket rect 2 2 0.1 at 2 2",
+                tool_material
+            );
+            let program = parse(&source);
+            let output = CodeGenerator::new().generate(&program);
+            let line = output.lines().find(|l| l.contains("Black Book: RPM=")).unwrap();
+            line["; Black Book: RPM=".len()..].split(',').next().unwrap().to_string()
+        };
+
+        // Gray iron's nominal SFM: 600 ceramic/CBN, 325 coated, 250 carbide (3.82 x SFM / 0.5)
+        assert_eq!(rpm("cbn"), "4584");
+        assert_eq!(rpm("ceramic"), "4584");
+        assert_eq!(rpm("coated"), "2483");
+        assert_eq!(rpm("carbide"), "1910");
+    }
+
+    #[test]
+    fn test_face_passes_lead_in_clear_of_stock() {
+        let source = "units imperial\nstock 4 x 2 x 1 \"Aluminum 6061-T6\"\n\
+            tool 1 dia 1.0 length 2.0 flutes 4 carbide\nface at stock depth 0.05";
+        let program = |source: &str| {
+            parse(source)
+        };
+        // (x, y) at the start of the face and the end of every pass across the stock
+        let passes = |output: String| -> Vec<(f64, f64)> {
+            let face = output.split("FACE MILLING").nth(1).unwrap();
+            let face = face.split("PROGRAM END").next().unwrap();
+            let mut y = 0.0;
+            let mut ends = Vec::new();
+            for line in face.lines() {
+                y = crate::post::extract_word(line, 'Y').unwrap_or(y);
+                if let Some(x) = crate::post::extract_word(line, 'X') {
+                    ends.push((x, y));
+                }
+            }
+            ends
+        };
+
+        // A 1" cutter starts 0.6" off the stock and every pass ends at least that far out
+        let zigzag = passes(CodeGenerator::new().generate(&program(source)));
+        assert_eq!(zigzag[0], (-0.6, 0.4));
+        assert!(zigzag.iter().all(|&(x, _)| x <= -0.5 || x >= 4.5), "{:?}", zigzag);
+
+        // 10% overlap steps 0.9", from 0.1" below the front edge to past the back
+        let ys: Vec<f64> = zigzag[1..].iter().map(|&(_, y)| y).collect();
+        assert_eq!(ys, [0.4, 1.3, 2.2]);
+        assert_eq!(zigzag[1..].iter().map(|&(x, _)| x).collect::<Vec<_>>(), [4.6, -0.6, 4.6]);
+
+        let half = passes(CodeGenerator::new().with_face_overlap(50.0).generate(&program(source)));
+        assert_eq!(half[2].1 - half[1].1, 0.5);
+
+        // Climbing, every pass runs -X and the cutter lifts over the stock to get back
+        let climb = CodeGenerator::new()
+            .generate(&program(&source.replacen('\n', "\nsetup {\n    climb\n}\n", 1)));
+        let face = climb.split("FACE MILLING").nth(1).unwrap();
+        assert_eq!(face.matches("G00 Z0.1").count(), 3);
+        let one_way = passes(climb);
+        assert!(one_way.iter().all(|&(x, _)| x == 4.6 || x == -0.6));
+        assert_eq!(one_way.iter().filter(|&&(x, _)| x == -0.6).count(), 3);
+    }
+
     #[test]
     fn test_cutting_parameters_summary() {
         let mut gen = CodeGenerator::new();
 
         // Setup with material
         let setup = SetupBlock {
-            zero: ZeroConfig {
-                x_ref: crate::ast::XRef::Left,
-                y_ref: crate::ast::YRef::Front,
-                z_ref: crate::ast::ZRef::Top,
-            },
             material: Some("Aluminum 6061-T6".to_string()),
             z_min: Some(0.0),
-            y_limit: None,
+            ..Default::default()
         };
         gen.setup = Some(setup.clone());
         gen.current_material = setup.material;
@@ -1574,6 +4514,7 @@ mod tests {
                 length: 1.0,
                 flutes: 3,
                 material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
             }),
         };
         gen.emit_tool_change(&tool_change);
@@ -1596,14 +4537,9 @@ mod tests {
 
         // Setup with material
         let setup = SetupBlock {
-            zero: ZeroConfig {
-                x_ref: crate::ast::XRef::Left,
-                y_ref: crate::ast::YRef::Front,
-                z_ref: crate::ast::ZRef::Top,
-            },
             material: Some("Aluminum 6061-T6".to_string()),
-            z_min: Some(0.0),
-            y_limit: None,
+            z_min: Some(-1.0),
+            ..Default::default()
         };
         gen.setup = Some(setup.clone());
         gen.current_material = setup.material;
@@ -1616,6 +4552,7 @@ mod tests {
                 length: 2.0,
                 flutes: 4,
                 material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
             }),
         };
         gen.emit_tool_change(&tool_change);
@@ -1628,6 +4565,12 @@ mod tests {
             },
             position: Position::new(1.0, 0.75),
             depth: 0.25,
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
         };
         gen.emit_pocket_v2(&pocket);
 
@@ -1658,14 +4601,9 @@ mod tests {
 
         // Setup with material
         let setup = SetupBlock {
-            zero: ZeroConfig {
-                x_ref: crate::ast::XRef::Left,
-                y_ref: crate::ast::YRef::Front,
-                z_ref: crate::ast::ZRef::Top,
-            },
             material: Some("Aluminum 6061-T6".to_string()),
-            z_min: Some(0.0),
-            y_limit: None,
+            z_min: Some(-1.0),
+            ..Default::default()
         };
         gen.setup = Some(setup.clone());
         gen.current_material = setup.material;
@@ -1678,6 +4616,7 @@ mod tests {
                 length: 1.5,
                 flutes: 4,
                 material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
             }),
         };
         gen.emit_tool_change(&tool_change);
@@ -1687,6 +4626,12 @@ mod tests {
             shape: PocketShape::Circle { diameter: 1.0 },
             position: Position::new(2.0, 2.0),
             depth: 0.125,
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
         };
         gen.emit_pocket_v2(&pocket);
 
@@ -1713,14 +4658,9 @@ mod tests {
 
         // Setup with material
         let setup = SetupBlock {
-            zero: ZeroConfig {
-                x_ref: crate::ast::XRef::Left,
-                y_ref: crate::ast::YRef::Front,
-                z_ref: crate::ast::ZRef::Top,
-            },
             material: Some("Steel 1018".to_string()),
-            z_min: Some(0.0),
-            y_limit: None,
+            z_min: Some(-1.0),
+            ..Default::default()
         };
         gen.setup = Some(setup.clone());
         gen.current_material = setup.material;
@@ -1733,6 +4673,7 @@ mod tests {
                 length: 2.0,
                 flutes: 4,
                 material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
             }),
         };
         gen.emit_tool_change(&tool_change);
@@ -1745,6 +4686,12 @@ mod tests {
             },
             position: Position::new(0.5, 0.375),
             depth: 0.5, // Deep pocket
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
         };
         gen.emit_pocket_v2(&pocket);
 
@@ -1761,20 +4708,55 @@ mod tests {
         assert!(pass2_count >= 1);
     }
 
+    #[test]
+    fn test_pocket_engagement_overrides() {
+        let compile = |options: &str| {
+            let source = format!(
+                "units imperial\nsetup {{\n    zero left front top\n    material \"Aluminum 6061-T6\"\n}}\ntool 1 dia 0.25 length 2.0 flutes 3 carbide\npocket rect 1.0 1.0 0.25 at 0.5 0.5 {}",
+                options
+            );
+            let program = parse(&source);
+            CodeGenerator::new().generate(&program)
+        };
+        // Widest step between raster rows in the first pass, and how many rows
+        let rows = |output: &str| {
+            let pass = output.split("Pass 1/").nth(1).unwrap();
+            let pass = pass.split("Pass 2/").next().unwrap();
+            let mut ys: Vec<f64> = pass
+                .lines()
+                .filter(|l| l.contains("G01") && l.contains('Y'))
+                .filter_map(|l| crate::post::extract_word(l, 'Y'))
+                .collect();
+            ys.dedup();
+            let widest = ys.windows(2).map(|w| w[1] - w[0]).fold(0.0, f64::max);
+            (widest, ys.len())
+        };
+
+        // Black Book: 40% stepover, one diameter deep
+        let default = compile("");
+        assert!(default.contains("Passes required: 1"));
+        assert!(!default.contains("override"));
+        let (default_step, default_rows) = rows(&default);
+        assert!(default_step > 0.05 && default_step <= 0.1 + 1e-3);
+
+        let fine = compile("stepover 0.2 stepdown 0.1");
+        assert!(fine.contains("; Stepover override: 0.050\" (20% of tool)"));
+        assert!(fine.contains("; Stepdown override: 0.100\""));
+        assert!(fine.contains("Passes required: 3 (DOC=0.100\")"));
+        let (fine_step, fine_rows) = rows(&fine);
+        assert!(fine_step <= 0.05 + 1e-3, "{}", fine_step);
+        assert!(fine_rows > default_rows);
+    }
+
     #[test]
     fn test_pocket_respects_tool_diameter() {
         let mut gen = CodeGenerator::new();
 
         // Setup with material
         let setup = SetupBlock {
-            zero: ZeroConfig {
-                x_ref: crate::ast::XRef::Left,
-                y_ref: crate::ast::YRef::Front,
-                z_ref: crate::ast::ZRef::Top,
-            },
             material: Some("Aluminum 6061-T6".to_string()),
-            z_min: Some(0.0),
-            y_limit: None,
+            z_min: Some(-1.0),
+            ..Default::default()
         };
         gen.setup = Some(setup.clone());
         gen.current_material = setup.material;
@@ -1787,6 +4769,7 @@ mod tests {
                 length: 3.0,
                 flutes: 4,
                 material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
             }),
         };
         gen.emit_tool_change(&tool_change);
@@ -1796,6 +4779,12 @@ mod tests {
             shape: PocketShape::Circle { diameter: 1.25 }, // Only 0.25" larger than tool
             position: Position::new(0.0, 0.0),
             depth: 0.1,
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
         };
         gen.emit_pocket_v2(&pocket);
 
@@ -1804,7 +4793,1440 @@ mod tests {
         // Should still generate output even with tight clearances
         assert!(output.contains("POCKET CIRCLE"));
 
-        // With tool larger than pocket radius, should just do center point
-        assert!(output.contains("G00 X0.0000 Y0.0000"));
+        // With tool larger than pocket radius, helix in on the small clearance then
+        // step to the center point
+        assert!(output.contains("G00 X0.1250 Y0.0000"));
+        assert!(output.contains("G01 X0.0000 Y0.0000"));
+    }
+
+    #[test]
+    fn test_tool_max_rpm_clamps_and_scales_feed() {
+        let tool_change = ToolChange {
+            tool_id: Some("EM_250_4FL".to_string()),
+            tool_number: 1,
+            tool_data: Some(ToolData {
+                diameter: 0.25,
+                length: 1.0,
+                flutes: 4,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
+            }),
+        };
+        let pocket_params = |tool_max_rpm: Option<f64>| {
+            let mut library = crate::tool_library::default_tool_library();
+            library.tools.get_mut("EM_250_4FL").unwrap().max_rpm = tool_max_rpm;
+            let mut gen = CodeGenerator::new()
+                .with_max_rpm(20000.0)
+                .with_tool_library(library);
+            gen.current_material = Some("6061-T6".to_string());
+            gen.emit_tool_change(&tool_change);
+            let (rpm, feed, _, _) = gen.calculate_pocket_params(0.25, 0.25);
+            (rpm, feed, gen.output.to_string())
+        };
+
+        // The coated 1/4" end mill wants more than the machine allows
+        let (rpm, feed, output) = pocket_params(None);
+        assert_eq!(rpm, 20000.0);
+        assert!(output.contains("clamped to 20000 by machine max RPM"));
+
+        // A lower per-tool max wins, and chip load is kept
+        let (clamped_rpm, clamped_feed, output) = pocket_params(Some(6000.0));
+        assert_eq!(clamped_rpm, 6000.0);
+        assert!((clamped_feed - feed * 6000.0 / rpm).abs() < 1e-9);
+        assert!(output.contains("clamped to 6000 by tool max RPM"));
+    }
+
+    #[test]
+    fn test_header_max_rpm_clamps_black_book_speed() {
+        let source = "units imperial\nmax-rpm 15000\nsetup {\n    material \"6061-T6\"\n}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 2.0 1.5 0.25 at 2.0 1.0";
+        let program = parse(source);
+        assert_eq!(program.header.safety.max_spindle_rpm, Some(15000.0));
+
+        // The Black Book wants 18336 RPM for this cutter
+        let output = CodeGenerator::new().generate(&program);
+        assert!(output.contains("; RPM 18336 clamped to 15000 by machine max RPM"));
+        assert!(output.contains("S15000 M03"));
+
+        // A lower limit from the caller still wins
+        let output = CodeGenerator::new().with_max_rpm(12000.0).generate(&program);
+        assert!(output.contains("S12000 M03"));
+    }
+
+    #[test]
+    fn test_plunge_feed_from_library_tool() {
+        let tool_change = ToolChange {
+            tool_id: Some("EM_250_4FL".to_string()),
+            tool_number: 1,
+            tool_data: Some(ToolData {
+                diameter: 0.25,
+                length: 1.0,
+                flutes: 4,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: true,
+            }),
+        };
+        let pocket = PocketV2Op {
+            shape: PocketShape::Rect { width: 1.0, height: 1.0 },
+            position: Position::new(1.0, 1.0),
+            depth: 0.1,
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
+        };
+        let pocket_output = |plunge_feed: Option<f64>, pocket: &PocketV2Op| {
+            let mut library = crate::tool_library::default_tool_library();
+            library.tools.get_mut("EM_250_4FL").unwrap().default_plunge_feed = plunge_feed;
+            let mut gen = CodeGenerator::new().with_tool_library(library);
+            gen.current_material = Some("6061-T6".to_string());
+            gen.emit_tool_change(&tool_change);
+            gen.emit_pocket_v2(pocket);
+            gen.output.to_string()
+        };
+        let plunges = |output: &str| -> Vec<String> {
+            output
+                .lines()
+                .filter(|l| l.contains("G01 Z-"))
+                .map(|l| l.rsplit(' ').next().unwrap().to_string())
+                .collect()
+        };
+
+        let output = pocket_output(Some(12.0), &pocket);
+        assert!(output.contains("; Plunge: 12.0 IPM (tool library)"));
+        assert_eq!(plunges(&output), ["F12.0"]);
+
+        // Without one, aluminum plunges at half the feed
+        let output = pocket_output(None, &pocket);
+        assert!(output.contains("(Black Book 50% of feed)"));
+
+        // The pocket's own plunge wins over both
+        let output = pocket_output(Some(12.0), &PocketV2Op { plunge: Some(5.0), ..pocket.clone() });
+        assert!(output.contains("; Plunge: 5.0 IPM (plunge override)"));
+        assert_eq!(plunges(&output), ["F5.0"]);
+    }
+
+    #[test]
+    fn test_ramp_entry_uses_plunge_feed() {
+        // Not center-cutting, so the rect pocket ramps and the circle helixes in
+        let tool_change = ToolChange {
+            tool_id: Some("EM_250_4FL".to_string()),
+            tool_number: 1,
+            tool_data: Some(ToolData {
+                diameter: 0.25,
+                length: 1.0,
+                flutes: 4,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
+            }),
+        };
+        let pocket = |shape: PocketShape, plunge: Option<f64>| {
+            let mut library = crate::tool_library::default_tool_library();
+            library.tools.get_mut("EM_250_4FL").unwrap().default_plunge_feed = Some(12.0);
+            let mut gen = CodeGenerator::new().with_tool_library(library);
+            gen.current_material = Some("6061-T6".to_string());
+            gen.emit_tool_change(&tool_change);
+            gen.emit_pocket_v2(&PocketV2Op {
+                shape,
+                position: Position::new(1.0, 1.0),
+                depth: 0.1,
+                finish: None,
+                corners: CornerRelief::None,
+                adaptive: false,
+                plunge,
+                stepover: None,
+                stepdown: None,
+            });
+            gen.output.to_string()
+        };
+        let entry_feeds = |output: &str| -> Vec<String> {
+            output
+                .lines()
+                .filter(|l| l.contains(" Z-") && (l.contains("G01 X") || l.contains("G03 X")))
+                .map(|l| l.rsplit(' ').next().unwrap().to_string())
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect()
+        };
+
+        let rect = PocketShape::Rect { width: 1.0, height: 1.0 };
+        let output = pocket(rect.clone(), None);
+        assert!(output.contains("; Ramp entry at 3.0 deg, F12.0 (tool library)"));
+        assert_eq!(entry_feeds(&output), ["F12.0"]);
+
+        let output = pocket(PocketShape::Circle { diameter: 1.0 }, None);
+        assert!(output.contains("lap(s), F12.0 (tool library)"));
+        assert_eq!(entry_feeds(&output), ["F12.0"]);
+
+        // The pocket's own plunge feed covers ramps too
+        let output = pocket(rect, Some(5.0));
+        assert!(output.contains("; Ramp entry at 3.0 deg, F5.0 (plunge override)"));
+        assert_eq!(entry_feeds(&output), ["F5.0"]);
+    }
+
+    #[test]
+    fn test_pocket_finish_allowance() {
+        let source = "units imperial\nsetup {\n    material \"6061-T6\"\n}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 2.0 1.5 0.25 at 2.0 1.0 finish 0.02";
+        let program = parse(source);
+        let output = CodeGenerator::new().generate(&program);
+        let (roughing, finish) = output.split_at(output.find("FINISH PASS").expect("finish pass"));
+
+        // Tool center walls at 2.0 +/- 0.875 and 1.0 +/- 0.625, roughed 0.02 inside them
+        let x_words = roughing
+            .lines()
+            .filter(|l| l.starts_with('N'))
+            .flat_map(|l| l.split_whitespace().filter_map(|w| w.strip_prefix('X')))
+            .map(|x| x.parse::<f64>().unwrap());
+        let (min_x, max_x) = x_words.fold((f64::MAX, f64::MIN), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        assert!((min_x - 1.145).abs() < 1e-9 && (max_x - 2.855).abs() < 1e-9);
+
+        // Spring pass at full depth and final size
+        assert!(finish.contains("G01 X1.1250 Y0.3750 Z-0.2500"));
+        assert!(finish.contains("G01 X2.8750"));
+        assert!(finish.contains("G01 Y1.6250"));
+
+        // 174 IPM over 3 flutes at 18336 RPM is 0.0032 IPT on a 0.125" radius
+        assert!(roughing.contains("; Estimated wall finish: Ra 2.5 uin (0.06 um)"));
+    }
+
+    #[test]
+    fn test_existing_part_roughs_close_to_size() {
+        let compile = |part: &str, finish: &str| {
+            let source = format!(
+                "units imperial\n{}\nsetup {{\n    material \"6061-T6\"\n}}\n\
+                tool 1 dia 0.25 length 1.0 flutes 3 carbide\n\
+                pocket rect 2.0 1.5 0.25 at 2.0 1.0{}",
+                part, finish
+            );
+            let program = parse(&source);
+            CodeGenerator::new().generate(&program)
+        };
+        let rough_min_x = |output: &str| {
+            output[..output.find("FINISH PASS").expect("finish pass")]
+                .lines()
+                .skip_while(|l| !l.contains("POCKET"))
+                .filter(|l| l.starts_with('N'))
+                .flat_map(|l| l.split_whitespace().filter_map(|w| w.strip_prefix('X')))
+                .map(|x| x.parse::<f64>().unwrap())
+                .fold(f64::MAX, f64::min)
+        };
+
+        // Fresh stock is roughed to size unless a finish allowance is asked for
+        let fresh = compile("part bracket", "");
+        assert!(!fresh.contains("EXISTING PART"));
+        assert!(!fresh.contains("FINISH PASS"));
+        assert!((rough_min_x(&compile("part bracket", " finish 0.02")) - 1.145).abs() < 1e-9);
+
+        // A reworked part's walls are near size, so roughing leaves only 0.005 by default
+        let existing = compile("part bracket existing", "");
+        assert!(existing.contains("; EXISTING PART: pockets without a finish allowance leave 0.0050\""));
+        assert!(existing.contains("; Finish allowance: 0.0050\""));
+        assert!((rough_min_x(&existing) - 1.13).abs() < 1e-9);
+        assert!(existing.contains("G01 X1.1250 Y0.3750 Z-0.2500"));
+
+        // An allowance the pocket sets is kept
+        let explicit = compile("part bracket existing", " finish 0.02");
+        assert!(explicit.contains("; Finish allowance: 0.0200\""));
+        assert!((rough_min_x(&explicit) - 1.145).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_heavy_cut_trips_machine_hp_limit() {
+        let source = "units imperial\nsetup {\n    material \"Steel 4140\"\n}\ntool 1 dia 0.75 length 3.0 flutes 4 carbide\npocket rect 3.0 2.0 0.5 at 2.0 1.5";
+        let program = parse(source);
+        let compile = |max_hp: f64| {
+            let machine = MachineProfile {
+                max_rpm: 10000,
+                max_feed: 300.0,
+                max_hp,
+                low_torque_rpm: None,
+            };
+            CodeGenerator::new().with_machine(machine).generate(&program)
+        };
+
+        let benchtop = compile(0.5);
+        assert!(benchtop.contains("; WARNING MACHINE_HP_LIMIT: Operation requires"));
+        assert!(benchtop.contains("machine rated for 0.50 HP"));
+        assert!(!compile(20.0).contains("MACHINE_HP_LIMIT"));
+    }
+
+    #[test]
+    fn test_consecutive_pockets_retract_before_repositioning() {
+        use crate::post::extract_word;
+
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\nspindle cw rpm 8000\n\
+            pocket rect 1.0 1.0 0.5 at 1.0 1.0\npocket rect 1.0 1.0 0.5 at 3.0 1.0\npocket circle 0.75 0.25 at 5.0 1.0";
+        let program = parse(source);
+        let output = CodeGenerator::new().generate_output(&program);
+
+        // The first rapid of each pocket, over from the one before, starts at safe Z
+        let mut z = f64::INFINITY;
+        let mut entering = false;
+        let mut entries = 0;
+        for line in output.lines() {
+            if line.starts_with("; POCKET") {
+                entering = true;
+            } else if entering && line.contains("G00 X") {
+                assert!(z > 0.0, "rapid across at Z{}: {}", z, line);
+                entering = false;
+                entries += 1;
+            }
+            if !line.starts_with(';') {
+                z = extract_word(line, 'Z').unwrap_or(z);
+            }
+        }
+        assert_eq!(entries, 3);
+        crate::validator::Validator::new()
+            .validate_rapids(&output)
+            .expect("rapids stay above the stock");
+    }
+
+    #[test]
+    fn test_adaptive_slot_keeps_constant_engagement() {
+        use crate::post::extract_word;
+
+        let source = "units imperial\nsetup {\n    material \"Steel 4140\"\n}\ntool 1 dia 0.5 length 3.0 flutes 4 carbide\npocket rect 3.0 0.75 0.5 at 2.0 1.0 adaptive";
+        let program = parse(source);
+        let output = CodeGenerator::new().generate(&program);
+        assert!(output.contains("Stepover=0.050\""), "adaptive WOC is a tenth of the tool");
+
+        // Loop centers are the centers of the arcs that ride the 0.125 loop radius;
+        // back arcs returning through the slot swing wider
+        let mut at = (0.0, 0.0);
+        let mut arcs = 0;
+        let mut centers: Vec<(f64, f64)> = Vec::new();
+        for line in output.lines().filter(|l| l.starts_with('N')) {
+            if line.contains(" G03 ") && !line.contains(" Z") {
+                arcs += 1;
+                let (i, j) = (extract_word(line, 'I').unwrap(), extract_word(line, 'J').unwrap());
+                let center = (at.0 + i, at.1 + j);
+                let moved = centers
+                    .last()
+                    .is_none_or(|c| (c.0 - center.0).hypot(c.1 - center.1) > 1e-3);
+                if (i.hypot(j) - 0.125).abs() < 1e-6 && moved {
+                    centers.push(center);
+                }
+            }
+            if let (Some(x), Some(y)) = (extract_word(line, 'X'), extract_word(line, 'Y')) {
+                at = (x, y);
+            }
+        }
+        assert!(arcs > 80, "expected a long chain of loops, got {} arcs", arcs);
+
+        // Every loop advances the same distance, so every front arc takes the same bite
+        let advances: Vec<f64> = centers
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+            .collect();
+        assert!(advances.len() > 40);
+        assert!(advances.iter().all(|a| (a - 0.05).abs() < 1e-3), "{:?}", advances);
+    }
+
+    #[test]
+    fn test_adaptive_lane_lift_follows_units() {
+        let source = "units metric\nsetup {\n    material \"6061-T6\"\n}\ntool 1 dia 12 length 60 flutes 3 carbide\npocket rect 80 40 30 at 50 25 adaptive";
+        let output = CodeGenerator::new().generate(&parse(source));
+
+        // Crossing to the next lane, or over the first pass's floor, clears it by 0.5 mm
+        let lifts: Vec<&str> = output
+            .lines()
+            .filter(|l| l.contains("G00 Z") && !l.contains("Z2.5") && !l.contains("Z25."))
+            .collect();
+        assert_eq!(lifts.len(), 3);
+        assert!(lifts[0].ends_with("G00 Z0.5000"));
+        assert!(lifts[1..].iter().all(|l| l.ends_with("G00 Z-17.5000")));
+    }
+
+    #[test]
+    fn test_dogbone_corner_relief() {
+        let compile = |corners: &str| {
+            let source = format!(
+                "units imperial\nsetup {{\n    material \"6061-T6\"\n}}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 2.0 1.5 0.25 at 2.0 1.0 corners {}",
+                corners
+            );
+            let program = parse(&source);
+            CodeGenerator::new().generate(&program)
+        };
+
+        // Wall path corners are at X1.125/2.875 Y0.375/1.625; relief moves go past them
+        let output = compile("dogbone");
+        let start = output.find("CORNER RELIEF dogbone").expect("corner relief");
+        let relief = &output[start..start + output[start..].find("G00 Z0.1").unwrap()];
+        let past_wall = relief
+            .lines()
+            .filter_map(|l| l.split_whitespace().find_map(|w| w.strip_prefix('X')))
+            .map(|x| x.parse::<f64>().unwrap())
+            .filter(|x| *x < 1.125 - 1e-6 || *x > 2.875 + 1e-6)
+            .count();
+        assert_eq!(past_wall, 4);
+        assert!(relief.contains("G01 X1.0884 Y0.3384"));
+        assert!(relief.contains("G01 X2.9116 Y1.6616"));
+
+        // T-bones run along the long (X) walls
+        let output = compile("tbone");
+        assert!(output.contains("G01 X1.0000 Y0.3750"));
+        assert!(output.contains("G01 X3.0000 Y1.6250"));
+
+        assert!(!compile("none").contains("CORNER RELIEF"));
+    }
+
+    #[test]
+    fn test_pocket_stepdown_follows_material_doc_limit() {
+        let tool_change = ToolChange {
+            tool_id: Some("EM_500_4FL".to_string()),
+            tool_number: 1,
+            tool_data: Some(ToolData {
+                diameter: 0.5,
+                length: 2.0,
+                flutes: 4,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: true,
+            }),
+        };
+        let stepdown = |material: &str, depth: f64| {
+            let mut gen = CodeGenerator::new();
+            gen.current_material = Some(material.to_string());
+            gen.emit_tool_change(&tool_change);
+            gen.calculate_pocket_params(0.5, depth).2
+        };
+
+        // Tool steel takes 0.3x diameter a pass, aluminum the full diameter
+        let a2 = stepdown("A2", 2.0);
+        let aluminum = stepdown("6061-T6", 2.0);
+        assert!((a2 - 0.15).abs() < 1e-9);
+        assert!((aluminum - 0.5).abs() < 1e-9);
+        assert!(a2 < aluminum / 3.0);
+
+        // A shallow pocket is cut in one pass at its own depth
+        assert!((stepdown("6061-T6", 0.2) - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cut_generates_stepped_passes() {
+        let mut gen = CodeGenerator::new();
+
+        let cut = CutOp {
+            direction: Direction::YPositive,
+            sweep: 0.625,
+            depth: 0.125,
+            height: 0.3,
+            z_constraint: ZConstraint::Positive,
+            origin: Position::default(),
+        };
+        gen.emit_cut(&cut);
+
+        let output = gen.output.to_string();
+
+        // Default 1/4" tool, stepdown 0.125" -> ceil(0.3 / 0.125) = 3 passes
+        let (_, _, stepdown, _) = gen.calculate_pocket_params(0.25, cut.height);
+        let expected = (cut.height / stepdown).ceil() as usize;
+        assert_eq!(output.matches("; Pass ").count(), expected);
+        assert!(output.contains("G01 X0.6250"));
+        assert!(output.contains("Y0.1250"));
+        assert!(!output.contains("TODO"));
+
+        // Z+ never cuts below Z0
+        assert!(!output.contains("Z-"));
+    }
+
+    #[test]
+    fn test_clear_rasters_area() {
+        let mut gen = CodeGenerator::new();
+
+        let clear = ClearOp {
+            direction: Direction::XPositive,
+            sweep: 1.0,
+            depth: 0.5,
+            height: 0.25,
+            z_constraint: ZConstraint::Free,
+            origin: Position::default(),
+        };
+        gen.emit_clear(&clear);
+
+        let output = gen.output.to_string();
+
+        assert_eq!(output.matches("; Pass ").count(), 2);
+        assert!(output.contains("G01 Z-0.2500"));
+        assert!(output.contains("G01 X0.5000"));
+        assert!(output.contains("G01 Y1.0000"));
+    }
+
+    #[test]
+    fn test_pocket_respects_z_min() {
+        let mut gen = CodeGenerator::new();
+
+        let setup = SetupBlock {
+            z_min: Some(-0.3),
+            ..Default::default()
+        };
+        gen.setup = Some(setup);
+
+        let pocket = PocketV2Op {
+            shape: PocketShape::Rect {
+                width: 1.0,
+                height: 1.0,
+            },
+            position: Position::new(0.5, 0.5),
+            depth: 0.5,
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
+        };
+        gen.emit_pocket_v2(&pocket);
+
+        let output = gen.output.to_string();
+        assert!(output.contains("WARNING: depth 0.5000 violates z-min"));
+
+        // No commanded Z may go below the floor
+        for word in output.split_whitespace() {
+            if let Some(z) = word.strip_prefix('Z').and_then(|v| v.parse::<f64>().ok()) {
+                assert!(z >= -0.3 - 1e-9, "Z{} is below z-min", z);
+            }
+        }
+        assert!(output.contains("Z-0.3000"));
+    }
+
+    #[test]
+    fn test_pocket_and_face_respect_y_limit() {
+        let mut gen = CodeGenerator::new();
+
+        let setup = SetupBlock {
+            y_limit: Some(1.0),
+            ..Default::default()
+        };
+        gen.setup = Some(setup);
+        gen.stock = Some(StockDef {
+            material: "Aluminum 6061-T6".to_string(),
+            size_x: 2.0,
+            size_y: 2.0,
+            size_z: 0.5,
+        });
+
+        // 1/4" default tool: centre may not pass Y0.875
+        let pocket = PocketV2Op {
+            shape: PocketShape::Rect {
+                width: 1.0,
+                height: 1.0,
+            },
+            position: Position::new(1.0, 1.0),
+            depth: 0.1,
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
+        };
+        gen.emit_pocket_v2(&pocket);
+        gen.emit_face_v2(&FaceV2Op {
+            position: FacePosition::Stock,
+            depth: 0.02,
+        });
+
+        let output = gen.output.to_string();
+        assert!(output.contains("clipped to 0.8750 by y-limit"));
+        for word in output.split_whitespace() {
+            if let Some(y) = word.strip_prefix('Y').and_then(|v| v.parse::<f64>().ok()) {
+                assert!(y <= 1.0 + 1e-9, "Y{} is beyond y-limit", y);
+            }
+        }
+    }
+
+    fn v1_pocket_with_tool(diameter: f64) -> String {
+        let mut gen = CodeGenerator::new();
+        gen.emit_tool_change(&ToolChange {
+            tool_id: None,
+            tool_number: 1,
+            tool_data: Some(ToolData {
+                diameter,
+                length: 50.0,
+                flutes: 3,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
+            }),
+        });
+        gen.emit_pocket(&PocketOp {
+            geometry: Geometry::Rect(Rectangle {
+                bottom_left: Position::new(0.0, 0.0),
+                width: 40.0,
+                height: 40.0,
+                corner_radius: None,
+                rotation: 0.0,
+            }),
+            depth: 2.0,
+            stepdown: 2.0,
+            stepover: 0.5,
+            feed_rate: 500.0,
+            plunge_feed: 200.0,
+            finish_pass: None,
+        });
+        gen.output.to_string()
+    }
+
+    #[test]
+    fn test_v1_pocket_uses_tool_radius() {
+        // 12mm tool -> pocket boundary offset by 6mm
+        let output = v1_pocket_with_tool(12.0);
+        assert!(output.contains("G00 X6.000 Y6.000"));
+        assert!(output.contains("G01 X34.000"));
+
+        // 6mm tool -> 3mm offset
+        let output = v1_pocket_with_tool(6.0);
+        assert!(output.contains("G00 X3.000 Y3.000"));
+        assert!(output.contains("G01 X37.000"));
+    }
+
+    #[test]
+    fn test_v1_profile_uses_tool_radius() {
+        let mut gen = CodeGenerator::new();
+        gen.emit_tool_change(&ToolChange {
+            tool_id: None,
+            tool_number: 1,
+            tool_data: Some(ToolData {
+                diameter: 12.0,
+                length: 50.0,
+                flutes: 3,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
+            }),
+        });
+        gen.emit_profile(&ProfileOp {
+            geometry: Geometry::Rect(Rectangle {
+                bottom_left: Position::new(0.0, 0.0),
+                width: 100.0,
+                height: 80.0,
+                corner_radius: None,
+                rotation: 0.0,
+            }),
+            depth: 3.0,
+            side: CutSide::Outside,
+            stock_to_leave: 0.0,
+            feed_rate: 600.0,
+            plunge_feed: 300.0,
+            cutter_comp: false,
+            lead_radius: Some(0.0),
+        });
+
+        // Outside profile: tool centre 6mm outside the rectangle
+        let output = gen.output.to_string();
+        assert!(output.contains("G00 X-6.000 Y-6.000"));
+        assert!(output.contains("G01 X106.000"));
+    }
+
+    #[test]
+    fn test_tap_feed_follows_spindle_rpm() {
+        let mut gen = CodeGenerator::new();
+        gen.emit_spindle(&SpindleCommand {
+            direction: SpindleDir::CW,
+            rpm: 800.0,
+        });
+        gen.emit_tap(&TapOp {
+            positions: vec![Position::new(10.0, 10.0)],
+            depth: 12.0,
+            pitch: 1.25,
+            retract_height: 5.0,
+            thread: None,
+        });
+
+        let output = gen.output.to_string();
+        assert!(output.contains(&format!("F{:.2}", 800.0 * 1.25)));
+        assert!(!output.contains("WARNING"));
+    }
+
+    #[test]
+    fn test_rigid_tapping_engages_m29_before_g84() {
+        let tap = "spindle cw rpm 800\ntap at x 10 y 10 depth 12 pitch 1.25";
+        let program = parse(&format!("units metric\nsetup {{\n    rigid-tap\n}}\n{}", tap));
+        let output = CodeGenerator::new().generate(&program);
+
+        let g95 = output.find("G95").expect("feed per rev");
+        let m29 = output.find("M29 S800").expect("rigid tapping");
+        let g84 = output.find("G84 Z-12.000 R5.000 F1.2500").expect("pitch feed");
+        let g94 = output.find("G94").expect("feed per minute restored");
+        assert!(g95 < m29 && m29 < g84 && g84 < g94);
+
+        // Off by default: floating tap with F = RPM x pitch
+        let floating = CodeGenerator::new().generate(&parse(&format!("units metric\n{}", tap)));
+        assert!(!floating.contains("M29"));
+        assert!(floating.contains("F1000.00"));
+    }
+
+    #[test]
+    fn test_group_tools_merges_repeat_tool_visits() {
+        let operations = "tool 1 dia 6 length 60 flutes 2 hss\ndrill at x 10 y 10 depth 15 feed 150\n\
+            tool 2 dia 8 length 60 flutes 3 carbide\ndrill at x 30 y 10 depth 15 feed 150\n\
+            tool 1 dia 6 length 60 flutes 2 hss\ndrill at x 50 y 10 depth 15 feed 150";
+        let source = format!("units metric\nsetup {{\n    group-tools\n}}\n{}", operations);
+        let compile = |source: &str| CodeGenerator::new().generate(&parse(source));
+
+        // A-B-A in source order loads three times
+        let ordered = compile(&format!("units metric\n{}", operations));
+        assert_eq!(ordered.matches("M06").count(), 3);
+        assert!(!ordered.contains("REORDERED"));
+
+        // Grouped, both tool 1 holes run before tool 2 is loaded
+        let grouped = compile(&source);
+        assert_eq!(grouped.matches("M06").count(), 2);
+        assert!(grouped.contains("OPERATIONS REORDERED BY TOOL: 1 tool change(s) saved"));
+        let x50 = grouped.find("X50.").expect("third hole");
+        let t2 = grouped.find("T2 M06").expect("tool 2");
+        assert!(x50 < t2);
+
+        // A coolant change between visits keeps the order as written
+        let barrier = compile(&source.replace(
+            "tool 1 dia 6 length 60 flutes 2 hss\ndrill at x 50",
+            "coolant flood\ntool 1 dia 6 length 60 flutes 2 hss\ndrill at x 50",
+        ));
+        assert_eq!(barrier.matches("M06").count(), 3);
+    }
+
+    #[test]
+    fn test_grid_pattern_snakes_between_rows() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 2 hss\n\
+            drill 0.25 pattern grid rows 4 cols 4 spacing 1.0 1.0 at 1.0 1.0 depth 0.25";
+        let rapid_length = |source: &str| {
+            let program = parse(source);
+            let output = CodeGenerator::new().generate(&program);
+            let holes: Vec<(f64, f64)> = output
+                .split("PROGRAM END")
+                .next()
+                .unwrap()
+                .lines()
+                .filter(|l| l.contains("G00 X"))
+                .map(|l| {
+                    let x = crate::post::extract_word(l, 'X').unwrap();
+                    (x, crate::post::extract_word(l, 'Y').unwrap())
+                })
+                .collect();
+            assert_eq!(holes.len(), 16);
+            holes
+                .windows(2)
+                .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+                .sum::<f64>()
+        };
+
+        // Row by row rapids back 3" three times; snaking never moves more than one hole
+        let written = rapid_length(&format!("setup {{\n    keep-pattern-order\n}}\n{}", source));
+        let snaking = rapid_length(source);
+        assert!((written - (12.0 + 3.0 * 3.0f64.hypot(1.0))).abs() < 1e-9, "{}", written);
+        assert!((snaking - 15.0).abs() < 1e-9, "{}", snaking);
+    }
+
+    #[test]
+    fn test_tap_without_spindle_warns() {
+        let mut gen = CodeGenerator::new();
+        gen.emit_tap(&TapOp {
+            positions: vec![Position::new(10.0, 10.0)],
+            depth: 12.0,
+            pitch: 1.0,
+            retract_height: 5.0,
+            thread: None,
+        });
+
+        assert!(gen
+            .output
+            .to_string()
+            .contains("WARNING: no active spindle speed for tapping"));
+    }
+
+    #[test]
+    fn test_spot_cycle_precedes_peck_drill() {
+        let source = "units imperial\nsetup {\n    material \"Aluminum 6061-T6\"\n}\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\ndrill 0.25 at 1.0 1.0 depth 2.0 spot";
+        let program = parse(source);
+        let output = CodeGenerator::new().generate(&program);
+
+        let spot = output.find("G81 R0.1 Z-0.0500").expect("spot cycle");
+        // Aluminum pecks with chip-breaking G73 by default
+        let peck = output.find("G73 R0.1 Z-2.0000").expect("peck cycle");
+        assert!(spot < peck);
+        assert!(output.find("G00 X1.0000 Y1.0000").unwrap() < spot);
+    }
+
+    #[test]
+    fn test_spot_depth_follows_units() {
+        let source = "units metric\nsetup {\n    material \"Aluminum 6061-T6\"\n}\n\
+            tool 1 dia 6 length 75 flutes 2 carbide\ndrill 6 at 25 25 depth 50 spot";
+        let output = CodeGenerator::new().generate(&parse(source));
+
+        let spot = output.find("G81 R2.5 Z-1.2500").expect("metric spot cycle");
+        assert!(spot < output.find("G73 R2.5 Z-50.0000").expect("peck cycle"));
+    }
+
+    #[test]
+    fn test_peck_break_emits_g73() {
+        let compile = |drill: &str| {
+            let source = format!(
+                "units imperial\nsetup {{\n    material \"Steel 4140\"\n}}\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\n{}",
+                drill
+            );
+            let program = parse(&source);
+            CodeGenerator::new().generate_output(&program)
+        };
+
+        // Steel clears chips with full retracts unless told to break them
+        let deep = compile("drill 0.25 at 1.0 1.0 depth 2.0");
+        assert!(deep.lines().any(|l| l.contains("G83 R0.1 Z-2.0000")));
+        let output = compile("drill 0.25 at 1.0 1.0 depth 2.0 peck break");
+        assert!(output.lines().any(|l| l.contains("G73 R0.1 Z-2.0000")));
+        assert!(!output.lines().any(|l| l.contains("G83")));
+
+        // An explicit mode pecks even a hole shallower than one peck
+        let shallow = compile("drill 0.25 at 1.0 1.0 depth 0.1 spot peck clear");
+        assert!(shallow.lines().any(|l| l.contains("G83 R0.1 Z-0.1000")));
+
+        // Mach3 still expands G73 into long-form pecks
+        use crate::post::PostProcessor;
+        let mach3 = crate::post::mach3::Mach3Post.process(&output);
+        assert!(!mach3.lines().any(|l| l.contains("G73")));
+        assert!(mach3.lines().any(|l| l.contains("G01 Z-2.0000")));
+    }
+
+    #[test]
+    fn test_deep_hole_peck_follows_material() {
+        let peck = |material: &str| {
+            let source = format!(
+                "units imperial\nsetup {{\n    material \"{}\"\n}}\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\ndrill 0.25 at 1.0 1.0 depth 2.5",
+                material
+            );
+            let program = parse(&source);
+            let output = CodeGenerator::new().generate_output(&program);
+            let cycle = output.lines().find(|l| l.contains(" Q")).expect("peck cycle").to_string();
+            let q = cycle.split_whitespace().find_map(|w| w.strip_prefix('Q')).unwrap();
+            q.parse::<f64>().unwrap()
+        };
+
+        // A 10xD hole pecks 1.5xD in aluminum but only 1xD in mild steel
+        assert_eq!(peck("Aluminum 6061-T6"), 0.375);
+        assert_eq!(peck("Steel 1018"), 0.25);
+        assert!(peck("Stainless 304") < peck("Steel 1018"));
+    }
+
+    #[test]
+    fn test_drill_dwell_emits_g82() {
+        let source = "units imperial\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\ndrill 0.25 at 0 0 depth 0.2 dwell 0.5";
+        let program = parse(source);
+        let output = CodeGenerator::new().generate_output(&program);
+
+        assert!(output.lines().any(|l| l.contains("G82 R0.1 Z-0.2000 P0.50")));
+        assert!(!output.lines().any(|l| l.contains("G81")));
+
+        // Mach3 expands the dwell into a G04 at the bottom
+        use crate::post::PostProcessor;
+        let mach3 = crate::post::mach3::Mach3Post.process(&output);
+        assert!(mach3.lines().any(|l| l.contains("G04 P0.50")));
+    }
+
+    #[test]
+    fn test_polygon_and_path_cut_with_g01() {
+        let compile = |op: &str| {
+            let source = format!("units imperial\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\n{}", op);
+            let program = parse(&source);
+            CodeGenerator::new().generate_output(&program)
+        };
+        let segments = |output: &GCodeOutput| {
+            output
+                .lines()
+                .filter(|l| l.contains("G01 X") && l.contains(" Y"))
+                .count()
+        };
+
+        // One segment per side, closing back on the first corner
+        let hex = compile("profile outside polygon at 2 2 sides 6 radius 1 depth 0.1 lead 0");
+        assert_eq!(segments(&hex), 6);
+        let first = hex.lines().find(|l| l.contains("G00 X")).unwrap();
+        let last = hex.lines().rfind(|l| l.contains("G01 X")).unwrap();
+        assert_eq!(first.split_once("G00").unwrap().1, last.split_once("G01").unwrap().1);
+
+        let path = compile("profile on path 0 0 -> 3 0 -> 1.5 2 depth 0.1 lead 0");
+        assert_eq!(segments(&path), 3);
+        assert!(path.lines().any(|l| l.contains("G01 X1.500 Y2.000")));
+
+        // A pocket laps its walls after the rows, staying a tool radius inside
+        let pocket = compile("pocket polygon at 2 2 sides 6 radius 1 depth 0.1 stepover 0.5");
+        assert!(pocket.lines().any(|l| l.contains("G01 X2.856 Y2.000")));
+        assert!(!pocket.lines().any(|l| l.contains("UNSUPPORTED")));
+    }
+
+    #[test]
+    fn test_rotated_rect_swaps_footprint() {
+        let footprint = |op: &str| {
+            let source = format!("units imperial\ntool 1 dia 0.25 length 3.0 flutes 2 carbide\n{}", op);
+            let program = parse(&source);
+            let output = CodeGenerator::new().generate_output(&program);
+
+            // Extents of the tool center, following modal X and Y
+            let (mut x, mut y) = (f64::NAN, f64::NAN);
+            let (mut min, mut max) = ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY));
+            let operation = output
+                .lines()
+                .skip_while(|l| !l.ends_with("OPERATION"))
+                .take_while(|l| !l.contains("G00 Z1.000"));
+            for line in operation.filter(|l| l.contains("G00") || l.contains("G01")) {
+                x = crate::post::extract_word(line, 'X').unwrap_or(x);
+                y = crate::post::extract_word(line, 'Y').unwrap_or(y);
+                if !x.is_nan() && !y.is_nan() {
+                    min = (min.0.min(x), min.1.min(y));
+                    max = (max.0.max(x), max.1.max(y));
+                }
+            }
+            (max.0 - min.0, max.1 - min.1, output)
+        };
+
+        let (w, h, _) = footprint("pocket rect at x 0 y 0 width 2 height 1 depth 0.1");
+        assert!((w - 1.75).abs() < 1e-3 && (h - 0.75).abs() < 1e-3, "{} x {}", w, h);
+
+        // Turned a quarter about its center the 2x1 pocket stands 2 tall, passes cutting along Y
+        let (w, h, output) = footprint("pocket rect at x 0 y 0 width 2 height 1 rotate 90 depth 0.1");
+        assert!((w - 0.75).abs() < 1e-3 && (h - 1.75).abs() < 1e-3, "{} x {}", w, h);
+        assert!(output.lines().any(|l| l.contains("G00 X1.375 Y-0.375")));
+        assert!(output.lines().any(|l| l.contains("G01 Y1.375 F")));
+
+        let (w, h, _) = footprint("profile outside rect at x 0 y 0 width 2 height 1 rotate 90 depth 0.1 lead 0");
+        assert!((w - 1.25).abs() < 1e-3 && (h - 2.25).abs() < 1e-3, "{} x {}", w, h);
+    }
+
+    #[test]
+    fn test_tool_life_adds_up_across_operations() {
+        let run = |rpm: u32| {
+            let source = format!(
+                "units imperial\nsetup {{\n    zero left front top\n    material \"Steel 1018\"\n}}\ntool 1 dia 0.5 length 3.0 flutes 4 hss\nspindle cw rpm {}\npocket rect at x 0 y 0 width 4 height 4 depth 0.5 feed 5\npocket rect at x 0 y 5 width 4 height 4 depth 0.5 feed 5",
+                rpm
+            );
+            let program = parse(&source);
+            let mut gen = CodeGenerator::new();
+            let output = gen.generate(&program);
+            // (estimated life, minutes cut so far) under each pocket
+            let notes: Vec<(f64, f64)> = output
+                .lines()
+                .filter_map(|l| l.strip_prefix("; Tool life: ~"))
+                .map(|l| {
+                    let (life, rest) = l.split_once(" min estimated, T1 has cut ").unwrap();
+                    (life.parse().unwrap(), rest.trim_end_matches(" min").parse().unwrap())
+                })
+                .collect();
+            let exceeded = gen.issues().iter().any(|i| i.code == "TOOL_LIFE_EXCEEDED");
+            (notes, exceeded)
+        };
+
+        // Both pockets cut about as long, and the second counts the first
+        let (notes, exceeded) = run(1000);
+        assert_eq!(notes.len(), 2);
+        assert!(notes[0].1 > 5.0);
+        assert!((notes[1].1 - 2.0 * notes[0].1).abs() < 0.2, "{:?}", notes);
+        assert!(notes[1].1 < notes[1].0);
+        assert!(!exceeded);
+
+        // Faster wears the HSS out before the second pocket is done
+        let (notes, exceeded) = run(1100);
+        assert!(notes[0].1 < notes[0].0 && notes[1].1 > notes[1].0, "{:?}", notes);
+        assert!(exceeded);
+    }
+
+    #[test]
+    fn test_safe_retract_follows_units() {
+        let retracts = |source: &str| {
+            let program = parse(source);
+            let output = CodeGenerator::new().generate_output(&program);
+            output
+                .lines()
+                .filter(|l| l.contains("G00 Z"))
+                .filter_map(|l| crate::post::extract_word(l, 'Z'))
+                .filter(|&z| z > 0.5)
+                .collect::<Vec<_>>()
+        };
+
+        // Pocket, profile and footer each retract to the safe Z
+        let imperial = retracts("units imperial\ntool 1 dia 0.25 length 3.0\nspindle cw rpm 5000\npocket rect at x 0 y 0 width 1 height 1 depth 0.1\nprofile outside rect at x 0 y 0 width 2 height 1 depth 0.1");
+        assert_eq!(imperial, vec![1.0, 1.0, 1.0]);
+
+        let metric = retracts("units metric\ntool 1 dia 6 length 50\nspindle cw rpm 5000\npocket rect at x 0 y 0 width 25 height 25 depth 2\nprofile outside rect at x 0 y 0 width 50 height 25 depth 2");
+        assert_eq!(metric, vec![25.0, 25.0, 25.0]);
+
+        // Drill and tap cycles come down to an R plane in the program's units
+        let planes = |source: &str| {
+            let program = parse(source);
+            let output = CodeGenerator::new().generate_output(&program);
+            output
+                .lines()
+                .filter_map(|l| crate::post::extract_word(block_code(l), 'R'))
+                .collect::<Vec<_>>()
+        };
+        let holes = "tool 1 dia 0.25 length 3.0\nspindle cw rpm 3000\n";
+        let imperial = planes(&format!(
+            "units imperial\n{}drill at x 1 y 1 depth 0.25 feed 10\ndrill 0.25 at 2 1 depth 0.25\ntap 1/4-20 at x 3 y 1 depth 0.4",
+            holes
+        ));
+        assert_eq!(imperial, vec![0.2, 0.1, 0.2]);
+        let metric = planes(&format!(
+            "units metric\n{}drill at x 10 y 10 depth 5 feed 100\ndrill 6 at 20 10 depth 5\ntap M6 at x 30 y 10 depth 10",
+            holes
+        ));
+        assert_eq!(metric, vec![5.0, 2.5, 5.0]);
+
+        // Material left standing above Z0 raises the safe Z with it
+        let raised = retracts("units imperial\ntool 1 dia 0.25 length 3.0\nspindle cw rpm 5000\ncut Y+ 1.0 0.125 0.5 Z+\npocket rect at x 0 y 0 width 1 height 1 depth 0.1");
+        assert_eq!(raised[raised.len() - 2..], [1.5, 1.5]);
+        assert!(!raised.contains(&1.0));
+    }
+
+    #[test]
+    fn test_outside_chamfer_rides_cone_off_the_edge() {
+        let compile = |op: &str| {
+            let source = format!("units imperial\ntool 1 dia 0.5 length 3.0 flutes 4 carbide\n{}", op);
+            let program = parse(&source);
+            CodeGenerator::new().generate_output(&program)
+        };
+
+        // A 90 degree tool 0.04 deep is 0.04 wide at the top face, so its axis
+        // sits 0.01 off the 2x1.5 block for a 0.03 chamfer
+        let output = compile("chamfer outside rect 2.0 1.5 at 1.0 0.75 depth 0.04 width 0.03");
+        let lines: Vec<&str> = output.lines().collect();
+        let start = lines.iter().position(|l| l.ends_with("G00 X-0.0100 Y-0.0100")).expect("start");
+        assert!(lines[start + 2].contains("G01 Z-0.0400"));
+        assert!(lines[start + 3].contains("G01 X2.0100"));
+        assert!(lines[start + 4].ends_with("G01 Y1.5100"));
+
+        // Without a depth the tip rides the foot of the chamfer, right on the edge
+        let output = compile("chamfer outside 0.03 rect 2.0 1.5 at 1.0 0.75");
+        assert!(output.lines().any(|l| l.ends_with("G00 X0.0000 Y0.0000")));
+        assert!(output.lines().any(|l| l.contains("G01 Z-0.0300")));
+        let output = compile("chamfer inside 0.03 circle 1.0 at 2.0 2.0 angle 60");
+        assert!(output.lines().any(|l| l.contains("G01 Z-0.0520")));
+        assert!(output.lines().any(|l| l.contains("G03 X2.5000 Y2.0000 I-0.5000")));
+    }
+
+    #[test]
+    fn test_drill_thru_uses_stock_thickness() {
+        let mut gen = CodeGenerator::new();
+        gen.stock = Some(StockDef {
+            material: "Aluminum 6061-T6".to_string(),
+            size_x: 3.0,
+            size_y: 2.0,
+            size_z: 0.75,
+        });
+
+        gen.emit_drill_v2(&DrillV2Op {
+            diameter: 0.25,
+            position: Position::new(1.0, 1.0),
+            depth: DrillDepth::Thru,
+            spot: false,
+            peck: None,
+            dwell: None,
+        });
+
+        // 0.75 stock + 0.3 x 0.25 tip allowance
+        let output = gen.output.to_string();
+        assert!(output.contains("Thru depth: 0.8250"));
+        assert!(output.contains("Z-0.8250"));
+    }
+
+    #[test]
+    fn test_metric_program_matches_imperial_rpm() {
+        fn black_book_rpm(source: &str) -> String {
+            let program = parse(source);
+            let output = CodeGenerator::new().generate(&program);
+            output
+                .lines()
+                .find_map(|l| l.split("Black Book: RPM=").nth(1))
+                .and_then(|rest| rest.split(',').next())
+                .expect("pocket should report Black Book RPM")
+                .to_string()
+        }
+
+        let imperial = black_book_rpm(
+            "units imperial\nsetup {\n    material \"Aluminum 6061-T6\"\n}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 0.5 0.5 0.1 at 1.0 1.0",
+        );
+        let metric = black_book_rpm(
+            "units metric\nsetup {\n    material \"Aluminum 6061-T6\"\n}\ntool 1 dia 6.35 length 25.4 flutes 3 carbide\npocket rect 12.7 12.7 2.54 at 25.4 25.4",
+        );
+
+        assert_eq!(imperial, metric);
+        assert_ne!(imperial, "8000"); // Not the fallback default
+    }
+
+    #[test]
+    fn test_metric_comments_name_metric_units() {
+        let compile = |source: &str| {
+            let program = parse(source);
+            CodeGenerator::new().generate(&program)
+        };
+
+        let metric = compile("units metric\nsetup {\n    material \"Aluminum 6061-T6\"\n}\ntool 1 dia 6.35 length 25.4 flutes 3 carbide\npocket rect 25.4 19.05 6.35 at 25.4 25.4 finish 0.5\ndrill 6.35 at 50 10 depth 5");
+        let comments: Vec<&str> = metric.lines().filter(|l| l.starts_with(';')).collect();
+        assert!(comments.contains(&"; Finish allowance: 0.5000 mm"));
+        let pocket = comments.iter().find(|c| c.contains("Black Book:") && c.contains("Stepover")).unwrap();
+        assert!(pocket.contains(" mm/min, Stepdown=") && pocket.ends_with(" mm"), "{}", pocket);
+        let drill = comments.iter().find(|c| c.contains("Peck=")).unwrap();
+        assert!(drill.contains(" mm/min") && drill.ends_with(" mm"), "{}", drill);
+        assert!(comments.iter().all(|c| !c.contains("IPM") && !c.contains('"')));
+
+        let mut gen = CodeGenerator::new();
+        gen.units = Units::Metric;
+        gen.black_book.set_units(Units::Metric);
+        gen.current_material = Some("Aluminum 6061-T6".to_string());
+        gen.current_tool_data = Some(ToolData {
+            diameter: 6.35,
+            length: 25.4,
+            flutes: 3,
+            material: crate::ast::ToolMaterial::Carbide,
+            center_cutting: false,
+        });
+        gen.emit_cutting_parameters_summary();
+        let summary = gen.output.to_string();
+        let line = |label: &str| summary.lines().find(|l| l.contains(label)).unwrap().to_string();
+        assert!(line("Feed Rate:").ends_with(" mm/min"));
+        assert!(line("Surface Speed:").ends_with(" m/min"));
+        assert!(line("Chip Load:").ends_with(" mm/tooth"));
+
+        // Imperial programs keep their inch labels
+        let imperial = compile("units imperial\nsetup {\n    material \"Aluminum 6061-T6\"\n}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 1.0 0.75 0.25 at 1.0 1.0");
+        assert!(imperial.contains(" IPM, Stepdown=0.250\", Stepover=0.100\""));
+    }
+
+    #[test]
+    fn test_pocket_ramps_instead_of_plunging() {
+        use crate::post::extract_word;
+
+        fn compile(source: &str) -> String {
+            let program = parse(source);
+            CodeGenerator::new().generate(&program)
+        }
+
+        // Largest Z drop below the stock top made by a G01 with no X or Y word
+        fn max_straight_plunge(output: &str) -> f64 {
+            let mut z: f64 = 0.1;
+            let mut worst: f64 = 0.0;
+            for line in output.lines().filter(|l| !l.starts_with(';')) {
+                if let Some(new_z) = extract_word(line, 'Z') {
+                    let lateral = extract_word(line, 'X').is_some() || extract_word(line, 'Y').is_some();
+                    if line.contains("G01") && !lateral {
+                        worst = worst.max(z.min(0.0) - new_z);
+                    }
+                    z = new_z;
+                }
+            }
+            worst
+        }
+
+        fn stepdown(output: &str) -> f64 {
+            output
+                .lines()
+                .find_map(|l| l.split("(DOC=").nth(1))
+                .and_then(|rest| rest.split('"').next())
+                .and_then(|doc| doc.parse().ok())
+                .expect("pocket should report its DOC")
+        }
+
+        for pocket in ["pocket rect 1.0 0.75 0.5 at 1.0 1.0", "pocket circle 1.0 0.5 at 1.0 1.0"] {
+            let source = format!(
+                "units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\n{}",
+                pocket
+            );
+            let output = compile(&source);
+            let doc = stepdown(&output);
+            assert!(
+                max_straight_plunge(&output) <= doc + 1e-4,
+                "{} plunged more than one stepdown:\n{}",
+                pocket,
+                output
+            );
+            // Entry never plunges into uncut stock
+            assert!(max_straight_plunge(&output) < 1e-4, "{}", output);
+        }
+
+        let circle = compile("units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket circle 1.0 0.5 at 1.0 1.0");
+        assert!(circle.contains("Helical entry"));
+        assert!(circle.lines().any(|l| l.contains("G03") && l.contains("Z-")));
+
+        let rect = compile("units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 1.0 0.75 0.5 at 1.0 1.0");
+        assert!(rect.contains("Ramp entry at 3.0 deg"));
+
+        // Center-cutting tools keep the straight plunge
+        let plunged = compile("units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide center-cutting\npocket rect 1.0 0.75 0.5 at 1.0 1.0");
+        assert!(!plunged.contains("Ramp entry"));
+        assert!(max_straight_plunge(&plunged) > 0.0);
+    }
+
+    #[test]
+    fn test_circular_pocket_uses_arcs() {
+        let program = parse(
+            "units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide center-cutting\npocket circle 1.0 0.1 at 1.0 1.0",
+        );
+
+        let output = CodeGenerator::new().generate(&program);
+        let arcs = output.lines().filter(|l| l.contains("G03")).count();
+        assert!(arcs > 0, "expected G03 arcs:\n{}", output);
+        // Finish pass is one full circle back to its own start
+        assert!(output.contains("G03 X1.3750 Y1.0000 I-0.3750 J0.0000"));
+        // No chord soup: a handful of cutting moves, not 36 per lap
+        let feeds = output.lines().filter(|l| l.contains("G01")).count();
+        assert!(feeds < 10, "{}", output);
+
+        let linear = CodeGenerator::new().with_linear_arcs().generate(&program);
+        assert!(!linear.lines().any(|l| l.contains("G02") || l.contains("G03")));
+        assert!(linear.lines().filter(|l| l.contains("G01")).count() > arcs * 2);
+        // Chords land back on the arc end points
+        assert!(linear.contains("G01 X1.3750 Y1.0000 F"));
+    }
+
+    #[test]
+    fn test_drill_pattern_calls_one_subprogram() {
+        let source = "units imperial\ntool 1 dia 0.25 length 2.0 flutes 2 hss\nspindle cw rpm 3000\n\
+            drill 0.25 pattern grid rows 4 cols 5 spacing 0.5 0.5 at 1.0 1.0 depth 0.25";
+        let program = parse(source);
+
+        let cycles = |output: &str| output.lines().filter(|l| l.contains(" G81 ")).count();
+        let inline = CodeGenerator::new().generate(&program);
+        assert_eq!(cycles(&inline), 20);
+        assert!(!inline.contains("M98"));
+
+        let output = CodeGenerator::new().with_subprograms().generate(&program);
+        assert_eq!(output.lines().filter(|l| l.starts_with('O')).count(), 1);
+        assert_eq!(cycles(&output), 1);
+        assert_eq!(output.matches("M98 P1000").count(), 20);
+        assert!(output.contains(" G90 G81 R0.1 Z-0.2500"));
+
+        // The body comes after the main program ends, and returns to it in G91
+        let lines: Vec<&str> = output.lines().collect();
+        let end = lines.iter().position(|l| l.ends_with("M30")).unwrap();
+        assert_eq!(lines[end + 1], "O1000");
+        assert!(lines[end + 4].ends_with("G91"));
+        assert!(lines[end + 5].ends_with("M99"));
+
+        // Each call is a G91 step from the hole before
+        let first = lines.iter().position(|l| l.ends_with("M98 P1000")).unwrap();
+        assert!(lines[first - 1].ends_with("G00 X1.0000 Y1.0000"));
+        assert!(lines[first + 1].ends_with("G00 X0.5000 Y0.0000"));
+    }
+
+    #[test]
+    fn test_incremental_deltas_sum_to_absolute_positions() {
+        use crate::post::extract_word;
+
+        let absolute: GCodeOutput = [
+            "N0010 G90 G17",
+            "N0020 T1 M06",
+            "N0030 G00 X1.0000 Y2.0000",
+            "N0040 G00 Z0.1000",
+            "N0050 G01 Z-0.2500 F10.0",
+            "N0060 G01 X3.5000 Y2.0000 F20.0",
+            "N0070 G02 X4.0000 Y2.5000 I0.0000 J0.5000",
+            "N0080 G00 Z0.1000",
+            "N0090 G00 X5.0000 Y1.0000",
+            "N0100 G81 R0.1 Z-0.5000 F8.0",
+            "N0110 X6.0000",
+            "N0120 G80",
+            "N0130 G00 X0.0000 Y0.0000 Z1.0000",
+            "N0140 M30",
+        ]
+        .into_iter()
+        .collect();
+        let incremental = absolute.to_incremental();
+
+        let lines: Vec<&str> = incremental.lines().collect();
+        assert_eq!(lines[4], "N0050 G91");
+        assert_eq!(lines[5], "N0060 G01 Z-0.3500 F10.0");
+        assert_eq!(lines[6], "N0070 G01 X2.5000 F20.0");
+        assert_eq!(lines[7], "N0080 G02 X0.5000 Y0.5000 I0.0000 J0.5000");
+        assert_eq!(lines[10], "N0110 G90");
+        assert_eq!(lines[11], "N0120 G81 X5.0000 Y1.0000 R0.1 Z-0.5000 F8.0");
+        assert_eq!(lines[14], "N0150 G91");
+        assert_eq!(lines[15], "N0160 G00 X-6.0000 Y-1.0000 Z0.9000");
+        assert_eq!(lines[16], "N0170 G90");
+        assert_eq!(lines[17], "N0180 M30");
+
+        // Adding up the deltas lands on every absolute position
+        let positions = |output: &GCodeOutput| {
+            let mut relative = false;
+            let mut at = [0.0; 3];
+            let mut visited = Vec::new();
+            for line in output.lines() {
+                let code = line.split_once(' ').unwrap().1;
+                if code == "G90" || code == "G91" {
+                    relative = code == "G91";
+                    continue;
+                }
+                for (i, axis) in ['X', 'Y', 'Z'].into_iter().enumerate() {
+                    // A cycle's Z is its hole depth, not where it leaves the tool
+                    if i == 2 && code.starts_with("G81") {
+                        continue;
+                    }
+                    if let Some(v) = extract_word(code, axis) {
+                        at[i] = if relative { at[i] + v } else { v };
+                    }
+                }
+                visited.push(at.map(|v| (v * 10000.0_f64).round() as i64));
+            }
+            visited
+        };
+        assert_eq!(positions(&incremental), positions(&absolute));
+    }
+
+    #[test]
+    fn test_unknown_material_warns() {
+        let mut gen = CodeGenerator::new();
+        gen.emit_setup(&SetupBlock {
+            material: Some("Unobtainium".to_string()),
+            ..Default::default()
+        });
+        gen.emit_setup(&SetupBlock {
+            material: Some("6061".to_string()),
+            ..Default::default()
+        });
+
+        let output = gen.output.to_string();
+        assert_eq!(output.matches("WARNING: Unknown material: Unobtainium").count(), 1);
+        assert_eq!(output.matches("WARNING").count(), 1);
+    }
+
+    #[test]
+    fn test_profile_cutter_comp() {
+        let mut gen = CodeGenerator::new();
+        gen.emit_tool_change(&ToolChange {
+            tool_id: None,
+            tool_number: 2,
+            tool_data: Some(ToolData {
+                diameter: 10.0,
+                length: 50.0,
+                flutes: 3,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
+            }),
+        });
+        let profile = ProfileOp {
+            geometry: Geometry::Rect(Rectangle {
+                bottom_left: Position::new(0.0, 0.0),
+                width: 100.0,
+                height: 80.0,
+                corner_radius: None,
+                rotation: 0.0,
+            }),
+            depth: 3.0,
+            side: CutSide::Outside,
+            stock_to_leave: 0.0,
+            feed_rate: 600.0,
+            plunge_feed: 300.0,
+            cutter_comp: true,
+            lead_radius: None,
+        };
+        gen.emit_profile(&profile);
+        gen.emit_profile(&ProfileOp {
+            geometry: Geometry::Circle(Circle {
+                center: Position::new(50.0, 40.0),
+                diameter: 30.0,
+            }),
+            side: CutSide::Inside,
+            ..profile.clone()
+        });
+
+        let output = gen.output.to_string();
+        let lines: Vec<&str> = output.lines().collect();
+
+        // Outside rect: lead in from below the bottom edge, comp right, nominal contour
+        let engage = lines
+            .iter()
+            .position(|l| l.contains("G01 G42 D2 X50.000 Y0.000"))
+            .expect("G42 engage move");
+        assert!(lines[engage - 2].contains("G00 X50.000 Y-10.000"));
+        assert!(lines[engage + 1].contains("G01 X100.000"));
+        assert!(output.contains("G01 G40 X50.000 Y-10.000"));
+
+        // Inside circle: lead in from toward the centre, comp left
+        assert!(output.contains("G00 X55.000 Y40.000"));
+        assert!(output.contains("G01 G41 D2 X65.000 Y40.000"));
+        assert!(output.contains("G01 G40 X55.000 Y40.000"));
+    }
+
+    #[test]
+    fn test_climb_milling_passes_cut_one_way() {
+        let source = "stock 4 x 3 x 0.75 \"6061-T6\"\nsetup {\n    climb\n}\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 2.0 1.5 0.25 at 2.0 1.0\nprofile outside rectangle at x 0 y 0 width 2 height 1.5 depth 0.1 comp";
+        let program = parse(source);
+        let output = CodeGenerator::new().generate(&program);
+
+        let word = |line: &str, letter: char| {
+            line.split_whitespace()
+                .find(|w| w.starts_with(letter))
+                .and_then(|w| w[1..].parse::<f64>().ok())
+        };
+        let (mut x, mut z) = (0.0, 0.0);
+        let mut passes = 0;
+        for line in output.lines().filter(|l| l.starts_with('N')) {
+            let (nx, nz) = (word(line, 'X'), word(line, 'Z'));
+            // Pure X feeds on the pocket floor are the engaged raster passes
+            if line.contains("G01") && nz.is_none() && word(line, 'Y').is_none() && z < -0.2 {
+                if let Some(nx) = nx {
+                    assert!(nx < x, "pass cut +X: {}", line);
+                    passes += 1;
+                }
+            }
+            x = nx.unwrap_or(x);
+            z = nz.unwrap_or(z);
+        }
+        assert!(passes > 1);
+
+        // Climbing an outside profile runs clockwise with the tool on the left
+        assert!(output.contains("G01 G41"));
+        assert!(!output.contains("G42"));
+    }
+
+    #[test]
+    fn test_pass_lift_follows_units() {
+        let source = "units metric\nsetup {\n    climb\n}\ntool 1 dia 6 length 60 flutes 3 carbide\npocket rect 40 30 5 at 50 25";
+        let output = CodeGenerator::new().generate(&parse(source));
+
+        // Each return rapid clears the 3 mm and 5 mm floors by half a millimetre
+        let lifts: Vec<&str> = output.lines().filter(|l| l.contains("G00 Z-")).collect();
+        assert!(lifts.len() > 2);
+        assert!(lifts
+            .iter()
+            .all(|l| l.ends_with("G00 Z-2.5000") || l.ends_with("G00 Z-4.5000")));
+    }
+
+    #[test]
+    fn test_profile_arc_lead_in() {
+        let mut gen = CodeGenerator::new();
+        gen.emit_tool_change(&ToolChange {
+            tool_id: None,
+            tool_number: 1,
+            tool_data: Some(ToolData {
+                diameter: 12.0,
+                length: 50.0,
+                flutes: 3,
+                material: crate::ast::ToolMaterial::Carbide,
+                center_cutting: false,
+            }),
+        });
+        gen.emit_profile(&ProfileOp {
+            geometry: Geometry::Rect(Rectangle {
+                bottom_left: Position::new(0.0, 0.0),
+                width: 100.0,
+                height: 80.0,
+                corner_radius: None,
+                rotation: 0.0,
+            }),
+            depth: 3.0,
+            side: CutSide::Outside,
+            stock_to_leave: 0.0,
+            feed_rate: 600.0,
+            plunge_feed: 300.0,
+            cutter_comp: false,
+            lead_radius: None,
+        });
+
+        let output = gen.output.to_string();
+        let lines: Vec<&str> = output.lines().collect();
+        let plunge = lines.iter().position(|l| l.contains("G01 Z-3.000")).unwrap();
+
+        // Entry from outside the part, clockwise quarter arc onto the bottom edge midpoint
+        assert!(lines[plunge - 1].contains("G00 X44.000 Y-12.000"));
+        assert!(lines[plunge + 1].contains("G02 X50.000 Y-6.000 I6.000 J0.000"));
+        assert!(lines[plunge + 2].contains("G01 X106.000"));
+
+        // Matching exit arc after returning to the entry point
+        assert!(output.contains("G02 X56.000 Y-12.000 I0.000 J-6.000"));
     }
 }
@@ -0,0 +1,284 @@
+//! Dry run - follows the emitted G-code block by block and reports where the tool
+//! actually goes: the Z range, the XY footprint, rapid and feed distance, and any
+//! block that takes it below a fixture's Z floor. Validation checks the program
+//! before code is generated; this checks the code itself.
+
+use super::{arc_sweep, block_code, canned_cycle_travel, GCodeOutput, CANNED_CYCLES};
+use crate::post::extract_word;
+
+/// Slack for rounding in the G-code before a block counts as below the floor
+const TOLERANCE: f64 = 1e-6;
+
+/// A block that takes the tool below the Z floor
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloorViolation {
+    /// The block as written
+    pub block: String,
+    /// Lowest Z it reaches
+    pub z: f64,
+}
+
+/// Where a program takes the tool, in its own units
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimReport {
+    /// Lowest Z reached, counting the bottom of every canned-cycle hole
+    pub min_z: Option<f64>,
+    /// Highest Z reached
+    pub max_z: Option<f64>,
+    /// Lower-left and upper-right XY corners of the travel, arcs included
+    pub xy_bounds: Option<((f64, f64), (f64, f64))>,
+    /// Distance covered at rapid, cycle retracts included
+    pub rapid_distance: f64,
+    /// Distance covered at feed
+    pub feed_distance: f64,
+    /// Blocks reaching below the Z floor, in program order
+    pub below_floor: Vec<FloorViolation>,
+}
+
+impl SimReport {
+    /// Widen the ranges to a point, where its axes are known
+    fn reach(&mut self, x: Option<f64>, y: Option<f64>, z: Option<f64>) {
+        if let Some(z) = z {
+            self.min_z = Some(self.min_z.map_or(z, |m| m.min(z)));
+            self.max_z = Some(self.max_z.map_or(z, |m| m.max(z)));
+        }
+        if let (Some(x), Some(y)) = (x, y) {
+            self.xy_bounds = Some(match self.xy_bounds {
+                Some(((x0, y0), (x1, y1))) => ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y))),
+                None => ((x, y), (x, y)),
+            });
+        }
+    }
+
+    /// Note `block` if it reaches `z` below the floor
+    fn check_floor(&mut self, floor: Option<f64>, block: &str, z: f64) {
+        if floor.is_some_and(|floor| z < floor - TOLERANCE) {
+            self.below_floor.push(FloorViolation {
+                block: block.to_string(),
+                z,
+            });
+        }
+    }
+
+    /// One hole of a canned cycle under the tool, returning to where it started
+    fn drill(&mut self, at: [Option<f64>; 3], cycle: &str, block: &str, floor: Option<f64>) {
+        let r = extract_word(cycle, 'R');
+        let Some(initial) = at[2].or(r) else {
+            return;
+        };
+        let bottom = extract_word(cycle, 'Z').unwrap_or(initial);
+        let (rapid, fed) = canned_cycle_travel(cycle, initial);
+        self.rapid_distance += rapid;
+        self.feed_distance += fed;
+        self.reach(at[0], at[1], Some(bottom));
+        self.reach(at[0], at[1], Some(initial.max(r.unwrap_or(initial))));
+        self.check_floor(floor, block, bottom);
+    }
+}
+
+/// Run `output` dry and report where the tool goes. The machine starts at its home,
+/// and Z returns there at each tool change, so an axis counts once a block sets it.
+/// Canned cycles return to the plane they started from, and each `M98` call runs
+/// its subprogram's cycle where the tool is. Blocks reaching below `z_floor`, when
+/// given, are listed.
+pub fn simulate(output: &GCodeOutput, z_floor: Option<f64>) -> SimReport {
+    let mut report = SimReport::default();
+    let mut at: [Option<f64>; 3] = [None; 3];
+    let mut motion = "G00";
+    let mut cycle: Option<&str> = None;
+    let mut relative = false;
+
+    let subprograms = output.subprogram_cycles();
+    for line in output.lines() {
+        if line.starts_with(';') || line.starts_with('(') {
+            continue;
+        }
+        if line.starts_with('O') {
+            break;
+        }
+        let code = block_code(line);
+        let words: Vec<&str> = code.split_whitespace().collect();
+        let has = |w: &str| words.contains(&w);
+
+        if has("M06") || has("M6") {
+            at[2] = None;
+            cycle = None;
+        }
+        if has("G04") {
+            continue;
+        }
+        if has("M98") {
+            let number = extract_word(code, 'P');
+            if let Some(&(_, sub_cycle, incremental)) = subprograms.iter().find(|s| Some(s.0) == number) {
+                if let Some(sub_cycle) = sub_cycle {
+                    report.drill(at, sub_cycle, line, z_floor);
+                }
+                relative = incremental;
+            }
+            continue;
+        }
+        relative = (relative || has("G91")) && !has("G90");
+
+        if let Some(m) = ["G00", "G01", "G02", "G03"].into_iter().find(|m| has(m)) {
+            motion = m;
+            cycle = None;
+        }
+        if has("G80") {
+            cycle = None;
+        }
+
+        let target = |letter: char, from: Option<f64>| match extract_word(code, letter) {
+            Some(v) if relative => from.map(|from| from + v),
+            Some(v) => Some(v),
+            None => from,
+        };
+        let end = [target('X', at[0]), target('Y', at[1]), target('Z', at[2])];
+
+        let starts_cycle = words.iter().any(|w| CANNED_CYCLES.contains(w));
+        if starts_cycle {
+            cycle = Some(code);
+        }
+        if let Some(cycle) = cycle {
+            // Each new XY in a modal cycle is a rapid over and another hole
+            if starts_cycle || end[0] != at[0] || end[1] != at[1] {
+                report.rapid_distance += travel(&at[..2], &end[..2]);
+                at[0] = end[0];
+                at[1] = end[1];
+                report.drill(at, cycle, line, z_floor);
+            }
+            continue;
+        }
+
+        let arc = match (motion, at[0], at[1], end[0], end[1]) {
+            ("G02" | "G03", Some(x), Some(y), Some(end_x), Some(end_y))
+                if extract_word(code, 'I').is_some() || extract_word(code, 'J').is_some() =>
+            {
+                let center = (
+                    x + extract_word(code, 'I').unwrap_or(0.0),
+                    y + extract_word(code, 'J').unwrap_or(0.0),
+                );
+                Some(((x, y), (end_x, end_y), center))
+            }
+            _ => None,
+        };
+        let length = match arc {
+            Some((start, end_xy, center)) => {
+                let clockwise = motion == "G02";
+                let radius = (start.0 - center.0).hypot(start.1 - center.1);
+                let sweep = arc_sweep(start, end_xy, center, clockwise);
+                // The arc bulges out wherever it crosses an axis through its center
+                let start_angle = (start.1 - center.1).atan2(start.0 - center.0);
+                for quadrant in 0..4 {
+                    let angle = quadrant as f64 * std::f64::consts::FRAC_PI_2;
+                    let turned = if clockwise { start_angle - angle } else { angle - start_angle };
+                    if turned.rem_euclid(std::f64::consts::TAU) <= sweep {
+                        let x = center.0 + radius * angle.cos();
+                        let y = center.1 + radius * angle.sin();
+                        report.reach(Some(x), Some(y), None);
+                    }
+                }
+                let rise = match (at[2], end[2]) {
+                    (Some(from), Some(to)) => to - from,
+                    _ => 0.0,
+                };
+                (radius * sweep).hypot(rise)
+            }
+            None => travel(&at, &end),
+        };
+        if motion == "G00" {
+            report.rapid_distance += length;
+        } else {
+            report.feed_distance += length;
+        }
+
+        if end != at {
+            report.reach(end[0], end[1], end[2]);
+            if let Some(z) = end[2] {
+                report.check_floor(z_floor, line, z);
+            }
+        }
+        at = end;
+    }
+
+    report
+}
+
+/// Straight-line distance over the axes known at both ends
+fn travel(from: &[Option<f64>], to: &[Option<f64>]) -> f64 {
+    from.iter()
+        .zip(to)
+        .filter_map(|(a, b)| Some((b.as_ref()? - a.as_ref()?).powi(2)))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::CodeGenerator;
+
+    #[test]
+    fn test_min_z_matches_deepest_commanded_move() {
+        let source = "units imperial\nsetup {\n    material \"6061-T6\"\n}\n\
+            tool 1 dia 0.25 length 2.0 flutes 3 carbide\n\
+            pocket rect 1.0 0.75 0.375 at 1.0 1.0\n\
+            drill 0.201 at 3.0 1.0 depth 0.6";
+        let program = crate::parser::Parser::new(crate::lexer::lex(source))
+            .parse()
+            .expect("should parse");
+        let output = CodeGenerator::new().generate_output(&program);
+
+        let deepest = output
+            .lines()
+            .filter_map(|l| extract_word(block_code(l), 'Z'))
+            .fold(f64::INFINITY, f64::min);
+        let report = simulate(&output, None);
+        assert_eq!(report.min_z, Some(deepest));
+        assert!(report.max_z.unwrap() > 0.0);
+        assert!(report.feed_distance > 0.0 && report.rapid_distance > 0.0);
+        assert!(report.below_floor.is_empty());
+
+        // A fixture floor above the deepest cut flags the blocks under it
+        let report = simulate(&output, Some(deepest + 0.01));
+        assert!(!report.below_floor.is_empty());
+        assert!(report.below_floor.iter().all(|v| v.z < deepest + 0.01));
+    }
+
+    #[test]
+    fn test_bounds_distances_and_floor() {
+        let output: GCodeOutput = [
+            "N0010 G90 G17",
+            "N0020 T1 M06",
+            "N0030 G00 X0.000 Y0.000",
+            "N0040 G00 Z1.000",
+            "N0050 G01 Z-0.250 F10.0",
+            "; half circle bulging to Y-1",
+            "N0060 G03 X2.000 Y0.000 I1.000 J0.000",
+            "N0070 G00 Z1.000",
+            "N0080 G81 X3.000 Y0.000 Z-0.500 R0.100 F8.0",
+            "N0090 G80",
+            "N0100 M30",
+        ]
+        .into_iter()
+        .collect();
+
+        let report = simulate(&output, Some(-0.3));
+        assert_eq!(report.min_z, Some(-0.5));
+        assert_eq!(report.max_z, Some(1.0));
+        let ((x0, y0), (x1, y1)) = report.xy_bounds.unwrap();
+        assert_eq!((x0, x1), (0.0, 3.0));
+        assert!((y0 + 1.0).abs() < 1e-9 && y1.abs() < 1e-9);
+
+        // Feed: the plunge, the half circle, and the hole from R down
+        let feed = 1.25 + std::f64::consts::PI + 0.6;
+        assert!((report.feed_distance - feed).abs() < 1e-9);
+        // Rapid: the climb out, one over to the hole, and the cycle in and out
+        let rapid = 1.25 + 1.0 + 0.9 + 1.5;
+        assert!((report.rapid_distance - rapid).abs() < 1e-9);
+
+        // Only the hole goes under -0.3
+        assert_eq!(report.below_floor.len(), 1);
+        assert!(report.below_floor[0].block.contains("G81"));
+        assert_eq!(report.below_floor[0].z, -0.5);
+    }
+}
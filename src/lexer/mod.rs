@@ -13,20 +13,15 @@ pub enum Token {
     #[regex(r"-?\d+\.?\d*", |lex| lex.slice().parse::<f64>().ok())]
     Number(Option<f64>),
 
-    // Fractions like 5/8, 1/4
-    #[regex(r"\d+/\d+", |lex| {
-        let parts: Vec<&str> = lex.slice().split('/').collect();
-        if parts.len() == 2 {
-            if let (Ok(num), Ok(den)) = (parts[0].parse::<f64>(), parts[1].parse::<f64>()) {
-                if den != 0.0 {
-                    return Some(num / den);
-                }
-            }
-        }
-        None
-    })]
+    // Fractions like 5/8, 1/4, 1.5/8 and mixed numbers like 1-1/2.
+    // A zero denominator lexes as `Fraction(None)` so the parser can point at it.
+    #[regex(r"\d+-\d+/\d+|\d+(\.\d+)?/\d+", |lex| fraction(lex.slice()))]
     Fraction(Option<f64>),
 
+    // Thread sizes like 1/4-20, #10-32, M6x1.0
+    #[regex(r"(\d+/\d+|#?\d+)-\d+|M\d+(\.\d+)?[xX]\d+(\.\d+)?", |lex| lex.slice().to_string())]
+    Thread(String),
+
     #[regex(r#""[^"]*""#, |lex| lex.slice()[1..lex.slice().len()-1].to_string())]
     String(String),
 
@@ -46,6 +41,12 @@ pub enum Token {
     #[token("offset")]
     Offset,
 
+    #[token("return")]
+    Return,
+
+    #[token("end")]
+    End,
+
     // Keywords - Tools
     #[token("tool")]
     Tool,
@@ -63,9 +64,28 @@ pub enum Token {
     #[token("hss")]
     HSS,
 
+    #[token("center-cutting")]
+    CenterCutting,
+
     #[token("carbide")]
     Carbide,
 
+    #[token("cobalt")]
+    Cobalt,
+
+    #[token("ceramic")]
+    Ceramic,
+
+    #[token("coated")]
+    Coated,
+
+    #[token("cbn")]
+    CBN,
+
+    #[token("diamond")]
+    #[token("pcd")]
+    Diamond,
+
     // Keywords - Spindle
     #[token("spindle")]
     Spindle,
@@ -89,6 +109,9 @@ pub enum Token {
     #[token("mist")]
     Mist,
 
+    #[token("through")]
+    Through,
+
     #[token("off")]
     Off,
 
@@ -137,6 +160,18 @@ pub enum Token {
     #[token("radius")]
     Radius,
 
+    #[token("polygon")]
+    Polygon,
+
+    #[token("sides")]
+    Sides,
+
+    #[token("path")]
+    Path,
+
+    #[token("->")]
+    Arrow,
+
     #[token("width")]
     Width,
 
@@ -149,12 +184,21 @@ pub enum Token {
     #[token("thru")]
     Thru,
 
+    #[token("spot")]
+    Spot,
+
     #[token("corner")]
     Corner,
 
     #[token("corners")]
     Corners,
 
+    #[token("dogbone")]
+    Dogbone,
+
+    #[token("tbone")]
+    TBone,
+
     #[token("center")]
     Center,
 
@@ -190,6 +234,15 @@ pub enum Token {
     #[token("finish")]
     Finish,
 
+    #[token("adaptive")]
+    Adaptive,
+
+    #[token("comp")]
+    Comp,
+
+    #[token("lead")]
+    Lead,
+
     #[token("dwell")]
     Dwell,
 
@@ -271,6 +324,31 @@ pub enum Token {
     #[token("y-limit")]
     YLimit,
 
+    #[token("rigid-tap")]
+    RigidTap,
+
+    // Header safety limits
+    #[token("max-rpm")]
+    MaxRpm,
+
+    #[token("max-feed")]
+    MaxFeed,
+
+    #[token("no-length-comp")]
+    NoLengthComp,
+
+    #[token("group-tools")]
+    GroupTools,
+
+    #[token("keep-pattern-order")]
+    KeepPatternOrder,
+
+    #[token("climb")]
+    Climb,
+
+    #[token("conventional")]
+    Conventional,
+
     // Operators
     #[token("+")]
     Plus,
@@ -342,10 +420,13 @@ pub enum Token {
     #[token("\n")]
     Newline,
 
+    // Machinist notes - `;` comments are kept so they reach the G-code output
+    #[regex(r";[^\n]*", |lex| lex.slice()[1..].trim().to_string())]
+    LineComment(String),
+
     // Comments
     #[regex(r"//[^\n]*", logos::skip)]
     #[regex(r"/\*[^*]*\*+(?:[^/*][^*]*\*+)*/", logos::skip)]
-    #[regex(r";[^\n]*", logos::skip)]
     Comment,
 }
 
@@ -360,6 +441,20 @@ impl std::fmt::Display for LexerError {
 
 impl std::error::Error for LexerError {}
 
+/// Value of `num/den` or `whole-num/den`, `None` when the denominator is zero
+fn fraction(text: &str) -> Option<f64> {
+    let (whole, ratio) = match text.split_once('-') {
+        Some((whole, ratio)) => (whole.parse::<f64>().ok()?, ratio),
+        None => (0.0, text),
+    };
+    let (num, den) = ratio.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    if den == 0.0 {
+        return None;
+    }
+    Some(whole + num / den)
+}
+
 /// Lex the input string into tokens
 pub fn lex(input: &str) -> Vec<(Token, logos::Span)> {
     Token::lexer(input)
@@ -413,6 +508,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tool_materials() {
+        let input = "hss carbide cobalt ceramic coated cbn diamond pcd";
+        let tokens: Vec<_> = lex(input).into_iter().map(|(t, _)| t).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::HSS,
+                Token::Carbide,
+                Token::Cobalt,
+                Token::Ceramic,
+                Token::Coated,
+                Token::CBN,
+                Token::Diamond,
+                Token::Diamond,
+            ]
+        );
+    }
+
     #[test]
     fn test_units() {
         let input = "units imperial";
@@ -421,4 +536,37 @@ mod tests {
         println!("Tokens: {:?}", tokens);
         assert_eq!(tokens, vec![Token::Units, Token::Imperial,]);
     }
+
+    #[test]
+    fn test_line_comments_kept() {
+        let input = "; flip part here\ndrill at x 1 y 2 depth 3 ; note\n\n// skipped";
+        let tokens: Vec<_> = lex(input).into_iter().map(|(t, _)| t).collect();
+
+        assert_eq!(tokens[0], Token::LineComment("flip part here".to_string()));
+        assert_eq!(tokens[1], Token::Newline);
+        assert_eq!(tokens[10], Token::LineComment("note".to_string()));
+        assert_eq!(tokens.len(), 11);
+    }
+
+    #[test]
+    fn test_fractions() {
+        let value = |input: &str| match lex(input).as_slice() {
+            [(Token::Fraction(value), span)] => {
+                assert_eq!(*span, 0..input.len(), "{}", input);
+                *value
+            }
+            tokens => panic!("{} lexed as {:?}", input, tokens),
+        };
+
+        assert_eq!(value("5/8"), Some(0.625));
+        assert_eq!(value("1-1/2"), Some(1.5));
+        assert_eq!(value("2-3/8"), Some(2.375));
+        assert_eq!(value("1.5/8"), Some(0.1875));
+        assert_eq!(value("3/0"), None);
+        assert_eq!(value("1-3/0"), None);
+
+        // Thread sizes still win where the dash comes after the fraction
+        assert_eq!(lex("1/4-20")[0].0, Token::Thread("1/4-20".to_string()));
+        assert_eq!(lex("10-32")[0].0, Token::Thread("10-32".to_string()));
+    }
 }
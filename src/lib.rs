@@ -0,0 +1,493 @@
+//! swarf - machinist-friendly DSL to G-code compiler
+//! Library API so the compiler can be embedded in other tools, services, or WASM builds
+
+#![allow(dead_code)]
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod ast;
+pub mod black_book;
+pub mod codegen;
+pub mod lexer;
+pub mod parser;
+pub mod post;
+pub mod threads;
+pub mod tool_library;
+pub mod validator;
+
+#[cfg(feature = "viz")]
+pub mod viz;
+
+use black_book::validators::Severity;
+use black_book::{BlackBook, MachineProfile};
+use post::PostProcessorType;
+use thiserror::Error;
+use tool_library::ToolLibrary;
+
+#[derive(Error, Debug)]
+pub enum CompileError {
+    #[error("parse error: {0}")]
+    Parse(#[from] parser::ParseError),
+
+    #[error("validation failed with {} error(s)", .0.len())]
+    Validation(Vec<validator::ValidationError>),
+}
+
+/// Settings for `compile_source` beyond the source and post; the default compiles
+/// with the built-in Black Book and no limits
+#[derive(Default)]
+pub struct CompileOptions {
+    /// Spindle RPM cap; feeds scale down with it
+    pub max_rpm: Option<f64>,
+    /// Tools to resolve `tool` references against
+    pub tool_library: Option<ToolLibrary>,
+    /// Material database in place of the built-in one
+    pub black_book: Option<BlackBook>,
+    /// Machine to check spindle RPM, feed and horsepower against
+    pub machine: Option<MachineProfile>,
+    /// Replace every G02/G03 with G01 chords, for controls without arcs
+    pub linear_arcs: bool,
+    /// Write moves as G91 deltas
+    pub incremental: bool,
+    /// Machine rapid rate in IPM for the cycle-time estimate (400 when unset)
+    pub rapid_rate: Option<f64>,
+    /// Percent of the cutter diameter each face pass overlaps the last; 10 by default
+    pub face_overlap: Option<f64>,
+    /// Note the UTC time of generation in the prologue (off so output is reproducible)
+    pub timestamp: bool,
+    /// Degrees a pocket entry ramp or helix descends at; unset keeps a gentle 3
+    pub ramp_angle: Option<f64>,
+    /// `O` number for the generic post to start the program with
+    pub program_number: Option<u32>,
+}
+
+/// Compile swarf source to post-processed G-code
+pub fn compile_str(source: &str, post: PostProcessorType) -> Result<String, CompileError> {
+    compile_source(source, post, CompileOptions::default())
+}
+
+/// Compile swarf source to post-processed G-code with the given options
+pub fn compile_source(
+    source: &str,
+    post_type: PostProcessorType,
+    options: CompileOptions,
+) -> Result<String, CompileError> {
+    let CompileOptions {
+        max_rpm,
+        tool_library,
+        black_book,
+        machine,
+        linear_arcs,
+        incremental,
+        rapid_rate,
+        face_overlap,
+        timestamp,
+        ramp_angle,
+        program_number,
+    } = options;
+
+    // Lex
+    let tokens = lexer::lex(source);
+
+    // Parse
+    let mut parser = parser::Parser::new(tokens).with_source(source);
+    let program = parser.parse()?;
+
+    // Resolve tool references from library
+    let program = if let Some(ref lib) = tool_library {
+        resolve_tools(program, lib)
+    } else {
+        program
+    };
+
+    // Validate
+    let validator = validator::Validator::new();
+    validator
+        .validate_program(&program)
+        .map_err(CompileError::Validation)?;
+
+    // Generate G-code
+    let mut codegen = if let Some(rpm) = max_rpm {
+        codegen::CodeGenerator::new().with_max_rpm(rpm)
+    } else {
+        codegen::CodeGenerator::new()
+    };
+
+    // Pass tool library to codegen for auto-feeds/speeds
+    if let Some(lib) = tool_library {
+        codegen = codegen.with_tool_library(lib);
+    }
+
+    if let Some(book) = black_book {
+        codegen = codegen.with_black_book(book);
+    }
+
+    if let Some(machine) = machine {
+        codegen = codegen.with_machine(machine);
+    }
+
+    if linear_arcs {
+        codegen = codegen.with_linear_arcs();
+    }
+
+    if incremental {
+        codegen = codegen.with_incremental();
+    }
+
+    if let Some(ipm) = rapid_rate {
+        codegen = codegen.with_rapid_rate(ipm);
+    }
+
+    if let Some(pct) = face_overlap {
+        codegen = codegen.with_face_overlap(pct);
+    }
+
+    if timestamp {
+        codegen = codegen.with_timestamp();
+    }
+
+    if let Some(degrees) = ramp_angle {
+        codegen = codegen.with_ramp_angle(degrees);
+    }
+
+    let processor: Box<dyn post::PostProcessor> = match (post_type, program_number) {
+        (PostProcessorType::Generic, Some(number)) => {
+            Box::new(post::GenericPost::new().with_program_number(number))
+        }
+        _ => post_type.get_processor(),
+    };
+    if processor.supports_subroutines() {
+        codegen = codegen.with_subprograms();
+        if let Some(number) = processor.program_number() {
+            codegen = codegen.with_program_number(number);
+        }
+    }
+
+    let gcode_output = codegen.generate_output(&program);
+    validator
+        .validate_rapids(&gcode_output)
+        .map_err(|e| CompileError::Validation(vec![e]))?;
+
+    // Feeds and speeds findings are in the G-code as comments; warnings are worth
+    // a look before running it, and errors stop it from being written
+    let mut unsafe_params = Vec::new();
+    for issue in codegen.issues() {
+        match issue.severity {
+            Severity::Error => unsafe_params.push(validator::ValidationError::CuttingParameters {
+                code: issue.code.clone(),
+                message: issue.message.clone(),
+            }),
+            Severity::Warning => eprintln!("Warning: {}: {}", issue.code, issue.message),
+            Severity::Info => {}
+        }
+    }
+    if !unsafe_params.is_empty() {
+        return Err(CompileError::Validation(unsafe_params));
+    }
+
+    // Apply post-processor
+    Ok(processor.render(&gcode_output).into_string())
+}
+
+/// Resolve tool references by looking up in tool library
+fn resolve_tools(mut program: ast::Program, library: &ToolLibrary) -> ast::Program {
+    for op in &mut program.operations {
+        if let ast::Operation::ToolChange(ref mut tc) = op {
+            // Check if this is just a reference (no tool data) or has minimal data
+            let needs_lookup = tc.tool_data.is_none() || {
+                // If tool has no diameter, it needs lookup
+                tc.tool_data.as_ref().map(|d| d.diameter == 0.0).unwrap_or(true)
+            };
+
+            if needs_lookup {
+                // String ID or name if present, else the numeric ID
+                let tool_def = library.lookup(tc.tool_id.as_deref(), tc.tool_number);
+
+                if let Some(tool_def) = tool_def {
+                    // Update the tool number from the library tool's numeric ID
+                    tc.tool_number = tool_def.numeric_id();
+                    tc.tool_data = Some(ast::ToolData {
+                        diameter: tool_def.diameter,
+                        length: tool_def.length.unwrap_or(0.0),
+                        flutes: tool_def.flutes,
+                        material: tool_def.material.to_ast_material(),
+                        center_cutting: tool_def.is_center_cutting(),
+                    });
+                } else {
+                    let tool_ref_num = tc.tool_number.to_string();
+                    let tool_ref = tc.tool_id.as_deref()
+                        .unwrap_or(&tool_ref_num);
+                    eprintln!(
+                        "Warning: Tool '{}' not found in tool library",
+                        tool_ref
+                    );
+                }
+            }
+        }
+    }
+    program
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile_with(source: &str, options: CompileOptions) -> String {
+        compile_source(source, PostProcessorType::Generic, options).expect("compile failed")
+    }
+
+    #[test]
+    fn test_drill_program() {
+        let source = r#"
+units metric
+offset 54
+
+tool 1 dia 6 length 50
+spindle cw rpm 2500
+
+drill at x 10 y 20 depth 5 peck 2 feed 100
+"#;
+
+        let tokens = lexer::lex(source);
+        let mut parser = parser::Parser::new(tokens);
+        let program = parser.parse().expect("parse failed");
+
+        let validator = validator::Validator::new();
+        validator
+            .validate_program(&program)
+            .expect("validation failed");
+
+        let mut codegen = codegen::CodeGenerator::new();
+        let gcode = codegen.generate(&program);
+
+        assert!(gcode.contains("G83")); // Peck drill cycle
+        assert!(gcode.contains("M30")); // Program end
+    }
+
+    #[test]
+    fn test_imperial_units() {
+        let source = r#"
+units imperial
+offset 54
+
+tool 1 dia 0.125 length 1.0
+spindle cw rpm 5000
+
+drill at x 0.5 y 0.5 depth 0.25
+"#;
+
+        let tokens = lexer::lex(source);
+        let mut parser = parser::Parser::new(tokens);
+        let program = parser.parse().expect("parse failed");
+
+        let validator = validator::Validator::new();
+        validator
+            .validate_program(&program)
+            .expect("validation failed");
+
+        let mut codegen = codegen::CodeGenerator::new();
+        let gcode = codegen.generate(&program);
+
+        println!("Generated G-code:\n{}", gcode);
+        assert!(gcode.contains("G20")); // Imperial units
+        assert!(gcode.contains("M30")); // Program end
+    }
+
+    #[test]
+    fn test_compile_str() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 2 hss\ndrill 0.25 at 1.0 0.5 depth 0.25";
+
+        let gcode = compile_str(source, PostProcessorType::Haas).expect("compile failed");
+        assert!(gcode.contains("G20"));
+        assert!(gcode.contains("T1 M06"));
+        assert!(gcode.trim_end().ends_with('%')); // Haas tape end
+
+        let err = compile_str("spindle cw rpm 50000", PostProcessorType::Generic)
+            .expect_err("over-speed spindle should fail validation");
+        assert!(matches!(err, CompileError::Validation(ref errors) if errors.len() == 1));
+    }
+
+    #[test]
+    fn test_rapid_rate_option() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 2 hss\nspindle cw rpm 3000\n\
+            drill 0.25 pattern line count 5 spacing 4.0 X+ at 0.5 0.5 depth 0.25";
+        let cycle_time = |rapid_rate: Option<f64>| {
+            let gcode = compile_with(source, CompileOptions { rapid_rate, ..Default::default() });
+            gcode.lines().find(|l| l.contains("ESTIMATED CYCLE TIME:")).unwrap().to_string()
+        };
+
+        // 16" of rapids between holes take longer on a slower machine
+        assert_eq!(cycle_time(None), cycle_time(Some(400.0)));
+        assert_ne!(cycle_time(None), cycle_time(Some(20.0)));
+    }
+
+    #[test]
+    fn test_face_overlap_option() {
+        let source = "units imperial\nstock 4 x 3 x 0.5 \"6061-T6\"\n\
+            tool 1 dia 1.0 length 2.0 flutes 4 carbide\nface at stock depth 0.02";
+        let facing = |face_overlap: Option<f64>| {
+            let gcode = compile_with(source, CompileOptions { face_overlap, ..Default::default() });
+            gcode.lines().find(|l| l.contains("Facing:")).unwrap().to_string()
+        };
+
+        assert!(facing(None).ends_with("Facing: 4 passes, stepover: 0.900 (10% overlap)"));
+        assert!(facing(Some(50.0)).ends_with("Facing: 7 passes, stepover: 0.500 (50% overlap)"));
+    }
+
+    #[test]
+    fn test_timestamp_option() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 2 hss\ndrill 0.25 at 1.0 0.5 depth 0.25";
+        let generated = |timestamp: bool| {
+            let gcode = compile_with(source, CompileOptions { timestamp, ..Default::default() });
+            gcode.lines().find(|l| l.contains("GENERATED BY:")).unwrap().to_string()
+        };
+
+        assert!(!generated(false).contains(" on "));
+        assert!(generated(true).ends_with(" UTC"), "{}", generated(true));
+    }
+
+    #[test]
+    fn test_ramp_angle_option() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 1.0 0.75 0.25 at 1.0 1.0";
+        let ramp = |ramp_angle: Option<f64>| {
+            let gcode = compile_with(source, CompileOptions { ramp_angle, ..Default::default() });
+            let lines: Vec<String> = gcode.lines().map(str::to_string).collect();
+            let start = lines.iter().position(|l| l.contains("Ramp entry at")).expect("ramp entry");
+            let legs = lines[start + 1..]
+                .iter()
+                .take_while(|l| l.contains("G01 X") && l.contains(" Z-"))
+                .count();
+            (lines[start].clone(), legs)
+        };
+
+        let (comment, gentle) = ramp(None);
+        assert!(comment.contains("Ramp entry at 3.0 deg,"));
+        let (comment, steep) = ramp(Some(10.0));
+        assert!(comment.contains("Ramp entry at 10.0 deg,"));
+        assert!(steep < gentle);
+    }
+
+    #[test]
+    fn test_program_number_option() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 2 hss\ndrill 0.25 at 1.0 0.5 depth 0.25";
+        let options = CompileOptions { program_number: Some(1234), ..Default::default() };
+        let gcode = compile_with(source, options);
+        assert!(gcode.starts_with("O1234\n"));
+
+        // The prologue's tool list is the only one
+        assert_eq!(gcode.matches("T1 - 0.25 DIA").count(), 1);
+        assert!(!compile_str(source, PostProcessorType::Generic).unwrap().contains("O1234"));
+    }
+
+    #[test]
+    fn test_subprograms_follow_post_support() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 2 hss\nspindle cw rpm 3000\n\
+            drill 0.25 pattern line count 5 spacing 0.75 X+ at 0.5 0.5 depth 0.25";
+
+        let haas = compile_str(source, PostProcessorType::Haas).expect("compile failed");
+        assert_eq!(haas.matches("M98 P1000").count(), 5);
+
+        // GRBL has no subprograms, so every hole is drilled in line
+        let grbl = compile_str(source, PostProcessorType::Grbl).expect("compile failed");
+        assert!(!grbl.contains("M98"));
+        assert!(!grbl.lines().any(|l| l.starts_with('O')));
+
+        // LinuxCNC's main program is O1000, so the subprogram moves up one
+        let linuxcnc = compile_str(source, PostProcessorType::LinuxCNC).expect("compile failed");
+        assert_eq!(linuxcnc.matches("M98 P1001").count(), 5);
+        assert_eq!(linuxcnc.lines().filter(|l| l.starts_with("O1000")).count(), 1);
+        assert_eq!(linuxcnc.lines().filter(|l| *l == "O1001").count(), 1);
+
+        let options = CompileOptions { program_number: Some(1000), ..Default::default() };
+        let generic = compile_with(source, options);
+        assert_eq!(generic.matches("M98 P1001").count(), 5);
+        assert_eq!(generic.lines().filter(|l| *l == "O1000").count(), 1);
+    }
+
+    #[test]
+    fn test_tool_length_comp() {
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 2 hss\ndrill 0.25 at 1.0 0.5 depth 0.25";
+
+        for post in [PostProcessorType::Generic, PostProcessorType::Haas, PostProcessorType::LinuxCNC] {
+            let gcode = compile_str(source, post).expect("compile failed");
+            let lines: Vec<&str> = gcode.lines().collect();
+            let change = lines.iter().position(|l| l.ends_with("T1 M06")).unwrap();
+            assert!(lines[change + 1].ends_with("G43 H1"), "{:?}", post);
+            assert!(lines[change..].iter().any(|l| l.ends_with("G49")));
+        }
+
+        let gcode = compile_str(
+            &format!("setup {{\n    no-length-comp\n}}\n{}", source),
+            PostProcessorType::Generic,
+        )
+        .expect("compile failed");
+        assert!(!gcode.contains("G43"));
+    }
+
+    #[test]
+    fn test_library_tool_by_id() {
+        let source = "units imperial\nsetup {\n    material \"6061-T6\"\n}\ntool \"EM_250_4FL\"\npocket rect 2.0 1.5 0.25 at 2.0 1.0";
+        let compile = |library: ToolLibrary| {
+            compile_with(source, CompileOptions { tool_library: Some(library), ..Default::default() })
+        };
+        let rpm = |gcode: &str| -> f64 {
+            let line = gcode.lines().find(|l| l.contains("Black Book: RPM=")).expect("feeds");
+            line.split("RPM=").nth(1).and_then(|r| r.split(',').next()).unwrap().parse().unwrap()
+        };
+
+        // Geometry comes from the library and its max_rpm caps the spindle
+        let mut library = tool_library::default_tool_library();
+        let gcode = compile(library.clone());
+        assert!(gcode.contains("DIA=0.25 LEN=0 FLUTES=4 MAT=Carbide"));
+        assert!(gcode.contains("S18000 M03"));
+
+        // Uncapped, the TiAlN coating runs faster than a bare tool - too fast for a
+        // 1/4" end mill, so it no longer compiles
+        let generate = |library: ToolLibrary| {
+            let program = parser::Parser::new(lexer::lex(source)).parse().unwrap();
+            let program = resolve_tools(program, &library);
+            codegen::CodeGenerator::new().with_tool_library(library).generate(&program)
+        };
+        library.tools.get_mut("EM_250_4FL").unwrap().max_rpm = None;
+        let coated = rpm(&generate(library.clone()));
+        let options = CompileOptions {
+            tool_library: Some(library.clone()),
+            ..Default::default()
+        };
+        let err = compile_source(source, PostProcessorType::Generic, options).unwrap_err();
+        assert!(err.to_string().starts_with("validation failed"));
+        assert!(matches!(
+            &err,
+            CompileError::Validation(errors) if errors[0].to_string().contains("RPM_TOO_HIGH")
+        ));
+        library.tools.get_mut("EM_250_4FL").unwrap().coating = None;
+        assert!(coated > rpm(&generate(library)));
+    }
+
+    #[test]
+    fn test_black_book_issues_reach_the_output() {
+        let source = |max_rpm: u32| {
+            format!(
+                "units imperial\nmax-rpm {}\nsetup {{\n    material \"Stainless 304\"\n}}\ntool 1 dia 0.25 length 1.0 flutes 4 carbide\ncoolant flood\npocket rect 2.0 1.5 0.25 at 2.0 1.0\npocket rect 1.0 1.0 0.25 at 4.0 1.0",
+                max_rpm
+            )
+        };
+
+        // Slowed to 600 RPM, the clamped feed dawdles and work-hardens the 304
+        let program = parser::Parser::new(lexer::lex(&source(600))).parse().unwrap();
+        let mut codegen = codegen::CodeGenerator::new();
+        let gcode = codegen.generate(&program);
+        let warnings: Vec<&str> = gcode.lines().filter(|l| l.contains("WORK_HARDENING_RISK")).collect();
+        assert_eq!(warnings, ["; ERROR WORK_HARDENING_RISK: Low feed rate may cause work hardening in austenitic stainless"]);
+        assert!(codegen.issues().iter().any(|i| i.code == "WORK_HARDENING_RISK"));
+
+        // ... which stops the compile
+        let err = compile_str(&source(600), PostProcessorType::Generic).unwrap_err();
+        let CompileError::Validation(errors) = err else {
+            panic!("expected validation errors");
+        };
+        assert!(errors.iter().any(|e| e.to_string().contains("WORK_HARDENING_RISK")), "{:?}", errors);
+
+        // At its Black Book speed the same program compiles
+        compile_str(&source(20000), PostProcessorType::Generic).expect("compile failed");
+    }
+}
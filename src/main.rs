@@ -1,25 +1,16 @@
 #![allow(dead_code)]
-#![allow(clippy::upper_case_acronyms)]
 
-mod ast;
-pub mod black_book;
-mod codegen;
-mod lexer;
-mod parser;
-pub mod post;
-mod tool_library;
-mod validator;
+use std::fs;
 
-#[cfg(feature = "viz")]
-mod viz;
+use swarf::{black_book, post, tool_library, CompileError};
 
-use std::fs;
+#[cfg(feature = "viz")]
+use swarf::viz;
 
 #[derive(Debug)]
 enum Error {
     Io(std::io::Error),
-    Parse(parser::ParseError),
-    Validation(Vec<validator::ValidationError>),
+    Compile(CompileError),
 }
 
 impl From<std::io::Error> for Error {
@@ -28,9 +19,9 @@ impl From<std::io::Error> for Error {
     }
 }
 
-impl From<parser::ParseError> for Error {
-    fn from(e: parser::ParseError) -> Self {
-        Error::Parse(e)
+impl From<CompileError> for Error {
+    fn from(e: CompileError) -> Self {
+        Error::Compile(e)
     }
 }
 
@@ -49,10 +40,12 @@ fn main() {
             #[cfg(feature = "viz")]
             {
                 if args.len() < 3 {
-                    eprintln!("Usage: swarf --viz [--2d] [--png <output.png>] <path>");
+                    eprintln!("Usage: swarf --viz [--2d] [--png <output.png>] [--host <ip>] [--port <n>] <path>");
                     eprintln!("  <path>        G-code file (.nc), swarf file (.swarf), or folder");
                     eprintln!("  --2d          Use 2D canvas view (default is 3D if available)");
                     eprintln!("  --png <file>  Export to PNG file instead of starting server");
+                    eprintln!("  --host <ip>   Address to bind (default 127.0.0.1, 0.0.0.0 for the network)");
+                    eprintln!("  --port <n>    Port to serve on (default 3030)");
                     eprintln!();
                     eprintln!("Examples:");
                     eprintln!("  swarf --viz output.nc           # View G-code");
@@ -64,29 +57,46 @@ fn main() {
                 // Check for --2d flag
                 let use_2d = args.iter().any(|a| a == "--2d");
 
+                // Value following a flag, e.g. `--png out.png`
+                let flag_value = |flag: &str| {
+                    args.iter()
+                        .position(|a| a == flag)
+                        .and_then(|i| args.get(i + 1))
+                };
+
                 // Check for --png flag
-                let png_output = args
-                    .iter()
-                    .position(|a| a == "--png")
-                    .and_then(|i| args.get(i + 1));
+                let png_output = flag_value("--png");
+
+                // Bind address: --host and --port override 127.0.0.1:3030
+                let (default_ip, default_port) = viz::DEFAULT_ADDR;
+                let host = match flag_value("--host") {
+                    Some(h) => h.parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --host requires an IP address (e.g., 0.0.0.0)");
+                        std::process::exit(1);
+                    }),
+                    None => std::net::IpAddr::from(default_ip),
+                };
+                let port = match flag_value("--port") {
+                    Some(p) => p.parse().unwrap_or_else(|_| {
+                        eprintln!("Error: --port requires a port number (e.g., 3031)");
+                        std::process::exit(1);
+                    }),
+                    None => default_port,
+                };
 
                 // Find the file argument (first non-flag argument after command)
                 let file_arg = args
                     .iter()
+                    .enumerate()
                     .skip(2)
-                    .find(|a| {
+                    .find(|(i, a)| {
                         if a.starts_with("--") {
                             return false;
                         }
-                        // Skip the value after --png
-                        if let Some(png_idx) = args.iter().position(|x| x == "--png") {
-                            if args.get(png_idx + 1) == Some(a) {
-                                return false;
-                            }
-                        }
-                        true
+                        // Skip the values after --png, --host and --port
+                        !matches!(args[i - 1].as_str(), "--png" | "--host" | "--port")
                     })
-                    .cloned();
+                    .map(|(_, a)| a.clone());
 
                 let file_arg = match file_arg {
                     Some(f) => f,
@@ -105,7 +115,8 @@ fn main() {
                 } else {
                     // Start viz server
                     let rt = tokio::runtime::Runtime::new().unwrap();
-                    rt.block_on(viz::runviz(file_arg, use_2d));
+                    let addr = std::net::SocketAddr::new(host, port);
+                    rt.block_on(viz::runviz(file_arg, use_2d, addr));
                 }
             }
             #[cfg(not(feature = "viz"))]
@@ -114,6 +125,70 @@ fn main() {
                 std::process::exit(1);
             }
         }
+        "export" => {
+            #[cfg(feature = "viz")]
+            {
+                let output = args
+                    .iter()
+                    .position(|a| a == "-o")
+                    .and_then(|i| args.get(i + 1));
+                let input = args
+                    .iter()
+                    .enumerate()
+                    .skip(2)
+                    .find(|(i, a)| !a.starts_with('-') && args[i - 1] != "-o")
+                    .map(|(_, a)| a);
+
+                let (input, output) = match (input, output) {
+                    (Some(input), Some(output)) => (input, output),
+                    _ => {
+                        eprintln!("Usage: swarf export <file.nc> -o <out.svg|out.dxf|out.png>");
+                        std::process::exit(1);
+                    }
+                };
+
+                let extension = std::path::Path::new(output)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_ascii_lowercase());
+                let result = match extension.as_deref() {
+                    Some("svg") => viz::export_to_svg(input, output),
+                    Some("dxf") => viz::export_to_dxf(input, output),
+                    Some("png") => viz::export_to_png(input, output, 800, 600),
+                    _ => {
+                        eprintln!("Error: export format must be .svg, .dxf or .png");
+                        std::process::exit(1);
+                    }
+                };
+                if let Err(e) = result {
+                    eprintln!("Error exporting {}: {}", output, e);
+                    std::process::exit(1);
+                }
+            }
+            #[cfg(not(feature = "viz"))]
+            {
+                eprintln!("viz feature not enabled. Build with: cargo build --features viz");
+                std::process::exit(1);
+            }
+        }
+        "feeds" => {
+            run_feeds(&args[2..]);
+        }
+        "materials" => {
+            run_materials(&args[2..]);
+        }
+        "dump" => {
+            #[cfg(feature = "ast-serde")]
+            run_dump(&args[2..]);
+            #[cfg(not(feature = "ast-serde"))]
+            {
+                eprintln!("ast-serde feature not enabled. Build with: cargo build --features ast-serde");
+                std::process::exit(1);
+            }
+        }
+        "simulate" => {
+            run_simulate(&args[2..]);
+        }
         "--help" | "-h" | "help" => {
             print_usage();
         }
@@ -123,14 +198,20 @@ fn main() {
             println!("  mach3     - Mach3/Mach4 (expands canned cycles)");
             println!("  linuxcnc  - LinuxCNC");
             println!("  haas      - Haas");
+            println!("  grbl      - GRBL (expands canned cycles, no tool changer)");
+            println!("  tormach   - Tormach PathPilot (G43 H after tool changes)");
+            println!("  okuma     - Okuma OSP (G15 H work offsets, G56 length comp)");
+            println!("  heidenhain - Heidenhain TNC conversational (moves and drilling)");
         }
         _ => {
             // Parse options
             let mut post_type = post::PostProcessorType::Generic;
             let mut input_path = None;
             let mut output_path = "output.nc";
-            let mut max_rpm: Option<f64> = None;
+            let mut options = swarf::CompileOptions::default();
             let mut tools_path: Option<String> = None;
+            let mut materials_path: Option<String> = None;
+            let mut machine_path: Option<String> = None;
 
             let mut i = 1;
             while i < args.len() {
@@ -141,27 +222,101 @@ fn main() {
                                 "mach3" => post::PostProcessorType::Mach3,
                                 "linuxcnc" => post::PostProcessorType::LinuxCNC,
                                 "haas" => post::PostProcessorType::Haas,
+                                "grbl" => post::PostProcessorType::Grbl,
+                                "tormach" | "pathpilot" => post::PostProcessorType::Tormach,
+                                "okuma" | "osp" => post::PostProcessorType::Okuma,
+                                "heidenhain" | "tnc" => post::PostProcessorType::Heidenhain,
                                 _ => post::PostProcessorType::Generic,
                             };
                             i += 2;
                         } else {
-                            eprintln!("Error: --post requires an argument (mach3, linuxcnc, haas)");
+                            eprintln!("Error: --post requires an argument (mach3, linuxcnc, haas, grbl, tormach, okuma, heidenhain)");
                             std::process::exit(1);
                         }
                     }
-                    "--tools" => {
+                    "--tools" | "--tool-library" => {
                         if i + 1 < args.len() {
                             tools_path = Some(args[i + 1].clone());
                             i += 2;
                         } else {
-                            eprintln!("Error: --tools requires a path to tools.json");
+                            eprintln!("Error: {} requires a path to tools.json", args[i]);
+                            std::process::exit(1);
+                        }
+                    }
+                    "--materials" => {
+                        if i + 1 < args.len() {
+                            materials_path = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --materials requires a path to materials.json");
                             std::process::exit(1);
                         }
                     }
+                    "--machine" => {
+                        if i + 1 < args.len() {
+                            machine_path = Some(args[i + 1].clone());
+                            i += 2;
+                        } else {
+                            eprintln!("Error: --machine requires a path to machine.json");
+                            std::process::exit(1);
+                        }
+                    }
+                    "--linear-arcs" => {
+                        options.linear_arcs = true;
+                        i += 1;
+                    }
+                    "--timestamp" => {
+                        options.timestamp = true;
+                        i += 1;
+                    }
+                    "--incremental" => {
+                        options.incremental = true;
+                        i += 1;
+                    }
+                    "--rapid-rate" => {
+                        match args.get(i + 1).and_then(|v| v.parse().ok()) {
+                            Some(ipm) => options.rapid_rate = Some(ipm),
+                            None => {
+                                eprintln!("Error: --rapid-rate requires the machine's rapid rate in IPM (e.g., 600)");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
+                    }
+                    "--ramp-angle" => {
+                        match args.get(i + 1).and_then(|v| v.parse().ok()) {
+                            Some(degrees) => options.ramp_angle = Some(degrees),
+                            None => {
+                                eprintln!("Error: --ramp-angle requires an angle in degrees (e.g., 5)");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
+                    }
+                    "--program-number" => {
+                        match args.get(i + 1).and_then(|v| v.parse().ok()) {
+                            Some(number) => options.program_number = Some(number),
+                            None => {
+                                eprintln!("Error: --program-number requires an O number (e.g., 1234)");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
+                    }
+                    "--face-overlap" => {
+                        match args.get(i + 1).and_then(|v| v.parse().ok()) {
+                            Some(pct) => options.face_overlap = Some(pct),
+                            None => {
+                                eprintln!("Error: --face-overlap requires a percent of the cutter diameter (e.g., 25)");
+                                std::process::exit(1);
+                            }
+                        }
+                        i += 2;
+                    }
                     "--max-rpm" => {
                         if i + 1 < args.len() {
-                            max_rpm = args[i + 1].parse().ok();
-                            if max_rpm.is_none() {
+                            options.max_rpm = args[i + 1].parse().ok();
+                            if options.max_rpm.is_none() {
                                 eprintln!("Error: --max-rpm requires a valid number");
                                 std::process::exit(1);
                             }
@@ -201,7 +356,7 @@ fn main() {
             });
 
             // Load tool library if specified
-            let tool_library = if let Some(path) = tools_path {
+            options.tool_library = if let Some(path) = tools_path {
                 match tool_library::ToolLibrary::from_file(&path) {
                     Ok(lib) => {
                         println!("Loaded {} tools from {}", lib.tools.len(), path);
@@ -216,7 +371,40 @@ fn main() {
                 None
             };
 
-            if let Err(e) = compile_with_post_and_tools(input_path, output_path, post_type, max_rpm, tool_library) {
+            // Load shop materials over the built-in Black Book if specified
+            options.black_book = if let Some(path) = materials_path {
+                match black_book::BlackBook::from_file(&path) {
+                    Ok(book) => {
+                        println!("Loaded materials from {}", path);
+                        Some(book)
+                    }
+                    Err(e) => {
+                        // Falling back to the built-in book would give the wrong feeds
+                        eprintln!("Error: Failed to load materials file: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                None
+            };
+
+            // Load the machine profile to check spindle load against if specified
+            options.machine = if let Some(path) = machine_path {
+                match black_book::MachineProfile::from_file(&path) {
+                    Ok(machine) => {
+                        println!("Loaded machine profile from {}", path);
+                        Some(machine)
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: Failed to load machine profile: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            if let Err(e) = compile_with_post_and_tools(input_path, output_path, post_type, options) {
                 eprintln!("Error: {:?}", e);
                 std::process::exit(1);
             }
@@ -231,8 +419,22 @@ fn print_usage() {
     println!("  swarf <input.swarf> [output.nc]        Compile swarf to G-code");
     println!("  swarf <input.swarf> --post <type>      Use post-processor");
     println!("  swarf <input.swarf> --max-rpm <rpm>    Limit spindle RPM (scales feed)");
-    println!("  swarf --tools <file> <input.swarf>     Use tool library JSON");
+    println!("  swarf <input.swarf> --rapid-rate <ipm> Machine rapid rate for the cycle time (default 400)");
+    println!("  swarf <input.swarf> --face-overlap <%> Overlap between face passes (default 10% of the cutter)");
+    println!("  swarf <input.swarf> --ramp-angle <deg> Pocket entry ramp and helix angle (default 3)");
+    println!("  swarf <input.swarf> --program-number <n> Start the generic post's program with O<n>");
+    println!("  swarf --tool-library <file> <input.swarf> Use tool library JSON (alias --tools)");
+    println!("  swarf --materials <file> <input.swarf> Merge shop materials JSON into the Black Book");
+    println!("  swarf --machine <file> <input.swarf>   Check spindle RPM, feed and HP against a machine JSON");
+    println!("  swarf <input.swarf> --linear-arcs      Replace G02/G03 arcs with G01 segments");
+    println!("  swarf <input.swarf> --incremental      Write moves as G91 incremental deltas");
+    println!("  swarf <input.swarf> --timestamp        Note the generation time (UTC) in the header");
     println!("  swarf --viz <path>                     Start visualizer on http://localhost:3030");
+    println!("  swarf --viz --port <n> --host <ip> <path>  Serve the visualizer elsewhere");
+    println!("  swarf feeds --material <name> --dia <d> Look up feeds and speeds in the Black Book");
+    println!("  swarf materials [--category <name>]    List Black Book materials");
+    println!("  swarf dump <input.swarf>               Print the parsed program as JSON (ast-serde feature)");
+    println!("  swarf simulate <file> [--z-floor <z>]  Dry-run .swarf or G-code: Z range, XY extent, travel");
     println!("  swarf --list-posts                     List available post-processors");
     println!("  swarf --help                           Show this help");
     println!();
@@ -241,15 +443,27 @@ fn print_usage() {
     println!("  mach3     - Mach3/Mach4 (expands canned cycles)");
     println!("  linuxcnc  - LinuxCNC");
     println!("  haas      - Haas");
+    println!("  grbl      - GRBL");
+    println!("  tormach   - Tormach PathPilot");
+    println!("  okuma     - Okuma OSP");
+    println!("  heidenhain - Heidenhain TNC conversational (moves and drilling)");
     println!();
     println!("Tool Library:");
     println!("  swarf --tools tools.json part.swarf    Reference tools by ID or name");
     println!("  In swarf: tool 1  or  tool \"3/8 EM\"");
     println!();
+    println!("Feeds & Speeds:");
+    println!("  swarf feeds --material \"Steel 4140\" --dia 0.375 --flutes 4 --tool carbide --woc 20");
+    println!("  Options: --flutes <n> (default 4), --tool <hss|cobalt|carbide|coated|ceramic|cbn|diamond>");
+    println!("           --woc <% of dia> (default 40), --doc <depth> (default 1x dia)");
+    println!("           --units <imperial|metric>, --materials <file>, --ball (ball end mill)");
+    println!("           --coating <TiAlN|TiN|AlTiN|...>, --corner-radius <r>");
+    println!();
     println!("Visualizer:");
     println!("  swarf --viz output.nc                  View G-code file");
     println!("  swarf --viz part.swarf                 View swarf file (live reload)");
     println!("  swarf --viz examples/                  Browse folder of .swarf files");
+    println!("  swarf export output.nc -o part.svg     Export toolpath to SVG, DXF or PNG");
     println!();
     println!("Examples:");
     println!("  swarf program.swarf output.nc");
@@ -260,77 +474,303 @@ fn print_usage() {
     println!("  swarf --viz examples/");
 }
 
-fn compile(input_path: &str, output_path: &str) -> Result<(), Error> {
-    compile_with_post_and_tools(
-        input_path,
-        output_path,
-        post::PostProcessorType::Generic,
-        None,
-        None,
-    )
+/// `swarf simulate`: dry-run G-code, or a .swarf compiled for the generic post, and
+/// report where the tool goes. Exits non-zero when a block reaches below the floor.
+fn run_simulate(args: &[String]) {
+    let (path, z_floor) = match args {
+        [path] => (path, None),
+        [path, flag, value] | [flag, value, path] if flag == "--z-floor" => match value.parse() {
+            Ok(z) => (path, Some(z)),
+            Err(_) => {
+                eprintln!("Error: --z-floor requires a number");
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("Usage: swarf simulate <input.swarf|output.nc> [--z-floor <z>]");
+            std::process::exit(1);
+        }
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let gcode = if path.ends_with(".swarf") {
+        match swarf::compile_str(&source, post::PostProcessorType::Generic) {
+            Ok(gcode) => gcode,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        source
+    };
+
+    let output: swarf::codegen::GCodeOutput = gcode.lines().collect();
+    let report = swarf::codegen::simulate::simulate(&output, z_floor);
+    let range = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.4}", v));
+    println!("Z range:      {} to {}", range(report.min_z), range(report.max_z));
+    match report.xy_bounds {
+        Some(((x0, y0), (x1, y1))) => {
+            println!("XY extent:    X{:.4} to X{:.4}, Y{:.4} to Y{:.4}", x0, x1, y0, y1)
+        }
+        None => println!("XY extent:    -"),
+    }
+    println!("Rapid travel: {:.3}", report.rapid_distance);
+    println!("Feed travel:  {:.3}", report.feed_distance);
+
+    if let Some(floor) = z_floor {
+        if report.below_floor.is_empty() {
+            println!("Nothing below the Z{:.4} floor", floor);
+        } else {
+            for violation in &report.below_floor {
+                eprintln!("Below floor: Z{:.4} at {}", violation.z, violation.block);
+            }
+            eprintln!(
+                "Error: {} block(s) reach below the Z{:.4} floor",
+                report.below_floor.len(),
+                floor
+            );
+            std::process::exit(1);
+        }
+    }
 }
 
-fn compile_with_post(
-    input_path: &str,
-    output_path: &str,
-    post_type: post::PostProcessorType,
-    max_rpm: Option<f64>,
-) -> Result<(), Error> {
-    compile_with_post_and_tools(input_path, output_path, post_type, max_rpm, None)
+/// `swarf materials`: one line per material, the name last so columns split on whitespace
+fn run_materials(args: &[String]) {
+    let category = match args {
+        [] => None,
+        [flag, value] if flag == "--category" => Some(value.to_lowercase()),
+        _ => {
+            eprintln!("Usage: swarf materials [--category <name>]  (e.g. stainless, steel, plastic)");
+            std::process::exit(1);
+        }
+    };
+
+    let book = black_book::BlackBook::new();
+    let entries: Vec<_> = book
+        .catalog()
+        .into_iter()
+        .filter(|(_, m)| {
+            category
+                .as_deref()
+                .is_none_or(|c| m.category.to_string().contains(c))
+        })
+        .collect();
+
+    if entries.is_empty() {
+        eprintln!("Error: no materials in category '{}'", category.unwrap_or_default());
+        std::process::exit(1);
+    }
+
+    println!("{:<24} {:>6} {:>11}  MATERIAL", "CATEGORY", "MACH%", "CARBIDE_SFM");
+    for (name, material) in entries {
+        let (min, max, _) = material.sfm_carbide;
+        println!(
+            "{:<24} {:>6.0} {:>11}  {}",
+            material.category,
+            material.machinability_rating,
+            format!("{:.0}-{:.0}", min, max),
+            name
+        );
+    }
 }
 
-fn compile_with_post_and_tools(
-    input_path: &str,
-    output_path: &str,
-    post_type: post::PostProcessorType,
-    max_rpm: Option<f64>,
-    tool_library: Option<tool_library::ToolLibrary>,
-) -> Result<(), Error> {
-    // Read input
-    let source = fs::read_to_string(input_path)?;
+/// `swarf dump`: print the parsed program as JSON for editors and other tooling
+#[cfg(feature = "ast-serde")]
+fn run_dump(args: &[String]) {
+    let [path] = args else {
+        eprintln!("Usage: swarf dump <input.swarf>");
+        std::process::exit(1);
+    };
 
-    // Lex
-    let tokens = lexer::lex(&source);
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    // Parse
-    let mut parser = parser::Parser::new(tokens);
-    let program = parser.parse()?;
+    let program = match swarf::parser::Parser::new(swarf::lexer::lex(&source))
+        .with_source(&source)
+        .parse()
+    {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("Error: {}", CompileError::from(e));
+            std::process::exit(1);
+        }
+    };
 
-    // Resolve tool references from library
-    let program = if let Some(ref lib) = tool_library {
-        resolve_tools(program, lib)
-    } else {
-        program
+    match serde_json::to_string_pretty(&program) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `swarf feeds`: print the Black Book's cutting parameters for one tool and material
+fn run_feeds(args: &[String]) {
+    let mut material: Option<&str> = None;
+    let mut diameter: Option<f64> = None;
+    let mut flutes: u8 = 4;
+    let mut tool_material = black_book::ToolMaterial::Carbide;
+    let mut woc_pct = 40.0;
+    let mut doc: Option<f64> = None;
+    let mut units = swarf::ast::Units::Imperial;
+    let mut materials_path: Option<&str> = None;
+    let mut ball_nose = false;
+    let mut coating: Option<String> = None;
+    let mut corner_radius: Option<f64> = None;
+
+    let fail = |message: &str| -> ! {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
     };
 
-    // Validate
-    let validator = validator::Validator::new();
-    if let Err(errors) = validator.validate_program(&program) {
-        eprintln!("Validation errors:");
-        for err in errors {
-            eprintln!("  - {}", err);
+    let mut i = 0;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        if flag == "--ball" {
+            ball_nose = true;
+            i += 1;
+            continue;
+        }
+        let value = match args.get(i + 1) {
+            Some(value) => value.as_str(),
+            None => fail(&format!("{} requires an argument", flag)),
+        };
+        match flag {
+            "--material" => material = Some(value),
+            "--dia" => diameter = Some(value.parse().unwrap_or_else(|_| fail("--dia requires a number"))),
+            "--flutes" => flutes = value.parse().unwrap_or_else(|_| fail("--flutes requires a whole number")),
+            "--tool" => {
+                tool_material = match value.to_lowercase().as_str() {
+                    "hss" => black_book::ToolMaterial::HSS,
+                    "cobalt" => black_book::ToolMaterial::Cobalt,
+                    "carbide" => black_book::ToolMaterial::Carbide,
+                    "coated" | "coated-carbide" => black_book::ToolMaterial::CoatedCarbide,
+                    "ceramic" => black_book::ToolMaterial::Ceramic,
+                    "cbn" => black_book::ToolMaterial::CBN,
+                    "diamond" | "pcd" => black_book::ToolMaterial::Diamond,
+                    _ => fail("--tool must be hss, cobalt, carbide, coated, ceramic, cbn or diamond"),
+                }
+            }
+            "--woc" => woc_pct = value.parse().unwrap_or_else(|_| fail("--woc requires a percentage of the diameter")),
+            "--doc" => doc = Some(value.parse().unwrap_or_else(|_| fail("--doc requires a number"))),
+            "--units" => {
+                units = match value {
+                    "imperial" | "inch" => swarf::ast::Units::Imperial,
+                    "metric" | "mm" => swarf::ast::Units::Metric,
+                    _ => fail("--units must be imperial or metric"),
+                }
+            }
+            "--materials" => materials_path = Some(value),
+            "--coating" => coating = Some(value.to_string()),
+            "--corner-radius" => {
+                corner_radius = Some(value.parse().unwrap_or_else(|_| fail("--corner-radius requires a number")))
+            }
+            _ => fail(&format!("unknown feeds option '{}'", flag)),
+        }
+        i += 2;
+    }
+
+    let (material, diameter) = match (material, diameter) {
+        (Some(material), Some(diameter)) => (material, diameter),
+        _ => {
+            eprintln!("Usage: swarf feeds --material <name> --dia <d> [--flutes <n>] [--tool <material>] [--woc <%>] [--doc <d>] [--ball] [--units metric]");
+            std::process::exit(1);
         }
-        return Err(Error::Validation(vec![]));
+    };
+
+    let book = match materials_path {
+        Some(path) => black_book::BlackBook::from_file(path)
+            .unwrap_or_else(|e| fail(&format!("failed to load materials: {}", e))),
+        None => black_book::BlackBook::new(),
+    }
+    .with_units(units);
+
+    let tool = black_book::ToolGeometry {
+        diameter,
+        flute_count: flutes,
+        tool_material,
+        corner_radius,
+        coating,
+        ball_nose,
+    };
+    match feeds_report(&book, material, &tool, woc_pct, doc, units) {
+        Ok(report) => print!("{}", report),
+        Err(e) => fail(&e.to_string()),
     }
+}
 
-    // Generate G-code
-    let mut codegen = if let Some(rpm) = max_rpm {
-        codegen::CodeGenerator::new().with_max_rpm(rpm)
-    } else {
-        codegen::CodeGenerator::new()
+/// The `swarf feeds` report for `tool` cutting `material`, `woc_pct` of its diameter
+/// wide and `doc` deep (the full diameter if not given)
+fn feeds_report(
+    book: &black_book::BlackBook,
+    material: &str,
+    tool: &black_book::ToolGeometry,
+    woc_pct: f64,
+    doc: Option<f64>,
+    units: swarf::ast::Units,
+) -> Result<String, black_book::BlackBookError> {
+    let engagement = black_book::Engagement {
+        axial_doc: doc.unwrap_or(tool.diameter),
+        radial_woc: tool.diameter * woc_pct / 100.0,
+        radial_engagement_pct: woc_pct,
+    };
+    let params = book.calculate(material, tool, &engagement)?;
+
+    let (len, feed, volume) = match units {
+        swarf::ast::Units::Imperial => ("in", "IPM", "in³/min"),
+        swarf::ast::Units::Metric => ("mm", "mm/min", "cm³/min"),
     };
-    
-    // Pass tool library to codegen for auto-feeds/speeds
-    if let Some(lib) = tool_library {
-        codegen = codegen.with_tool_library(lib);
+    let shape = if tool.ball_nose { " ball" } else { "" };
+    let mut report = format!(
+        "{} / {} {} {}-flute {}{}\n",
+        material, tool.diameter, len, tool.flute_count, tool.tool_material, shape
+    );
+    report.push_str(&format!("  RPM:        {}\n", params.rpm));
+    report.push_str(&format!("  Feed:       {:.1} {}\n", params.feed_rate_ipm, feed));
+    report.push_str(&format!("  Chip load:  {:.4} {}/tooth\n", params.chip_load_ipt, len));
+    report.push_str(&format!("  SFM:        {:.0}\n", params.sfm));
+    report.push_str(&format!("  DOC x WOC:  {:.4} x {:.4} {}\n", params.doc, params.woc, len));
+    report.push_str(&format!("  HP:         {:.2}\n", params.hp_required));
+    report.push_str(&format!("  MRR:        {:.2} {}\n", params.material_removal_rate, volume));
+    for warning in &params.warnings {
+        report.push_str(&format!("  ⚠ {}\n", warning));
     }
-    
-    let gcode_output = codegen.generate_output(&program);
+    Ok(report)
+}
 
-    // Apply post-processor
-    let processor = post_type.get_processor();
-    let final_output = processor.process(&gcode_output);
-    let gcode = final_output.to_string();
+fn compile_with_post_and_tools(
+    input_path: &str,
+    output_path: &str,
+    post_type: post::PostProcessorType,
+    options: swarf::CompileOptions,
+) -> Result<(), Error> {
+    // Read input
+    let source = fs::read_to_string(input_path)?;
+
+    let gcode = match swarf::compile_source(&source, post_type, options) {
+        Ok(gcode) => gcode,
+        Err(CompileError::Validation(errors)) => {
+            eprintln!("Validation errors:");
+            for err in &errors {
+                eprintln!("  - {}", err);
+            }
+            return Err(CompileError::Validation(errors).into());
+        }
+        Err(e) => return Err(e.into()),
+    };
 
     // Write output
     fs::write(output_path, gcode)?;
@@ -338,116 +778,52 @@ fn compile_with_post_and_tools(
     println!(
         "Generated: {} (using {} post-processor)",
         output_path,
-        processor.name()
+        post_type.get_processor().name()
     );
 
     Ok(())
 }
 
-/// Resolve tool references by looking up in tool library
-fn resolve_tools(
-    mut program: ast::Program,
-    library: &tool_library::ToolLibrary,
-) -> ast::Program {
-    for op in &mut program.operations {
-        if let ast::Operation::ToolChange(ref mut tc) = op {
-            // Check if this is just a reference (no tool data) or has minimal data
-            let needs_lookup = tc.tool_data.is_none() || {
-                // If tool has no diameter, it needs lookup
-                tc.tool_data.as_ref().map(|d| d.diameter == 0.0).unwrap_or(true)
-            };
-
-            if needs_lookup {
-                // First try to find by string ID if present
-                let tool_def = tc.tool_id.as_ref()
-                    .and_then(|id| library.get_by_id(id))
-                    .or_else(|| {
-                        // Fall back to numeric ID lookup
-                        library.get_by_id(&tc.tool_number.to_string())
-                    });
-
-                if let Some(tool_def) = tool_def {
-                    // Update the tool number from the library tool's numeric ID
-                    tc.tool_number = tool_def.numeric_id();
-                    tc.tool_data = Some(ast::ToolData {
-                        diameter: tool_def.diameter,
-                        length: tool_def.length.unwrap_or(0.0),
-                        flutes: tool_def.flutes,
-                        material: tool_def.material.to_ast_material(),
-                    });
-                } else {
-                    let tool_ref_num = tc.tool_number.to_string();
-                    let tool_ref = tc.tool_id.as_deref()
-                        .unwrap_or(&tool_ref_num);
-                    eprintln!(
-                        "Warning: Tool '{}' not found in tool library",
-                        tool_ref
-                    );
-                }
-            }
-        }
-    }
-    program
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_drill_program() {
-        let source = r#"
-units metric
-offset 54
-
-tool 1 dia 6 length 50
-spindle cw rpm 2500
-
-drill at x 10 y 20 depth 5 peck 2 feed 100
-"#;
-
-        let tokens = lexer::lex(source);
-        let mut parser = parser::Parser::new(tokens);
-        let program = parser.parse().expect("parse failed");
-
-        let validator = validator::Validator::new();
-        validator
-            .validate_program(&program)
-            .expect("validation failed");
-
-        let mut codegen = codegen::CodeGenerator::new();
-        let gcode = codegen.generate(&program);
-
-        assert!(gcode.contains("G83")); // Peck drill cycle
-        assert!(gcode.contains("M30")); // Program end
+    fn end_mill(diameter: f64) -> black_book::ToolGeometry {
+        black_book::ToolGeometry {
+            diameter,
+            flute_count: 3,
+            tool_material: black_book::ToolMaterial::Carbide,
+            corner_radius: None,
+            coating: None,
+            ball_nose: false,
+        }
     }
 
     #[test]
-    fn test_imperial_units() {
-        let source = r#"
-units imperial
-offset 54
-
-tool 1 dia 0.125 length 1.0
-spindle cw rpm 5000
-
-drill at x 0.5 y 0.5 depth 0.25
-"#;
-
-        let tokens = lexer::lex(source);
-        let mut parser = parser::Parser::new(tokens);
-        let program = parser.parse().expect("parse failed");
-
-        let validator = validator::Validator::new();
-        validator
-            .validate_program(&program)
-            .expect("validation failed");
-
-        let mut codegen = codegen::CodeGenerator::new();
-        let gcode = codegen.generate(&program);
+    fn test_feeds_report_for_known_material() {
+        let book = black_book::BlackBook::new();
+        let tool = end_mill(0.5);
+        let report = feeds_report(&book, "6061-T6", &tool, 40.0, None, swarf::ast::Units::Imperial)
+            .expect("6061-T6 is in the Black Book");
+
+        let engagement = black_book::Engagement {
+            axial_doc: 0.5,
+            radial_woc: 0.2,
+            radial_engagement_pct: 40.0,
+        };
+        let params = book.calculate("6061-T6", &tool, &engagement).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert!(lines[0].starts_with("6061-T6 / 0.5 in 3-flute "));
+        assert_eq!(lines[1], format!("  RPM:        {}", params.rpm));
+        assert_eq!(lines[2], format!("  Feed:       {:.1} IPM", params.feed_rate_ipm));
+        assert_eq!(lines[5], format!("  DOC x WOC:  {:.4} x {:.4} in", params.doc, params.woc));
+    }
 
-        println!("Generated G-code:\n{}", gcode);
-        assert!(gcode.contains("G20")); // Imperial units
-        assert!(gcode.contains("M30")); // Program end
+    #[test]
+    fn test_feeds_report_unknown_material() {
+        let book = black_book::BlackBook::new();
+        let err = feeds_report(&book, "Unobtainium", &end_mill(0.5), 40.0, None, swarf::ast::Units::Imperial)
+            .unwrap_err();
+        assert!(matches!(err, black_book::BlackBookError::UnknownMaterial(_)));
     }
 }
@@ -19,8 +19,15 @@ pub enum ParseError {
     #[error("unknown work offset: {0}")]
     UnknownWorkOffset(String),
 
-    #[error("{message} at line {line}")]
-    WithLocation { message: String, line: usize },
+    #[error("unknown thread size: {0}")]
+    UnknownThread(String),
+
+    #[error("{message} at line {line}, column {column}")]
+    WithLocation {
+        message: String,
+        line: usize,
+        column: usize,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, ParseError>;
@@ -28,7 +35,16 @@ pub type Result<T> = std::result::Result<T, ParseError>;
 pub struct Parser {
     tokens: Vec<(Token, logos::Span)>,
     position: usize,
-    current_line: usize,
+    /// Text the tokens were lexed from, for exact error locations
+    source: Option<String>,
+    /// `;` notes found before/between header declarations, emitted ahead of the operations
+    header_comments: Vec<String>,
+    /// Park position and end code, set by `return to ...` / `end ...` statements
+    footer: Footer,
+    /// Coolant in effect, restored after an operation with its own `coolant` modifier
+    coolant: CoolantMode,
+    /// Program units, for thread pitches looked up by size
+    units: Units,
 }
 
 impl Parser {
@@ -36,18 +52,38 @@ impl Parser {
         Self {
             tokens,
             position: 0,
-            current_line: 1,
+            source: None,
+            header_comments: Vec::new(),
+            footer: Footer {
+                return_to: Position::default(),
+                end_code: "M30".to_string(),
+            },
+            coolant: CoolantMode::Off,
+            units: Units::Metric,
         }
     }
 
+    /// Keep the source the tokens came from, so errors point at its exact line and column
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
     /// Parse the full program
     pub fn parse(&mut self) -> Result<Program> {
+        self.parse_program()
+            .map_err(|e| self.locate(e, self.position))
+    }
+
+    fn parse_program(&mut self) -> Result<Program> {
         let header = self.parse_header()?;
-        let operations = self.parse_operations()?;
-        let footer = Footer {
-            return_to: Position::default(),
-            end_code: "M30".to_string(),
-        };
+        let mut operations: Vec<Operation> = self
+            .header_comments
+            .drain(..)
+            .map(Operation::Comment)
+            .collect();
+        operations.extend(self.parse_operations()?);
+        let footer = self.footer.clone();
 
         Ok(Program {
             header,
@@ -67,11 +103,14 @@ impl Parser {
 
         // Skip leading newlines
         self.skip_newlines();
+        self.collect_header_comments();
 
         // Parse header declarations
         while self.peek() == Some(&Token::Units)
             || self.peek() == Some(&Token::Offset)
             || self.peek() == Some(&Token::Coolant)
+            || self.peek() == Some(&Token::MaxRpm)
+            || self.peek() == Some(&Token::MaxFeed)
         {
             match self.peek() {
                 Some(Token::Units) => {
@@ -90,6 +129,7 @@ impl Parser {
                 }
                 Some(Token::Offset) => {
                     self.consume(Token::Offset)?;
+                    let number_at = self.position;
                     let offset_num = self.expect_number()? as u8;
                     work_offset = match offset_num {
                         54 => WorkOffset::G54,
@@ -98,32 +138,31 @@ impl Parser {
                         57 => WorkOffset::G57,
                         58 => WorkOffset::G58,
                         59 => WorkOffset::G59,
-                        _ => return Err(ParseError::UnknownWorkOffset(format!("G{}", offset_num))),
+                        _ => {
+                            let error = ParseError::UnknownWorkOffset(format!("G{}", offset_num));
+                            return Err(self.locate(error, number_at));
+                        }
                     };
                 }
                 Some(Token::Coolant) => {
-                    self.consume(Token::Coolant)?;
-                    safety.coolant = match self.peek() {
-                        Some(Token::Flood) => {
-                            self.advance();
-                            CoolantMode::Flood
-                        }
-                        Some(Token::Mist) => {
-                            self.advance();
-                            CoolantMode::Mist
-                        }
-                        Some(Token::Off) => {
-                            self.advance();
-                            CoolantMode::Off
-                        }
-                        _ => return Err(self.error("expected 'flood', 'mist', or 'off'"))?,
-                    };
+                    safety.coolant = self.parse_coolant()?;
+                    self.coolant = safety.coolant;
+                }
+                Some(Token::MaxRpm) => {
+                    self.advance();
+                    safety.max_spindle_rpm = Some(self.expect_number()?);
+                }
+                Some(Token::MaxFeed) => {
+                    self.advance();
+                    safety.max_feed_rate = Some(self.expect_number()?);
                 }
                 _ => break,
             }
             self.skip_newlines();
+            self.collect_header_comments();
         }
 
+        self.units = units;
         Ok(Header {
             units,
             work_offset,
@@ -195,9 +234,27 @@ impl Parser {
                     depth: c.depth,
                     height: c.height,
                     z_constraint: c.z_constraint,
+                    origin: c.origin,
                 })?),
                 Some(Token::Chamfer) => Operation::Chamfer(self.parse_chamfer()?),
                 Some(Token::Deburr) => Operation::Deburr(self.parse_deburr()?),
+                Some(Token::Coolant) => {
+                    self.coolant = self.parse_coolant()?;
+                    Operation::Coolant(self.coolant)
+                }
+                Some(Token::LineComment(text)) => {
+                    let text = text.clone();
+                    self.advance();
+                    Operation::Comment(text)
+                }
+                Some(Token::Return) => {
+                    self.parse_return()?;
+                    continue;
+                }
+                Some(Token::End) => {
+                    self.parse_end()?;
+                    continue;
+                }
                 Some(_) => {
                     // Unknown token, skip for now
                     self.advance();
@@ -206,12 +263,96 @@ impl Parser {
                 None => break,
             };
 
+            // A trailing `coolant <mode>` applies to this operation only
+            if self.peek() == Some(&Token::Coolant) {
+                let coolant = self.parse_coolant()?;
+                ops.extend([Operation::Coolant(coolant), op, Operation::Coolant(self.coolant)]);
+                continue;
+            }
+
             ops.push(op);
         }
 
         Ok(ops)
     }
 
+    /// Parse: coolant flood|mist|through|off (`thru` for short)
+    fn parse_coolant(&mut self) -> Result<CoolantMode> {
+        self.consume(Token::Coolant)?;
+        let mode = match self.peek() {
+            Some(Token::Flood) => CoolantMode::Flood,
+            Some(Token::Mist) => CoolantMode::Mist,
+            Some(Token::Through | Token::Thru) => CoolantMode::Through,
+            Some(Token::Off) => CoolantMode::Off,
+            _ => return Err(self.error("expected 'flood', 'mist', 'through', or 'off'")),
+        };
+        self.advance();
+        Ok(mode)
+    }
+
+    /// Parse: return to X <n> Y <n> [end M02|M30]
+    /// Also accepts `return to zero` or `return to <x> <y>`
+    fn parse_return(&mut self) -> Result<()> {
+        self.consume(Token::Return)?;
+        if self.check_identifier("to") {
+            self.advance();
+        } else {
+            return Err(self.error("expected 'to' after 'return'"));
+        }
+
+        let return_to = if self.peek_axis("x") {
+            self.advance();
+            let x = self.expect_number_or_fraction()?;
+            if !self.peek_axis("y") {
+                return Err(self.error("expected 'Y' after return X position"));
+            }
+            self.advance();
+            let y = self.expect_number_or_fraction()?;
+            Position::new(x, y)
+        } else {
+            self.parse_at_position()?
+        };
+        self.footer.return_to = return_to;
+
+        if self.peek() == Some(&Token::End) {
+            self.parse_end()?;
+        }
+        Ok(())
+    }
+
+    /// Parse: end M02|M30
+    fn parse_end(&mut self) -> Result<()> {
+        self.consume(Token::End)?;
+        let code = match self.peek() {
+            Some(Token::Identifier(s)) => s.to_uppercase(),
+            Some(other) => {
+                return Err(ParseError::UnexpectedToken {
+                    expected: "M02 or M30".to_string(),
+                    got: format!("{:?}", other),
+                })
+            }
+            None => return Err(ParseError::UnexpectedEOF),
+        };
+        self.footer.end_code = match code.as_str() {
+            "M02" | "M2" => "M02".to_string(),
+            "M30" => "M30".to_string(),
+            _ => return Err(self.error("expected end code M02 or M30")),
+        };
+        self.advance();
+        Ok(())
+    }
+
+    /// Axis letter in either case: `x` lexes as a keyword, `X` as an identifier
+    fn peek_axis(&self, axis: &str) -> bool {
+        match self.peek() {
+            Some(Token::X) => axis == "x",
+            Some(Token::Y) => axis == "y",
+            Some(Token::Z) => axis == "z",
+            Some(Token::Identifier(s)) => s.eq_ignore_ascii_case(axis),
+            _ => false,
+        }
+    }
+
     fn is_drill_v2(&self) -> bool {
         // Look ahead: drill <number> at ... (v2)
         // vs drill at ... (v1)
@@ -238,23 +379,19 @@ impl Parser {
         false
     }
 
+    /// A sized pocket (v2) gives its dimensions before `at`:
+    /// `pocket [rect|rectangle|circle] <dims> <depth> at ...`. An outlined pocket (v1)
+    /// names its geometry and places it straight away: `pocket rect|rectangle|circle at ...`,
+    /// or `pocket polygon ...` / `pocket path ...`. Anything else parses as sized so a
+    /// mistyped dimension reports against the sized syntax.
     fn is_pocket_v2(&self) -> bool {
-        // v2 uses different shape specification
-        // Look for pocket followed by rect/circle or dimensions without 'at'
-        if let Some((Token::Pocket, _)) = self.tokens.get(self.position) {
-            // If followed by dimensions and then 'at', it's v2
-            let mut pos = self.position + 1;
-            // Skip shape keyword if present
-            if let Some((Token::Rect | Token::Rectangle | Token::Circle, _)) = self.tokens.get(pos)
-            {
-                pos += 1;
-            }
-            // Should be numbers (dimensions)
-            if let Some((Token::Number(_) | Token::Fraction(_), _)) = self.tokens.get(pos) {
-                return true;
-            }
-        }
-        false
+        let token = |offset: usize| self.tokens.get(self.position + offset).map(|(t, _)| t);
+        let outlined = matches!(
+            (token(1), token(2)),
+            (Some(Token::Polygon | Token::Path), _)
+                | (Some(Token::Rect | Token::Rectangle | Token::Circle), Some(Token::At))
+        );
+        !outlined
     }
 
     fn has_pattern_after_drill(&self) -> bool {
@@ -265,6 +402,10 @@ impl Parser {
         if matches!(self.tokens.get(pos), Some((Token::Number(_) | Token::Fraction(_), _))) {
             pos += 1;
         }
+        // drill <dia> pattern ... (anchor comes from the pattern itself)
+        if matches!(self.tokens.get(pos), Some((Token::Pattern, _))) {
+            return true;
+        }
         // Skip 'at' and position
         if matches!(self.tokens.get(pos), Some((Token::At, _))) {
             pos += 1;
@@ -306,6 +447,10 @@ impl Parser {
                 pos += 1;
             }
         }
+        // pocket <shape> <dims> <depth> pattern ...
+        if matches!(self.tokens.get(pos), Some((Token::Pattern, _))) {
+            return true;
+        }
         // Skip 'at' and position
         if matches!(self.tokens.get(pos), Some((Token::At, _))) {
             pos += 1;
@@ -329,18 +474,21 @@ impl Parser {
         self.consume(Token::Drill)?;
         let diameter = self.expect_number_or_fraction()?;
 
+        // drill <dia> pattern ... [thru|depth <d>]
+        if self.peek() == Some(&Token::Pattern) {
+            let pattern = self.parse_pattern()?;
+            let depth = self.parse_drill_pattern_depth()?;
+            return Ok(DrillPatternOp {
+                diameter,
+                depth,
+                pattern,
+            });
+        }
+
         self.consume(Token::At)?;
         let _position = self.parse_at_position()?; // Starting position (used for single, ignored for pattern)
 
-        let depth = if self.peek() == Some(&Token::Thru) {
-            self.advance();
-            DrillDepth::Thru
-        } else if self.peek() == Some(&Token::Depth) {
-            self.advance();
-            DrillDepth::Depth(self.expect_number_or_fraction()?)
-        } else {
-            DrillDepth::Thru // Default
-        };
+        let depth = self.parse_drill_pattern_depth()?;
 
         let pattern = self.parse_pattern()?;
 
@@ -351,6 +499,18 @@ impl Parser {
         })
     }
 
+    fn parse_drill_pattern_depth(&mut self) -> Result<DrillDepth> {
+        if self.peek() == Some(&Token::Thru) {
+            self.advance();
+            Ok(DrillDepth::Thru)
+        } else if self.peek() == Some(&Token::Depth) {
+            self.advance();
+            Ok(DrillDepth::Depth(self.expect_number_or_fraction()?))
+        } else {
+            Ok(DrillDepth::Thru) // Default
+        }
+    }
+
     fn parse_pocket_pattern(&mut self) -> Result<PocketPatternOp> {
         self.consume(Token::Pocket)?;
 
@@ -372,8 +532,11 @@ impl Parser {
 
         let depth = self.expect_number_or_fraction()?;
 
-        self.consume(Token::At)?;
-        let _position = self.parse_at_position()?; // Starting position (used for single, ignored for pattern)
+        // pocket <shape> <dims> <depth> pattern ... has no separate 'at'
+        if self.peek() != Some(&Token::Pattern) {
+            self.consume(Token::At)?;
+            let _position = self.parse_at_position()?; // Starting position (used for single, ignored for pattern)
+        }
 
         let pattern = self.parse_pattern()?;
 
@@ -394,7 +557,7 @@ impl Parser {
                 self.advance();
                 (num, None)
             }
-            Some(Token::Identifier(id)) => {
+            Some(Token::Identifier(id)) | Some(Token::String(id)) => {
                 let id_str = id.clone();
                 self.advance();
                 // For string IDs, we'll use 0 as placeholder - codegen will resolve
@@ -430,14 +593,26 @@ impl Parser {
             2 // Default
         };
 
-        let material = if self.peek() == Some(&Token::HSS) {
+        let material = match self.peek() {
+            Some(Token::HSS) => Some(ToolMaterial::HSS),
+            Some(Token::Carbide) => Some(ToolMaterial::Carbide),
+            Some(Token::Cobalt) => Some(ToolMaterial::Cobalt),
+            Some(Token::Ceramic) => Some(ToolMaterial::Ceramic),
+            Some(Token::Coated) => Some(ToolMaterial::CoatedCarbide),
+            Some(Token::CBN) => Some(ToolMaterial::CBN),
+            Some(Token::Diamond) => Some(ToolMaterial::Diamond),
+            _ => None,
+        };
+        if material.is_some() {
             self.advance();
-            ToolMaterial::HSS
-        } else if self.peek() == Some(&Token::Carbide) {
+        }
+        let material = material.unwrap_or(ToolMaterial::Carbide); // Default
+
+        let center_cutting = if self.peek() == Some(&Token::CenterCutting) {
             self.advance();
-            ToolMaterial::Carbide
+            true
         } else {
-            ToolMaterial::Carbide // Default
+            false
         };
 
         Ok(ToolData {
@@ -445,6 +620,7 @@ impl Parser {
             length,
             flutes,
             material,
+            center_cutting,
         })
     }
 
@@ -497,7 +673,7 @@ impl Parser {
             self.advance();
             self.expect_number()?
         } else {
-            5.0 // Default 5mm above
+            self.default_retract()
         };
 
         let feed_rate = if self.peek() == Some(&Token::Feed) {
@@ -623,6 +799,23 @@ impl Parser {
             feed_rate * 0.5
         };
 
+        // Trailing options in any order: `comp`, `lead <radius>`
+        let mut cutter_comp = false;
+        let mut lead_radius = None;
+        loop {
+            match self.peek() {
+                Some(Token::Comp) => {
+                    self.advance();
+                    cutter_comp = true;
+                }
+                Some(Token::Lead) => {
+                    self.advance();
+                    lead_radius = Some(self.expect_number_or_fraction()?);
+                }
+                _ => break,
+            }
+        }
+
         Ok(Operation::Profile(ProfileOp {
             geometry,
             depth,
@@ -630,6 +823,8 @@ impl Parser {
             stock_to_leave,
             feed_rate,
             plunge_feed,
+            cutter_comp,
+            lead_radius,
         }))
     }
 
@@ -665,6 +860,23 @@ impl Parser {
 
     fn parse_tap(&mut self) -> Result<Operation> {
         self.consume(Token::Tap)?;
+
+        // A thread size in place of `pitch`: `tap 1/4-20 at ...`, `tap M6 at ...`
+        let thread_at = self.position;
+        let thread = match self.peek() {
+            Some(Token::Thread(s)) | Some(Token::Identifier(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let spec = match &thread {
+            Some(thread) => {
+                self.advance();
+                let spec = crate::threads::lookup(thread)
+                    .ok_or_else(|| self.locate(ParseError::UnknownThread(thread.clone()), thread_at))?;
+                Some(spec.in_units(self.units))
+            }
+            None => None,
+        };
+
         self.consume(Token::At)?;
 
         let positions = self.parse_positions()?;
@@ -672,14 +884,19 @@ impl Parser {
         self.consume(Token::Depth)?;
         let depth = self.expect_number()?;
 
-        self.consume(Token::Pitch)?;
-        let pitch = self.expect_number()?;
+        let pitch = match spec {
+            Some(spec) if self.peek() != Some(&Token::Pitch) => spec.pitch,
+            _ => {
+                self.consume(Token::Pitch)?;
+                self.expect_number()?
+            }
+        };
 
         let retract_height = if self.peek() == Some(&Token::Retract) {
             self.advance();
             self.expect_number()?
         } else {
-            5.0
+            self.default_retract()
         };
 
         Ok(Operation::Tap(TapOp {
@@ -687,6 +904,7 @@ impl Parser {
             depth,
             pitch,
             retract_height,
+            thread,
         }))
     }
 
@@ -696,10 +914,52 @@ impl Parser {
                 Ok(Geometry::Rect(self.parse_rectangle()?))
             }
             Some(Token::Circle) => Ok(Geometry::Circle(self.parse_circle()?)),
-            _ => Err(self.error("expected geometry (rectangle, circle)"))?,
+            Some(Token::Polygon) => Ok(Geometry::Polygon(self.parse_polygon()?)),
+            Some(Token::Path) => Ok(Geometry::Path(self.parse_path()?)),
+            _ => Err(self.error("expected geometry (rectangle, circle, polygon, path)"))?,
         }
     }
 
+    fn parse_polygon(&mut self) -> Result<Polygon> {
+        self.consume(Token::Polygon)?;
+        self.consume(Token::At)?;
+
+        let center = self.parse_point()?;
+
+        self.consume(Token::Sides)?;
+        let sides = self.expect_number()? as u8;
+
+        self.consume(Token::Radius)?;
+        let circumradius = self.expect_number_or_fraction()?;
+
+        let rotation = if self.peek() == Some(&Token::Rotate) {
+            self.advance();
+            self.expect_number()?
+        } else {
+            0.0
+        };
+
+        Ok(Polygon {
+            center,
+            circumradius,
+            sides,
+            rotation,
+        })
+    }
+
+    /// Points joined by arrows: `path 0 0 -> 2 0 -> 1 1.5`
+    fn parse_path(&mut self) -> Result<Vec<Position>> {
+        self.consume(Token::Path)?;
+
+        let mut points = vec![self.parse_point()?];
+        while self.peek() == Some(&Token::Arrow) {
+            self.advance();
+            points.push(self.parse_point()?);
+        }
+
+        Ok(points)
+    }
+
     fn parse_rectangle(&mut self) -> Result<Rectangle> {
         self.consume_one_of(&[Token::Rectangle, Token::Rect])?;
         self.consume(Token::At)?;
@@ -790,6 +1050,24 @@ impl Parser {
         Ok(Position::new(x, y))
     }
 
+    /// Either `x 1 y 2` or a bare `1 2`
+    fn parse_point(&mut self) -> Result<Position> {
+        if self.peek() == Some(&Token::X) {
+            return self.parse_position();
+        }
+        let x = self.expect_number_or_fraction()?;
+        let y = self.expect_number_or_fraction()?;
+        Ok(Position::new(x, y))
+    }
+
+    /// R plane of a drill or tap cycle with no `retract`: 5 mm, or 0.2" in inches
+    fn default_retract(&self) -> f64 {
+        match self.units {
+            Units::Metric => 5.0,
+            Units::Imperial => 0.2,
+        }
+    }
+
     // Helper methods
     fn peek(&self) -> Option<&Token> {
         self.tokens.get(self.position).map(|(t, _)| t)
@@ -848,18 +1126,75 @@ impl Parser {
 
     fn skip_newlines(&mut self) {
         while self.peek() == Some(&Token::Newline) {
-            self.current_line += 1;
             self.advance();
         }
     }
 
+    /// Collect `;` notes interleaved with header declarations (units/offset/coolant)
+    fn collect_header_comments(&mut self) {
+        while let Some(Token::LineComment(text)) = self.peek() {
+            let text = text.clone();
+            self.advance();
+            self.header_comments.push(text);
+            self.skip_newlines();
+        }
+    }
+
     fn error(&self, msg: &str) -> ParseError {
+        let text = self.get_current_token_text();
+        let message = if text.is_empty() {
+            msg.to_string()
+        } else {
+            format!("{}, found '{}'", msg, text)
+        };
+        let (line, column) = self.location(self.position);
         ParseError::WithLocation {
-            message: msg.to_string(),
-            line: self.current_line,
+            message,
+            line,
+            column,
         }
     }
 
+    /// Pin an error to the token at `position`, unless it already has a location
+    fn locate(&self, error: ParseError, position: usize) -> ParseError {
+        match error {
+            ParseError::WithLocation { .. } => error,
+            other => {
+                let (line, column) = self.location(position);
+                ParseError::WithLocation {
+                    message: other.to_string(),
+                    line,
+                    column,
+                }
+            }
+        }
+    }
+
+    /// Line and column, counting from 1, of the token at `position` (or the end of input).
+    /// Without the source, lines are counted from newline tokens, which miss blank lines
+    /// and block comments.
+    fn location(&self, position: usize) -> (usize, usize) {
+        let offset = match self.tokens.get(position) {
+            Some((_, span)) => span.start,
+            None => self.tokens.last().map_or(0, |(_, span)| span.end),
+        };
+
+        if let Some(source) = &self.source {
+            let before = &source[..offset.min(source.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+            return (line, column);
+        }
+
+        let newlines: Vec<&logos::Span> = self.tokens[..position.min(self.tokens.len())]
+            .iter()
+            .filter(|(token, _)| *token == Token::Newline)
+            .map(|(_, span)| span)
+            .collect();
+        let line_start = newlines.last().map_or(0, |span| span.end);
+        (newlines.len() + 1, offset.saturating_sub(line_start) + 1)
+    }
+
     fn check_identifier(&self, expected: &str) -> bool {
         matches!(self.peek(), Some(Token::Identifier(s)) if s == expected)
     }
@@ -938,14 +1273,15 @@ impl Parser {
         self.consume(Token::Setup)?;
         self.consume(Token::LBrace)?;
 
-        let mut zero = ZeroConfig {
-            x_ref: XRef::Left,
-            y_ref: YRef::Front,
-            z_ref: ZRef::Top,
-        };
+        let mut zero = ZeroConfig::default();
         let mut material = None;
         let mut z_min = None;
         let mut y_limit = None;
+        let mut rigid_tapping = false;
+        let mut no_length_comp = false;
+        let mut group_tools = false;
+        let mut keep_pattern_order = false;
+        let mut milling_direction = None;
 
         while self.peek() != Some(&Token::RBrace) {
             match self.peek() {
@@ -965,9 +1301,37 @@ impl Parser {
                     self.advance();
                     y_limit = Some(self.expect_number()?);
                 }
+                Some(Token::RigidTap) => {
+                    self.advance();
+                    rigid_tapping = true;
+                }
+                Some(Token::NoLengthComp) => {
+                    self.advance();
+                    no_length_comp = true;
+                }
+                Some(Token::GroupTools) => {
+                    self.advance();
+                    group_tools = true;
+                }
+                Some(Token::KeepPatternOrder) => {
+                    self.advance();
+                    keep_pattern_order = true;
+                }
+                Some(Token::Climb) => {
+                    self.advance();
+                    milling_direction = Some(MillingDirection::Climb);
+                }
+                Some(Token::Conventional) => {
+                    self.advance();
+                    milling_direction = Some(MillingDirection::Conventional);
+                }
+                Some(Token::LineComment(_)) => {
+                    // Notes inside the setup block annotate the setup, not the program
+                    self.advance();
+                }
                 _ => {
                     return Err(self.error(
-                        "expected 'zero', 'material', 'z-min', or 'y-limit' in setup block",
+                        "expected 'zero', 'material', 'z-min', 'y-limit', 'rigid-tap', 'no-length-comp', 'group-tools', 'keep-pattern-order', 'climb', or 'conventional' in setup block",
                     ));
                 }
             }
@@ -982,6 +1346,11 @@ impl Parser {
             material,
             z_min,
             y_limit,
+            rigid_tapping,
+            no_length_comp,
+            group_tools,
+            keep_pattern_order,
+            milling_direction,
         })
     }
 
@@ -1058,6 +1427,7 @@ impl Parser {
             depth,
             height,
             z_constraint,
+            origin: Position::default(),
         })
     }
 
@@ -1098,8 +1468,23 @@ impl Parser {
     fn parse_chamfer(&mut self) -> Result<ChamferOp> {
         self.consume(Token::Chamfer)?;
 
-        // Parse chamfer width (leg of 45° triangle)
-        let width = self.expect_number_or_fraction()?;
+        let side = match self.peek() {
+            Some(Token::Outside) => {
+                self.advance();
+                CutSide::Outside
+            }
+            Some(Token::Inside) => {
+                self.advance();
+                CutSide::Inside
+            }
+            _ => CutSide::Inside, // Default
+        };
+
+        // Chamfer width up front, or later as `width <w>`
+        let mut width = match self.peek() {
+            Some(Token::Number(_) | Token::Fraction(_)) => Some(self.expect_number_or_fraction()?),
+            _ => None,
+        };
 
         // Parse geometry type
         let geometry = if self.peek() == Some(&Token::Rect) || self.peek() == Some(&Token::Rectangle) {
@@ -1122,10 +1507,35 @@ impl Parser {
         self.consume(Token::At)?;
         let position = self.parse_at_position()?;
 
+        // Trailing options in any order: width, depth, angle
+        let mut depth = None;
+        let mut tool_angle = 90.0; // Standard chamfer mill
+        loop {
+            match self.peek() {
+                Some(Token::Width) => {
+                    self.advance();
+                    width = Some(self.expect_number_or_fraction()?);
+                }
+                Some(Token::Depth) => {
+                    self.advance();
+                    depth = Some(self.expect_number_or_fraction()?);
+                }
+                Some(Token::Identifier(s)) if s == "angle" => {
+                    self.advance();
+                    tool_angle = self.expect_number()?;
+                }
+                _ => break,
+            }
+        }
+        let width = width.ok_or_else(|| self.error("expected a chamfer width"))?;
+
         Ok(ChamferOp {
             width,
             geometry,
             position,
+            side,
+            depth,
+            tool_angle,
         })
     }
 
@@ -1180,10 +1590,40 @@ impl Parser {
             DrillDepth::Depth(self.expect_number_or_fraction()?)
         };
 
+        // Trailing modifiers in any order: spot, peck break|clear, dwell <seconds>
+        let mut spot = false;
+        let mut peck = None;
+        let mut dwell = None;
+        loop {
+            match self.peek() {
+                Some(Token::Spot) => {
+                    self.advance();
+                    spot = true;
+                }
+                Some(Token::Peck) => {
+                    self.advance();
+                    peck = Some(match self.peek() {
+                        Some(Token::Identifier(s)) if s == "break" => PeckMode::Break,
+                        Some(Token::Clear) => PeckMode::Clear,
+                        _ => return Err(self.error("expected 'break' or 'clear' after 'peck'")),
+                    });
+                    self.advance();
+                }
+                Some(Token::Dwell) => {
+                    self.advance();
+                    dwell = Some(self.expect_number_or_fraction()?);
+                }
+                _ => break,
+            }
+        }
+
         Ok(DrillV2Op {
             diameter,
             position,
             depth,
+            spot,
+            peck,
+            dwell,
         })
     }
 
@@ -1214,10 +1654,58 @@ impl Parser {
         self.consume(Token::At)?;
         let position = self.parse_at_position()?;
 
+        let mut finish = None;
+        let mut corners = CornerRelief::None;
+        let mut adaptive = false;
+        let mut plunge = None;
+        let mut stepover = None;
+        let mut stepdown = None;
+        loop {
+            match self.peek() {
+                Some(Token::Finish) => {
+                    self.advance();
+                    finish = Some(self.expect_number_or_fraction()?);
+                }
+                Some(Token::Adaptive) => {
+                    self.advance();
+                    adaptive = true;
+                }
+                Some(Token::Plunge) => {
+                    self.advance();
+                    plunge = Some(self.expect_number()?);
+                }
+                Some(Token::Stepover) => {
+                    self.advance();
+                    stepover = Some(self.expect_number()?);
+                }
+                Some(Token::Stepdown) => {
+                    self.advance();
+                    stepdown = Some(self.expect_number_or_fraction()?);
+                }
+                Some(Token::Corners) => {
+                    self.advance();
+                    corners = match self.peek() {
+                        Some(Token::Dogbone) => CornerRelief::Dogbone,
+                        Some(Token::TBone) => CornerRelief::TBone,
+                        Some(Token::Identifier(s)) if s == "none" => CornerRelief::None,
+                        _ => return Err(self.error("expected 'dogbone', 'tbone', or 'none' after 'corners'")),
+                    };
+                    self.advance();
+                }
+                _ => break,
+            }
+        }
+
         Ok(PocketV2Op {
             shape,
             position,
             depth,
+            finish,
+            corners,
+            adaptive,
+            plunge,
+            stepover,
+            stepdown,
         })
     }
 
@@ -1306,10 +1794,14 @@ impl Parser {
 
         // Parse: grid <rows> x <cols> spacing <sx> <sy> starting at <x> <y>
         // Or: grid <rows> <cols> spacing <sx> <sy> starting at <x> <y>
+        // Or: grid rows <rows> cols <cols> spacing <sx> <sy> at <x> <y>
+        if self.peek() == Some(&Token::Rows) {
+            self.advance();
+        }
         let rows = self.expect_number_or_fraction()? as u32;
 
-        // Optional 'x' or just second number
-        if self.peek() == Some(&Token::X) {
+        // Optional 'x' / 'cols' or just second number
+        if self.peek() == Some(&Token::X) || self.peek() == Some(&Token::Cols) {
             self.advance();
         }
 
@@ -1319,8 +1811,7 @@ impl Parser {
         let spacing_x = self.expect_number_or_fraction()?;
         let spacing_y = self.expect_number_or_fraction()?;
 
-        self.consume(Token::Starting)?;
-        self.consume(Token::At)?;
+        self.consume_anchor(Token::Starting)?;
         let start_position = self.parse_at_position()?;
 
         Ok(Pattern::Grid {
@@ -1339,13 +1830,16 @@ impl Parser {
 
     fn parse_bolt_circle_pattern_contents(&mut self) -> Result<Pattern> {
         // Parse: circle <count> dia <diameter> center at <x> <y>
+        // Or: circle count <count> dia <diameter> at <x> <y>
+        if self.peek() == Some(&Token::Count) {
+            self.advance();
+        }
         let count = self.expect_number_or_fraction()? as u32;
 
         self.consume(Token::Diameter)?;
         let diameter = self.expect_number_or_fraction()?;
 
-        self.consume(Token::Center)?;
-        self.consume(Token::At)?;
+        self.consume_anchor(Token::Center)?;
         let center = self.parse_at_position()?;
 
         // Optional start angle (default 0)
@@ -1369,6 +1863,10 @@ impl Parser {
         self.consume(Token::Line)?;
 
         // Parse: line <count> spacing <spacing> <direction> starting at <x> <y>
+        // Or: line count <count> spacing <spacing> <direction> at <x> <y>
+        if self.peek() == Some(&Token::Count) {
+            self.advance();
+        }
         let count = self.expect_number_or_fraction()? as u32;
 
         self.consume(Token::Spacing)?;
@@ -1390,8 +1888,7 @@ impl Parser {
             _ => return Err(self.error("expected direction like X+, Y-")),
         };
 
-        self.consume(Token::Starting)?;
-        self.consume(Token::At)?;
+        self.consume_anchor(Token::Starting)?;
         let start_position = self.parse_at_position()?;
 
         Ok(Pattern::Line {
@@ -1406,13 +1903,16 @@ impl Parser {
         self.consume(Token::Arc)?;
         
         // Parse: arc <count> radius <radius> center at <x> <y> starting at <angle> to <angle>
+        // Or: arc count <count> radius <radius> at <x> <y> starting at <angle> to <angle>
+        if self.peek() == Some(&Token::Count) {
+            self.advance();
+        }
         let count = self.expect_number_or_fraction()? as u32;
 
         self.consume(Token::Radius)?;
         let radius = self.expect_number_or_fraction()?;
 
-        self.consume(Token::Center)?;
-        self.consume(Token::At)?;
+        self.consume_anchor(Token::Center)?;
         let center = self.parse_at_position()?;
 
         // Parse start angle - "starting at <angle>"
@@ -1437,6 +1937,14 @@ impl Parser {
         })
     }
 
+    /// Pattern anchors read `<keyword> at` (starting at / center at) or just `at`
+    fn consume_anchor(&mut self, keyword: Token) -> Result<()> {
+        if self.peek() == Some(&keyword) {
+            self.advance();
+        }
+        self.consume(Token::At)
+    }
+
     fn expect_number_or_fraction(&mut self) -> Result<f64> {
         match self.peek() {
             Some(Token::Number(Some(n))) => {
@@ -1449,9 +1957,8 @@ impl Parser {
                 self.advance();
                 Ok(val)
             }
-            Some(Token::Number(None)) | Some(Token::Fraction(None)) => {
-                Err(ParseError::InvalidNumber)
-            }
+            Some(Token::Number(None)) => Err(ParseError::InvalidNumber),
+            Some(Token::Fraction(None)) => Err(self.error("fraction has a zero denominator")),
             Some(other) => Err(ParseError::UnexpectedToken {
                 expected: "number or fraction".to_string(),
                 got: format!("{:?}", other),
@@ -1480,25 +1987,45 @@ impl Parser {
         }
     }
 
+    /// Source text of the current token; empty without the source or at the end of input
     fn get_current_token_text(&self) -> String {
-        if self.tokens.get(self.position).is_some() {
-            // This would need the original input to work properly
-            // For now, return a placeholder
-            String::new()
-        } else {
-            String::new()
+        match (&self.source, self.tokens.get(self.position)) {
+            (Some(source), Some((_, span))) => source.get(span.clone()).unwrap_or("").to_string(),
+            _ => String::new(),
         }
     }
 }
 
-// Add missing token variants for the new DSL
-// These should be added to the Token enum in lexer/mod.rs
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lexer::lex;
 
+    #[test]
+    fn test_errors_report_source_line_and_column() {
+        // The blank line and block comment leave no newline tokens behind
+        let source = "units imperial\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\n\n/* roughing */\npocket rect 1.0 oops 0.25 at 2 2\n";
+        let err = Parser::new(lex(source)).with_source(source).parse().unwrap_err();
+        assert!(
+            matches!(err, ParseError::WithLocation { line: 5, column: 17, .. }),
+            "{}",
+            err
+        );
+        assert!(err.to_string().ends_with("at line 5, column 17"));
+
+        // Parser-raised messages quote the offending text
+        let source = "units imperial\n\n\n\npocket rect 1.0 1.0 0.25 at 2 2 corners round\n";
+        let err = Parser::new(lex(source)).with_source(source).parse().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expected 'dogbone', 'tbone', or 'none' after 'corners', found 'round' at line 5, column 41"
+        );
+
+        let source = "offset 60\ndrill 0.25 at 1 1 depth 0.2";
+        let err = Parser::new(lex(source)).with_source(source).parse().unwrap_err();
+        assert_eq!(err.to_string(), "unknown work offset: G60 at line 1, column 8");
+    }
+
     #[test]
     fn test_parse_new_dsl_syntax() {
         let input = r#"part housing-mod existing
@@ -1548,6 +2075,13 @@ cut Y+ 0.625 0.125 0.3 Z+"#;
         let token_types: Vec<_> = tokens.iter().map(|(t, _)| t).collect();
         assert!(matches!(token_types[2], Token::Fraction(Some(0.625))));
         assert!(matches!(token_types[3], Token::Fraction(Some(0.125))));
+
+        let source = "units imperial\npocket 1-1/2 3/0 1/4 at 1 1";
+        let err = Parser::new(lex(source)).with_source(source).parse().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "fraction has a zero denominator, found '3/0' at line 2, column 14"
+        );
     }
 
     #[test]
@@ -1560,6 +2094,30 @@ cut Y+ 0.625 0.125 0.3 Z+"#;
         assert!(matches!(&tokens[2].0, Token::Direction(s) if s == "Z+"));
     }
 
+    #[test]
+    fn test_tool_material_keywords() {
+        for (word, material) in [
+            ("hss", ToolMaterial::HSS),
+            ("carbide", ToolMaterial::Carbide),
+            ("cobalt", ToolMaterial::Cobalt),
+            ("ceramic", ToolMaterial::Ceramic),
+            ("coated", ToolMaterial::CoatedCarbide),
+            ("cbn", ToolMaterial::CBN),
+            ("diamond", ToolMaterial::Diamond),
+            ("pcd", ToolMaterial::Diamond),
+        ] {
+            let input = format!("tool 1 dia 0.25 length 1 flutes 4 {}", word);
+            let program = Parser::new(lex(&input)).parse().expect("should parse tool");
+
+            match &program.operations[0] {
+                Operation::ToolChange(tc) => {
+                    assert_eq!(tc.tool_data.as_ref().unwrap().material, material)
+                }
+                other => panic!("expected tool change, got {:?}", other),
+            }
+        }
+    }
+
     #[test]
     fn test_drill_v2_parsing() {
         let input = "drill 0.25 at 1.0 0.5 thru";
@@ -1571,6 +2129,12 @@ cut Y+ 0.625 0.125 0.3 Z+"#;
         assert_eq!(op.position.x, 1.0);
         assert_eq!(op.position.y, 0.5);
         assert!(matches!(op.depth, DrillDepth::Thru));
+        assert!(!op.spot);
+
+        let mut parser = Parser::new(lex("drill 1/4 at 1.0 0.5 depth 2.0 spot"));
+        let op = parser.parse_drill_v2().expect("should parse spotted drill");
+        assert!(matches!(op.depth, DrillDepth::Depth(d) if d == 2.0));
+        assert!(op.spot);
     }
 
     #[test]
@@ -1603,6 +2167,20 @@ cut Y+ 0.625 0.125 0.3 Z+"#;
         assert_eq!(op.depth, 0.25);
         assert_eq!(op.position.x, 0.5);
         assert_eq!(op.position.y, 0.5);
+        assert!(!op.adaptive);
+
+        let op = Parser::new(lex("pocket rect 3.0 0.75 0.5 at 2.0 1.0 finish 0.01 adaptive"))
+            .parse_pocket_v2()
+            .expect("should parse adaptive pocket");
+        assert!(op.adaptive);
+        assert_eq!(op.finish, Some(0.01));
+        assert_eq!((op.stepover, op.stepdown), (None, None));
+
+        let op = Parser::new(lex("pocket rect 2.0 1.5 0.25 at 0.5 0.5 stepover 0.15 stepdown 1/16"))
+            .parse_pocket_v2()
+            .expect("should parse engagement overrides");
+        assert_eq!(op.stepover, Some(0.15));
+        assert_eq!(op.stepdown, Some(0.0625));
     }
 
     #[test]
@@ -1617,4 +2195,318 @@ cut Y+ 0.625 0.125 0.3 Z+"#;
         assert!(matches!(&op.shape, PocketShape::Circle { diameter } if *diameter == 1.0));
         assert_eq!(op.depth, 0.25);
     }
+
+    #[test]
+    fn test_pocket_syntax_dispatch() {
+        let pocket = |source: &str| {
+            let mut ops = Parser::new(lex(source)).parse().expect(source).operations;
+            assert_eq!(ops.len(), 1, "{}", source);
+            ops.remove(0)
+        };
+        let sized = |source: &str| match pocket(source) {
+            Operation::PocketV2(p) => (p.shape, p.depth),
+            op => panic!("{} parsed as {:?}", source, op),
+        };
+        let outlined = |source: &str| match pocket(source) {
+            Operation::Pocket(p) => (p.geometry, p.depth),
+            op => panic!("{} parsed as {:?}", source, op),
+        };
+
+        // Sizes before `at`: rect, rectangle, or no keyword at all
+        let slot = (PocketShape::Rect { width: 2.0, height: 0.5 }, 0.25);
+        assert_eq!(sized("pocket rect 2 0.5 0.25 at 1 1"), slot);
+        assert_eq!(sized("pocket rectangle 2 0.5 0.25 at 1 1"), slot);
+        assert_eq!(sized("pocket 2 1/2 1/4 at 1 1"), slot);
+        assert_eq!(sized("pocket circle 1 0.25 at 1 1"), (PocketShape::Circle { diameter: 1.0 }, 0.25));
+        assert!(matches!(
+            pocket("pocket rectangle 0.5 0.5 0.125 pattern grid rows 2 cols 2 spacing 1.0 1.0 at 4.0 1.0"),
+            Operation::PocketPattern(_)
+        ));
+
+        // A shape placed straight away is an outline, either spelling
+        for keyword in ["rect", "rectangle"] {
+            let (geometry, depth) = outlined(&format!(
+                "pocket {} at x 1 y 0.75 width 2 height 0.5 depth 0.25",
+                keyword
+            ));
+            assert!(matches!(geometry, Geometry::Rect(r) if r.width == 2.0 && r.bottom_left.y == 0.75));
+            assert_eq!(depth, 0.25);
+        }
+        let (circle, _) = outlined("pocket circle at x 2 y 1 diameter 1 depth 0.25");
+        assert!(matches!(circle, Geometry::Circle(c) if c.diameter == 1.0));
+        assert!(matches!(
+            outlined("pocket polygon at 2 2 sides 6 radius 0.75 depth 0.25").0,
+            Geometry::Polygon(_)
+        ));
+
+        // A bad size reports against the sized syntax, not the outline's `at`
+        let err = Parser::new(lex("pocket rect wide 0.5 0.25 at 1 1")).parse().unwrap_err();
+        assert!(err.to_string().contains("number"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(feature = "ast-serde")]
+    fn test_program_json_round_trip() {
+        for source in [
+            include_str!("../../examples/bracket.swarf"),
+            include_str!("../../examples/patterns.swarf"),
+            include_str!("../../examples/m16-selector.swarf"),
+        ] {
+            let program = Parser::new(lex(source)).parse().unwrap();
+            let json = serde_json::to_string(&program).unwrap();
+            let back: Program = serde_json::from_str(&json).unwrap();
+            assert_eq!(back, program);
+        }
+    }
+
+    #[test]
+    fn test_line_comments_become_operations() {
+        let input = r#"; bracket job
+units imperial
+
+; flip part here
+drill 0.25 at 1.0 0.5 thru ; through both plates
+
+drill 0.25 at 2.0 0.5 thru"#;
+        let tokens = lex(input);
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().expect("should parse program with comments");
+        assert_eq!(program.header.units, Units::Imperial);
+        assert_eq!(
+            program.operations[0],
+            Operation::Comment("bracket job".to_string())
+        );
+        assert_eq!(
+            program.operations[1],
+            Operation::Comment("flip part here".to_string())
+        );
+        assert!(matches!(program.operations[2], Operation::DrillV2(_)));
+        assert_eq!(
+            program.operations[3],
+            Operation::Comment("through both plates".to_string())
+        );
+        assert!(matches!(program.operations[4], Operation::DrillV2(_)));
+        assert_eq!(program.operations.len(), 5);
+    }
+
+    #[test]
+    fn test_comments_inside_setup_block() {
+        let input = "setup {\n    zero left front top ; origin\n    material \"Aluminum 6061-T6\"\n}";
+        let tokens = lex(input);
+        let mut parser = Parser::new(tokens);
+
+        let setup = parser
+            .parse_setup_block()
+            .expect("should parse setup with comments");
+        assert_eq!(setup.material, Some("Aluminum 6061-T6".to_string()));
+    }
+
+    #[test]
+    fn test_profile_cutter_comp_keyword() {
+        let program = Parser::new(lex(
+            "profile outside rect at x 0 y 0 width 50 height 40 depth 3 feed 600 comp",
+        ))
+        .parse()
+        .expect("should parse");
+
+        assert!(matches!(
+            &program.operations[0],
+            Operation::Profile(p) if p.cutter_comp && p.side == CutSide::Outside
+        ));
+
+        let program = Parser::new(lex(
+            "profile inside circle at x 0 y 0 diameter 40 depth 3 lead 2.5",
+        ))
+        .parse()
+        .expect("should parse");
+
+        assert!(matches!(
+            &program.operations[0],
+            Operation::Profile(p) if !p.cutter_comp && p.lead_radius == Some(2.5)
+        ));
+    }
+
+    #[test]
+    fn test_chamfer_side_depth_and_width() {
+        let program = Parser::new(lex(
+            "chamfer outside rect 2.0 1.5 at 1.0 0.75 depth 0.04 width 0.03 angle 60\nchamfer 0.02 hole 0.25 at 1.0 1.0",
+        ))
+        .parse()
+        .expect("should parse");
+
+        let Operation::Chamfer(outside) = &program.operations[0] else {
+            panic!("expected a chamfer, got {:?}", program.operations[0]);
+        };
+        assert_eq!(outside.side, CutSide::Outside);
+        assert_eq!((outside.width, outside.depth, outside.tool_angle), (0.03, Some(0.04), 60.0));
+        assert_eq!(outside.geometry, ChamferGeometry::Rect { width: 2.0, height: 1.5 });
+
+        let Operation::Chamfer(hole) = &program.operations[1] else {
+            panic!("expected a chamfer, got {:?}", program.operations[1]);
+        };
+        assert_eq!((hole.side, hole.width, hole.depth, hole.tool_angle), (CutSide::Inside, 0.02, None, 90.0));
+
+        assert!(Parser::new(lex("chamfer outside rect 2.0 1.5 at 1.0 0.75")).parse().is_err());
+    }
+
+    #[test]
+    fn test_polygon_and_path_geometry() {
+        let program = Parser::new(lex(
+            "pocket polygon at 2 2 sides 6 radius 1 rotate 30 depth 0.25\nprofile outside path 0 0 -> 3 0 -> x 1.5 y 2 depth 0.1",
+        ))
+        .parse()
+        .expect("should parse");
+
+        let Operation::Pocket(PocketOp { geometry: Geometry::Polygon(hex), .. }) = &program.operations[0]
+        else {
+            panic!("expected a polygon pocket, got {:?}", program.operations[0]);
+        };
+        assert_eq!((hex.sides, hex.circumradius, hex.rotation), (6, 1.0, 30.0));
+        assert_eq!(hex.center, Position::new(2.0, 2.0));
+
+        assert!(matches!(
+            &program.operations[1],
+            Operation::Profile(ProfileOp { geometry: Geometry::Path(points), .. })
+                if points == &[Position::new(0.0, 0.0), Position::new(3.0, 0.0), Position::new(1.5, 2.0)]
+        ));
+    }
+
+    #[test]
+    fn test_return_and_end_statements() {
+        let input = "drill 0.25 at 1.0 0.5 thru\nreturn to X 0 Y 10\nend M02";
+        let tokens = lex(input);
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().expect("should parse return/end");
+        assert_eq!(program.operations.len(), 1);
+        assert_eq!(program.footer.return_to, Position::new(0.0, 10.0));
+        assert_eq!(program.footer.end_code, "M02");
+    }
+
+    #[test]
+    fn test_footer_defaults() {
+        let tokens = lex("return to 2.5 4");
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().expect("should parse return without end");
+        assert_eq!(program.footer.return_to, Position::new(2.5, 4.0));
+        assert_eq!(program.footer.end_code, "M30");
+    }
+
+    #[test]
+    fn test_drill_grid_pattern_keywords() {
+        let input = "drill 0.25 pattern grid rows 2 cols 3 spacing 1.0 0.5 at 1.0 1.0 depth 0.25";
+        let tokens = lex(input);
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().expect("should parse drill grid pattern");
+        match &program.operations[0] {
+            Operation::DrillPattern(op) => {
+                assert_eq!(op.diameter, 0.25);
+                assert!(matches!(op.depth, DrillDepth::Depth(d) if d == 0.25));
+                assert_eq!(
+                    op.pattern,
+                    Pattern::Grid {
+                        rows: 2,
+                        cols: 3,
+                        spacing_x: 1.0,
+                        spacing_y: 0.5,
+                        start_position: Position::new(1.0, 1.0),
+                    }
+                );
+            }
+            other => panic!("expected drill pattern, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_drill_bolt_circle_line_arc_patterns() {
+        let input = r#"drill 1/4 pattern bolt circle count 6 dia 2.0 at 3.0 2.0 thru
+drill 0.25 pattern line count 5 spacing 0.75 X+ at 0.5 0.5
+drill 0.25 pattern arc count 4 radius 1.0 at 5.0 1.0 starting at 0 to 90"#;
+        let tokens = lex(input);
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().expect("should parse pattern variants");
+        let patterns: Vec<_> = program
+            .operations
+            .iter()
+            .map(|op| match op {
+                Operation::DrillPattern(p) => p.pattern.clone(),
+                other => panic!("expected drill pattern, got {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(
+            patterns[0],
+            Pattern::BoltCircle {
+                count: 6,
+                diameter: 2.0,
+                center: Position::new(3.0, 2.0),
+                start_angle: 0.0,
+            }
+        );
+        assert_eq!(
+            patterns[1],
+            Pattern::Line {
+                count: 5,
+                spacing: 0.75,
+                direction: Direction::XPositive,
+                start_position: Position::new(0.5, 0.5),
+            }
+        );
+        assert_eq!(
+            patterns[2],
+            Pattern::Arc {
+                count: 4,
+                radius: 1.0,
+                center: Position::new(5.0, 1.0),
+                start_angle: 0.0,
+                end_angle: 90.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_pocket_pattern_without_at() {
+        let input = "pocket rect 0.5 0.5 0.125 pattern grid rows 2 cols 2 spacing 1.0 1.0 at 4.0 1.0";
+        let tokens = lex(input);
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().expect("should parse pocket pattern");
+        assert!(matches!(
+            &program.operations[0],
+            Operation::PocketPattern(op) if op.depth == 0.125
+        ));
+    }
+
+    #[test]
+    fn test_tap_thread_size_sets_pitch() {
+        let tap = |source: &str| match Parser::new(lex(source)).parse() {
+            Ok(Program { operations, .. }) => match &operations[0] {
+                Operation::Tap(t) => t.clone(),
+                other => panic!("expected a tap, got {:?}", other),
+            },
+            Err(e) => panic!("{}", e),
+        };
+
+        let t = tap("units imperial\ntap 1/4-20 at x 1 y 1 depth 0.4");
+        assert!((t.pitch - 0.05).abs() < 1e-9);
+        assert_eq!(t.thread.as_deref(), Some("1/4-20"));
+
+        let t = tap("units metric\ntap M6x1.0 at x 10 y 10 depth 12");
+        assert_eq!(t.pitch, 1.0);
+        // Coarse by default, converted to the program's units
+        let t = tap("units imperial\ntap M6 at x 1 y 1 depth 0.4");
+        assert!((t.pitch - 1.0 / 25.4).abs() < 1e-9);
+        // An explicit pitch still wins
+        let t = tap("units imperial\ntap 10-32 at x 1 y 1 depth 0.4 pitch 0.03");
+        assert_eq!(t.pitch, 0.03);
+
+        let err = Parser::new(lex("units imperial\ntap 1/4-19 at x 1 y 1 depth 0.4"))
+            .parse()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "unknown thread size: 1/4-19 at line 2, column 5");
+    }
 }
@@ -0,0 +1,283 @@
+//! GRBL post-processor
+//!
+//! GRBL (hobby routers and 3018-style mills) has no canned cycles, no tool changer,
+//! no tool length offsets from a table, and rejects `%`/`O` program markers.
+//! Cycles are expanded to long-form and unsupported words are stripped.
+
+use crate::codegen::GCodeOutput;
+use crate::post::{
+    extract_word, g81_to_long_form, g82_to_long_form, g83_to_long_form, PostProcessor,
+};
+
+/// Words GRBL's parser rejects; they carry no motion so they can be dropped
+const UNSUPPORTED_WORDS: [&str; 5] = ["G43", "G64", "G80", "G98", "G99"];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrblPost {
+    /// Emit `N` words; GRBL accepts them but senders usually don't want them
+    line_numbers: bool,
+}
+
+/// R plane, depth and peck for a cycle block missing those words, in inches
+const INCH_CYCLE_DEFAULTS: (f64, f64, f64) = (0.1, -0.5, 0.25);
+
+/// The same in millimetres, once the program selects `G21`
+const MM_CYCLE_DEFAULTS: (f64, f64, f64) = (2.5, -12.5, 6.0);
+
+/// A drilling cycle that stays modal until `G80`
+#[derive(Debug, Clone, Copy)]
+enum Cycle {
+    Drill { r: f64, z: f64, f: f64 },
+    Dwell { r: f64, z: f64, p: f64, f: f64 },
+    Peck { r: f64, z: f64, q: f64, f: f64 },
+    Tap,
+}
+
+impl GrblPost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_line_numbers(mut self) -> Self {
+        self.line_numbers = true;
+        self
+    }
+
+    fn expand(cycle: Cycle, x: f64, y: f64) -> Vec<String> {
+        match cycle {
+            Cycle::Drill { r, z, f } => g81_to_long_form(x, y, r, z.abs(), f),
+            Cycle::Dwell { r, z, p, f } => g82_to_long_form(x, y, r, z.abs(), p, f),
+            Cycle::Peck { r, z, q, f } => g83_to_long_form(x, y, r, z.abs(), q, f),
+            Cycle::Tap => vec![format!(
+                "; WARNING: GRBL cannot rigid tap - tap by hand at X{:.4} Y{:.4}",
+                x, y
+            )],
+        }
+    }
+}
+
+impl PostProcessor for GrblPost {
+    fn process(&self, input: &GCodeOutput) -> GCodeOutput {
+        let mut output_lines = vec!["; GRBL compatible output".to_string()];
+        let mut last_x = 0.0;
+        let mut last_y = 0.0;
+        let mut last_f = 0.0;
+        let mut active: Option<Cycle> = None;
+        let mut defaults = INCH_CYCLE_DEFAULTS;
+
+        for line in input.lines() {
+            let trimmed = line.trim();
+
+            // Program markers and O-numbers are rejected by GRBL
+            if trimmed == "%" || trimmed.starts_with('O') {
+                continue;
+            }
+            if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('(') {
+                output_lines.push(trimmed.to_string());
+                continue;
+            }
+
+            // Strip line number and trailing comment
+            let code = match trimmed.split_once(' ') {
+                Some((n, rest)) if n.starts_with('N') => rest,
+                _ => trimmed,
+            };
+            let code = code.split(';').next().unwrap_or("").trim();
+
+            if let Some(x) = extract_word(code, 'X') {
+                last_x = x;
+            }
+            if let Some(y) = extract_word(code, 'Y') {
+                last_y = y;
+            }
+
+            let words: Vec<&str> = code.split_whitespace().collect();
+            let has = |w: &str| words.contains(&w);
+
+            // G80 is still meaningful on the safety line, but with cycles expanded it's noise
+            if has("G80") {
+                active = None;
+            }
+
+            if has("G21") {
+                defaults = MM_CYCLE_DEFAULTS;
+            } else if has("G20") {
+                defaults = INCH_CYCLE_DEFAULTS;
+            }
+
+            let (default_r, default_z, default_q) = defaults;
+            let r = extract_word(code, 'R').unwrap_or(default_r);
+            let z = extract_word(code, 'Z').unwrap_or(default_z);
+            let f = extract_word(code, 'F').unwrap_or(last_f);
+            let cycle = if has("G81") {
+                Some(Cycle::Drill { r, z, f })
+            } else if has("G82") {
+                // Dwell is in seconds whatever the units
+                let p = extract_word(code, 'P').unwrap_or(0.5);
+                Some(Cycle::Dwell { r, z, p, f })
+            } else if has("G83") || has("G73") {
+                // G73 chip-break becomes full-retract pecking
+                let q = extract_word(code, 'Q').unwrap_or(default_q);
+                Some(Cycle::Peck { r, z, q, f })
+            } else if has("G84") {
+                Some(Cycle::Tap)
+            } else {
+                None
+            };
+
+            if let Some(cycle) = cycle {
+                last_f = f;
+                active = Some(cycle);
+                output_lines.extend(Self::expand(cycle, last_x, last_y));
+                continue;
+            }
+
+            // An XY move inside a modal cycle drills another hole there
+            if let Some(cycle) = active {
+                if extract_word(code, 'X').is_some() || extract_word(code, 'Y').is_some() {
+                    output_lines.extend(Self::expand(cycle, last_x, last_y));
+                    continue;
+                }
+            }
+
+            // No tool changer: stop the spindle and pause for a manual change
+            if let Some(tool) = words.iter().find(|w| w.starts_with('T')) {
+                if has("M06") || has("M6") {
+                    output_lines.push("M05".to_string());
+                    output_lines.push(format!("M00 ; Change to {}", tool));
+                    continue;
+                }
+            }
+
+            if let Some(f) = extract_word(code, 'F') {
+                last_f = f;
+            }
+
+            // No through-spindle output on a hobby controller; flood is the nearest
+            let kept: Vec<&str> = words
+                .iter()
+                .copied()
+                .filter(|w| !UNSUPPORTED_WORDS.contains(w) && !w.starts_with('H'))
+                .map(|w| if w == "M51" { "M08" } else { w })
+                .collect();
+            if !kept.is_empty() {
+                output_lines.push(kept.join(" "));
+            }
+        }
+
+        // Number only real blocks, never comments
+        let mut line_num = 10;
+        if self.line_numbers {
+            for line in output_lines.iter_mut() {
+                if !line.is_empty() && !line.starts_with(';') && !line.starts_with('(') {
+                    *line = format!("N{} {}", line_num, line);
+                    line_num += 10;
+                }
+            }
+        }
+
+        let mut output = GCodeOutput::numbered_from(line_num, 10);
+        output.extend(output_lines);
+        output
+    }
+
+    fn name(&self) -> &str {
+        "GRBL"
+    }
+
+    fn supports_canned_cycles(&self) -> bool {
+        false // We expand them to long-form
+    }
+
+    fn supports_subroutines(&self) -> bool {
+        false
+    }
+
+    fn use_line_numbers(&self) -> bool {
+        self.line_numbers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle_input() -> GCodeOutput {
+        let mut input = GCodeOutput::numbered_from(130, 10);
+        input.extend([
+            "%",
+            "O1000",
+            "N0005 G90 G17 G40 G49 G80",
+            "N0010 T1 M06",
+            "N0020 G43 H1",
+            "N0030 G00 X1.000 Y0.500",
+            "N0040 G83 Z-0.550 R0.100 Q0.250 F15.0",
+            "N0050 G00 X2.000 Y0.500",
+            "N0060 G80",
+            "N0070 G00 X1.0000 Y1.0000",
+            "N0080 G81 R0.1 Z-0.2500 F12.0",
+            "N0090 G80",
+            "N0100 G00 X3.000 Y3.000",
+            "N0110 G84 Z-0.500 R0.100 F25.00",
+            "N0120 G80",
+        ]);
+        input
+    }
+
+    #[test]
+    fn test_grbl_expands_all_cycles() {
+        let output = GrblPost::new().process(&cycle_input());
+
+        for code in ["G73", "G80", "G81", "G82", "G83", "G84"] {
+            assert!(
+                !output.lines().any(|l| l.contains(code)),
+                "{} survived GRBL post",
+                code
+            );
+        }
+
+        // Both modal G83 holes are drilled
+        assert!(output.lines().any(|l| l == "G00 X1.0000 Y0.5000"));
+        assert!(output.lines().any(|l| l == "G00 X2.0000 Y0.5000"));
+        assert!(output
+            .lines()
+            .any(|l| l.contains("G01 Z-0.2500 F12.0")));
+        assert!(output
+            .lines()
+            .any(|l| l.contains("GRBL cannot rigid tap")));
+    }
+
+    #[test]
+    fn test_grbl_cycle_defaults_follow_units() {
+        let mut input = GCodeOutput::new();
+        input.extend(["G21 G90", "G00 X10.000 Y10.000", "G83 F100.0", "G80"]);
+        let output = GrblPost::new().process(&input);
+
+        // A bare metric peck cycle gets a 2.5 mm R plane and 6 mm pecks to 12.5 mm
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.contains(&"G00 Z2.5000"));
+        assert!(lines.contains(&"G01 Z-6.0000 F100.0"));
+        assert!(lines.contains(&"G01 Z-12.5000 F100.0"));
+        assert!(!lines.iter().any(|l| l.contains("Z0.1000")));
+    }
+
+    #[test]
+    fn test_grbl_strips_unsupported_words() {
+        let output = GrblPost::new().process(&cycle_input());
+
+        assert!(!output.lines().any(|l| l == "%" || l.starts_with('O')));
+        assert!(!output
+            .lines()
+            .any(|l| l.contains("M06") || l.contains("G43")));
+        assert!(output.lines().any(|l| l == "M00 ; Change to T1"));
+        assert!(output.lines().any(|l| l == "G90 G17 G40 G49"));
+        assert!(!output.lines().any(|l| l.starts_with('N')));
+
+        let numbered = GrblPost::new().with_line_numbers().process(&cycle_input());
+        assert!(numbered.lines().any(|l| l == "N20 M05"));
+
+        let mut through = GCodeOutput::new();
+        through.emit("M51");
+        assert!(GrblPost::new().process(&through).lines().any(|l| l == "M08"));
+    }
+}
@@ -4,45 +4,67 @@
 //! We'll use standard cycles but add Haas-specific features where beneficial.
 
 use crate::codegen::GCodeOutput;
-use crate::post::PostProcessor;
+use crate::post::{extract_word, PostProcessor};
 
 pub struct HaasPost;
 
 impl PostProcessor for HaasPost {
     fn process(&self, input: &GCodeOutput) -> GCodeOutput {
-        let mut output_lines = vec![
-            "%".to_string(),
-            "(HAAS CNC PROGRAM)".to_string(),
-            "G20 ; Inches mode".to_string(),
-            "G17 ; XY plane".to_string(),
-            "G40 ; Cancel cutter comp".to_string(),
-            "G49 ; Cancel tool length comp".to_string(),
-            "G80 ; Cancel canned cycles".to_string(),
-            "G90 ; Absolute positioning".to_string(),
-            "G94 ; Feed per minute".to_string(),
-            "G98 ; Return to initial plane (Haas default)".to_string(),
-            "".to_string(),
-        ];
+        let mut output = GCodeOutput::numbered_from(input.line_number, input.step);
+        output.extend([
+            "%",
+            "(HAAS CNC PROGRAM)",
+            "G20 ; Inches mode",
+            "G17 ; XY plane",
+            "G40 ; Cancel cutter comp",
+            "G49 ; Cancel tool length comp",
+            "G80 ; Cancel canned cycles",
+            "G90 ; Absolute positioning",
+            "G94 ; Feed per minute",
+            "G98 ; Return to initial plane (Haas default)",
+            "",
+        ]);
 
         // Copy input lines with potential Haas optimizations
-        for line in &input.lines {
+        let mut last_s = None;
+        let mut rigid_engaged = false;
+        let mut through_spindle = false;
+        for line in input.lines() {
             // Haas is mostly compatible, just pass through
             // Could add specific optimizations here like:
             // - G73 high-speed peck instead of G83 for certain materials
-            // - G84 rigid tapping (already using G84)
-            output_lines.push(line.clone());
+            let code = line.trim();
+            if !code.starts_with(';') {
+                last_s = extract_word(code, 'S').or(last_s);
+
+                // Without M29 a Haas runs G84 as floating-holder tapping
+                if code.contains("G84") && !rigid_engaged {
+                    output.push_line(&match last_s {
+                        Some(rpm) => format!("M29 S{:.0} ; Rigid tapping", rpm),
+                        None => "M29 ; Rigid tapping".to_string(),
+                    });
+                }
+                rigid_engaged = code.contains("M29");
+
+                // Through-spindle coolant is M88 on a Haas, and needs its own M89 to stop
+                let words: Vec<&str> = code.split_whitespace().collect();
+                if words.contains(&"M51") {
+                    through_spindle = true;
+                    output.push_line(&line.replace("M51", "M88"));
+                    continue;
+                }
+                if words.contains(&"M09") && through_spindle {
+                    through_spindle = false;
+                    output.push_line(&line.replace("M09", "M89"));
+                    continue;
+                }
+            }
+            output.push_line(line);
         }
 
         // Add program end
-        output_lines.push("".to_string());
-        output_lines.push("M30 ; Program end and rewind".to_string());
-        output_lines.push("%".to_string());
-
-        GCodeOutput {
-            lines: output_lines,
-            line_number: input.line_number,
-            step: input.step,
-        }
+        output.extend(["", "M30 ; Program end and rewind", "%"]);
+        output
     }
 
     fn name(&self) -> &str {
@@ -57,3 +79,45 @@ impl PostProcessor for HaasPost {
         true // Haas supports subroutines
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rigid_tapping_injected_before_g84() {
+        let mut input = GCodeOutput::new();
+        input.emit("S600 M03");
+        input.emit("G00 X1.000 Y1.000");
+        input.emit("G84 Z-0.500 R0.100 F12.00");
+        input.emit("G80");
+
+        let output = HaasPost.process(&input).to_string();
+        let m29 = output.find("M29 S600").expect("M29 should be injected");
+        assert!(m29 < output.find("G84").unwrap());
+
+        // Codegen that already engaged rigid tapping isn't doubled up
+        let mut rigid = GCodeOutput::new();
+        rigid.emit("M29 S600");
+        rigid.emit("G84 Z-0.500 R0.100 F0.0500");
+        assert_eq!(HaasPost.process(&rigid).to_string().matches("M29").count(), 1);
+    }
+
+    #[test]
+    fn test_through_spindle_coolant_uses_m88() {
+        let mut input = GCodeOutput::new();
+        input.emit("M51");
+        input.emit("G83 X1.000 Y1.000 Z-1.500 R0.100 Q0.250 F8.00");
+        input.emit("M09");
+        input.emit("M08");
+        input.emit("M09");
+
+        let output = HaasPost.process(&input).to_string();
+        let coolant: Vec<&str> = output
+            .lines()
+            .filter_map(|l| l.split_whitespace().nth(1))
+            .filter(|code| code.starts_with('M') && code != &"M30")
+            .collect();
+        assert_eq!(coolant, ["M88", "M89", "M08", "M09"]);
+    }
+}
@@ -0,0 +1,329 @@
+//! Heidenhain TNC post-processor (conversational / Klartext)
+//!
+//! A first pass: rapids and feeds become `L` blocks, arcs `CC` + `C`, tool
+//! changes and speeds `TOOL CALL`, and G81/G82/G83 drilling becomes
+//! `CYCL DEF 200 DRILLING` called at each hole with `M99`. Miscellaneous
+//! functions ride on the next positioning block. Work offsets are left to the
+//! active preset, and other canned cycles (tapping, boring) are written as
+//! comments for the programmer to fill in.
+
+use crate::codegen::GCodeOutput;
+use crate::post::{extract_word, PostProcessor};
+
+/// Program name written on `BEGIN PGM` and `END PGM`
+const PROGRAM_NAME: &str = "SWARF";
+
+pub struct HeidenhainPost;
+
+/// Modal state while walking the Fanuc program
+struct Modal {
+    x: f64,
+    y: f64,
+    z: f64,
+    feed: f64,
+    motion: &'static str,
+    incremental: bool,
+    /// Radius compensation on `L` and `C` blocks: R0, RL or RR
+    comp: &'static str,
+    /// M functions waiting for the next positioning block
+    pending: Vec<String>,
+    /// Drilling cycle defined and called at each new X/Y until G80
+    cycle: bool,
+}
+
+impl Modal {
+    /// Absolute value of an axis word, or `from` when the block doesn't move it
+    fn target(&self, code: &str, axis: char, from: f64) -> f64 {
+        match extract_word(code, axis) {
+            Some(v) if self.incremental => from + v,
+            Some(v) => v,
+            None => from,
+        }
+    }
+
+    /// Append the waiting M functions to a block
+    fn with_pending(&mut self, mut block: String) -> String {
+        for m in self.pending.drain(..) {
+            block.push(' ');
+            block.push_str(&m);
+        }
+        block
+    }
+}
+
+/// Cycle 200 for a Fanuc G81/G82/G83 block started from `initial_z`. Heights are
+/// measured from the surface, which is Z0 on the stock top, and the cycle
+/// returns to the 2nd set-up clearance as G98 returns to the initial plane.
+fn drilling_cycle(code: &str, cycle: &str, initial_z: f64, feed: f64) -> Vec<String> {
+    let r = extract_word(code, 'R').unwrap_or(initial_z);
+    let depth = extract_word(code, 'Z').unwrap_or(0.0);
+    let peck = match cycle {
+        "G83" => extract_word(code, 'Q').unwrap_or(depth.abs()),
+        _ => depth.abs(),
+    };
+    let dwell = match cycle {
+        "G82" => extract_word(code, 'P').unwrap_or(0.0),
+        _ => 0.0,
+    };
+    let feed = extract_word(code, 'F').unwrap_or(feed);
+    vec![
+        "CYCL DEF 200 DRILLING".to_string(),
+        format!("  Q200={:.4} ;SET-UP CLEARANCE", r),
+        format!("  Q201={:+.4} ;DEPTH", depth),
+        format!("  Q206={:.1} ;FEED RATE FOR PLNGNG", feed),
+        format!("  Q202={:.4} ;PLUNGING DEPTH", peck),
+        "  Q210=0 ;DWELL TIME AT TOP".to_string(),
+        "  Q203=+0 ;SURFACE COORDINATE".to_string(),
+        format!("  Q204={:.4} ;2ND SET-UP CLEARANCE", initial_z.max(r)),
+        format!("  Q211={} ;DWELL TIME AT DEPTH", dwell),
+    ]
+}
+
+impl PostProcessor for HeidenhainPost {
+    fn process(&self, input: &GCodeOutput) -> GCodeOutput {
+        let units = if input
+            .lines()
+            .any(|l| l.split_whitespace().any(|w| w == "G21"))
+        {
+            "MM"
+        } else {
+            "INCH"
+        };
+
+        let mut blocks = vec![format!("BEGIN PGM {} {}", PROGRAM_NAME, units)];
+        let mut modal = Modal {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            feed: 0.0,
+            motion: "G00",
+            incremental: false,
+            comp: "R0",
+            pending: Vec::new(),
+            cycle: false,
+        };
+
+        for line in input.lines() {
+            let code = match line.split_once(' ') {
+                Some((n, rest)) if n.starts_with('N') => rest,
+                _ => line,
+            };
+            let (code, comment) = match code.split_once(';') {
+                Some((code, comment)) => (code, comment.trim()),
+                None => (code, ""),
+            };
+            if !comment.is_empty() {
+                blocks.push(format!("; {}", comment));
+            }
+            let words: Vec<&str> = code.split_whitespace().collect();
+            let has = |w: &str| words.contains(&w);
+
+            modal.incremental = (modal.incremental || has("G91")) && !has("G90");
+            modal.feed = extract_word(code, 'F').unwrap_or(modal.feed);
+            if let Some(m) = ["G00", "G01", "G02", "G03"].into_iter().find(|m| has(m)) {
+                modal.motion = m;
+                modal.cycle = false;
+            }
+            if has("G80") {
+                modal.cycle = false;
+            }
+            for (word, comp) in [("G40", "R0"), ("G41", "RL"), ("G42", "RR")] {
+                if has(word) {
+                    modal.comp = comp;
+                }
+            }
+
+            // Tool and speed
+            if has("M06") || has("M6") {
+                if let Some(tool) = extract_word(code, 'T') {
+                    // Stop the last tool before calling the next
+                    if !modal.pending.is_empty() {
+                        let stop = modal.with_pending("L".to_string());
+                        blocks.push(stop);
+                    }
+                    blocks.push(format!("TOOL CALL {:.0} Z", tool));
+                }
+            }
+            if let Some(rpm) = extract_word(code, 'S') {
+                blocks.push(format!("TOOL CALL Z S{:.0}", rpm));
+            }
+            for &word in &words {
+                let m = match word {
+                    "M03" | "M3" => "M3",
+                    "M04" | "M4" => "M4",
+                    "M05" | "M5" => "M5",
+                    "M07" | "M7" | "M08" | "M8" | "M51" => "M8",
+                    "M09" | "M9" => "M9",
+                    _ => continue,
+                };
+                modal.pending.push(m.to_string());
+            }
+            if has("G04") {
+                blocks.push("CYCL DEF 9.0 DWELL TIME".to_string());
+                blocks.push(format!(
+                    "CYCL DEF 9.1 DWELL {}",
+                    extract_word(code, 'P').unwrap_or(0.0)
+                ));
+            }
+
+            let x = modal.target(code, 'X', modal.x);
+            let y = modal.target(code, 'Y', modal.y);
+
+            // Drilling cycles: define once, call at each hole
+            if let Some(&cycle) = words
+                .iter()
+                .find(|w| ["G73", "G81", "G82", "G83", "G84", "G85"].contains(w))
+            {
+                if matches!(cycle, "G81" | "G82" | "G83") {
+                    blocks.extend(drilling_cycle(code, cycle, modal.z, modal.feed));
+                    modal.cycle = true;
+                } else {
+                    blocks.push(format!(
+                        "; NOT TRANSLATED: {} - program this cycle by hand",
+                        code.trim()
+                    ));
+                    continue;
+                }
+            }
+            if modal.cycle {
+                if x != modal.x || y != modal.y || words.iter().any(|w| w.starts_with('G')) {
+                    let call = format!("L X{:+.4} Y{:+.4} R0 FMAX M99", x, y);
+                    blocks.push(modal.with_pending(call));
+                    modal.x = x;
+                    modal.y = y;
+                }
+                continue;
+            }
+
+            let z = modal.target(code, 'Z', modal.z);
+            let moved = x != modal.x || y != modal.y || z != modal.z;
+            let mut axes = String::new();
+            for (letter, to, from) in [('X', x, modal.x), ('Y', y, modal.y), ('Z', z, modal.z)] {
+                if to != from || extract_word(code, letter).is_some() {
+                    axes.push_str(&format!(" {}{:+.4}", letter, to));
+                }
+            }
+            let feed = match modal.motion {
+                "G00" => "FMAX".to_string(),
+                _ => format!("F{:.1}", modal.feed),
+            };
+
+            match modal.motion {
+                "G02" | "G03"
+                    if extract_word(code, 'I').is_some() || extract_word(code, 'J').is_some() =>
+                {
+                    let cx = modal.x + extract_word(code, 'I').unwrap_or(0.0);
+                    let cy = modal.y + extract_word(code, 'J').unwrap_or(0.0);
+                    let dr = if modal.motion == "G02" { "DR-" } else { "DR+" };
+                    blocks.push(format!("CC X{:+.4} Y{:+.4}", cx, cy));
+                    let arc = format!("C{} {} {} {}", axes, dr, modal.comp, feed);
+                    blocks.push(modal.with_pending(arc));
+                }
+                _ if moved || !axes.is_empty() => {
+                    let l = format!("L{} {} {}", axes, modal.comp, feed);
+                    blocks.push(modal.with_pending(l));
+                }
+                _ => {}
+            }
+            modal.x = x;
+            modal.y = y;
+            modal.z = z;
+
+            if has("M30") || has("M02") || has("M2") {
+                modal.pending.push("M30".to_string());
+            }
+        }
+
+        if !modal.pending.is_empty() {
+            let last = modal.with_pending("L".to_string());
+            blocks.push(last);
+        }
+        blocks.push(format!("END PGM {} {}", PROGRAM_NAME, units));
+
+        // Every block is numbered from 0; cycle parameters belong to the block above
+        let mut output = GCodeOutput::new();
+        let mut number = 0;
+        for block in blocks {
+            if block.starts_with("  ") {
+                output.push_line(&block);
+            } else {
+                output.push_line(&format!("{} {}", number, block));
+                number += 1;
+            }
+        }
+        output
+    }
+
+    fn name(&self) -> &str {
+        "Heidenhain TNC"
+    }
+
+    fn supports_canned_cycles(&self) -> bool {
+        true
+    }
+
+    fn supports_subroutines(&self) -> bool {
+        false
+    }
+
+    fn use_line_numbers(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_klartext_drilling_and_moves() {
+        let input: GCodeOutput = [
+            "N0010 G90 G17 G40 G49 G80",
+            "N0020 G21",
+            "N0025 M05",
+            "N0030 T1 M06",
+            "N0040 S2000 M03",
+            "N0050 M08",
+            "; DRILL CYCLE",
+            "N0060 G00 Z10.000",
+            "N0070 G00 X10.000 Y10.000",
+            "N0080 G83 Z-15.000 R2.000 Q5.000 F150.0",
+            "N0090 X90.000",
+            "N0100 G80",
+            "N0110 G01 Z-1.000 F100.0",
+            "N0120 G02 X20.000 Y10.000 I5.000 J0.000 F200.0",
+            "N0130 G84 X5.000 Z-8.000 R2.000 F250.0",
+            "N0140 M05",
+            "N0150 M30",
+        ]
+        .into_iter()
+        .collect();
+
+        let output = HeidenhainPost.render(&input).to_string();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "0 BEGIN PGM SWARF MM");
+        assert_eq!(lines[1], "1 L M5");
+        assert_eq!(lines[2], "2 TOOL CALL 1 Z");
+        assert_eq!(lines[3], "3 TOOL CALL Z S2000");
+
+        // Spindle and coolant start with the first move
+        assert!(lines.contains(&"5 L Z+10.0000 R0 FMAX M3 M8"));
+
+        let cycle = lines
+            .iter()
+            .position(|l| l.ends_with("CYCL DEF 200 DRILLING"))
+            .unwrap();
+        assert_eq!(lines[cycle + 1], "  Q200=2.0000 ;SET-UP CLEARANCE");
+        assert_eq!(lines[cycle + 2], "  Q201=-15.0000 ;DEPTH");
+        assert_eq!(lines[cycle + 4], "  Q202=5.0000 ;PLUNGING DEPTH");
+        assert_eq!(lines[cycle + 7], "  Q204=10.0000 ;2ND SET-UP CLEARANCE");
+        assert!(lines[cycle + 9].ends_with("L X+10.0000 Y+10.0000 R0 FMAX M99"));
+        assert!(lines[cycle + 10].ends_with("L X+90.0000 Y+10.0000 R0 FMAX M99"));
+
+        assert!(output.contains(" L Z-1.0000 R0 F100.0\n"));
+        assert!(output.contains(" CC X+95.0000 Y+10.0000\n"));
+        assert!(output.contains(" C X+20.0000 Y+10.0000 DR- R0 F200.0\n"));
+        assert!(output.contains("; NOT TRANSLATED: G84"));
+        assert!(output.ends_with("14 L M5 M30\n15 END PGM SWARF MM"));
+    }
+}
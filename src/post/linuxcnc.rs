@@ -1,44 +1,48 @@
 //! LinuxCNC post-processor
 //!
 //! LinuxCNC is mostly Fanuc-compatible with some extensions.
-//! We'll keep canned cycles but may need to adjust specific parameters.
+//! Canned cycles pass through untouched; comments are rewritten to the
+//! parenthesized form LinuxCNC documents, and path blending is enabled up front.
 
 use crate::codegen::GCodeOutput;
 use crate::post::PostProcessor;
 
 pub struct LinuxCncPost;
 
+/// O-number of the main program
+const PROGRAM_NUMBER: u32 = 1000;
+
 impl PostProcessor for LinuxCncPost {
     fn process(&self, input: &GCodeOutput) -> GCodeOutput {
-        let mut output_lines = vec![
-            "; LinuxCNC compatible output".to_string(),
-            "G20 ; Inches mode (change to G21 for metric)".to_string(),
-            "G17 ; XY plane".to_string(),
-            "G40 ; Cancel cutter comp".to_string(),
-            "G49 ; Cancel tool length comp".to_string(),
-            "G80 ; Cancel canned cycles".to_string(),
-            "G90 ; Absolute positioning".to_string(),
-            "G94 ; Feed per minute".to_string(),
-            "".to_string(),
-        ];
-
-        // Copy input lines
-        for line in &input.lines {
-            // LinuxCNC is mostly compatible, just pass through
-            output_lines.push(line.clone());
-        }
-
-        GCodeOutput {
-            lines: output_lines,
-            line_number: input.line_number,
-            step: input.step,
-        }
+        let mut output = GCodeOutput::numbered_from(input.line_number, input.step);
+        output.push_line("%");
+        output.push_line(&format!("O{} (LINUXCNC PROGRAM)", PROGRAM_NUMBER));
+        output.extend([
+            "G17 (XY plane)",
+            "G40 (Cancel cutter comp)",
+            "G49 (Cancel tool length comp)",
+            "G80 (Cancel canned cycles)",
+            "G90 (Absolute positioning)",
+            "G94 (Feed per minute)",
+            "G64 P0.001 (Path blending tolerance)",
+            "",
+        ]);
+
+        // Units come from the program's own G20/G21, so only comments need rewriting
+        output.extend(input.lines().map(normalize_comment));
+
+        output.push_line("%");
+        output
     }
 
     fn name(&self) -> &str {
         "LinuxCNC"
     }
 
+    fn program_number(&self) -> Option<u32> {
+        Some(PROGRAM_NUMBER)
+    }
+
     fn supports_canned_cycles(&self) -> bool {
         true
     }
@@ -47,3 +51,70 @@ impl PostProcessor for LinuxCncPost {
         true // LinuxCNC has O-subroutines
     }
 }
+
+/// Rewrite a `;` comment (whole-line or trailing) as a `( )` comment
+pub(crate) fn normalize_comment(line: &str) -> String {
+    let Some((code, comment)) = line.split_once(';') else {
+        return line.to_string();
+    };
+
+    // Nested parentheses end a LinuxCNC comment early
+    let comment: String = comment
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '(' => '[',
+            ')' => ']',
+            c => c,
+        })
+        .collect();
+
+    let code = code.trim_end();
+    if code.is_empty() {
+        format!("({})", comment)
+    } else {
+        format!("{} ({})", code, comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linuxcnc_header() {
+        let input: GCodeOutput = ["N0010 G21"].into_iter().collect();
+
+        let output = LinuxCncPost.process(&input);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines[0], "%");
+        assert!(lines[1].starts_with("O1000"));
+        assert!(lines.iter().any(|l| l.starts_with("G64 P0.001")));
+        assert_eq!(lines.last(), Some(&"%"));
+
+        // The program's own units are the only unit word
+        assert!(!lines.iter().any(|l| l.contains("G20")));
+    }
+
+    #[test]
+    fn test_linuxcnc_passes_g83_and_normalizes_comments() {
+        let input: GCodeOutput = [
+            "; DRILL (peck)",
+            "N0010 G00 X1.0000 Y0.5000",
+            "N0020 G83 R0.1 Z-0.55 Q0.25 F15.0",
+            "N0030 G80 ; cancel",
+        ]
+        .into_iter()
+        .collect();
+
+        let output = LinuxCncPost.process(&input);
+
+        assert!(output
+            .lines()
+            .any(|l| l == "N0020 G83 R0.1 Z-0.55 Q0.25 F15.0"));
+        assert!(output.lines().any(|l| l == "(DRILL [peck])"));
+        assert!(output.lines().any(|l| l == "N0030 G80 (cancel)"));
+        assert!(!output.lines().any(|l| l.contains(';')));
+    }
+}
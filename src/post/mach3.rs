@@ -3,7 +3,9 @@
 //! Mach3 has limited canned cycle support. We'll convert G83/G81 to long-form G-code.
 
 use crate::codegen::GCodeOutput;
-use crate::post::{g81_to_long_form, g82_to_long_form, g83_to_long_form, PostProcessor};
+use crate::post::{
+    extract_word, g81_to_long_form, g82_to_long_form, g83_to_long_form, PostProcessor,
+};
 
 pub struct Mach3Post;
 
@@ -14,7 +16,7 @@ impl PostProcessor for Mach3Post {
         let mut last_y = 0.0;
         let mut last_f = 0.0;
 
-        for line in &input.lines {
+        for line in input.lines() {
             let trimmed = line.trim();
 
             // Skip line numbers for processing
@@ -30,33 +32,17 @@ impl PostProcessor for Mach3Post {
             // Check for position commands
             if code.contains("G00") || code.contains("G01") {
                 // Extract X and Y coordinates
-                if let Some(x_pos) = code.find('X') {
-                    if let Some(end) =
-                        code[x_pos + 1..].find(|c: char| c.is_whitespace() || c == 'Y' || c == 'Z')
-                    {
-                        if let Ok(x) = code[x_pos + 1..x_pos + 1 + end].parse::<f64>() {
-                            last_x = x;
-                        }
-                    }
-                }
-                if let Some(y_pos) = code.find('Y') {
-                    if let Some(end) =
-                        code[y_pos + 1..].find(|c: char| c.is_whitespace() || c == 'Z')
-                    {
-                        if let Ok(y) = code[y_pos + 1..y_pos + 1 + end].parse::<f64>() {
-                            last_y = y;
-                        }
-                    }
-                }
+                last_x = extract_word(code, 'X').unwrap_or(last_x);
+                last_y = extract_word(code, 'Y').unwrap_or(last_y);
             }
 
             // Detect G83 cycles
             if code.contains("G83") {
                 // Parse G83 parameters from the line
-                let r = extract_param(code, 'R').unwrap_or(0.1);
-                let z = extract_param(code, 'Z').unwrap_or(-0.5);
-                let q = extract_param(code, 'Q').unwrap_or(0.25);
-                let _f = extract_param(code, 'F').unwrap_or(last_f);
+                let r = extract_word(code, 'R').unwrap_or(0.1);
+                let z = extract_word(code, 'Z').unwrap_or(-0.5);
+                let q = extract_word(code, 'Q').unwrap_or(0.25);
+                let _f = extract_word(code, 'F').unwrap_or(last_f);
 
                 // Convert to long-form and add
                 let long_form = g83_to_long_form(last_x, last_y, r, z.abs(), q, last_f);
@@ -68,9 +54,9 @@ impl PostProcessor for Mach3Post {
 
             // Detect G81 cycles
             if code.contains("G81") {
-                let r = extract_param(code, 'R').unwrap_or(0.1);
-                let z = extract_param(code, 'Z').unwrap_or(-0.5);
-                let f = extract_param(code, 'F').unwrap_or(last_f);
+                let r = extract_word(code, 'R').unwrap_or(0.1);
+                let z = extract_word(code, 'Z').unwrap_or(-0.5);
+                let f = extract_word(code, 'F').unwrap_or(last_f);
 
                 // Convert to long-form and add
                 let long_form = g81_to_long_form(last_x, last_y, r, z.abs(), f);
@@ -82,10 +68,10 @@ impl PostProcessor for Mach3Post {
 
             // Detect G82 cycles (drill with dwell)
             if code.contains("G82") {
-                let r = extract_param(code, 'R').unwrap_or(0.1);
-                let z = extract_param(code, 'Z').unwrap_or(-0.5);
-                let p = extract_param(code, 'P').unwrap_or(0.5); // Dwell time in seconds
-                let f = extract_param(code, 'F').unwrap_or(last_f);
+                let r = extract_word(code, 'R').unwrap_or(0.1);
+                let z = extract_word(code, 'Z').unwrap_or(-0.5);
+                let p = extract_word(code, 'P').unwrap_or(0.5); // Dwell time in seconds
+                let f = extract_word(code, 'F').unwrap_or(last_f);
 
                 // Convert to long-form with dwell
                 let long_form = g82_to_long_form(last_x, last_y, r, z.abs(), p, f);
@@ -97,10 +83,10 @@ impl PostProcessor for Mach3Post {
 
             // Detect G73 cycles (high-speed peck)
             if code.contains("G73") {
-                let r = extract_param(code, 'R').unwrap_or(0.1);
-                let z = extract_param(code, 'Z').unwrap_or(-0.5);
-                let q = extract_param(code, 'Q').unwrap_or(0.25);
-                let f = extract_param(code, 'F').unwrap_or(last_f);
+                let r = extract_word(code, 'R').unwrap_or(0.1);
+                let z = extract_word(code, 'Z').unwrap_or(-0.5);
+                let q = extract_word(code, 'Q').unwrap_or(0.25);
+                let f = extract_word(code, 'F').unwrap_or(last_f);
 
                 // Convert G73 to G83-style (full retract) for Mach3 compatibility
                 // G73 is chip-breaking (short retract), G83 is full retract
@@ -113,34 +99,26 @@ impl PostProcessor for Mach3Post {
             }
 
             // Check for feed rate
-            if let Some(f) = extract_param(code, 'F') {
+            if let Some(f) = extract_word(code, 'F') {
                 last_f = f;
             }
 
             // Add non-cycle lines as-is (but skip G80 - cancel cycle)
             if !code.contains("G80") {
-                output_lines.push(line.clone());
+                output_lines.push(line.to_string());
             }
         }
 
-        // Renumber lines
-        let mut renumbered = Vec::new();
-        let mut line_num = 10;
-        for line in output_lines {
+        // Renumber lines; comments don't get line numbers
+        let mut output = GCodeOutput::new();
+        for line in &output_lines {
             if line.starts_with(';') || line.starts_with('(') {
-                // Comments don't get line numbers
-                renumbered.push(line);
+                output.push_line(line);
             } else {
-                renumbered.push(format!("N{:04} {}", line_num, line));
-                line_num += 10;
+                output.emit(line);
             }
         }
-
-        GCodeOutput {
-            lines: renumbered,
-            line_number: line_num,
-            step: 10,
-        }
+        output
     }
 
     fn name(&self) -> &str {
@@ -156,20 +134,6 @@ impl PostProcessor for Mach3Post {
     }
 }
 
-/// Extract a parameter value from G-code line
-fn extract_param(line: &str, param: char) -> Option<f64> {
-    if let Some(pos) = line.find(param) {
-        let start = pos + 1;
-        let end = line[start..]
-            .find(|c: char| c.is_whitespace() || c == 'R' || c == 'Z' || c == 'Q' || c == 'F')
-            .map(|i| start + i)
-            .unwrap_or(line.len());
-        line[start..end].parse::<f64>().ok()
-    } else {
-        None
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -177,72 +141,81 @@ mod tests {
 
     #[test]
     fn test_mach3_converts_g83() {
-        let input = GCodeOutput {
-            lines: vec![
-                "N0010 G00 X1.0000 Y0.5000".to_string(),
-                "N0020 G83 R0.1 Z-0.55 Q0.25 F15.0".to_string(),
-                "N0030 G80".to_string(),
-                "N0040 G00 Z0.1".to_string(),
-            ],
-            line_number: 50,
-            step: 10,
-        };
+        let input = [
+            "N0010 G00 X1.0000 Y0.5000",
+            "N0020 G83 R0.1 Z-0.55 Q0.25 F15.0",
+            "N0030 G80",
+            "N0040 G00 Z0.1",
+        ]
+        .into_iter()
+        .collect::<GCodeOutput>();
 
         let post = Mach3Post;
         let output = post.process(&input);
 
         // Should have expanded G83 to long-form
-        assert!(output.lines.iter().any(|l| l.contains("G01 Z-0.2500")));
-        assert!(output.lines.iter().any(|l| l.contains("G00 Z0.1000")));
+        assert!(output.lines().any(|l| l.contains("G01 Z-0.2500")));
+        assert!(output.lines().any(|l| l.contains("G00 Z0.1000")));
 
         // Should NOT have G83 or G80
-        assert!(!output.lines.iter().any(|l| l.contains("G83")));
-        assert!(!output.lines.iter().any(|l| l.contains("G80")));
+        assert!(!output.lines().any(|l| l.contains("G83")));
+        assert!(!output.lines().any(|l| l.contains("G80")));
+    }
+
+    #[test]
+    fn test_mach3_cycle_after_packed_move() {
+        let input = [
+            "N0010 G00X-1.5Y2.0",
+            "N0020 G81R0.1Z-0.25F10.",
+            "N0030 G80",
+        ]
+        .into_iter()
+        .collect::<GCodeOutput>();
+
+        let output = Mach3Post.process(&input);
+        assert!(output.lines().any(|l| l.ends_with("G00 X-1.5000 Y2.0000")));
+        assert!(output.lines().any(|l| l.ends_with("G01 Z-0.2500 F10.0")));
     }
 
     #[test]
     fn test_mach3_converts_g82() {
-        let input = GCodeOutput {
-            lines: vec![
-                "N0010 G00 X1.0000 Y0.5000".to_string(),
-                "N0020 G82 R0.1 Z-0.25 P0.5 F15.0".to_string(),
-                "N0030 G80".to_string(),
-            ],
-            line_number: 40,
-            step: 10,
-        };
+        let input = [
+            "N0010 G00 X1.0000 Y0.5000",
+            "N0020 G82 R0.1 Z-0.25 P0.5 F15.0",
+            "N0030 G80",
+        ]
+        .into_iter()
+        .collect::<GCodeOutput>();
 
         let post = Mach3Post;
         let output = post.process(&input);
 
         // Should have G04 dwell
-        assert!(output.lines.iter().any(|l| l.contains("G04")));
+        assert!(output.lines().any(|l| l.contains("G04")));
 
         // Should NOT have G82 or G80
-        assert!(!output.lines.iter().any(|l| l.contains("G82")));
-        assert!(!output.lines.iter().any(|l| l.contains("G80")));
+        assert!(!output.lines().any(|l| l.contains("G82")));
+        assert!(!output.lines().any(|l| l.contains("G80")));
     }
 
     #[test]
     fn test_mach3_converts_g73() {
-        let input = GCodeOutput {
-            lines: vec![
-                "N0010 G00 X1.0000 Y0.5000".to_string(),
-                "N0020 G73 R0.1 Z-0.50 Q0.20 F12.0".to_string(),
-                "N0030 G80".to_string(),
-            ],
-            line_number: 40,
-            step: 10,
-        };
+        let input = [
+            "N0010 G00 X1.0000 Y0.5000",
+            "N0020 G73 R0.1 Z-0.50 Q0.20 F12.0",
+            "N0030 G80",
+        ]
+        .into_iter()
+        .collect::<GCodeOutput>();
 
         let post = Mach3Post;
         let output = post.process(&input);
 
         // G73 should be converted to G83-style (long-form)
-        assert!(output.lines.iter().any(|l| l.contains("G01 Z-0.2000")));
+        assert!(output.lines().any(|l| l.contains("G01 Z-0.2000")));
 
         // Should NOT have G73 or G80
-        assert!(!output.lines.iter().any(|l| l.contains("G73")));
-        assert!(!output.lines.iter().any(|l| l.contains("G80")));
+        assert!(!output.lines().any(|l| l.contains("G73")));
+        assert!(!output.lines().any(|l| l.contains("G80")));
     }
 }
@@ -5,9 +5,13 @@
 
 use crate::codegen::GCodeOutput;
 
+pub mod grbl;
 pub mod haas;
+pub mod heidenhain;
 pub mod linuxcnc;
 pub mod mach3;
+pub mod okuma;
+pub mod tormach;
 
 /// Post-processor trait - implemented for each controller type
 pub trait PostProcessor {
@@ -22,46 +26,101 @@ pub trait PostProcessor {
 
     /// Whether this controller supports subroutines/macros
     fn supports_subroutines(&self) -> bool;
+
+    /// O-number the post starts the main program with, if any
+    fn program_number(&self) -> Option<u32> {
+        None
+    }
+
+    /// Decimal places for axis words, or `None` to keep the generator's
+    fn coord_precision(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether blocks carry `N` words
+    fn use_line_numbers(&self) -> bool {
+        true
+    }
+
+    /// Convert the program, then apply this controller's number formatting
+    fn render(&self, input: &GCodeOutput) -> GCodeOutput {
+        self.process(input)
+            .reformat(self.coord_precision(), self.use_line_numbers())
+    }
 }
 
 /// Available post-processors
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PostProcessorType {
-    Generic,  // Fanuc-compatible (default)
-    Mach3,    // Mach3/Mach4 (limited canned cycles)
-    LinuxCNC, // LinuxCNC (full Fanuc + extensions)
-    Haas,     // Haas (Fanuc + Haas specifics)
+    Generic,    // Fanuc-compatible (default)
+    Mach3,      // Mach3/Mach4 (limited canned cycles)
+    LinuxCNC,   // LinuxCNC (full Fanuc + extensions)
+    Haas,       // Haas (Fanuc + Haas specifics)
+    Grbl,       // GRBL (no canned cycles, hobby machines)
+    Tormach,    // Tormach PathPilot (LinuxCNC + tool length comp)
+    Okuma,      // Okuma OSP (G15 H work offsets, G71/M53 cycle returns)
+    Heidenhain, // Heidenhain TNC conversational (first pass: moves and drilling)
 }
 
 impl PostProcessorType {
     /// Get the post-processor implementation
     pub fn get_processor(&self) -> Box<dyn PostProcessor> {
         match self {
-            PostProcessorType::Generic => Box::new(GenericPost),
+            PostProcessorType::Generic => Box::new(GenericPost::new()),
             PostProcessorType::Mach3 => Box::new(mach3::Mach3Post),
             PostProcessorType::LinuxCNC => Box::new(linuxcnc::LinuxCncPost),
             PostProcessorType::Haas => Box::new(haas::HaasPost),
+            PostProcessorType::Grbl => Box::new(grbl::GrblPost::new()),
+            PostProcessorType::Tormach => Box::new(tormach::TormachPost),
+            PostProcessorType::Okuma => Box::new(okuma::OkumaPost),
+            PostProcessorType::Heidenhain => Box::new(heidenhain::HeidenhainPost),
         }
     }
 }
 
 /// Generic/Fanuc-compatible post-processor (default)
-pub struct GenericPost;
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenericPost {
+    /// `O` number written at the top of the program
+    program_number: Option<u32>,
+}
+
+impl GenericPost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the program with `O{number}`. Subprograms are numbered from
+    /// `O1000`, skipping it.
+    pub fn with_program_number(mut self, number: u32) -> Self {
+        self.program_number = Some(number);
+        self
+    }
+}
 
 impl PostProcessor for GenericPost {
     fn process(&self, input: &GCodeOutput) -> GCodeOutput {
         // Generic is already the default format
-        GCodeOutput {
-            lines: input.lines.clone(),
-            line_number: input.line_number,
-            step: input.step,
+        let Some(number) = self.program_number else {
+            return input.clone();
+        };
+
+        let mut output = GCodeOutput::numbered_from(input.line_number, input.step);
+        output.push_line(&format!("O{:04}", number));
+        for line in input.lines() {
+            output.push_line(line);
         }
+        output
     }
 
     fn name(&self) -> &str {
         "Generic Fanuc"
     }
 
+    fn program_number(&self) -> Option<u32> {
+        self.program_number
+    }
+
     fn supports_canned_cycles(&self) -> bool {
         true
     }
@@ -71,6 +130,45 @@ impl PostProcessor for GenericPost {
     }
 }
 
+/// Value of a single-letter word (e.g. `F` in `G81 Z-0.25 F12.0`) in a block. Words
+/// needn't be separated (`G01X-1.5Y2.`), and comments, `;` to the end or in
+/// parentheses, are skipped.
+pub fn extract_word(code: &str, letter: char) -> Option<f64> {
+    let code = code.split(';').next().unwrap_or("");
+    let mut depth = 0;
+    for (i, c) in code.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 && c.eq_ignore_ascii_case(&letter) => {
+                let rest = &code[i + 1..];
+                let len = number_len(rest);
+                if let Ok(value) = rest[..len].parse::<f64>() {
+                    return Some(value);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Length of the signed decimal number (`-1.5`, `.25`, `2.`) at the start of `s`
+fn number_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut seen_point = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '+' | '-' if i == 0 => {}
+            '.' if !seen_point => seen_point = true,
+            _ if c.is_ascii_digit() => {}
+            _ => break,
+        }
+        len = i + 1;
+    }
+    len
+}
+
 /// Convert G83 peck drill to long-form G-code for controllers without canned cycles
 pub fn g83_to_long_form(
     x: f64,
@@ -145,6 +243,104 @@ pub fn g82_to_long_form(
 mod tests {
     use super::*;
 
+    /// Generic output for a control that wants three decimals and no line numbers
+    struct ShortPost;
+
+    impl PostProcessor for ShortPost {
+        fn process(&self, input: &GCodeOutput) -> GCodeOutput {
+            GenericPost::new().process(input)
+        }
+
+        fn name(&self) -> &str {
+            "Short"
+        }
+
+        fn supports_canned_cycles(&self) -> bool {
+            true
+        }
+
+        fn supports_subroutines(&self) -> bool {
+            true
+        }
+
+        fn coord_precision(&self) -> Option<usize> {
+            Some(3)
+        }
+
+        fn use_line_numbers(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_post_formatting_strips_line_numbers_and_rounds() {
+        let mut input = GCodeOutput::new();
+        input.push_line("%");
+        input.emit_comment("DRILL at X1.23456");
+        input.emit("G00 X1.23456 Y-0.00004 (X9.87654)");
+        input.emit("G83 Z-0.5500 R0.1000 Q0.2500 F15.0 ; peck X2.5");
+
+        let output = ShortPost.render(&input);
+        assert!(!output.lines().any(|l| l.starts_with('N')));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "%",
+                "; DRILL at X1.23456",
+                "G00 X1.235 Y0.000 (X9.87654)",
+                "G83 Z-0.550 R0.100 Q0.250 F15.0 ; peck X2.5",
+            ]
+        );
+
+        // The defaults renumber in order and leave the digits alone
+        input.push_line("M05");
+        input.emit("M30");
+        let generic = GenericPost::new().render(&input).to_string();
+        assert!(generic.contains("N0010 G00 X1.23456 Y-0.00004"));
+        assert!(generic.contains("N0030 M30"));
+        assert!(generic.contains("\nM05\n"));
+    }
+
+    #[test]
+    fn test_extract_word_reads_packed_words() {
+        let line = "G01X-1.5Y2.Z.25F12 (X9.0) ; Y8.0";
+        assert_eq!(extract_word(line, 'X'), Some(-1.5));
+        assert_eq!(extract_word(line, 'Y'), Some(2.0));
+        assert_eq!(extract_word(line, 'Z'), Some(0.25));
+        assert_eq!(extract_word(line, 'F'), Some(12.0));
+        assert_eq!(extract_word(line, 'R'), None);
+        assert_eq!(extract_word("G00 X1.0000 Y0.5000", 'Y'), Some(0.5));
+
+        // Posts built on it drill where a packed move left the tool
+        let input: GCodeOutput = [
+            "N0010 G01 X-1.5Y2.0 F20.0",
+            "N0020 G81 R0.1 Z-0.25 F10.0",
+            "N0030 G80",
+        ]
+        .into_iter()
+        .collect();
+        let output = grbl::GrblPost::new().process(&input);
+        assert!(output.lines().any(|l| l == "G00 X-1.5000 Y2.0000"));
+    }
+
+    #[test]
+    fn test_generic_program_number() {
+        let mut input = GCodeOutput::new();
+        input.emit_comment("PROGRAM START");
+        input.emit("T1 M06");
+        input.emit("M30");
+
+        // Off by default
+        assert_eq!(GenericPost::new().process(&input).to_string(), input.to_string());
+
+        let post = GenericPost::new().with_program_number(1234);
+        let output = post.render(&input).to_string();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[..2], ["O1234", "; PROGRAM START"]);
+        assert!(output.contains("N0010 T1 M06"));
+    }
+
     #[test]
     fn test_g83_long_form() {
         let lines = g83_to_long_form(1.0, 0.5, 0.1, 0.55, 0.25, 15.0);
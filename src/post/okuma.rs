@@ -0,0 +1,164 @@
+//! Okuma OSP post-processor
+//!
+//! OSP reads Fanuc-style motion but selects work offsets with `G15 H<n>` instead
+//! of G54-G59, applies tool length with `G56 H<n>` instead of G43, and dwells
+//! with `G04 F<seconds>`. Its drilling cycles return to the R point unless the
+//! block carries `M53`, which sends them back to the level set by `G71 Z`; each
+//! cycle gets the Z it starts from there, matching the initial-plane return of
+//! the Fanuc output. Comments are parenthesized.
+
+use crate::codegen::GCodeOutput;
+use crate::post::linuxcnc::normalize_comment;
+use crate::post::{extract_word, PostProcessor};
+
+/// Drilling cycles OSP runs with Fanuc's X/Y/Z/R/Q/F words
+const CYCLES: [&str; 4] = ["G73", "G81", "G82", "G83"];
+
+pub struct OkumaPost;
+
+impl OkumaPost {
+    /// One block's words in OSP form
+    fn translate(words: &[&str]) -> Vec<String> {
+        words
+            .iter()
+            .map(|&word| match word {
+                "G54" | "G55" | "G56" | "G57" | "G58" | "G59" => {
+                    let offset = word[1..].parse::<u32>().unwrap_or(54) - 53;
+                    format!("G15 H{}", offset)
+                }
+                "G43" => "G56".to_string(),
+                _ if words.contains(&"G04") && word.starts_with('P') => format!("F{}", &word[1..]),
+                _ => word.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl PostProcessor for OkumaPost {
+    fn process(&self, input: &GCodeOutput) -> GCodeOutput {
+        let mut output = GCodeOutput::numbered_from(input.line_number, input.step);
+        output.extend(["(OKUMA OSP PROGRAM)", ""]);
+
+        // Absolute Z the tool sits at, once a move has set it
+        let mut z: Option<f64> = None;
+        let mut incremental = false;
+        let mut in_cycle = false;
+        for line in input.lines() {
+            let (number, rest) = match line.split_once(' ') {
+                Some((n, rest)) if n.starts_with('N') => (Some(n), rest),
+                _ => (None, line),
+            };
+            let (code, comment) = match rest.split_once(';') {
+                Some((code, comment)) => (code, Some(comment)),
+                None => (rest, None),
+            };
+            let words: Vec<&str> = code.split_whitespace().collect();
+            if words.is_empty() {
+                output.push_line(&normalize_comment(line));
+                continue;
+            }
+            let has = |w: &str| words.contains(&w);
+
+            incremental = (incremental || has("G91")) && !has("G90");
+            let starts_cycle = words.iter().any(|w| CYCLES.contains(w));
+            if ["G00", "G01", "G02", "G03", "G80"].iter().any(|m| has(m)) {
+                in_cycle = false;
+            }
+
+            let mut block = Self::translate(&words);
+            if starts_cycle {
+                if let Some(z) = z {
+                    let mut level = format!("G71 Z{:.4}", z);
+                    if let Some(n) = number {
+                        level = format!("{} {}", n, level);
+                    }
+                    output.push_line(&level);
+                }
+                block.push("M53".to_string());
+                in_cycle = true;
+            } else if !in_cycle {
+                // A cycle feeds its own Z and comes back to where it started
+                if let Some(v) = extract_word(code, 'Z') {
+                    z = match (incremental, z) {
+                        (true, Some(z)) => Some(z + v),
+                        (true, None) => None,
+                        (false, _) => Some(v),
+                    };
+                }
+            }
+
+            let mut translated = block.join(" ");
+            if let Some(n) = number {
+                translated = format!("{} {}", n, translated);
+            }
+            if let Some(comment) = comment {
+                translated = format!("{} ;{}", translated, comment);
+            }
+            output.push_line(&normalize_comment(&translated));
+        }
+
+        output
+    }
+
+    fn name(&self) -> &str {
+        "Okuma OSP"
+    }
+
+    fn supports_canned_cycles(&self) -> bool {
+        true
+    }
+
+    fn supports_subroutines(&self) -> bool {
+        false // OSP calls subprograms with CALL, not M98
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osp_drilling_cycles_and_offsets() {
+        let input: GCodeOutput = [
+            "N0010 G90 G17 G40 G49 G80",
+            "N0020 G20",
+            "N0030 G55",
+            "N0040 T1 M06",
+            "N0050 G43 H1",
+            "; DRILL CYCLE",
+            "N0060 G00 Z1.000",
+            "N0070 G00 X1.000 Y1.000",
+            "N0080 G81 Z-0.250 R0.100 F10.0",
+            "N0090 X2.000",
+            "N0100 G80",
+            "N0110 G00 Z0.500 ; lower plane",
+            "N0120 G83 X3.000 Y1.000 Z-1.500 R0.100 Q0.250 F8.0",
+            "N0130 G80",
+            "N0140 G04 P0.5",
+            "N0150 M30",
+        ]
+        .into_iter()
+        .collect();
+
+        let output = OkumaPost.process(&input).to_string();
+        let lines: Vec<&str> = output.lines().collect();
+        let after =
+            |block: &str| lines[lines.iter().position(|l| l.starts_with(block)).unwrap() + 1];
+
+        assert!(output.contains("N0030 G15 H2\n"));
+        assert!(output.contains("N0050 G56 H1\n"));
+        assert!(output.contains("(DRILL CYCLE)"));
+        assert_eq!(after("N0070"), "N0080 G71 Z1.0000");
+        assert_eq!(after("N0080 G71"), "N0080 G81 Z-0.250 R0.100 F10.0 M53");
+        assert_eq!(after("N0080 G81"), "N0090 X2.000");
+
+        // The return level follows the Z the next cycle starts from
+        assert_eq!(after("N0110"), "N0120 G71 Z0.5000");
+        assert_eq!(
+            after("N0120 G71"),
+            "N0120 G83 X3.000 Y1.000 Z-1.500 R0.100 Q0.250 F8.0 M53"
+        );
+        assert!(lines.contains(&"N0110 G00 Z0.500 (lower plane)"));
+        assert!(lines.contains(&"N0140 G04 F0.5"));
+    }
+}
@@ -0,0 +1,86 @@
+//! Tormach PathPilot post-processor
+//!
+//! PathPilot runs on LinuxCNC, so canned cycles pass through and comments use
+//! the parenthesized form. It takes no `%` tape markers, and every tool change
+//! is followed by `G43 H<tool>` so the tool table's length offset is applied,
+//! even when the program was generated without length comp.
+
+use crate::codegen::GCodeOutput;
+use crate::post::linuxcnc::normalize_comment;
+use crate::post::{extract_word, PostProcessor};
+
+pub struct TormachPost;
+
+impl PostProcessor for TormachPost {
+    fn process(&self, input: &GCodeOutput) -> GCodeOutput {
+        let mut output = GCodeOutput::numbered_from(input.line_number, input.step);
+        output.extend([
+            "(TORMACH PATHPILOT PROGRAM)",
+            "G20 (Inch)",
+            "G17 G40 G49 G80 G90 G94 (Safe start)",
+            "",
+        ]);
+
+        let mut lines = input.lines().peekable();
+        while let Some(line) = lines.next() {
+            output.push_line(&normalize_comment(line));
+
+            // Length comp for the tool just loaded, unless the program already applies it
+            let code = line.split(';').next().unwrap_or("");
+            if code.split_whitespace().any(|w| w == "M06" || w == "M6")
+                && !lines.peek().is_some_and(|next| next.contains("G43"))
+            {
+                if let Some(tool) = extract_word(code, 'T') {
+                    output.push_line(&format!("G43 H{:.0} (Tool length offset)", tool));
+                }
+            }
+        }
+
+        output
+    }
+
+    fn name(&self) -> &str {
+        "Tormach PathPilot"
+    }
+
+    fn supports_canned_cycles(&self) -> bool {
+        true
+    }
+
+    fn supports_subroutines(&self) -> bool {
+        true // O-word subroutines, as on LinuxCNC
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_length_comp_follows_tool_change() {
+        let input: GCodeOutput = [
+            "; TOOL 1",
+            "N0010 T1 M06",
+            "N0020 G83 R0.1 Z-0.55 Q0.25 F15.0",
+            "N0030 T12 M06 ; spot drill",
+            "N0040 T3 M06",
+            "N0050 G43 H3",
+            "N0060 M30",
+        ]
+        .into_iter()
+        .collect();
+
+        let output = TormachPost.process(&input);
+        let lines: Vec<&str> = output.lines().collect();
+
+        let t1 = lines.iter().position(|l| *l == "N0010 T1 M06").unwrap();
+        assert_eq!(lines[t1 + 1], "G43 H1 (Tool length offset)");
+        let t12 = lines.iter().position(|l| *l == "N0030 T12 M06 (spot drill)").unwrap();
+        assert_eq!(lines[t12 + 1], "G43 H12 (Tool length offset)");
+        assert_eq!(lines.iter().filter(|l| l.contains("G43 H3")).count(), 1);
+
+        assert!(lines.contains(&"N0020 G83 R0.1 Z-0.55 Q0.25 F15.0"));
+        assert!(lines.contains(&"(TOOL 1)"));
+        assert!(!lines.iter().any(|l| l.contains('%') || l.contains(';')));
+    }
+}
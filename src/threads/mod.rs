@@ -0,0 +1,141 @@
+//! Standard thread sizes - pitch and tap drill for the unified (UNC/UNF) and
+//! metric coarse/fine threads a shop taps most, so `tap 1/4-20` needs no pitch
+
+use crate::ast::Units;
+
+/// One thread size, in its own units: inches for unified, millimeters for metric
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThreadSpec {
+    pub major: f64,
+    pub pitch: f64,
+    pub tap_drill: f64,
+    pub metric: bool,
+}
+
+/// Unified sizes: (size, threads per inch, major diameter, tap drill), inches
+const UNIFIED: &[(&str, u32, f64, f64)] = &[
+    // UNC
+    ("4", 40, 0.112, 0.089),
+    ("6", 32, 0.138, 0.1065),
+    ("8", 32, 0.164, 0.136),
+    ("10", 24, 0.190, 0.1495),
+    ("1/4", 20, 0.250, 0.201),
+    ("5/16", 18, 0.3125, 0.257),
+    ("3/8", 16, 0.375, 0.3125),
+    ("7/16", 14, 0.4375, 0.368),
+    ("1/2", 13, 0.500, 0.4219),
+    ("5/8", 11, 0.625, 0.5312),
+    ("3/4", 10, 0.750, 0.6562),
+    // UNF
+    ("4", 48, 0.112, 0.0935),
+    ("6", 40, 0.138, 0.113),
+    ("8", 36, 0.164, 0.136),
+    ("10", 32, 0.190, 0.159),
+    ("1/4", 28, 0.250, 0.213),
+    ("5/16", 24, 0.3125, 0.272),
+    ("3/8", 24, 0.375, 0.332),
+    ("7/16", 20, 0.4375, 0.3906),
+    ("1/2", 20, 0.500, 0.4531),
+    ("5/8", 18, 0.625, 0.5781),
+    ("3/4", 16, 0.750, 0.6875),
+];
+
+/// Metric sizes: (major diameter, pitch, tap drill), millimeters. The first entry
+/// for a diameter is its coarse pitch.
+const METRIC: &[(f64, f64, f64)] = &[
+    (2.0, 0.4, 1.6),
+    (2.5, 0.45, 2.05),
+    (3.0, 0.5, 2.5),
+    (4.0, 0.7, 3.3),
+    (5.0, 0.8, 4.2),
+    (6.0, 1.0, 5.0),
+    (8.0, 1.25, 6.8),
+    (8.0, 1.0, 7.0),
+    (10.0, 1.5, 8.5),
+    (10.0, 1.25, 8.8),
+    (10.0, 1.0, 9.0),
+    (12.0, 1.75, 10.2),
+    (12.0, 1.5, 10.5),
+    (12.0, 1.25, 10.8),
+    (16.0, 2.0, 14.0),
+    (16.0, 1.5, 14.5),
+    (20.0, 2.5, 17.5),
+    (20.0, 1.5, 18.5),
+];
+
+/// Look up a thread by designation: `1/4-20`, `#10-32` or `10-32`, `M6x1.0`,
+/// or `M6` for the coarse pitch
+pub fn lookup(designation: &str) -> Option<ThreadSpec> {
+    let designation = designation.trim();
+    if let Some(metric) = designation
+        .strip_prefix('M')
+        .or_else(|| designation.strip_prefix('m'))
+    {
+        let (major, pitch) = match metric.split_once(['x', 'X']) {
+            Some((major, pitch)) => (major.parse::<f64>().ok()?, Some(pitch.parse::<f64>().ok()?)),
+            None => (metric.parse::<f64>().ok()?, None),
+        };
+        let same = |a: f64, b: f64| (a - b).abs() < 1e-6;
+        return METRIC
+            .iter()
+            .find(|&&(d, p, _)| same(d, major) && pitch.is_none_or(|pitch| same(p, pitch)))
+            .map(|&(major, pitch, tap_drill)| ThreadSpec {
+                major,
+                pitch,
+                tap_drill,
+                metric: true,
+            });
+    }
+
+    let (size, tpi) = designation.trim_start_matches('#').split_once('-')?;
+    let tpi = tpi.parse::<u32>().ok()?;
+    UNIFIED
+        .iter()
+        .find(|&&(s, t, _, _)| s == size && t == tpi)
+        .map(|&(_, tpi, major, tap_drill)| ThreadSpec {
+            major,
+            pitch: 1.0 / tpi as f64,
+            tap_drill,
+            metric: false,
+        })
+}
+
+impl ThreadSpec {
+    /// The same thread measured in `units`
+    pub fn in_units(self, units: Units) -> ThreadSpec {
+        let scale = match (self.metric, units) {
+            (true, Units::Imperial) => 1.0 / 25.4,
+            (false, Units::Metric) => 25.4,
+            _ => 1.0,
+        };
+        ThreadSpec {
+            major: self.major * scale,
+            pitch: self.pitch * scale,
+            tap_drill: self.tap_drill * scale,
+            metric: units == Units::Metric,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_designations_resolve_pitch() {
+        let quarter_twenty = lookup("1/4-20").unwrap();
+        assert!((quarter_twenty.pitch - 0.05).abs() < 1e-9);
+        assert_eq!(quarter_twenty.tap_drill, 0.201);
+        assert_eq!(lookup("#10-32"), lookup("10-32"));
+        assert!((lookup("10-32").unwrap().pitch - 1.0 / 32.0).abs() < 1e-9);
+
+        let m6 = lookup("M6x1.0").unwrap();
+        assert_eq!((m6.pitch, m6.tap_drill), (1.0, 5.0));
+        assert_eq!(lookup("M6"), Some(m6));
+        assert_eq!(lookup("M10x1.25").unwrap().tap_drill, 8.8);
+        assert!((m6.in_units(Units::Imperial).pitch - 1.0 / 25.4).abs() < 1e-9);
+
+        assert_eq!(lookup("1/4-19"), None);
+        assert_eq!(lookup("M6x0.9"), None);
+    }
+}
@@ -45,6 +45,20 @@ pub enum ToolCoating {
     Diamond,
 }
 
+impl ToolCoating {
+    /// Coating name as the Black Book expects it
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToolCoating::None => "none",
+            ToolCoating::TiN => "TiN",
+            ToolCoating::TiAlN => "TiAlN",
+            ToolCoating::TiCN => "TiCN",
+            ToolCoating::AlTiN => "AlTiN",
+            ToolCoating::Diamond => "diamond",
+        }
+    }
+}
+
 /// Coolant type for tool
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
 pub enum CoolantType {
@@ -73,6 +87,12 @@ pub enum ToolMaterial {
     Cobalt,
     #[serde(rename = "ceramic", alias = "CERAMIC")]
     Ceramic,
+    #[serde(rename = "coated", alias = "COATED", alias = "coated_carbide")]
+    CoatedCarbide,
+    #[serde(rename = "cbn", alias = "CBN")]
+    CBN,
+    #[serde(rename = "diamond", alias = "DIAMOND", alias = "pcd", alias = "PCD")]
+    Diamond,
 }
 
 impl ToolMaterial {
@@ -83,6 +103,9 @@ impl ToolMaterial {
             ToolMaterial::Carbide => crate::ast::ToolMaterial::Carbide,
             ToolMaterial::Cobalt => crate::ast::ToolMaterial::Cobalt,
             ToolMaterial::Ceramic => crate::ast::ToolMaterial::Ceramic,
+            ToolMaterial::CoatedCarbide => crate::ast::ToolMaterial::CoatedCarbide,
+            ToolMaterial::CBN => crate::ast::ToolMaterial::CBN,
+            ToolMaterial::Diamond => crate::ast::ToolMaterial::Diamond,
         }
     }
 }
@@ -142,9 +165,19 @@ pub struct ToolDefinition {
     /// Optional: List of recommended materials
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recommended_materials: Option<Vec<String>>,
+
+    /// Optional: Whether the tool can plunge straight down (defaults to true for drills)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub center_cutting: Option<bool>,
 }
 
 impl ToolDefinition {
+    /// Whether pockets may plunge this tool straight to depth instead of ramping
+    pub fn is_center_cutting(&self) -> bool {
+        self.center_cutting
+            .unwrap_or(self.tool_type == ToolType::Drill)
+    }
+
     /// Get a numeric ID for G-code output (T-number)
     /// Extracts number from ID like "EM_125_4FL" → 1, or uses hash
     pub fn numeric_id(&self) -> u8 {
@@ -200,6 +233,14 @@ impl ToolLibrary {
         self.get_by_name(id_or_name)
     }
     
+    /// Tool a `tool` statement refers to: its ID or name if it has one, else its number
+    pub fn lookup(&self, tool_id: Option<&str>, tool_number: u8) -> Option<&ToolDefinition> {
+        match tool_id {
+            Some(id) => self.get(id),
+            None => self.get_by_id(&tool_number.to_string()),
+        }
+    }
+    
     /// Get tools by type
     pub fn get_by_type(&self, tool_type: ToolType) -> Vec<&ToolDefinition> {
         self.tools.values()
@@ -396,6 +437,7 @@ mod tests {
             coolant_type: None,
             coating: None,
             recommended_materials: None,
+            center_cutting: None,
         };
         
         // No leading digit, should hash
@@ -1,5 +1,10 @@
+use std::borrow::Cow;
+
 use thiserror::Error;
 
+use crate::codegen::datum::Datum;
+use crate::codegen::GCodeOutput;
+
 #[derive(Error, Debug)]
 pub enum ValidationError {
     #[error("tool collision: tool {tool} cannot reach depth {depth} with length {length}")]
@@ -19,6 +24,67 @@ pub enum ValidationError {
 
     #[error("rapid into workpiece: move to Z{z} below safe height {safe}")]
     RapidCollision { z: f64, safe: f64 },
+
+    #[error("rapid at Z{z} below the stock top outside the cut: {block}")]
+    RapidBelowSafeHeight { block: String, z: f64 },
+
+    #[error("{operation} lies entirely beyond y-limit {limit}")]
+    YLimit { operation: String, limit: f64 },
+
+    #[error("tapping {thread} into a {drill} hole: its tap drill is {tap_drill}")]
+    TapDrill {
+        thread: String,
+        drill: f64,
+        tap_drill: f64,
+    },
+
+    #[error("{operation} before any tool change")]
+    NoActiveTool { operation: String },
+
+    #[error("{operation} with the spindle stopped")]
+    NoActiveSpindle { operation: String },
+
+    #[error("unsafe cutting parameters ({code}): {message}")]
+    CuttingParameters { code: String, message: String },
+}
+
+/// Slack for rounding in coordinates, the G-code's printed ones and the program's own
+const TOLERANCE: f64 = 1e-6;
+
+/// XY bounds and floor of the material a tool has fed through
+#[derive(Clone, Copy)]
+struct CutRegion {
+    min_x: f64,
+    max_x: f64,
+    min_y: f64,
+    max_y: f64,
+    floor: f64,
+}
+
+impl CutRegion {
+    fn at(x: f64, y: f64, z: f64) -> Self {
+        Self {
+            min_x: x,
+            max_x: x,
+            min_y: y,
+            max_y: y,
+            floor: z,
+        }
+    }
+
+    fn include(&mut self, x: f64, y: f64, z: f64) {
+        self.min_x = self.min_x.min(x);
+        self.max_x = self.max_x.max(x);
+        self.min_y = self.min_y.min(y);
+        self.max_y = self.max_y.max(y);
+        self.floor = self.floor.min(z);
+    }
+
+    fn contains(&self, x: f64, y: f64, z: f64) -> bool {
+        (self.min_x - TOLERANCE..=self.max_x + TOLERANCE).contains(&x)
+            && (self.min_y - TOLERANCE..=self.max_y + TOLERANCE).contains(&y)
+            && z >= self.floor - TOLERANCE
+    }
 }
 
 pub struct Validator {
@@ -55,11 +121,99 @@ impl Validator {
         program: &crate::ast::Program,
     ) -> Result<(), Vec<ValidationError>> {
         let mut errors = Vec::new();
+        let mut y_limit = None;
+        let mut zero = None;
+        let mut stock = None;
+        let mut tool = None;
+        let mut tool_loaded = false;
+        let mut spindle_on = false;
+        // Drilled holes so far: (x, y, diameter)
+        let mut holes: Vec<(f64, f64, f64)> = Vec::new();
+
+        // Limits the program declares in its header replace the defaults
+        let safety = &program.header.safety;
+        let limits = Validator {
+            max_spindle_rpm: safety.max_spindle_rpm.unwrap_or(self.max_spindle_rpm),
+            max_feed_rate: safety.max_feed_rate.unwrap_or(self.max_feed_rate),
+            // The safe height is in millimetres
+            safe_height: match program.header.units {
+                crate::ast::Units::Metric => self.safe_height,
+                crate::ast::Units::Imperial => self.safe_height / 25.4,
+            },
+        };
 
         for op in &program.operations {
-            if let Err(e) = self.validate_operation(op) {
+            match op {
+                crate::ast::Operation::Setup(setup) => {
+                    y_limit = setup.y_limit;
+                    zero = Some(&setup.zero);
+                }
+                crate::ast::Operation::StockDef(def) => stock = Some(def),
+                crate::ast::Operation::ToolChange(tc) => {
+                    tool = tc.tool_data.as_ref().map(|d| (tc.tool_number, d));
+                    tool_loaded = true;
+                    spindle_on = false; // The tool change stops the spindle
+                }
+                crate::ast::Operation::Spindle(sp) => {
+                    spindle_on = sp.direction != crate::ast::SpindleDir::Off
+                }
+                _ => {}
+            }
+
+            // Check positions on the stock frame the G-code runs in
+            let mapped = match (zero, stock) {
+                (Some(zero), Some(stock)) => Cow::Owned(Datum::new(zero, stock).operation(op)),
+                _ => Cow::Borrowed(op),
+            };
+            let op = mapped.as_ref();
+
+            if let Err(e) = Self::validate_machine_state(op, tool_loaded, spindle_on) {
+                errors.push(e);
+            }
+            if let Some((number, data)) = tool {
+                if let Err(e) = self.validate_cutter_comp(op, data.diameter) {
+                    errors.push(e);
+                }
+                if let Err(e) = Self::validate_pocket_fit(op, data.diameter) {
+                    errors.push(e);
+                }
+                let stock_thickness = stock.map(|s| s.size_z);
+                if let Err(e) = self.validate_tool_reach(op, number, data.length, stock_thickness) {
+                    errors.push(e);
+                }
+            }
+            if let Some(stock) = stock {
+                let tool_radius = tool.map_or(0.0, |(_, data)| data.diameter / 2.0);
+                if let Err(e) = Self::validate_stock_bounds(op, stock, tool_radius) {
+                    errors.push(e);
+                }
+            }
+            if let Err(e) = limits.validate_operation(op) {
+                errors.push(e);
+            }
+            if let Some(limit) = y_limit {
+                if let Err(e) = self.validate_y_limit(op, limit) {
+                    errors.push(e);
+                }
+            }
+            if let Err(e) = Self::validate_tap_drill(op, &holes, program.header.units) {
                 errors.push(e);
             }
+
+            match op {
+                crate::ast::Operation::Drill(d) => {
+                    if let Some((_, data)) = tool {
+                        holes.extend(d.positions.iter().map(|p| (p.x, p.y, data.diameter)));
+                    }
+                }
+                crate::ast::Operation::DrillV2(d) => {
+                    holes.push((d.position.x, d.position.y, d.diameter))
+                }
+                crate::ast::Operation::DrillPattern(d) => holes.extend(
+                    d.pattern.positions().iter().map(|p| (p.x, p.y, d.diameter)),
+                ),
+                _ => {}
+            }
         }
 
         if errors.is_empty() {
@@ -69,6 +223,93 @@ impl Validator {
         }
     }
 
+    /// Check generated G-code for XY rapids below the stock top (Z0). Below it a rapid
+    /// may only move within the area fed out since the tool last came up to Z0, and no
+    /// deeper than it was cut, as when stepping between passes of one pocket. G91
+    /// moves are added to the position before them.
+    pub fn validate_rapids(&self, gcode: &GCodeOutput) -> Result<(), ValidationError> {
+        use crate::post::extract_word;
+
+        // The machine starts, and leaves every tool change, at its Z home
+        let (mut x, mut y, mut z) = (0.0, 0.0, f64::INFINITY);
+        let mut motion = "G00";
+        let mut incremental = false;
+        // Material cut since the tool was last above Z0
+        let mut cut: Option<CutRegion> = None;
+
+        for line in gcode.lines() {
+            if line.starts_with(';') || line.starts_with('(') {
+                continue;
+            }
+            let code = match line.split_once(' ') {
+                Some((n, rest)) if n.starts_with('N') => rest,
+                _ => line,
+            };
+            let code = code.split(';').next().unwrap_or("");
+            let words: Vec<&str> = code.split_whitespace().collect();
+            let has = |w: &str| words.contains(&w);
+
+            if has("M06") || has("M6") {
+                z = f64::INFINITY;
+                cut = None;
+            }
+            if let Some(m) = ["G00", "G01", "G02", "G03"].into_iter().find(|m| has(m)) {
+                motion = m;
+            }
+            // Canned cycles feed their own Z and return to where they started
+            let in_cycle = words
+                .iter()
+                .any(|w| ["G73", "G81", "G82", "G83", "G84", "G85"].contains(w));
+
+            incremental = (incremental || has("G91")) && !has("G90");
+            let word = |letter: char, from: f64| match extract_word(code, letter) {
+                Some(v) if incremental => from + v,
+                Some(v) => v,
+                None => from,
+            };
+            let end_x = word('X', x);
+            let end_y = word('Y', y);
+            let end_z = if in_cycle { z } else { word('Z', z) };
+            let moves_xy = end_x != x || end_y != y;
+
+            if moves_xy && (motion == "G00" || in_cycle) {
+                let low = z.min(end_z);
+                let inside = cut.is_some_and(|c| c.contains(end_x, end_y, low));
+                if low < -TOLERANCE && !inside {
+                    return Err(ValidationError::RapidBelowSafeHeight {
+                        block: line.to_string(),
+                        z: low,
+                    });
+                }
+            } else if motion != "G00" && !in_cycle {
+                // Arcs can bulge past their end points, so count their whole circle
+                let reach = match (extract_word(code, 'I'), extract_word(code, 'J')) {
+                    (None, None) => None,
+                    (i, j) if motion != "G01" => {
+                        let (i, j) = (i.unwrap_or(0.0), j.unwrap_or(0.0));
+                        Some(((x + i, y + j), i.hypot(j)))
+                    }
+                    _ => None,
+                };
+                let region = cut.get_or_insert(CutRegion::at(x, y, z));
+                region.include(end_x, end_y, end_z);
+                if let Some(((cx, cy), r)) = reach {
+                    region.include(cx - r, cy - r, end_z);
+                    region.include(cx + r, cy + r, end_z);
+                }
+            }
+
+            x = end_x;
+            y = end_y;
+            z = end_z;
+            if z >= 0.0 {
+                cut = None;
+            }
+        }
+
+        Ok(())
+    }
+
     fn validate_operation(&self, op: &crate::ast::Operation) -> Result<(), ValidationError> {
         use crate::ast::*;
 
@@ -167,10 +408,517 @@ impl Validator {
                 Ok(())
             }
 
+            Operation::DrillV2(d) => {
+                Self::validate_drill_depth(&d.depth)?;
+                if let Some(dwell) = d.dwell {
+                    if dwell <= 0.0 {
+                        return Err(ValidationError::Geometry {
+                            message: format!("drill dwell {} must be positive", dwell),
+                        });
+                    }
+                    if d.peck.is_some() {
+                        return Err(ValidationError::Geometry {
+                            message: "drill dwell can't be combined with peck: G82 drills in one plunge"
+                                .to_string(),
+                        });
+                    }
+                }
+                Self::validate_diameter("drill", d.diameter)
+            }
+
+            Operation::PocketV2(p) => {
+                if p.depth <= 0.0 {
+                    return Err(ValidationError::InvalidDepth { depth: p.depth });
+                }
+                Self::validate_pocket_shape(&p.shape)?;
+                if p.corners != CornerRelief::None && matches!(p.shape, PocketShape::Circle { .. }) {
+                    return Err(ValidationError::Geometry {
+                        message: "corner relief needs a rectangular pocket".to_string(),
+                    });
+                }
+                if p.adaptive && matches!(p.shape, PocketShape::Circle { .. }) {
+                    return Err(ValidationError::Geometry {
+                        message: "adaptive clearing needs a rectangular pocket or slot".to_string(),
+                    });
+                }
+                if let Some(finish) = p.finish {
+                    let smallest = match p.shape {
+                        PocketShape::Rect { width, height } => width.min(height),
+                        PocketShape::Circle { diameter } => diameter,
+                    };
+                    if finish <= 0.0 || finish * 2.0 >= smallest {
+                        return Err(ValidationError::Geometry {
+                            message: format!(
+                                "pocket finish allowance {} must be positive and under half the pocket size",
+                                finish
+                            ),
+                        });
+                    }
+                }
+                if let Some(stepover) = p.stepover.filter(|s| *s <= 0.0 || *s > 1.0) {
+                    return Err(ValidationError::Geometry {
+                        message: format!(
+                            "pocket stepover {} must be a fraction of the tool diameter, above 0 and up to 1",
+                            stepover
+                        ),
+                    });
+                }
+                if let Some(stepdown) = p.stepdown.filter(|s| *s <= 0.0) {
+                    return Err(ValidationError::InvalidDepth { depth: stepdown });
+                }
+                Ok(())
+            }
+
+            Operation::FaceV2(f) => {
+                if f.depth <= 0.0 {
+                    return Err(ValidationError::InvalidDepth { depth: f.depth });
+                }
+                Ok(())
+            }
+
+            Operation::Cut(CutOp { sweep, depth, .. })
+            | Operation::Clear(ClearOp { sweep, depth, .. }) => {
+                if *depth <= 0.0 {
+                    return Err(ValidationError::InvalidDepth { depth: *depth });
+                }
+                if *sweep <= 0.0 {
+                    return Err(ValidationError::Geometry {
+                        message: format!("cut has invalid sweep {}", sweep),
+                    });
+                }
+                Ok(())
+            }
+
+            Operation::DrillPattern(d) => {
+                Self::validate_drill_depth(&d.depth)?;
+                Self::validate_diameter("drill", d.diameter)?;
+                Self::validate_pattern(&d.pattern)
+            }
+
+            Operation::PocketPattern(p) => {
+                if p.depth <= 0.0 {
+                    return Err(ValidationError::InvalidDepth { depth: p.depth });
+                }
+                Self::validate_pocket_shape(&p.shape)?;
+                Self::validate_pattern(&p.pattern)
+            }
+
+            Operation::Chamfer(c) => {
+                if c.width <= 0.0 || c.tool_angle <= 0.0 || c.tool_angle >= 180.0 {
+                    return Err(ValidationError::Geometry {
+                        message: format!(
+                            "chamfer width {} with a {}° tool is invalid",
+                            c.width, c.tool_angle
+                        ),
+                    });
+                }
+                // A tip shallower than the chamfer's foot cuts a V-groove and leaves a lip on the wall
+                if c.tip_depth() < c.min_depth() - 1e-9 {
+                    return Err(ValidationError::Geometry {
+                        message: format!(
+                            "chamfer depth {} leaves a lip: a {}° tool needs at least {:.4} for width {}",
+                            c.tip_depth(),
+                            c.tool_angle,
+                            c.min_depth(),
+                            c.width
+                        ),
+                    });
+                }
+                Ok(())
+            }
+
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_drill_depth(depth: &crate::ast::DrillDepth) -> Result<(), ValidationError> {
+        match depth {
+            crate::ast::DrillDepth::Depth(d) if *d <= 0.0 => {
+                Err(ValidationError::InvalidDepth { depth: *d })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_diameter(feature: &str, diameter: f64) -> Result<(), ValidationError> {
+        if diameter <= 0.0 {
+            return Err(ValidationError::Geometry {
+                message: format!("{} has invalid diameter {}", feature, diameter),
+            });
+        }
+        Ok(())
+    }
+
+    fn validate_pocket_shape(shape: &crate::ast::PocketShape) -> Result<(), ValidationError> {
+        use crate::ast::PocketShape;
+
+        match shape {
+            PocketShape::Rect { width, height } => {
+                if *width <= 0.0 || *height <= 0.0 {
+                    return Err(ValidationError::Geometry {
+                        message: format!("pocket has invalid dimensions {}x{}", width, height),
+                    });
+                }
+                Ok(())
+            }
+            PocketShape::Circle { diameter } => Self::validate_diameter("pocket", *diameter),
+        }
+    }
+
+    fn validate_pattern(pattern: &crate::ast::Pattern) -> Result<(), ValidationError> {
+        use crate::ast::Pattern;
+
+        let count = match pattern {
+            Pattern::Grid { rows, cols, .. } => rows * cols,
+            Pattern::BoltCircle { count, diameter, .. } => {
+                Self::validate_diameter("bolt circle", *diameter)?;
+                *count
+            }
+            Pattern::Line { count, .. } => *count,
+            Pattern::Arc { count, radius, .. } => {
+                Self::validate_diameter("arc pattern", radius * 2.0)?;
+                *count
+            }
+        };
+        if count == 0 {
+            return Err(ValidationError::Geometry {
+                message: "pattern has no positions".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Cutting needs a tool in the spindle and, unless the operation starts the
+    /// spindle itself from Black Book speeds, a prior spindle-on
+    fn validate_machine_state(
+        op: &crate::ast::Operation,
+        tool_loaded: bool,
+        spindle_on: bool,
+    ) -> Result<(), ValidationError> {
+        use crate::ast::*;
+
+        let (operation, starts_spindle) = match op {
+            Operation::Drill(_) => ("drill", false),
+            Operation::Pocket(_) => ("pocket", false),
+            Operation::Profile(_) => ("profile", false),
+            Operation::Face(_) => ("face", false),
+            Operation::Tap(_) => ("tap", false),
+            Operation::Chamfer(_) => ("chamfer", false),
+            Operation::Deburr(_) => ("deburr", false),
+            Operation::Cut(_) => ("cut", true),
+            Operation::Clear(_) => ("clear", true),
+            Operation::DrillV2(_) | Operation::DrillPattern(_) => ("drill", true),
+            Operation::PocketV2(_) | Operation::PocketPattern(_) => ("pocket", true),
+            Operation::FaceV2(_) => ("face", true),
+            _ => return Ok(()),
+        };
+
+        if !tool_loaded {
+            return Err(ValidationError::NoActiveTool {
+                operation: operation.to_string(),
+            });
+        }
+        if !spindle_on && !starts_spindle {
+            return Err(ValidationError::NoActiveSpindle {
+                operation: operation.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// A tap given by thread size must go into a hole drilled within 5% of its tap
+    /// drill, when an earlier drill made a hole at that spot
+    fn validate_tap_drill(
+        op: &crate::ast::Operation,
+        holes: &[(f64, f64, f64)],
+        units: crate::ast::Units,
+    ) -> Result<(), ValidationError> {
+        let crate::ast::Operation::Tap(tap) = op else {
+            return Ok(());
+        };
+        let Some(thread) = &tap.thread else {
+            return Ok(());
+        };
+        let Some(spec) = crate::threads::lookup(thread) else {
+            return Ok(());
+        };
+        let tap_drill = spec.in_units(units).tap_drill;
+
+        for p in &tap.positions {
+            let drilled = holes.iter().rev().find(|&&(x, y, _)| {
+                (x - p.x).abs() < TOLERANCE && (y - p.y).abs() < TOLERANCE
+            });
+            if let Some(&(_, _, drill)) = drilled {
+                if (drill - tap_drill).abs() > tap_drill * 0.05 {
+                    return Err(ValidationError::TapDrill {
+                        thread: thread.clone(),
+                        drill,
+                        tap_drill,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reject operations whose geometry sits wholly past the setup's y-limit
+    fn validate_y_limit(
+        &self,
+        op: &crate::ast::Operation,
+        limit: f64,
+    ) -> Result<(), ValidationError> {
+        use crate::ast::*;
+
+        // Lowest Y touched by each operation's geometry
+        let (operation, min_y) = match op {
+            Operation::Drill(d) => (
+                "drill",
+                d.positions.iter().map(|p| p.y).fold(f64::INFINITY, f64::min),
+            ),
+            Operation::Pocket(p) => ("pocket", Self::geometry_min_y(&p.geometry)),
+            Operation::Profile(p) => ("profile", Self::geometry_min_y(&p.geometry)),
+            Operation::DrillV2(d) => ("drill", d.position.y - d.diameter / 2.0),
+            Operation::PocketV2(p) => {
+                let half = match p.shape {
+                    PocketShape::Rect { height, .. } => height / 2.0,
+                    PocketShape::Circle { diameter } => diameter / 2.0,
+                };
+                ("pocket", p.position.y - half)
+            }
+            _ => return Ok(()),
+        };
+
+        if min_y > limit {
+            return Err(ValidationError::YLimit {
+                operation: operation.to_string(),
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Cutter comp engages on a lead-in move, which needs at least a tool radius of room
+    fn validate_cutter_comp(
+        &self,
+        op: &crate::ast::Operation,
+        tool_diameter: f64,
+    ) -> Result<(), ValidationError> {
+        use crate::ast::*;
+
+        let Operation::Profile(p) = op else {
+            return Ok(());
+        };
+        if !p.cutter_comp || p.side != CutSide::Inside {
+            return Ok(());
+        }
+
+        // Smallest inside span after stock to leave; the lead-in and tool both fit within it
+        let span = match &p.geometry {
+            Geometry::Rect(r) => r.width.min(r.height),
+            Geometry::Circle(c) => c.diameter,
+            _ => return Ok(()),
+        } - 2.0 * p.stock_to_leave;
+
+        if span < tool_diameter {
+            return Err(ValidationError::Geometry {
+                message: format!(
+                    "no room for a cutter comp lead-in of {} (tool radius) inside a {} wide profile",
+                    tool_diameter / 2.0,
+                    span
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// A pocket must be at least as wide as the tool, less any finish allowance on
+    /// each wall; a tool-wide slot or a tool-sized round pocket is a single plunge
+    /// line, but a smaller one would leave nothing to cut
+    fn validate_pocket_fit(
+        op: &crate::ast::Operation,
+        tool_diameter: f64,
+    ) -> Result<(), ValidationError> {
+        use crate::ast::*;
+
+        let shape_span = |shape: &PocketShape| match shape {
+            PocketShape::Rect { width, height } => width.min(*height),
+            PocketShape::Circle { diameter } => *diameter,
+        };
+        let span = match op {
+            Operation::Pocket(p) => {
+                let size = match &p.geometry {
+                    Geometry::Rect(r) => r.width.min(r.height),
+                    Geometry::Circle(c) => c.diameter,
+                    _ => return Ok(()),
+                };
+                size - 2.0 * p.finish_pass.unwrap_or(0.0)
+            }
+            Operation::PocketV2(p) => shape_span(&p.shape) - 2.0 * p.finish.unwrap_or(0.0),
+            Operation::PocketPattern(p) => shape_span(&p.shape),
+            _ => return Ok(()),
+        };
+
+        if tool_diameter > span + TOLERANCE {
+            return Err(ValidationError::Geometry {
+                message: format!(
+                    "a {} tool doesn't fit a pocket {} across - it would cut nothing",
+                    tool_diameter, span
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// The active tool's length is its usable reach; anything deeper buries the holder
+    fn validate_tool_reach(
+        &self,
+        op: &crate::ast::Operation,
+        tool: u8,
+        length: f64,
+        stock_thickness: Option<f64>,
+    ) -> Result<(), ValidationError> {
+        use crate::ast::*;
+
+        // Library tools without a length can't be checked
+        if length <= 0.0 {
+            return Ok(());
+        }
+
+        let drill_depth = |depth: &DrillDepth| match depth {
+            DrillDepth::Depth(d) => Some(*d),
+            DrillDepth::Thru => stock_thickness,
+        };
+        let depth = match op {
+            Operation::Drill(d) => Some(d.depth),
+            Operation::Pocket(p) => Some(p.depth),
+            Operation::Profile(p) => Some(p.depth),
+            Operation::DrillV2(d) => drill_depth(&d.depth),
+            Operation::PocketV2(p) => Some(p.depth),
+            Operation::DrillPattern(d) => drill_depth(&d.depth),
+            Operation::PocketPattern(p) => Some(p.depth),
+            _ => None,
+        };
+
+        match depth {
+            Some(depth) if depth > length => Err(ValidationError::ToolCollision {
+                tool,
+                depth,
+                length,
+            }),
             _ => Ok(()),
         }
     }
 
+    /// Material removed by each operation must lie on the stock, from X0 Y0 to its
+    /// size. Cuts centred on a point - holes and on-line profiles - reach out by the
+    /// tool radius; pockets and inside or outside profiles keep the tool on their own
+    /// side of the line.
+    fn validate_stock_bounds(
+        op: &crate::ast::Operation,
+        stock: &crate::ast::StockDef,
+        tool_radius: f64,
+    ) -> Result<(), ValidationError> {
+        use crate::ast::*;
+
+        // Box around points grown by half widths: ((min x, min y), (max x, max y))
+        let around = |points: &[Position], half_x: f64, half_y: f64| {
+            points.iter().fold(
+                ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY)),
+                |((min_x, min_y), (max_x, max_y)), p| {
+                    (
+                        (min_x.min(p.x - half_x), min_y.min(p.y - half_y)),
+                        (max_x.max(p.x + half_x), max_y.max(p.y + half_y)),
+                    )
+                },
+            )
+        };
+        let shape_half = |shape: &PocketShape| match *shape {
+            PocketShape::Rect { width, height } => (width / 2.0, height / 2.0),
+            PocketShape::Circle { diameter } => (diameter / 2.0, diameter / 2.0),
+        };
+
+        let (operation, ((min_x, min_y), (max_x, max_y))) = match op {
+            Operation::Drill(d) => ("drill", around(&d.positions, tool_radius, tool_radius)),
+            Operation::Tap(t) => ("tap", around(&t.positions, tool_radius, tool_radius)),
+            Operation::DrillV2(d) => {
+                let r = d.diameter / 2.0;
+                ("drill", around(&[d.position], r, r))
+            }
+            Operation::DrillPattern(d) => {
+                let r = d.diameter / 2.0;
+                ("drill pattern", around(&d.pattern.positions(), r, r))
+            }
+            Operation::Pocket(p) => ("pocket", Self::geometry_extents(&p.geometry, 0.0)),
+            Operation::Profile(p) => {
+                let reach = if p.side == CutSide::On { tool_radius } else { 0.0 };
+                ("profile", Self::geometry_extents(&p.geometry, reach))
+            }
+            Operation::PocketV2(p) => {
+                let (half_x, half_y) = shape_half(&p.shape);
+                ("pocket", around(&[p.position], half_x, half_y))
+            }
+            Operation::PocketPattern(p) => {
+                let (half_x, half_y) = shape_half(&p.shape);
+                ("pocket pattern", around(&p.pattern.positions(), half_x, half_y))
+            }
+            _ => return Ok(()),
+        };
+
+        if min_x < -TOLERANCE
+            || min_y < -TOLERANCE
+            || max_x > stock.size_x + TOLERANCE
+            || max_y > stock.size_y + TOLERANCE
+        {
+            return Err(ValidationError::Geometry {
+                message: format!(
+                    "{} spans X{:.4}..{:.4} Y{:.4}..{:.4}, off the stock at X0.0000..{:.4} Y0.0000..{:.4}",
+                    operation, min_x, max_x, min_y, max_y, stock.size_x, stock.size_y
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Bounding box of a geometry grown by `reach`: ((min x, min y), (max x, max y))
+    fn geometry_extents(geom: &crate::ast::Geometry, reach: f64) -> ((f64, f64), (f64, f64)) {
+        use crate::ast::*;
+
+        let ((min_x, min_y), (max_x, max_y)) = match geom {
+            Geometry::Rect(r) => r.corners().iter().fold(
+                ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY)),
+                |((min_x, min_y), (max_x, max_y)), p| {
+                    ((min_x.min(p.x), min_y.min(p.y)), (max_x.max(p.x), max_y.max(p.y)))
+                },
+            ),
+            Geometry::Circle(c) => {
+                let r = c.diameter / 2.0;
+                ((c.center.x - r, c.center.y - r), (c.center.x + r, c.center.y + r))
+            }
+            Geometry::Polygon(p) => {
+                let r = p.circumradius;
+                ((p.center.x - r, p.center.y - r), (p.center.x + r, p.center.y + r))
+            }
+            Geometry::Path(points) => points.iter().fold(
+                ((f64::INFINITY, f64::INFINITY), (f64::NEG_INFINITY, f64::NEG_INFINITY)),
+                |((min_x, min_y), (max_x, max_y)), p| {
+                    ((min_x.min(p.x), min_y.min(p.y)), (max_x.max(p.x), max_y.max(p.y)))
+                },
+            ),
+        };
+        ((min_x - reach, min_y - reach), (max_x + reach, max_y + reach))
+    }
+
+    fn geometry_min_y(geom: &crate::ast::Geometry) -> f64 {
+        use crate::ast::*;
+
+        match geom {
+            Geometry::Rect(r) => r.corners().iter().map(|p| p.y).fold(f64::INFINITY, f64::min),
+            Geometry::Circle(c) => c.center.y - c.diameter / 2.0,
+            Geometry::Polygon(p) => p.center.y - p.circumradius,
+            Geometry::Path(points) => points.iter().map(|p| p.y).fold(f64::INFINITY, f64::min),
+        }
+    }
+
     fn validate_geometry(&self, geom: &crate::ast::Geometry) -> Result<(), ValidationError> {
         use crate::ast::*;
 
@@ -194,7 +942,373 @@ impl Validator {
                 }
                 Ok(())
             }
-            _ => Ok(()),
+            Geometry::Polygon(p) => {
+                if p.sides < 3 || p.circumradius <= 0.0 {
+                    return Err(ValidationError::Geometry {
+                        message: format!(
+                            "polygon has invalid {} sides of radius {}",
+                            p.sides, p.circumradius
+                        ),
+                    });
+                }
+                Ok(())
+            }
+            Geometry::Path(points) => {
+                if points.len() < 3 {
+                    return Err(ValidationError::Geometry {
+                        message: format!(
+                            "path has {} point(s), needs at least 3 to close a loop",
+                            points.len()
+                        ),
+                    });
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::parser::Parser;
+
+    fn parse(input: &str) -> crate::ast::Program {
+        Parser::new(lex(input)).parse().expect("should parse")
+    }
+
+    #[test]
+    fn test_rapid_below_stock_top_must_stay_in_the_cut() {
+        let mut gcode = GCodeOutput::new();
+        for line in [
+            "G00 X0.0 Y0.0",
+            "G01 Z-0.2 F10.0",
+            "G01 X1.0 F20.0",
+            "G00 Z-0.18",
+            "G00 X0.0",
+        ] {
+            gcode.emit(line);
         }
+        // Returning along the pass just cut is clear
+        Validator::new().validate_rapids(&gcode).expect("inside the cut");
+
+        gcode.emit("G00 X3.0 Y0.0");
+        let err = Validator::new().validate_rapids(&gcode).unwrap_err();
+        assert!(matches!(err, ValidationError::RapidBelowSafeHeight { z, .. } if z == -0.18));
+
+        let mut retracted = GCodeOutput::new();
+        for line in ["G01 Z-0.2 F10.0", "G00 Z0.1", "G00 X3.0 Y1.0"] {
+            retracted.emit(line);
+        }
+        Validator::new().validate_rapids(&retracted).expect("retracted first");
+    }
+
+    #[test]
+    fn test_short_tool_cannot_reach_deep_pocket() {
+        let program = parse(
+            "tool 1 dia 0.25 length 1.0 flutes 3 carbide\npocket rect 1.0 1.0 0.5 at 1.0 1.0\npocket rect 1.0 1.0 1.5 at 3.0 1.0\ntool 2 dia 0.25 length 2.0 flutes 3 carbide\npocket rect 1.0 1.0 1.5 at 3.0 1.0",
+        );
+
+        let errors = Validator::new()
+            .validate_program(&program)
+            .expect_err("1.5\" pocket with a 1.0\" tool should fail");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            ValidationError::ToolCollision { tool: 1, depth, length } if depth == 1.5 && length == 1.0
+        ));
+    }
+
+    #[test]
+    fn test_v2_operations_are_validated() {
+        use crate::ast::*;
+
+        let validator = Validator::new();
+        let pocket = PocketV2Op {
+            shape: PocketShape::Circle { diameter: 1.0 },
+            position: Position::new(0.0, 0.0),
+            depth: -0.5,
+            finish: None,
+            corners: CornerRelief::None,
+            adaptive: false,
+            plunge: None,
+            stepover: None,
+            stepdown: None,
+        };
+        assert!(matches!(
+            validator.validate_operation(&Operation::PocketV2(pocket.clone())),
+            Err(ValidationError::InvalidDepth { depth }) if depth == -0.5
+        ));
+        assert!(validator
+            .validate_operation(&Operation::PocketV2(PocketV2Op { depth: 0.5, ..pocket.clone() }))
+            .is_ok());
+        assert!(matches!(
+            validator.validate_operation(&Operation::PocketV2(PocketV2Op {
+                depth: 0.5,
+                adaptive: true,
+                plunge: None,
+                ..pocket.clone()
+            })),
+            Err(ValidationError::Geometry { message }) if message.contains("adaptive")
+        ));
+        assert!(matches!(
+            validator.validate_operation(&Operation::PocketV2(PocketV2Op {
+                depth: 0.5,
+                stepover: Some(40.0),
+                ..pocket.clone()
+            })),
+            Err(ValidationError::Geometry { message }) if message.contains("stepover")
+        ));
+        assert!(matches!(
+            validator.validate_operation(&Operation::PocketV2(PocketV2Op {
+                depth: 0.5,
+                stepdown: Some(0.0),
+                ..pocket
+            })),
+            Err(ValidationError::InvalidDepth { .. })
+        ));
+
+        let drill = DrillV2Op {
+            diameter: 0.0,
+            position: Position::new(1.0, 1.0),
+            depth: DrillDepth::Thru,
+            spot: false,
+            peck: None,
+            dwell: None,
+        };
+        assert!(matches!(
+            validator.validate_operation(&Operation::DrillV2(drill.clone())),
+            Err(ValidationError::Geometry { .. })
+        ));
+        let dwell_peck = DrillV2Op {
+            diameter: 0.25,
+            dwell: Some(0.5),
+            peck: Some(PeckMode::Break),
+            ..drill
+        };
+        assert!(matches!(
+            validator.validate_operation(&Operation::DrillV2(dwell_peck)),
+            Err(ValidationError::Geometry { message }) if message.contains("peck")
+        ));
+
+        let chamfer = ChamferOp {
+            width: 0.03,
+            geometry: ChamferGeometry::Rect { width: 2.0, height: 1.5 },
+            position: Position::new(1.0, 0.75),
+            side: CutSide::Outside,
+            depth: Some(0.02),
+            tool_angle: 90.0,
+        };
+        assert!(matches!(
+            validator.validate_operation(&Operation::Chamfer(chamfer.clone())),
+            Err(ValidationError::Geometry { message }) if message.contains("lip")
+        ));
+        assert!(validator
+            .validate_operation(&Operation::Chamfer(ChamferOp { depth: None, ..chamfer }))
+            .is_ok());
+
+        let pattern = PocketPatternOp {
+            shape: PocketShape::Rect { width: 0.5, height: 0.5 },
+            depth: 0.1,
+            pattern: Pattern::Grid {
+                rows: 0,
+                cols: 2,
+                spacing_x: 1.0,
+                spacing_y: 1.0,
+                start_position: Position::new(0.0, 0.0),
+            },
+        };
+        assert!(matches!(
+            validator.validate_operation(&Operation::PocketPattern(pattern)),
+            Err(ValidationError::Geometry { .. })
+        ));
+    }
+
+    #[test]
+    fn test_tap_must_match_its_drilled_hole() {
+        let program = |drill: f64| {
+            parse(&format!(
+                "units imperial\ntool 1 dia {} length 2.0\nspindle cw rpm 1000\ndrill at x 1 y 1 depth 0.5 feed 10\ntool 2 dia 0.25 length 2.0\nspindle cw rpm 300\ntap 1/4-20 at x 1 y 1 depth 0.4\ntap 1/4-20 at x 2 y 1 depth 0.4",
+                drill
+            ))
+        };
+
+        assert!(Validator::new().validate_program(&program(0.201)).is_ok());
+        // A #8 drill is close enough
+        assert!(Validator::new().validate_program(&program(0.199)).is_ok());
+
+        let errors = Validator::new()
+            .validate_program(&program(0.25))
+            .expect_err("tapping 1/4-20 into a 1/4\" hole should fail");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "tapping 1/4-20 into a 0.25 hole: its tap drill is 0.201"
+        );
+    }
+
+    #[test]
+    fn test_tap_checks_patterned_holes() {
+        let program = |drill: f64| {
+            parse(&format!(
+                "units metric\ntool 1 dia {0} length 50\nspindle cw rpm 2000\ndrill {0} at 0 0 depth 12 pattern circle 4 dia 40 center at 50 50\ntool 2 dia 6 length 50\nspindle cw rpm 300\ntap M6x1.0 at x 70 y 50 depth 10",
+                drill
+            ))
+        };
+
+        assert!(Validator::new().validate_program(&program(5.0)).is_ok());
+
+        let errors = Validator::new()
+            .validate_program(&program(6.0))
+            .expect_err("tapping M6 into a 6mm hole should fail");
+        assert!(matches!(errors[..], [ValidationError::TapDrill { .. }]));
+    }
+
+    #[test]
+    fn test_header_limits_replace_defaults() {
+        let program = |header: &str| {
+            parse(&format!(
+                "units imperial\n{}\ntool 1 dia 0.25 length 1.0\nspindle cw rpm 15000\ndrill at x 1 y 1 depth 0.25 feed 10",
+                header
+            ))
+        };
+
+        let errors = Validator::new()
+            .validate_program(&program(""))
+            .expect_err("15000 RPM is over the default 10000");
+        assert!(matches!(errors[0], ValidationError::SpindleSpeed { max, .. } if max == 10000.0));
+
+        assert!(Validator::new()
+            .validate_program(&program("max-rpm 18000"))
+            .is_ok());
+
+        let errors = Validator::new()
+            .validate_program(&program("max-rpm 18000\nmax-feed 8"))
+            .expect_err("feed 10 is over the declared 8");
+        assert!(matches!(errors[0], ValidationError::FeedRate { max, .. } if max == 8.0));
+    }
+
+    #[test]
+    fn test_cutting_requires_tool_and_spindle() {
+        let errors = Validator::new()
+            .validate_program(&parse(
+                "spindle cw rpm 5000\ndrill at x 1 y 1 depth 0.25 feed 10",
+            ))
+            .expect_err("drilling without a tool should fail");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::NoActiveTool { operation } if operation == "drill"
+        ));
+
+        // The tool change stops the spindle, so it must be restarted after
+        let errors = Validator::new()
+            .validate_program(&parse(
+                "spindle cw rpm 5000\ntool 1 dia 0.25 length 1.0\ndrill at x 1 y 1 depth 0.25 feed 10",
+            ))
+            .expect_err("drilling with the spindle off should fail");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::NoActiveSpindle { operation } if operation == "drill"
+        ));
+
+        assert!(Validator::new()
+            .validate_program(&parse(
+                "tool 1 dia 0.25 length 1.0\nspindle cw rpm 5000\ndrill at x 1 y 1 depth 0.25 feed 10",
+            ))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_y_limit_rejects_operation_beyond_limit() {
+        let program = parse(
+            "setup {\n    y-limit 1.0\n}\ntool 1 dia 0.25 length 1.0\npocket rect 1.0 0.5 0.1 at 1.0 2.0\npocket rect 1.0 0.5 0.1 at 1.0 0.5",
+        );
+
+        let errors = Validator::new()
+            .validate_program(&program)
+            .expect_err("pocket past the clamp should fail");
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::YLimit { operation, limit } if operation == "pocket" && *limit == 1.0
+        ));
+    }
+
+    #[test]
+    fn test_cutter_comp_needs_lead_in_room() {
+        let program = parse(
+            "tool 1 dia 10 length 50\nspindle cw rpm 3000\nprofile inside rect at x 0 y 0 width 40 height 8 depth 3 comp\nprofile inside rect at x 0 y 0 width 40 height 20 depth 3 comp",
+        );
+
+        let errors = Validator::new()
+            .validate_program(&program)
+            .expect_err("8mm slot can't fit a 10mm tool's comp lead-in");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("cutter comp lead-in"));
+    }
+
+    #[test]
+    fn test_pocket_smaller_than_tool_fails() {
+        let program = |pocket: &str| {
+            parse(&format!(
+                "tool 1 dia 0.5 length 2.0 flutes 3 carbide\nspindle cw rpm 5000\n{}",
+                pocket
+            ))
+        };
+        let fit_error = |pocket: &str| {
+            let errors = Validator::new()
+                .validate_program(&program(pocket))
+                .expect_err("0.5\" tool in a 0.4\" pocket should fail");
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            errors[0].to_string()
+        };
+
+        assert_eq!(
+            fit_error("pocket circle 0.4 0.25 at 1.0 1.0"),
+            "geometry error: a 0.5 tool doesn't fit a pocket 0.4 across - it would cut nothing"
+        );
+        assert!(fit_error("pocket rect 0.4 2.0 0.25 at 1.0 1.0").contains("pocket 0.4 across"));
+        assert!(fit_error("pocket rect at x 0 y 0 width 2 height 0.4 depth 0.25").contains("pocket 0.4 across"));
+        // The finish allowance comes off both walls
+        assert!(fit_error("pocket rect 0.7 2.0 0.25 at 1.0 1.0 finish 0.15").contains("doesn't fit"));
+
+        // A tool-wide slot is still a pocket
+        assert!(Validator::new()
+            .validate_program(&program("pocket rect 0.5 2.0 0.25 at 1.0 1.0"))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_pocket_must_fit_on_stock() {
+        let program = |pocket: &str| {
+            parse(&format!(
+                "stock 3 x 2 x 0.75 \"6061-T6\"\ntool 1 dia 0.25 length 1.0 flutes 3 carbide\n{}",
+                pocket
+            ))
+        };
+
+        assert!(Validator::new()
+            .validate_program(&program("pocket circle 1.5 0.25 at 1.5 1.0"))
+            .is_ok());
+
+        let errors = Validator::new()
+            .validate_program(&program("pocket circle 4.0 0.25 at 1.5 1.0"))
+            .expect_err("4\" pocket on 3 x 2 stock should fail");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "geometry error: pocket spans X-0.5000..3.5000 Y-1.0000..3.0000, off the stock at X0.0000..3.0000 Y0.0000..2.0000"
+        );
+
+        // Positions are from a centered zero here, and holes reach out by their radius
+        let centered = parse("stock 3 x 2 x 0.75 \"6061-T6\"\nsetup {\n    zero center center top\n}\ntool 1 dia 0.25 length 1.0\nspindle cw rpm 5000\ndrill at x 1.3 y 0 depth 0.25 feed 10\ndrill at x 1.45 y 0 depth 0.25 feed 10");
+        let errors = Validator::new()
+            .validate_program(&centered)
+            .expect_err("hole past the right edge should fail");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().starts_with("geometry error: drill spans X2.8250..3.0750"));
     }
 }
@@ -1,5 +1,6 @@
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::{broadcast, RwLock};
@@ -13,6 +14,8 @@ struct Toolpath {
     bounds: Bounds,
 }
 
+/// `seq` orders moves across `lines`, `rapids` and `arcs` for playback;
+/// `feed` is the commanded feed rate, 0 for rapids
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Line {
     x1: f64,
@@ -21,8 +24,12 @@ struct Line {
     x2: f64,
     y2: f64,
     z2: f64,
+    seq: usize,
+    feed: f64,
 }
 
+/// A G02/G03 move: `x`/`y`/`z` is where it starts, `i`/`j` the center offset from there.
+/// `end_angle` is unwrapped past `start_angle` so a full circle spans 2π.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ArcMove {
     x: f64,
@@ -33,8 +40,25 @@ struct ArcMove {
     start_angle: f64,
     end_angle: f64,
     clockwise: bool,
+    seq: usize,
+    feed: f64,
 }
 
+/// Modal motion state while walking G-code
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Motion {
+    Rapid,
+    Linear,
+    Arc { clockwise: bool },
+    /// Modal canned drilling cycle: every XY position drills a hole
+    Cycle { r: f64, depth: f64, retract: f64 },
+}
+
+/// Canned drilling cycles that plunge without a motion word of their own
+const CYCLE_CODES: [&str; 5] = ["G73", "G81", "G82", "G83", "G84"];
+
+/// XY covers every move; Z covers only cutting moves so clearance rapids
+/// don't flatten the viewer's depth shading
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct Bounds {
     min_x: f64,
@@ -45,11 +69,14 @@ struct Bounds {
     max_z: f64,
 }
 
+/// Address the visualizer binds when no `--host`/`--port` is given
+pub const DEFAULT_ADDR: ([u8; 4], u16) = ([127, 0, 0, 1], 3030);
+
 /// Run visualizer with auto-compilation support for swarf files
-pub async fn runviz(file_path: String, use_2d: bool) {
+pub async fn runviz(file_path: String, use_2d: bool, addr: SocketAddr) {
     // Check if path is a directory (folder mode)
     if std::path::Path::new(&file_path).is_dir() {
-        runviz_folder(file_path, use_2d).await;
+        runviz_folder(file_path, use_2d, addr).await;
         return;
     }
 
@@ -57,14 +84,20 @@ pub async fn runviz(file_path: String, use_2d: bool) {
     let is_swarf = file_path.ends_with(".swarf");
 
     if is_swarf {
-        runviz_swarf(file_path, use_2d).await;
+        runviz_swarf(file_path, use_2d, addr).await;
     } else {
-        runviz_gcode(file_path, use_2d).await;
+        runviz_gcode(file_path, use_2d, addr).await;
     }
 }
 
+/// Exit with a readable message when the port is taken or the host isn't local
+fn bind_failed(addr: SocketAddr, e: warp::Error) -> ! {
+    eprintln!("Error: cannot bind visualizer to {}: {}", addr, e);
+    std::process::exit(1);
+}
+
 /// Run visualizer for G-code files (original behavior)
-async fn runviz_gcode(gcode_file: String, use_2d: bool) {
+async fn runviz_gcode(gcode_file: String, use_2d: bool, addr: SocketAddr) {
     let file_path = Arc::new(gcode_file);
     let toolpath = Arc::new(RwLock::new(parse_gcode(&file_path)));
 
@@ -144,14 +177,17 @@ async fn runviz_gcode(gcode_file: String, use_2d: bool) {
 
     let routes = ws_route.or(index);
 
-    println!("🚀 swarf-viz running at http://localhost:3030");
+    let (bound, server) = warp::serve(routes)
+        .try_bind_ephemeral(addr)
+        .unwrap_or_else(|e| bind_failed(addr, e));
+    println!("🚀 swarf-viz running at http://{}", bound);
     println!("📁 Watching: {}", file_path);
 
-    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+    server.await;
 }
 
 /// Run visualizer for swarf files with auto-compilation
-async fn runviz_swarf(swarf_file: String, use_2d: bool) {
+async fn runviz_swarf(swarf_file: String, use_2d: bool, addr: SocketAddr) {
     use crate::codegen::CodeGenerator;
     use crate::lexer;
     use crate::parser;
@@ -163,7 +199,7 @@ async fn runviz_swarf(swarf_file: String, use_2d: bool) {
     fn compile_swarf_to_gcode(swarf_path: &str) -> Result<String, String> {
         let source = std::fs::read_to_string(swarf_path).map_err(|e| e.to_string())?;
         let tokens = lexer::lex(&source);
-        let mut parser = parser::Parser::new(tokens);
+        let mut parser = parser::Parser::new(tokens).with_source(&source);
         let program = parser.parse().map_err(|e| e.to_string())?;
 
         let mut codegen = CodeGenerator::new();
@@ -289,15 +325,18 @@ async fn runviz_swarf(swarf_file: String, use_2d: bool) {
 
     let routes = ws_route.or(index);
 
-    println!("🚀 swarf-viz running at http://localhost:3030");
+    let (bound, server) = warp::serve(routes)
+        .try_bind_ephemeral(addr)
+        .unwrap_or_else(|e| bind_failed(addr, e));
+    println!("🚀 swarf-viz running at http://{}", bound);
     println!("📁 Watching swarf: {}", swarf_file);
     println!("💡 Edit the swarf file and save to see changes instantly");
 
-    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+    server.await;
 }
 
 /// Run visualizer in folder mode - shows file selector
-async fn runviz_folder(folder_path: String, _use_2d: bool) {
+async fn runviz_folder(folder_path: String, _use_2d: bool, addr: SocketAddr) {
 
     // Scan folder for .swarf files
     fn scan_swarf_files(folder: &str) -> Vec<String> {
@@ -396,10 +435,13 @@ async fn runviz_folder(folder_path: String, _use_2d: bool) {
     let index = warp::path::end().map(move || warp::reply::html(html.clone()));
     let routes = ws_route.or(load_route).or(index);
 
-    println!("🚀 swarf-viz folder mode at http://localhost:3030");
+    let (bound, server) = warp::serve(routes)
+        .try_bind_ephemeral(addr)
+        .unwrap_or_else(|e| bind_failed(addr, e));
+    println!("🚀 swarf-viz folder mode at http://{}", bound);
     println!("💡 Click any file in the browser to view it");
 
-    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+    server.await;
 }
 
 /// Load and compile a swarf file, return toolpath
@@ -412,7 +454,7 @@ fn load_swarf_file(path: &str) -> Toolpath {
     if path.ends_with(".swarf") {
         if let Ok(source) = std::fs::read_to_string(path) {
             let tokens = lexer::lex(&source);
-            let mut parser = parser::Parser::new(tokens);
+            let mut parser = parser::Parser::new(tokens).with_source(&source);
             if let Ok(program) = parser.parse() {
                 let mut codegen = CodeGenerator::new();
                 let gcode_output = codegen.generate_output(&program);
@@ -478,7 +520,7 @@ fn parse_gcode_string(content: &str) -> Toolpath {
 fn parse_gcode_content(content: &str) -> Toolpath {
     let mut lines: Vec<Line> = Vec::new();
     let mut rapids: Vec<Line> = Vec::new();
-    let arcs: Vec<ArcMove> = Vec::new();
+    let mut arcs: Vec<ArcMove> = Vec::new();
 
     let mut x = 0.0;
     let mut y = 0.0;
@@ -494,7 +536,11 @@ fn parse_gcode_content(content: &str) -> Toolpath {
     let mut min_z = f64::INFINITY;
     let mut max_z = f64::NEG_INFINITY;
 
-    let mut is_rapid = true;
+    let mut motion = Motion::Rapid;
+    let mut feed = 0.0;
+    let mut seq = 0;
+    // G91: axis words are distances from the current position
+    let mut incremental = false;
 
     for line in content.lines() {
         let line = line.trim();
@@ -505,18 +551,141 @@ fn parse_gcode_content(content: &str) -> Toolpath {
         }
 
         let upper = line.to_uppercase();
+        feed = parse_coord(line, 'F').unwrap_or(feed);
+
+        for word in upper.split_whitespace() {
+            match word {
+                "G90" => incremental = false,
+                "G91" => incremental = true,
+                _ => {}
+            }
+        }
+        // Where an axis word puts the tool, or None without one
+        let target = |axis: char, from: f64| {
+            parse_coord(line, axis).map(|v| if incremental { from + v } else { v })
+        };
 
         // Check for G-codes
         if upper.contains("G00") || upper.contains("G0 ") {
-            is_rapid = true;
+            motion = Motion::Rapid;
         } else if upper.contains("G01") || upper.contains("G1 ") {
-            is_rapid = false;
+            motion = Motion::Linear;
+        } else if upper.contains("G02") || upper.contains("G2 ") {
+            motion = Motion::Arc { clockwise: true };
+        } else if upper.contains("G03") || upper.contains("G3 ") {
+            motion = Motion::Arc { clockwise: false };
+        } else if upper.contains("G80") {
+            motion = Motion::Rapid;
+        }
+
+        let starts_cycle = CYCLE_CODES.iter().any(|code| upper.contains(code));
+        if starts_cycle {
+            // Incremental R is from the starting level and Z from the R plane
+            let r = target('R', z).unwrap_or(z);
+            let depth = match parse_coord(line, 'Z') {
+                Some(d) if incremental => r + d,
+                Some(d) => d,
+                None => z,
+            };
+            motion = Motion::Cycle {
+                r,
+                depth,
+                // G99 returns to the R plane, G98 (the default) to the starting level
+                retract: if upper.contains("G99") { r } else { z.max(r) },
+            };
+        }
+
+        // Each hole: rapid over it, rapid to R, feed to depth, rapid back out
+        if let Motion::Cycle { r, depth, retract } = motion {
+            let hole_x = target('X', x);
+            let hole_y = target('Y', y);
+            if starts_cycle || hole_x.is_some() || hole_y.is_some() {
+                let (hx, hy) = (hole_x.unwrap_or(x), hole_y.unwrap_or(y));
+                let segment = |z1: f64, z2: f64, from: (f64, f64), seq: usize, feed: f64| Line {
+                    x1: from.0,
+                    y1: from.1,
+                    z1,
+                    x2: hx,
+                    y2: hy,
+                    z2,
+                    seq,
+                    feed,
+                };
+                rapids.push(segment(z, z, (x, y), seq, 0.0));
+                rapids.push(segment(z, r, (hx, hy), seq + 1, 0.0));
+                lines.push(segment(r, depth, (hx, hy), seq + 2, feed));
+                rapids.push(segment(depth, retract, (hx, hy), seq + 3, 0.0));
+                seq += 4;
+
+                min_x = min_x.min(x).min(hx);
+                max_x = max_x.max(x).max(hx);
+                min_y = min_y.min(y).min(hy);
+                max_y = max_y.max(y).max(hy);
+                min_z = min_z.min(depth);
+                max_z = max_z.max(r);
+
+                x = hx;
+                y = hy;
+                z = retract;
+                prev_x = x;
+                prev_y = y;
+                prev_z = z;
+                continue;
+            }
         }
 
         // Parse coordinates
-        let new_x = parse_coord(line, 'X').unwrap_or(x);
-        let new_y = parse_coord(line, 'Y').unwrap_or(y);
-        let new_z = parse_coord(line, 'Z').unwrap_or(z);
+        let new_x = target('X', x).unwrap_or(x);
+        let new_y = target('Y', y).unwrap_or(y);
+        let new_z = target('Z', z).unwrap_or(z);
+
+        // Arcs need a center; a full circle ends where it starts
+        let center = match (parse_coord(line, 'I'), parse_coord(line, 'J')) {
+            (None, None) => None,
+            (i, j) => Some((i.unwrap_or(0.0), j.unwrap_or(0.0))),
+        };
+        if let (Motion::Arc { clockwise }, Some((i, j))) = (motion, center) {
+            let (cx, cy) = (x + i, y + j);
+            let radius = i.hypot(j);
+            let start_angle = (y - cy).atan2(x - cx);
+            let end = (new_y - cy).atan2(new_x - cx);
+            let tau = 2.0 * std::f64::consts::PI;
+            let mut sweep = if clockwise { start_angle - end } else { end - start_angle }.rem_euclid(tau);
+            if sweep < 1e-9 {
+                sweep = tau;
+            }
+            let end_angle = if clockwise { start_angle - sweep } else { start_angle + sweep };
+
+            arcs.push(ArcMove {
+                x,
+                y,
+                z,
+                i,
+                j,
+                start_angle,
+                end_angle,
+                clockwise,
+                seq,
+                feed,
+            });
+            seq += 1;
+
+            let (ax0, ax1, ay0, ay1) = arc_extents(cx, cy, radius, start_angle, end_angle);
+            min_x = min_x.min(ax0);
+            max_x = max_x.max(ax1);
+            min_y = min_y.min(ay0);
+            max_y = max_y.max(ay1);
+            min_z = min_z.min(z).min(new_z);
+            max_z = max_z.max(z).max(new_z);
+
+            prev_x = new_x;
+            prev_y = new_y;
+            prev_z = new_z;
+            x = new_x;
+            y = new_y;
+            z = new_z;
+            continue;
+        }
 
         // Only add line if position changed
         if (new_x - x).abs() > 0.0001 || (new_y - y).abs() > 0.0001 || (new_z - z).abs() > 0.0001 {
@@ -527,21 +696,24 @@ fn parse_gcode_content(content: &str) -> Toolpath {
                 x2: new_x,
                 y2: new_y,
                 z2: new_z,
+                seq,
+                feed: if motion == Motion::Rapid { 0.0 } else { feed },
             };
+            seq += 1;
 
-            if is_rapid {
+            if motion == Motion::Rapid {
                 rapids.push(line_seg);
             } else {
                 lines.push(line_seg);
+                min_z = min_z.min(prev_z).min(new_z);
+                max_z = max_z.max(prev_z).max(new_z);
             }
 
-            // Update bounds
-            min_x = min_x.min(new_x);
-            max_x = max_x.max(new_x);
-            min_y = min_y.min(new_y);
-            max_y = max_y.max(new_y);
-            min_z = min_z.min(new_z);
-            max_z = max_z.max(new_z);
+            // Update bounds from both ends, so the move out of the starting position fits
+            min_x = min_x.min(prev_x).min(new_x);
+            max_x = max_x.max(prev_x).max(new_x);
+            min_y = min_y.min(prev_y).min(new_y);
+            max_y = max_y.max(prev_y).max(new_y);
 
             prev_x = new_x;
             prev_y = new_y;
@@ -561,6 +733,11 @@ fn parse_gcode_content(content: &str) -> Toolpath {
         min_z = 0.0;
         max_z = 10.0;
     }
+    // Rapids only - nothing to shade
+    if min_z == f64::INFINITY {
+        min_z = 0.0;
+        max_z = 0.0;
+    }
 
     Toolpath {
         lines,
@@ -577,6 +754,26 @@ fn parse_gcode_content(content: &str) -> Toolpath {
     }
 }
 
+/// Bounding box of an arc: its end points plus every axis crossing it sweeps through
+fn arc_extents(cx: f64, cy: f64, radius: f64, start: f64, end: f64) -> (f64, f64, f64, f64) {
+    let quarter = std::f64::consts::FRAC_PI_2;
+    let (lo, hi) = (start.min(end), start.max(end));
+    let mut angles = vec![start, end];
+    let mut k = (lo / quarter).ceil();
+    while k * quarter <= hi {
+        angles.push(k * quarter);
+        k += 1.0;
+    }
+
+    angles.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY, f64::NEG_INFINITY),
+        |(x0, x1, y0, y1), a| {
+            let (px, py) = (cx + radius * a.cos(), cy + radius * a.sin());
+            (x0.min(px), x1.max(px), y0.min(py), y1.max(py))
+        },
+    )
+}
+
 fn parse_coord(line: &str, coord: char) -> Option<f64> {
     // Simple parser: find X/Y/Z followed by number
     let prefix = format!("{}", coord);
@@ -613,6 +810,31 @@ static INDEX_HTML: &str = r#"<!DOCTYPE html>
             font-size: 12px;
         }
         .disconnected { color: #f44 !important; }
+        #depth-toggle {
+            position: fixed; bottom: 10px; left: 10px;
+            color: #fff; background: rgba(0,0,0,0.7);
+            border: 1px solid #555; border-radius: 8px;
+            padding: 8px 15px; font-size: 12px; cursor: pointer;
+        }
+        #depth-legend {
+            position: fixed; bottom: 50px; left: 10px; display: none;
+            color: #fff; background: rgba(0,0,0,0.7);
+            padding: 10px; border-radius: 8px; font-size: 12px;
+        }
+        #depth-legend .bar {
+            width: 160px; height: 10px; margin: 5px 0;
+            background: linear-gradient(to right, rgb(255,224,160), rgb(110,55,0));
+        }
+        #playback {
+            position: fixed; bottom: 10px; left: 50%; transform: translateX(-50%);
+            display: flex; align-items: center; gap: 10px;
+            color: #fff; background: rgba(0,0,0,0.7);
+            padding: 8px 15px; border-radius: 8px; font-size: 12px;
+        }
+        #playback button, #playback select {
+            color: #fff; background: #333; border: 1px solid #555; border-radius: 4px;
+        }
+        #scrub { width: 40vw; }
         #mode-badge {
             position: fixed; top: 10px; left: 50%; transform: translateX(-50%);
             background: rgba(255,170,0,0.3);
@@ -632,13 +854,113 @@ static INDEX_HTML: &str = r#"<!DOCTYPE html>
         <span id="stats"></span>
     </div>
     <div id="status">● Live</div>
+    <button id="depth-toggle" title="Toggle depth shading (D)">Depth shading: off</button>
+    <div id="playback">
+        <button id="play" title="Play / pause (Space)">▶</button>
+        <input id="scrub" type="range" min="0" max="1000" value="1000">
+        <select id="speed" title="Playback speed">
+            <option value="1">1x</option>
+            <option value="10" selected>10x</option>
+            <option value="50">50x</option>
+            <option value="200">200x</option>
+        </select>
+        <span id="clock"></span>
+    </div>
+    <div id="depth-legend">
+        Cut depth
+        <div class="bar"></div>
+        <span id="depth-top"></span> <span style="float: right" id="depth-bottom"></span>
+    </div>
     
     <script>
         const canvas = document.getElementById('canvas');
         const ctx = canvas.getContext('2d');
-        let toolpath = { lines: [], rapids: [], bounds: { min_x: 0, max_x: 100, min_y: 0, max_y: 100 } };
+        let toolpath = { lines: [], arcs: [], rapids: [], bounds: { min_x: 0, max_x: 100, min_y: 0, max_y: 100, min_z: 0, max_z: 0 } };
         let scale = 1, offsetX = 0, offsetY = 0;
         let isDragging = false, lastX = 0, lastY = 0;
+        let depthShading = false;
+        
+        // Playback: moves are timed by length / feed and revealed up to the playhead (minutes)
+        const RAPID_RATE = 200; // Stand-in traverse rate for G00, units/min
+        let timeline = [], totalTime = 0, playhead = 0, playing = false, lastFrame = 0;
+        
+        function moveLength(item) {
+            const m = item.move;
+            if (item.kind === 'arc') return Math.hypot(m.i, m.j) * Math.abs(m.end_angle - m.start_angle);
+            return Math.hypot(m.x2 - m.x1, m.y2 - m.y1, m.z2 - m.z1);
+        }
+        
+        function buildTimeline() {
+            timeline = [
+                ...toolpath.rapids.map(move => ({ kind: 'rapid', move })),
+                ...toolpath.lines.map(move => ({ kind: 'cut', move })),
+                ...(toolpath.arcs || []).map(move => ({ kind: 'arc', move })),
+            ].sort((a, b) => a.move.seq - b.move.seq);
+            let t = 0;
+            for (const item of timeline) {
+                item.move.t0 = t;
+                t += moveLength(item) / (item.move.feed > 0 ? item.move.feed : RAPID_RATE);
+                item.move.t1 = t;
+            }
+            totalTime = t;
+            playhead = totalTime;
+            playing = false;
+        }
+        
+        // Fraction of a move already travelled at the playhead
+        function revealed(m) {
+            if (m.t1 === undefined || playhead >= m.t1) return 1;
+            if (playhead <= m.t0) return 0;
+            return (playhead - m.t0) / (m.t1 - m.t0);
+        }
+        
+        function pointAlong(item, f) {
+            const m = item.move;
+            if (item.kind === 'arc') {
+                const a = m.start_angle + (m.end_angle - m.start_angle) * f;
+                const r = Math.hypot(m.i, m.j);
+                return { x: m.x + m.i + r * Math.cos(a), y: m.y + m.j + r * Math.sin(a) };
+            }
+            return { x: m.x1 + (m.x2 - m.x1) * f, y: m.y1 + (m.y2 - m.y1) * f };
+        }
+        
+        function setPlaying(on) {
+            playing = on;
+            if (playing && playhead >= totalTime) playhead = 0;
+            document.getElementById('play').textContent = playing ? '❚❚' : '▶';
+            lastFrame = performance.now();
+            if (playing) requestAnimationFrame(tick);
+        }
+        
+        function tick(now) {
+            if (!playing) return;
+            const speed = parseFloat(document.getElementById('speed').value);
+            playhead += (now - lastFrame) / 60000 * speed;
+            lastFrame = now;
+            if (playhead >= totalTime) {
+                playhead = totalTime;
+                setPlaying(false);
+            }
+            draw();
+            if (playing) requestAnimationFrame(tick);
+        }
+        
+        // Light near the top of the cut, dark at the bottom
+        function depthColor(z) {
+            const b = toolpath.bounds;
+            const range = b.max_z - b.min_z;
+            const t = range > 0 ? Math.min(1, Math.max(0, (b.max_z - z) / range)) : 0;
+            const lerp = (a, c) => Math.round(a + (c - a) * t);
+            return `rgb(${lerp(255, 110)}, ${lerp(224, 55)}, ${lerp(160, 0)})`;
+        }
+        
+        function toggleDepthShading() {
+            depthShading = !depthShading;
+            document.getElementById('depth-toggle').textContent =
+                `Depth shading: ${depthShading ? 'on' : 'off'}`;
+            document.getElementById('depth-legend').style.display = depthShading ? 'block' : 'none';
+            draw();
+        }
         
         function resize() {
             canvas.width = window.innerWidth;
@@ -685,8 +1007,10 @@ static INDEX_HTML: &str = r#"<!DOCTYPE html>
             ctx.lineWidth = 1;
             ctx.setLineDash([5, 5]);
             for (const line of toolpath.rapids) {
+                const f = revealed(line);
+                if (f <= 0) continue;
                 const p1 = worldToScreen(line.x1, line.y1);
-                const p2 = worldToScreen(line.x2, line.y2);
+                const p2 = worldToScreen(line.x1 + (line.x2 - line.x1) * f, line.y1 + (line.y2 - line.y1) * f);
                 ctx.beginPath(); ctx.moveTo(p1.x, p1.y); ctx.lineTo(p2.x, p2.y); ctx.stroke();
             }
             ctx.setLineDash([]);
@@ -695,11 +1019,38 @@ static INDEX_HTML: &str = r#"<!DOCTYPE html>
             ctx.strokeStyle = '#ffaa00';
             ctx.lineWidth = 2;
             for (const line of toolpath.lines) {
+                const f = revealed(line);
+                if (f <= 0) continue;
                 const p1 = worldToScreen(line.x1, line.y1);
-                const p2 = worldToScreen(line.x2, line.y2);
+                const p2 = worldToScreen(line.x1 + (line.x2 - line.x1) * f, line.y1 + (line.y2 - line.y1) * f);
+                if (depthShading) ctx.strokeStyle = depthColor(Math.min(line.z1, line.z2));
                 ctx.beginPath(); ctx.moveTo(p1.x, p1.y); ctx.lineTo(p2.x, p2.y); ctx.stroke();
             }
             
+            // Draw arcs (cyan)
+            ctx.strokeStyle = '#00cccc';
+            for (const arc of toolpath.arcs || []) {
+                const f = revealed(arc);
+                if (f <= 0) continue;
+                if (depthShading) ctx.strokeStyle = depthColor(arc.z);
+                const c = worldToScreen(arc.x + arc.i, arc.y + arc.j);
+                const r = Math.hypot(arc.i, arc.j) * scale;
+                const end = arc.start_angle + (arc.end_angle - arc.start_angle) * f;
+                // Screen Y is flipped, so angles negate
+                ctx.beginPath(); ctx.arc(c.x, c.y, r, -arc.start_angle, -end, !arc.clockwise); ctx.stroke();
+            }
+            
+            // Tool dot rides the move under the playhead
+            if (playhead < totalTime) {
+                const current = timeline.find(item => playhead < item.move.t1);
+                if (current) {
+                    const p = pointAlong(current, revealed(current.move));
+                    const dot = worldToScreen(p.x, p.y);
+                    ctx.fillStyle = '#fff';
+                    ctx.beginPath(); ctx.arc(dot.x, dot.y, 6, 0, Math.PI * 2); ctx.fill();
+                }
+            }
+            
             // Draw start point
             if (toolpath.lines.length > 0) {
                 const start = worldToScreen(toolpath.lines[0].x1, toolpath.lines[0].y1);
@@ -712,7 +1063,12 @@ static INDEX_HTML: &str = r#"<!DOCTYPE html>
             document.getElementById('bounds').textContent = 
                 `X: ${b.min_x.toFixed(1)} to ${b.max_x.toFixed(1)} | Y: ${b.min_y.toFixed(1)} to ${b.max_y.toFixed(1)}`;
             document.getElementById('stats').textContent = 
-                `${toolpath.lines.length} cuts, ${toolpath.rapids.length} rapids`;
+                `${toolpath.lines.length} cuts, ${(toolpath.arcs || []).length} arcs, ${toolpath.rapids.length} rapids`;
+            document.getElementById('clock').textContent =
+                `${playhead.toFixed(2)} / ${totalTime.toFixed(2)} min`;
+            if (totalTime > 0) document.getElementById('scrub').value = Math.round(playhead / totalTime * 1000);
+            document.getElementById('depth-top').textContent = `Z${b.max_z.toFixed(3)}`;
+            document.getElementById('depth-bottom').textContent = `Z${b.min_z.toFixed(3)}`;
         }
         
         // Mouse controls
@@ -733,10 +1089,23 @@ static INDEX_HTML: &str = r#"<!DOCTYPE html>
             draw();
         });
         
+        document.getElementById('depth-toggle').addEventListener('click', toggleDepthShading);
+        window.addEventListener('keydown', e => {
+            if (e.key === 'd' || e.key === 'D') toggleDepthShading();
+            if (e.key === ' ') { e.preventDefault(); setPlaying(!playing); }
+        });
+        document.getElementById('play').addEventListener('click', () => setPlaying(!playing));
+        document.getElementById('scrub').addEventListener('input', e => {
+            setPlaying(false);
+            playhead = e.target.value / 1000 * totalTime;
+            draw();
+        });
+        
         // WebSocket
-        const ws = new WebSocket('ws://localhost:3030/ws');
+        const ws = new WebSocket(`ws://${location.host}/ws`);
         ws.onmessage = (event) => {
             toolpath = JSON.parse(event.data);
+            buildTimeline();
             fitToView();
         };
         ws.onclose = () => {
@@ -910,7 +1279,7 @@ static FOLDER_HTML: &str = r#"<!DOCTYPE html>
     <script>
         const files = {{FILE_LIST}};
         let currentFile = files[0];
-        let toolpath = { lines: [], rapids: [], bounds: { min_x: 0, max_x: 100, min_y: 0, max_y: 100 } };
+        let toolpath = { lines: [], arcs: [], rapids: [], bounds: { min_x: 0, max_x: 100, min_y: 0, max_y: 100 } };
         let ws = null;
         let scale = 1, offsetX = 0, offsetY = 0;
         let isDragging = false, lastX = 0, lastY = 0;
@@ -1042,6 +1411,16 @@ static FOLDER_HTML: &str = r#"<!DOCTYPE html>
             });
             ctx.stroke();
 
+            // Arcs (cyan); screen Y is flipped, so angles negate
+            ctx.strokeStyle = '#00cccc';
+            (toolpath.arcs || []).forEach(arc => {
+                const c = worldToScreen(arc.x + arc.i, arc.y + arc.j);
+                const r = Math.hypot(arc.i, arc.j) * scale;
+                ctx.beginPath();
+                ctx.arc(c.x, c.y, r, -arc.start_angle, -arc.end_angle, !arc.clockwise);
+                ctx.stroke();
+            });
+
             // Start point (green)
             if (toolpath.rapids.length > 0) {
                 const start = worldToScreen(toolpath.rapids[0].x1, toolpath.rapids[0].y1);
@@ -1092,33 +1471,37 @@ pub fn export_to_png(
     width: u32,
     height: u32,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    use image::{Rgb, RgbImage};
-
     let toolpath = parse_gcode(gcode_file);
+    let img = render_image(&toolpath, width, height);
+
+    // Save image
+    img.save(output_path)?;
+    println!("✓ Exported to {}", output_path);
+    println!("  Dimensions: {}x{}", width, height);
+    let bounds = &toolpath.bounds;
+    println!(
+        "  Bounds: X{:.3} to {:.3}, Y{:.3} to {:.3}",
+        bounds.min_x, bounds.max_x, bounds.min_y, bounds.max_y
+    );
+
+    Ok(())
+}
+
+/// Rasterize the toolpath's XY plan view, fitted to a `width` x `height` image
+fn render_image(toolpath: &Toolpath, width: u32, height: u32) -> image::RgbImage {
+    use image::{Rgb, RgbImage};
 
     // Create image buffer with dark background
     let mut img = RgbImage::from_pixel(width, height, Rgb([26, 26, 26]));
 
     // Calculate scale and offset to fit toolpath in image
     let bounds = &toolpath.bounds;
-    let margin = 50.0;
-
-    let x_range = bounds.max_x - bounds.min_x;
-    let y_range = bounds.max_y - bounds.min_y;
-
-    if x_range > 0.0 && y_range > 0.0 {
-        let scale_x = (width as f64 - 2.0 * margin) / x_range;
-        let scale_y = (height as f64 - 2.0 * margin) / y_range;
-        let scale = scale_x.min(scale_y);
-
-        let offset_x = (width as f64 - x_range * scale) / 2.0 - bounds.min_x * scale;
-        let offset_y = (height as f64 - y_range * scale) / 2.0 + bounds.min_y * scale;
 
+    if let Some(view) = Viewport::fit(bounds, width, height) {
         // Helper to transform world coords to image coords
         let world_to_screen = |x: f64, y: f64| -> (i32, i32) {
-            let sx = (x * scale + offset_x) as i32;
-            let sy = (height as f64 - (y * scale + offset_y)) as i32;
-            (sx, sy)
+            let (sx, sy) = view.world_to_screen(x, y);
+            (sx as i32, sy as i32)
         };
 
         // Draw bounds rectangle
@@ -1140,6 +1523,23 @@ pub fn export_to_png(
             draw_line(&mut img, x1, y1, x2, y2, Rgb([255, 170, 0]));
         }
 
+        // Draw arcs (amber/gold, they're cuts too) as short chords
+        for arc in &toolpath.arcs {
+            let (cx, cy) = (arc.x + arc.i, arc.y + arc.j);
+            let radius = arc.i.hypot(arc.j);
+            let sweep = arc.end_angle - arc.start_angle;
+            // Chords no longer than ~2 px, so large arcs stay smooth
+            let length = radius * sweep.abs() * view.scale;
+            let steps = (length / 2.0).ceil().clamp(8.0, 4096.0) as usize;
+            let mut last = world_to_screen(arc.x, arc.y);
+            for step in 1..=steps {
+                let a = arc.start_angle + sweep * step as f64 / steps as f64;
+                let next = world_to_screen(cx + radius * a.cos(), cy + radius * a.sin());
+                draw_line(&mut img, last.0, last.1, next.0, next.1, Rgb([255, 170, 0]));
+                last = next;
+            }
+        }
+
         // Draw start point (green)
         if let Some(first) = toolpath.lines.first() {
             let (sx, sy) = world_to_screen(first.x1, first.y1);
@@ -1147,18 +1547,178 @@ pub fn export_to_png(
         }
     }
 
-    // Save image
-    img.save(output_path)?;
+    img
+}
+
+/// Scale and offset that fit the toolpath bounds into an image, Y flipped to point up
+struct Viewport {
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+    height: f64,
+}
+
+impl Viewport {
+    /// Pixels kept clear around the toolpath
+    const MARGIN: f64 = 50.0;
+
+    /// `None` when the toolpath has no extent along X or Y
+    fn fit(bounds: &Bounds, width: u32, height: u32) -> Option<Self> {
+        let x_range = bounds.max_x - bounds.min_x;
+        let y_range = bounds.max_y - bounds.min_y;
+        if x_range <= 0.0 || y_range <= 0.0 {
+            return None;
+        }
+
+        let scale_x = (width as f64 - 2.0 * Self::MARGIN) / x_range;
+        let scale_y = (height as f64 - 2.0 * Self::MARGIN) / y_range;
+        let scale = scale_x.min(scale_y);
+
+        Some(Self {
+            scale,
+            offset_x: (width as f64 - x_range * scale) / 2.0 - bounds.min_x * scale,
+            offset_y: (height as f64 - y_range * scale) / 2.0 + bounds.min_y * scale,
+            height: height as f64,
+        })
+    }
+
+    fn world_to_screen(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.scale + self.offset_x, self.height - (y * self.scale + self.offset_y))
+    }
+}
+
+/// Export the toolpath to an SVG with `rapids` and `cuts` layers
+pub fn export_to_svg(gcode_file: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let toolpath = parse_gcode(gcode_file);
+    std::fs::write(output_path, toolpath_to_svg(&toolpath, 800, 600))?;
     println!("✓ Exported to {}", output_path);
-    println!("  Dimensions: {}x{}", width, height);
-    println!(
-        "  Bounds: X{:.3} to {:.3}, Y{:.3} to {:.3}",
-        bounds.min_x, bounds.max_x, bounds.min_y, bounds.max_y
-    );
+    Ok(())
+}
 
+/// Export the toolpath to a DXF in machine units with `RAPIDS` and `CUTS` layers
+pub fn export_to_dxf(gcode_file: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let toolpath = parse_gcode(gcode_file);
+    std::fs::write(output_path, toolpath_to_dxf(&toolpath))?;
+    println!("✓ Exported to {}", output_path);
     Ok(())
 }
 
+/// Render the toolpath as SVG, colored like the viewer: grey rapids, amber cuts, cyan arcs
+fn toolpath_to_svg(toolpath: &Toolpath, width: u32, height: u32) -> String {
+    use std::fmt::Write;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = width,
+        h = height
+    );
+    svg.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"#1a1a1a\"/>\n");
+
+    let Some(view) = Viewport::fit(&toolpath.bounds, width, height) else {
+        svg.push_str("</svg>\n");
+        return svg;
+    };
+    let line = |svg: &mut String, l: &Line| {
+        let (x1, y1) = view.world_to_screen(l.x1, l.y1);
+        let (x2, y2) = view.world_to_screen(l.x2, l.y2);
+        let _ = writeln!(
+            svg,
+            "    <line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\"/>",
+            x1, y1, x2, y2
+        );
+    };
+
+    svg.push_str("  <g id=\"rapids\" stroke=\"#666666\" stroke-width=\"1\" fill=\"none\">\n");
+    for rapid in &toolpath.rapids {
+        line(&mut svg, rapid);
+    }
+    svg.push_str("  </g>\n");
+
+    svg.push_str("  <g id=\"cuts\" stroke=\"#ffaa00\" stroke-width=\"1.5\" fill=\"none\">\n");
+    for cut in &toolpath.lines {
+        line(&mut svg, cut);
+    }
+    // Split each arc at its midpoint so a full circle stays drawable and
+    // neither half needs the large-arc flag. Flipping Y turns world CCW into
+    // SVG's positive sweep.
+    for arc in &toolpath.arcs {
+        let (cx, cy) = (arc.x + arc.i, arc.y + arc.j);
+        let radius = arc.i.hypot(arc.j);
+        let at = |a: f64| view.world_to_screen(cx + radius * a.cos(), cy + radius * a.sin());
+        let (sx, sy) = at(arc.start_angle);
+        let (mx, my) = at((arc.start_angle + arc.end_angle) / 2.0);
+        let (ex, ey) = at(arc.end_angle);
+        let r = radius * view.scale;
+        let sweep = if arc.clockwise { 0 } else { 1 };
+        let _ = writeln!(
+            svg,
+            "    <path stroke=\"#00cccc\" d=\"M {:.2} {:.2} A {r:.2} {r:.2} 0 0 {s} {:.2} {:.2} A {r:.2} {r:.2} 0 0 {s} {:.2} {:.2}\"/>",
+            sx, sy, mx, my, ex, ey,
+            r = r,
+            s = sweep
+        );
+    }
+    svg.push_str("  </g>\n</svg>\n");
+    svg
+}
+
+/// Render the toolpath as an R12 ASCII DXF in world coordinates.
+/// Arcs stay true ARC/CIRCLE entities; DXF arcs always run CCW, so CW arcs swap their ends.
+fn toolpath_to_dxf(toolpath: &Toolpath) -> String {
+    use std::fmt::Write;
+
+    // AutoCAD color index: 8 grey, 40 amber, 4 cyan
+    let mut dxf = String::from("0\nSECTION\n2\nTABLES\n0\nTABLE\n2\nLAYER\n70\n2\n");
+    for (name, color) in [("RAPIDS", 8), ("CUTS", 40)] {
+        let _ = write!(dxf, "0\nLAYER\n2\n{}\n70\n0\n62\n{}\n6\nCONTINUOUS\n", name, color);
+    }
+    dxf.push_str("0\nENDTAB\n0\nENDSEC\n0\nSECTION\n2\nENTITIES\n");
+
+    let line = |dxf: &mut String, layer: &str, l: &Line| {
+        let _ = write!(
+            dxf,
+            "0\nLINE\n8\n{}\n10\n{:.4}\n20\n{:.4}\n30\n{:.4}\n11\n{:.4}\n21\n{:.4}\n31\n{:.4}\n",
+            layer, l.x1, l.y1, l.z1, l.x2, l.y2, l.z2
+        );
+    };
+    for rapid in &toolpath.rapids {
+        line(&mut dxf, "RAPIDS", rapid);
+    }
+    for cut in &toolpath.lines {
+        line(&mut dxf, "CUTS", cut);
+    }
+    for arc in &toolpath.arcs {
+        let (cx, cy) = (arc.x + arc.i, arc.y + arc.j);
+        let radius = arc.i.hypot(arc.j);
+        let sweep = (arc.end_angle - arc.start_angle).abs();
+        if sweep >= 2.0 * std::f64::consts::PI - 1e-9 {
+            let _ = write!(
+                dxf,
+                "0\nCIRCLE\n8\nCUTS\n62\n4\n10\n{:.4}\n20\n{:.4}\n30\n{:.4}\n40\n{:.4}\n",
+                cx, cy, arc.z, radius
+            );
+        } else {
+            let (from, to) = if arc.clockwise {
+                (arc.end_angle, arc.start_angle)
+            } else {
+                (arc.start_angle, arc.end_angle)
+            };
+            let _ = write!(
+                dxf,
+                "0\nARC\n8\nCUTS\n62\n4\n10\n{:.4}\n20\n{:.4}\n30\n{:.4}\n40\n{:.4}\n50\n{:.4}\n51\n{:.4}\n",
+                cx,
+                cy,
+                arc.z,
+                radius,
+                from.to_degrees().rem_euclid(360.0),
+                to.to_degrees().rem_euclid(360.0)
+            );
+        }
+    }
+    dxf.push_str("0\nENDSEC\n0\nEOF\n");
+    dxf
+}
+
 /// Draw a line on the image using Bresenham's algorithm
 fn draw_line(img: &mut image::RgbImage, x0: i32, y0: i32, x1: i32, y1: i32, color: image::Rgb<u8>) {
     let mut x0 = x0;
@@ -1212,3 +1772,139 @@ fn draw_circle(img: &mut image::RgbImage, cx: i32, cy: i32, r: i32, color: image
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gcode_drill_cycle() {
+        let toolpath = parse_gcode_content(
+            "G00 X1.0000 Y0.5000\nG00 Z0.5000\nG83 Z-0.5500 R0.1000 Q0.2500 F15.0\nX2.0000 Y0.5000\nG80",
+        );
+
+        // Both holes are drilled at feed, from the R plane to full depth
+        let plunges: Vec<&Line> = toolpath
+            .lines
+            .iter()
+            .filter(|l| (l.z2 + 0.55).abs() < 1e-9)
+            .collect();
+        assert_eq!(plunges.len(), 2);
+        assert!(plunges.iter().all(|l| (l.z1 - 0.1).abs() < 1e-9));
+        assert!((plunges[1].x2 - 2.0).abs() < 1e-9);
+
+        // Playback order and timing: the plunge follows its approach rapids at the cycle feed
+        assert_eq!(plunges[0].feed, 15.0);
+        assert!(toolpath.rapids.iter().all(|l| l.feed == 0.0));
+        assert!(toolpath.rapids.iter().any(|l| l.seq + 1 == plunges[0].seq));
+
+        assert!(!toolpath.rapids.iter().any(|l| l.z2 < -1e-9));
+        assert!((toolpath.bounds.min_z + 0.55).abs() < 1e-9);
+        // Z bounds span the cut, not the clearance rapids
+        assert!((toolpath.bounds.max_z - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parse_gcode_arcs() {
+        let toolpath = parse_gcode_content(
+            "G00 X1.0000 Y0.0000\nG01 Z-0.1000 F10.0\nG03 X1.0000 Y0.0000 I-1.0000 J0.0000 F20.0\nG02 X0.0000 Y1.0000 I-1.0000 J0.0000",
+        );
+
+        assert_eq!(toolpath.arcs.len(), 2);
+        assert_eq!(toolpath.lines.len(), 1); // The arcs are not chords
+
+        let circle = &toolpath.arcs[0];
+        assert!(!circle.clockwise);
+        assert!((circle.end_angle - circle.start_angle - 2.0 * std::f64::consts::PI).abs() < 1e-9);
+
+        let quarter = &toolpath.arcs[1];
+        assert!(quarter.clockwise);
+        assert!((quarter.start_angle - quarter.end_angle - 1.5 * std::f64::consts::PI).abs() < 1e-9);
+
+        // The full circle around the origin sets the bounds
+        assert!((toolpath.bounds.min_x + 1.0).abs() < 1e-9);
+        assert!((toolpath.bounds.min_y + 1.0).abs() < 1e-9);
+        assert!((toolpath.bounds.max_y - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bounds_hold_arc_bulge_and_start() {
+        let toolpath = parse_gcode_content(
+            "G00 X10.0000 Y10.0000\nG01 Z-0.1000 F10.0\nG02 X12.0000 Y10.0000 I1.0000 J0.0000",
+        );
+
+        // The clockwise half circle bulges up to Y11, above both of its end points
+        assert!((toolpath.bounds.max_y - 11.0).abs() < 1e-9);
+        assert!((toolpath.bounds.max_x - 12.0).abs() < 1e-9);
+        // The first rapid is drawn from the starting position at the origin
+        assert!(toolpath.bounds.min_x.abs() < 1e-9);
+        assert!(toolpath.bounds.min_y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_incremental_moves_match_absolute() {
+        let absolute = parse_gcode_content(
+            "G90 G00 X1.0000 Y1.0000\nG01 Z-0.1000 F10.0\nG01 X3.0000\nG03 X3.0000 Y3.0000 I0.0000 J1.0000\nG01 X1.0000\nG00 Z0.5000\nG81 X4.0000 Y4.0000 R0.1000 Z-0.2500 F5.0\nG80",
+        );
+        let incremental = parse_gcode_content(
+            "G90 G00 X1.0000 Y1.0000\nG91 G01 Z-5.1000 F10.0\nG01 X2.0000\nG03 X0.0000 Y2.0000 I0.0000 J1.0000\nG01 X-2.0000\nG00 Z0.6000\nG81 X3.0000 Y1.0000 R-0.4000 Z-0.3500 F5.0\nG80\nG90",
+        );
+
+        // Every move's end point in playback order, to 4 places
+        let ends = |toolpath: &Toolpath| -> Vec<(f64, f64, f64)> {
+            let round = |v: f64| (v * 1e4).round() / 1e4;
+            let mut moves: Vec<&Line> = toolpath.lines.iter().chain(&toolpath.rapids).collect();
+            moves.sort_by_key(|l| l.seq);
+            moves.iter().map(|l| (round(l.x2), round(l.y2), round(l.z2))).collect()
+        };
+        assert_eq!(ends(&incremental), ends(&absolute));
+        assert_eq!(incremental.arcs.len(), 1);
+        assert!((incremental.arcs[0].x - 3.0).abs() < 1e-9);
+        assert!((incremental.arcs[0].y - 1.0).abs() < 1e-9);
+        assert!((incremental.bounds.max_x - 4.0).abs() < 1e-9);
+        assert!((incremental.bounds.min_z + 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_png_draws_arcs_in_cut_color() {
+        let toolpath = parse_gcode_content(
+            "G00 X-1.0000 Y0.0000\nG01 Z-0.1000 F10.0\nG02 X1.0000 Y0.0000 I1.0000 J0.0000\nG00 Z0.5000",
+        );
+        let img = render_image(&toolpath, 400, 300);
+        let view = Viewport::fit(&toolpath.bounds, 400, 300).unwrap();
+
+        // Points along the clockwise half circle over the origin, off any rapid
+        for angle in [150.0_f64, 120.0, 90.0, 60.0, 30.0] {
+            let (x, y) = (angle.to_radians().cos(), angle.to_radians().sin());
+            let (sx, sy) = view.world_to_screen(x, y);
+            let near = (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)));
+            let hit = near
+                .map(|(dx, dy)| img.get_pixel((sx as i32 + dx) as u32, (sy as i32 + dy) as u32))
+                .any(|p| p.0 == [255, 170, 0]);
+            assert!(hit, "no arc pixel near {angle} degrees");
+        }
+    }
+
+    #[test]
+    fn test_vector_exports_keep_layers_and_arcs() {
+        let toolpath = parse_gcode_content(
+            "G00 X1.0000 Y0.0000\nG01 Z-0.1000 F10.0\nG03 X1.0000 Y0.0000 I-1.0000 J0.0000\nG02 X0.0000 Y1.0000 I-1.0000 J0.0000\nG00 Z0.5000",
+        );
+
+        let svg = toolpath_to_svg(&toolpath, 800, 600);
+        assert!(svg.contains("<g id=\"rapids\" stroke=\"#666666\""));
+        assert!(svg.contains("<g id=\"cuts\" stroke=\"#ffaa00\""));
+        // Each arc is one path of two real SVG arcs, CCW then CW
+        assert_eq!(svg.matches("<path").count(), 2);
+        assert_eq!(svg.matches(" 0 0 1 ").count(), 2);
+        assert_eq!(svg.matches(" 0 0 0 ").count(), 2);
+
+        let dxf = toolpath_to_dxf(&toolpath);
+        assert!(dxf.contains("0\nLAYER\n2\nRAPIDS\n"));
+        assert!(dxf.contains("0\nLAYER\n2\nCUTS\n"));
+        assert!(dxf.contains("0\nCIRCLE\n8\nCUTS\n"));
+        // The 270° CW arc from 0° to 90° is written as the CCW arc from 90° round to 0°
+        assert!(dxf.contains("40\n1.0000\n50\n90.0000\n51\n0.0000\n"));
+        assert!(dxf.trim_end().ends_with("EOF"));
+    }
+}
@@ -28,6 +28,11 @@ impl Camera {
         }
     }
     
+    /// Distance from the camera to the point it orbits
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+    
     pub fn view_matrix(&self) -> glm::Mat4 {
         glm::look_at(&self.position, &self.target, &self.up)
     }
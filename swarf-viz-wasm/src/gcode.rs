@@ -100,6 +100,33 @@ pub fn parse(gcode: &str) -> Toolpath {
             || (new_y - y).abs() > 0.0001 
             || (new_z - z).abs() > 0.0001;
         
+        // Arcs with a center become short chords so the renderer draws a curve
+        let is_arc = matches!(move_type, MoveType::ArcCW | MoveType::ArcCCW);
+        let center = match (parse_coord(line, 'I'), parse_coord(line, 'J')) {
+            (None, None) => None,
+            (i, j) => Some((x + i.unwrap_or(0.0), y + j.unwrap_or(0.0))),
+        };
+        if let (true, Some(center)) = (is_arc, center) {
+            let clockwise = matches!(move_type, MoveType::ArcCW);
+            moves.extend(tessellate_arc(
+                move_type.clone(),
+                (x, y, z),
+                (new_x, new_y, new_z),
+                center,
+                clockwise,
+                new_feed,
+            ));
+
+            prev_x = new_x;
+            prev_y = new_y;
+            prev_z = new_z;
+            x = new_x;
+            y = new_y;
+            z = new_z;
+            feed = new_feed;
+            continue;
+        }
+
         if pos_changed {
             moves.push(Move {
                 kind: move_type.clone(),
@@ -121,6 +148,61 @@ pub fn parse(gcode: &str) -> Toolpath {
     Toolpath { moves }
 }
 
+/// Largest angle one chord of a tessellated arc may span (5 degrees)
+const ARC_STEP: f32 = std::f32::consts::PI / 36.0;
+
+/// Split an arc into chords; a start point equal to the end point is a full circle
+fn tessellate_arc(
+    kind: MoveType,
+    start: (f32, f32, f32),
+    end: (f32, f32, f32),
+    center: (f32, f32),
+    clockwise: bool,
+    feed: f32,
+) -> Vec<Move> {
+    let (cx, cy) = center;
+    let radius = (start.0 - cx).hypot(start.1 - cy);
+    let start_angle = (start.1 - cy).atan2(start.0 - cx);
+    let end_angle = (end.1 - cy).atan2(end.0 - cx);
+
+    let tau = 2.0 * std::f32::consts::PI;
+    let mut sweep = if clockwise {
+        start_angle - end_angle
+    } else {
+        end_angle - start_angle
+    }
+    .rem_euclid(tau);
+    if sweep < 1e-6 {
+        sweep = tau;
+    }
+    let sweep = if clockwise { -sweep } else { sweep };
+
+    let steps = (sweep.abs() / ARC_STEP).ceil().max(1.0) as usize;
+    let mut moves = Vec::with_capacity(steps);
+    let mut last = start;
+    for step in 1..=steps {
+        let t = step as f32 / steps as f32;
+        let next = if step == steps {
+            end
+        } else {
+            let angle = start_angle + sweep * t;
+            (
+                cx + radius * angle.cos(),
+                cy + radius * angle.sin(),
+                start.2 + (end.2 - start.2) * t,
+            )
+        };
+        moves.push(Move {
+            kind: kind.clone(),
+            x1: last.0, y1: last.1, z1: last.2,
+            x2: next.0, y2: next.1, z2: next.2,
+            feed,
+        });
+        last = next;
+    }
+    moves
+}
+
 fn parse_coord(line: &str, coord: char) -> Option<f32> {
     let prefix = coord.to_string();
     if let Some(pos) = line.to_uppercase().find(&prefix) {
@@ -133,3 +215,31 @@ fn parse_coord(line: &str, coord: char) -> Option<f32> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quarter_arc_is_tessellated_on_circle() {
+        // CCW quarter circle of radius 2 around (1, 1)
+        let toolpath = parse("G00 X3.0 Y1.0\nG03 X1.0 Y3.0 I-2.0 J0.0 F20.0");
+
+        let arcs: Vec<&Move> = toolpath
+            .moves
+            .iter()
+            .filter(|m| matches!(m.kind, MoveType::ArcCCW))
+            .collect();
+        assert!(arcs.len() > 4, "expected several segments, got {}", arcs.len());
+
+        for m in &arcs {
+            let r = (m.x2 - 1.0).hypot(m.y2 - 1.0);
+            assert!((r - 2.0).abs() < 1e-4, "endpoint off the circle: r={}", r);
+            // Quarter from 3 to 12 o'clock stays in the upper-right quadrant
+            assert!(m.x2 >= 1.0 - 1e-4 && m.y2 >= 1.0 - 1e-4);
+        }
+
+        let last = arcs.last().unwrap();
+        assert_eq!((last.x2, last.y2), (1.0, 3.0));
+    }
+}
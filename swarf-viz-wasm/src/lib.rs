@@ -1,15 +1,17 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGl2RenderingContext as GL, HtmlCanvasElement};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{WebGl2RenderingContext as GL, HtmlCanvasElement, Response};
 use js_sys::Float32Array;
 use serde::{Deserialize, Serialize};
 use nalgebra_glm as glm;
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 mod gcode;
 mod renderer;
 mod camera;
 
-use gcode::Toolpath;
+use gcode::{Bounds, Toolpath};
 use renderer::Renderer;
 use camera::Camera;
 
@@ -26,6 +28,13 @@ pub struct Viz3D {
     renderer: Renderer,
     camera: Camera,
     toolpath: Toolpath,
+    /// Orthographic instead of perspective projection
+    ortho: bool,
+    /// Largest toolpath extent and camera distance at the last fit, so the
+    /// orthographic frame holds the toolpath and follows zoom
+    fit: (f32, f32),
+    /// Stock size from `set_stock`; without it the box follows the toolpath
+    stock: Option<(f32, f32, f32)>,
 }
 
 #[wasm_bindgen]
@@ -57,6 +66,9 @@ impl Viz3D {
             renderer,
             camera,
             toolpath: Toolpath::default(),
+            ortho: false,
+            fit: (100.0, 100.0 * 3.0_f32.sqrt()),
+            stock: None,
         })
     }
     
@@ -64,24 +76,38 @@ impl Viz3D {
     pub fn load_gcode(&mut self, gcode: &str) {
         self.toolpath = gcode::parse(gcode);
         self.renderer.update_toolpath(&self.gl, &self.toolpath);
+        self.update_reference();
         
         // Auto-fit camera to toolpath bounds
-        if let Some(bounds) = self.toolpath.bounds() {
-            let center = glm::vec3(
-                (bounds.min_x + bounds.max_x) / 2.0,
-                (bounds.min_y + bounds.max_y) / 2.0,
-                (bounds.min_z + bounds.max_z) / 2.0,
-            );
-            let size = (bounds.max_x - bounds.min_x)
-                .max(bounds.max_y - bounds.min_y)
-                .max(bounds.max_z - bounds.min_z);
-            let distance = size * 1.5;
-            
-            self.camera = Camera::new(
-                glm::vec3(center.x + distance, center.y + distance, center.z + distance),
-                center,
-            );
-        }
+        self.reset_view();
+    }
+    
+    /// Load G-code from a file input or drop, as the file's raw bytes
+    #[wasm_bindgen]
+    pub fn load_bytes(&mut self, bytes: &[u8]) {
+        self.load_gcode(&gcode_text(bytes));
+    }
+    
+    /// Re-fit the camera to the toolpath from the isometric corner, undoing any
+    /// orbit, pan, or zoom
+    #[wasm_bindgen]
+    pub fn reset_view(&mut self) {
+        self.fit_view(glm::vec3(1.0, 1.0, 1.0));
+    }
+    
+    /// Fit the camera to the toolpath looking from `top`, `front`, `right`, or `iso`
+    #[wasm_bindgen]
+    pub fn set_view(&mut self, preset: &str) -> Result<(), JsValue> {
+        let direction = match preset {
+            "top" => glm::vec3(0.0, 0.0, 1.0),
+            // Straight down -Y would look along the camera's up axis
+            "front" => glm::vec3(0.0, -1.0, 0.01),
+            "right" => glm::vec3(1.0, 0.0, 0.0),
+            "iso" => glm::vec3(1.0, 1.0, 1.0),
+            _ => return Err(JsValue::from_str(&format!("unknown view preset: {}", preset))),
+        };
+        self.fit_view(direction);
+        Ok(())
     }
     
     #[wasm_bindgen]
@@ -92,12 +118,7 @@ impl Viz3D {
         self.gl.viewport(0, 0, width, height);
         self.gl.clear(GL::COLOR_BUFFER_BIT | GL::DEPTH_BUFFER_BIT);
         
-        let projection = glm::perspective(
-            width as f32 / height as f32,
-            45.0_f32.to_radians(),
-            0.1,
-            10000.0,
-        );
+        let projection = self.projection(width as f32 / height as f32);
         
         self.renderer.render(
             &self.gl,
@@ -106,6 +127,33 @@ impl Viz3D {
         );
     }
     
+    /// Draw the stock as an `x` by `y` by `z` box, with the work zero at its left
+    /// front top corner as swarf writes it
+    #[wasm_bindgen]
+    pub fn set_stock(&mut self, x: f32, y: f32, z: f32) {
+        self.stock = Some((x, y, z));
+        self.update_reference();
+    }
+    
+    /// Show or hide the stock box and origin axes
+    #[wasm_bindgen]
+    pub fn set_show_reference(&mut self, show: bool) {
+        self.renderer.show_reference = show;
+    }
+    
+    /// Color cuts by depth (`true`) or by move type
+    #[wasm_bindgen]
+    pub fn set_depth_shading(&mut self, on: bool) {
+        self.renderer.depth_shading = on;
+        self.renderer.update_toolpath(&self.gl, &self.toolpath);
+    }
+    
+    /// Switch between orthographic (`true`) and perspective projection
+    #[wasm_bindgen]
+    pub fn set_projection(&mut self, ortho: bool) {
+        self.ortho = ortho;
+    }
+    
     #[wasm_bindgen]
     pub fn rotate_camera(&mut self, delta_x: f32, delta_y: f32) {
         self.camera.orbit(delta_x * 0.01, delta_y * 0.01);
@@ -121,3 +169,98 @@ impl Viz3D {
         self.camera.pan(delta_x * 0.1, delta_y * 0.1);
     }
 }
+
+impl Viz3D {
+    /// Aim at the center of the toolpath bounds from `direction`, scaled so the
+    /// largest extent fits; with nothing loaded, the starting view
+    fn fit_view(&mut self, direction: glm::Vec3) {
+        let Some(bounds) = self.toolpath.bounds() else {
+            self.camera = Camera::new(direction * 100.0, glm::vec3(0.0, 0.0, 0.0));
+            self.fit = (100.0, self.camera.distance());
+            return;
+        };
+        let center = glm::vec3(
+            (bounds.min_x + bounds.max_x) / 2.0,
+            (bounds.min_y + bounds.max_y) / 2.0,
+            (bounds.min_z + bounds.max_z) / 2.0,
+        );
+        let size = (bounds.max_x - bounds.min_x)
+            .max(bounds.max_y - bounds.min_y)
+            .max(bounds.max_z - bounds.min_z);
+        let distance = size * 1.5;
+        
+        self.camera = Camera::new(center + direction * distance, center);
+        self.fit = (size, self.camera.distance());
+    }
+    
+    /// Rebuild the stock box and axes: the stock from `set_stock`, otherwise the
+    /// toolpath's footprint from its deepest point up to Z0
+    fn update_reference(&mut self) {
+        let stock = match (self.stock, self.toolpath.bounds()) {
+            (Some((x, y, z)), _) => Bounds {
+                min_x: 0.0, max_x: x,
+                min_y: 0.0, max_y: y,
+                min_z: -z, max_z: 0.0,
+            },
+            (None, Some(bounds)) => Bounds {
+                max_z: if bounds.min_z < 0.0 { 0.0 } else { bounds.max_z },
+                ..bounds
+            },
+            (None, None) => return,
+        };
+        self.renderer.update_reference(&self.gl, &stock);
+    }
+    
+    /// Projection for a viewport of `aspect` width over height. The orthographic
+    /// box is the fitted toolpath with a margin, shrinking as the camera zooms in.
+    fn projection(&self, aspect: f32) -> glm::Mat4 {
+        if !self.ortho {
+            return glm::perspective(aspect, 45.0_f32.to_radians(), 0.1, 10000.0);
+        }
+        let (size, fit_distance) = self.fit;
+        let half_height = size * 0.75 * self.camera.distance() / fit_distance;
+        let half_width = half_height * aspect;
+        glm::ortho(-half_width, half_width, -half_height, half_height, 0.1, 10000.0)
+    }
+}
+
+/// Fetch a G-code file's text for `load_gcode`. A network failure or an HTTP
+/// error status rejects the promise
+#[wasm_bindgen]
+pub async fn fetch_text(url: String) -> Result<String, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window to fetch from"))?;
+    let response: Response = JsFuture::from(window.fetch_with_str(&url)).await?.dyn_into()?;
+    if !response.ok() {
+        return Err(JsValue::from_str(&format!(
+            "fetching {} failed: {} {}",
+            url,
+            response.status(),
+            response.status_text()
+        )));
+    }
+    let text = JsFuture::from(response.text()?).await?;
+    text.as_string()
+        .ok_or_else(|| JsValue::from_str(&format!("{} is not a text file", url)))
+}
+
+/// A G-code file's bytes as text. Controllers save comments in Latin-1 as often
+/// as UTF-8, so bytes that aren't UTF-8 become U+FFFD instead of failing the load
+fn gcode_text(bytes: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_bytes_text() {
+        // `(0.5° chamfer)` saved in Latin-1
+        let bytes = b"G00 X1.0 Y1.0 (0.5\xb0 chamfer)\nG01 Z-0.1 F10.0\nG01 X2.0\n";
+        let text = gcode_text(bytes);
+        assert!(text.contains("(0.5\u{fffd} chamfer)"));
+        assert_eq!(gcode::parse(&text).moves.len(), 3);
+
+        assert!(matches!(gcode_text(b"G00 X1.0"), Cow::Borrowed("G00 X1.0")));
+    }
+}
@@ -3,10 +3,22 @@ use nalgebra_glm as glm;
 use js_sys::Float32Array;
 use wasm_bindgen::JsCast;
 
-use crate::gcode::{Toolpath, MoveType};
+use crate::gcode::{Bounds, Toolpath, MoveType};
 
 pub struct Renderer {
     program: WebGlProgram,
+    toolpath: LineBuffer,
+    stock: LineBuffer,
+    axes: LineBuffer,
+    /// Draw the stock box and origin axes along with the toolpath
+    pub show_reference: bool,
+    /// Color cuts by depth instead of by move type; takes effect on the next
+    /// `update_toolpath`
+    pub depth_shading: bool,
+}
+
+/// Vertex buffers for one set of colored line segments
+struct LineBuffer {
     position_buffer: WebGlBuffer,
     color_buffer: WebGlBuffer,
     vertex_count: i32,
@@ -46,17 +58,16 @@ impl Renderer {
     pub fn new(gl: &GL) -> Result<Renderer, String> {
         let program = create_program(gl, VERTEX_SHADER, FRAGMENT_SHADER)?;
         
-        let position_buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
-        let color_buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
-        
         gl.enable(GL::DEPTH_TEST);
         gl.clear_color(0.1, 0.1, 0.1, 1.0);
         
         Ok(Renderer {
             program,
-            position_buffer,
-            color_buffer,
-            vertex_count: 0,
+            toolpath: LineBuffer::new(gl)?,
+            stock: LineBuffer::new(gl)?,
+            axes: LineBuffer::new(gl)?,
+            show_reference: true,
+            depth_shading: false,
         })
     }
     
@@ -69,31 +80,123 @@ impl Renderer {
         let cut_color = [1.0_f32, 0.65, 0.0];       // Amber
         let arc_color = [0.0_f32, 0.8, 1.0];        // Cyan
         
+        // Depth shading darkens cuts from amber at the top of the toolpath
+        // toward a quarter of it at the bottom
+        let (min_z, max_z) = toolpath
+            .bounds()
+            .map_or((0.0, 0.0), |b| (b.min_z, b.max_z));
+        let shade = |z: f32| {
+            let t = if max_z > min_z { (z - min_z) / (max_z - min_z) } else { 1.0 };
+            cut_color.map(|c| c * (0.25 + 0.75 * t))
+        };
+        
         for m in &toolpath.moves {
-            let color = match m.kind {
-                MoveType::Rapid => &rapid_color,
-                MoveType::Linear => &cut_color,
-                MoveType::ArcCW | MoveType::ArcCCW => &arc_color,
+            let (start_color, end_color) = match m.kind {
+                MoveType::Rapid => (rapid_color, rapid_color),
+                _ if self.depth_shading => (shade(m.z1), shade(m.z2)),
+                MoveType::Linear => (cut_color, cut_color),
+                MoveType::ArcCW | MoveType::ArcCCW => (arc_color, arc_color),
             };
             
             // Start point
             positions.push(m.x1);
             positions.push(m.y1);
             positions.push(m.z1);
-            colors.extend_from_slice(color);
+            colors.extend_from_slice(&start_color);
             
             // End point
             positions.push(m.x2);
             positions.push(m.y2);
             positions.push(m.z2);
-            colors.extend_from_slice(color);
+            colors.extend_from_slice(&end_color);
+        }
+        
+        self.toolpath.upload(gl, &positions, &colors);
+    }
+    
+    /// Rebuild the wireframe stock box and the XYZ axes at the origin, which are
+    /// scaled to a quarter of the stock's largest side
+    pub fn update_reference(&mut self, gl: &GL, stock: &Bounds) {
+        let stock_color = [0.7_f32, 0.7, 0.7];
+        let xs = [stock.min_x, stock.max_x];
+        let ys = [stock.min_y, stock.max_y];
+        let zs = [stock.min_z, stock.max_z];
+        
+        // Twelve edges: four along each axis
+        let mut positions: Vec<f32> = Vec::new();
+        for &a in &ys {
+            for &b in &zs {
+                positions.extend_from_slice(&[xs[0], a, b, xs[1], a, b]);
+            }
+        }
+        for &a in &xs {
+            for &b in &zs {
+                positions.extend_from_slice(&[a, ys[0], b, a, ys[1], b]);
+            }
+        }
+        for &a in &xs {
+            for &b in &ys {
+                positions.extend_from_slice(&[a, b, zs[0], a, b, zs[1]]);
+            }
         }
+        let colors: Vec<f32> = stock_color.repeat(positions.len() / 3);
+        self.stock.upload(gl, &positions, &colors);
         
+        let length = (stock.max_x - stock.min_x)
+            .max(stock.max_y - stock.min_y)
+            .max(stock.max_z - stock.min_z)
+            * 0.25;
+        let positions = [
+            0.0, 0.0, 0.0, length, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, length, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0, length,
+        ];
+        // X red, Y green, Z blue
+        let colors = [
+            1.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0, 1.0, 0.0,
+            0.0, 0.3, 1.0, 0.0, 0.3, 1.0,
+        ];
+        self.axes.upload(gl, &positions, &colors);
+    }
+    
+    pub fn render(&self, gl: &GL, view_matrix: &glm::Mat4, projection_matrix: &glm::Mat4) {
+        gl.use_program(Some(&self.program));
+        
+        // Set uniforms
+        let model_view_loc = gl.get_uniform_location(&self.program, "modelViewMatrix");
+        let view_flat: Vec<f32> = view_matrix.iter().cloned().collect();
+        gl.uniform_matrix4fv_with_f32_array(model_view_loc.as_ref(), false, &view_flat);
+        
+        let proj_loc = gl.get_uniform_location(&self.program, "projectionMatrix");
+        let proj_flat: Vec<f32> = projection_matrix.iter().cloned().collect();
+        gl.uniform_matrix4fv_with_f32_array(proj_loc.as_ref(), false, &proj_flat);
+        
+        // Draw
+        self.toolpath.draw(gl, &self.program);
+        if self.show_reference {
+            self.stock.draw(gl, &self.program);
+            self.axes.draw(gl, &self.program);
+        }
+    }
+}
+
+impl LineBuffer {
+    fn new(gl: &GL) -> Result<LineBuffer, String> {
+        Ok(LineBuffer {
+            position_buffer: gl.create_buffer().ok_or("Failed to create buffer")?,
+            color_buffer: gl.create_buffer().ok_or("Failed to create buffer")?,
+            vertex_count: 0,
+        })
+    }
+    
+    /// Replace the segments with `positions` and `colors`, three floats per vertex
+    fn upload(&mut self, gl: &GL, positions: &[f32], colors: &[f32]) {
         self.vertex_count = positions.len() as i32 / 3;
         
         // Upload positions
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
-        let positions_array = Float32Array::from(positions.as_slice());
+        let positions_array = Float32Array::from(positions);
         gl.buffer_data_with_array_buffer_view(
             GL::ARRAY_BUFFER,
             &positions_array,
@@ -102,7 +205,7 @@ impl Renderer {
         
         // Upload colors
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.color_buffer));
-        let colors_array = Float32Array::from(colors.as_slice());
+        let colors_array = Float32Array::from(colors);
         gl.buffer_data_with_array_buffer_view(
             GL::ARRAY_BUFFER,
             &colors_array,
@@ -110,31 +213,23 @@ impl Renderer {
         );
     }
     
-    pub fn render(&self, gl: &GL, view_matrix: &glm::Mat4, projection_matrix: &glm::Mat4) {
-        gl.use_program(Some(&self.program));
+    fn draw(&self, gl: &GL, program: &WebGlProgram) {
+        if self.vertex_count == 0 {
+            return;
+        }
         
         // Set up position attribute
-        let position_loc = gl.get_attrib_location(&self.program, "position") as u32;
+        let position_loc = gl.get_attrib_location(program, "position") as u32;
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
         gl.vertex_attrib_pointer_with_i32(position_loc, 3, GL::FLOAT, false, 0, 0);
         gl.enable_vertex_attrib_array(position_loc);
         
         // Set up color attribute
-        let color_loc = gl.get_attrib_location(&self.program, "color") as u32;
+        let color_loc = gl.get_attrib_location(program, "color") as u32;
         gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.color_buffer));
         gl.vertex_attrib_pointer_with_i32(color_loc, 3, GL::FLOAT, false, 0, 0);
         gl.enable_vertex_attrib_array(color_loc);
         
-        // Set uniforms
-        let model_view_loc = gl.get_uniform_location(&self.program, "modelViewMatrix");
-        let view_flat: Vec<f32> = view_matrix.iter().cloned().collect();
-        gl.uniform_matrix4fv_with_f32_array(model_view_loc.as_ref(), false, &view_flat);
-        
-        let proj_loc = gl.get_uniform_location(&self.program, "projectionMatrix");
-        let proj_flat: Vec<f32> = projection_matrix.iter().cloned().collect();
-        gl.uniform_matrix4fv_with_f32_array(proj_loc.as_ref(), false, &proj_flat);
-        
-        // Draw
         gl.draw_arrays(GL::LINES, 0, self.vertex_count);
     }
 }